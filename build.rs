@@ -0,0 +1,10 @@
+fn main() {
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/fw_fanctrl.proto");
+        let protoc = protoc_bin_vendored::protoc_bin_path().expect("vendored protoc not found");
+        std::env::set_var("PROTOC", protoc);
+        tonic_prost_build::compile_protos("proto/fw_fanctrl.proto")
+            .expect("Failed to compile proto/fw_fanctrl.proto");
+    }
+}
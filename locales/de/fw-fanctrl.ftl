@@ -0,0 +1,119 @@
+# Deutsche Ausgabe von fw-fanctrl. Gleiche Nachrichten wie locales/en.
+
+## print all
+
+status-strategy = Strategie
+status-default = (Standard)
+status-since = Seit
+status-profile = Profil
+status-active = Aktiv
+status-active-yes = ja
+status-active-no = nein (pausiert)
+status-mode = Modus
+status-fan = Lüfter
+status-fanless = keiner (lüfterlos)
+status-fan-speed = Lüfterdrehzahl
+status-fan-rpm = Lüfter U/min
+status-speed-bias = Drehzahlversatz
+status-temperature = Temperatur
+status-moving-average = Gleitender Mittelwert
+status-effective = Wirksam
+status-window-fill = { $samples }/{ $window } Messwerte
+status-trend = Tendenz
+status-trend-breakpoint = Kurvenpunkt { $temperature }°C in { $eta }
+status-trend-safety = safetyTemp { $temperature }°C in { $eta }
+status-sensors = Sensoren
+status-sensors-value = { $aggregation } aller
+status-battery = Akku
+status-quiet-hours = Ruhezeiten
+status-quiet-hours-value = { $start }-{ $end }, höchstens { $speed }%
+status-alerts = Alarme
+status-degraded = Eingeschränkter Modus
+status-fan-control = Lüftersteuerung
+status-monitor-only = nur Überwachung, Drehzahl wird nicht geschrieben
+status-duty-drift = Drehzahlabweichung
+status-duty-drift-value = EC meldet { $duty }%, ein anderer Prozess setzt sie womöglich
+status-power = Stromversorgung
+status-power-ac = Netzteil
+status-power-battery = Akku
+status-power-battery-at = { $source }, Akku bei { $percent }%
+status-platform = Plattform
+status-uptime = Laufzeit
+status-configuration = Konfiguration:
+status-default-strategy = Standardstrategie
+status-on-battery = Im Akkubetrieb
+status-strategies = Strategien
+
+## Kurze Antworten
+
+reply-ok = OK
+reply-fan-speed = Lüfterdrehzahl: { $speed }%
+reply-fan-rpm = Lüfter U/min: { $rpm }
+reply-speed-bias = Drehzahlversatz: { $bias }%
+reply-active = Aktiv: { $active }
+reply-battery-excluded = Akkusensor bei der Regeltemperatur nicht berücksichtigt
+reply-battery-included = Akkusensor bei der Regeltemperatur berücksichtigt
+reply-current-strategy = Aktuelle Strategie: { $strategy }
+reply-pending-switch = Wechsel zu { $strategy } in { $seconds }s (Stromversorgung geändert)
+reply-last-change = Letzte Änderung: { $reason }
+reply-client-version = Client: { $version }
+reply-daemon-version = Daemon: { $version } (Protokoll { $protocol })
+reply-started-job = Auftrag { $id } gestartet
+
+reason-power-source = Stromversorgung
+reason-battery-threshold = Akkuschwelle
+reason-charge-state = Ladezustand
+reason-quiet-hours = Ruhezeiten
+
+error-label = Fehler:
+
+## sanity-check
+
+sanity-title = === Selbstprüfung ===
+sanity-done = === Fertig ===
+sanity-degraded = Modus:       Eingeschränkt (kein Zugriff auf den EC, nur hwmon-Temperaturen)
+sanity-firmware = Firmware:    { $status }
+sanity-failed = FEHLGESCHLAGEN
+sanity-temperature = Temperatur
+sanity-temperature-ok = Temperatur:  { $celsius }°C - OK
+sanity-power = Stromversorgung
+sanity-power-ac = Strom:       Netzteil angeschlossen - OK
+sanity-power-battery = Strom:       Akku - OK
+sanity-fan-skipped-degraded = Lüftersteuerung: ÜBERSPRUNGEN (im eingeschränkten Modus nicht verfügbar)
+sanity-fan-skipped-fanless = Lüftersteuerung: ÜBERSPRUNGEN (lüfterlos, der EC meldet keinen Lüfter)
+sanity-fan-skipped-quirk = Lüftersteuerung: ÜBERSPRUNGEN (die EC-Firmware hat einen bekannten Fehler bei der Lüftersteuerung)
+sanity-fan-testing = Lüftersteuerung wird getestet...
+sanity-fan-ok = Lüftersteuerung: OK (Automatik wiederhergestellt)
+sanity-fan-mode = Lüftermodus
+sanity-fan-mode-auto = Lüftermodus: Automatik
+
+## Befehle
+
+warning-label = Warnung:
+no-command = Kein Befehl angegeben. Mit --help gibt es eine Übersicht.
+column-speed = Stufe%
+column-rpm = U/min
+column-duty = Stufe%
+column-temperature = Temp°C
+column-settled = Stabil
+selftest-ok = Lüftersteuerung: OK (an die Steuerung zurückgegeben)
+export-done = { $samples } Messwerte nach { $path } exportiert
+apply-done = Lüfterstufe auf { $duty }% bei { $temperature }°C gesetzt ({ $strategy })
+characterize-start = { $duties } Lüfterstufen werden vermessen (je bis zu { $seconds }s); Abbruch mit Strg+C
+characterize-aborted = Abgebrochen, EC-Lüfterautomatik wird wiederhergestellt
+characterize-required = Niedrigste Stufe, die { $temperature }°C hält: { $duty }%
+characterize-curve = Vorgeschlagene speedCurve:
+autotune-analysis = { $samples } aktive Messwerte über { $seconds }s ausgewertet: { $oscillations } Schwankungen/h, { $overheat }% über { $limit }°C, Spitze { $peak }°C
+autotune-no-changes = Keine Änderungen vorgeschlagen
+autotune-applied = In { $path } übernommen
+autotune-dry-run = Mit --apply erneut ausführen, um die Änderungen zu schreiben
+commit-nothing = Nichts zu speichern, { $path } entspricht dem Dienst
+commit-saved = In { $path } gespeichert
+curve-saved = '{ $strategy }' in { $path } gespeichert
+curve-discarded = Änderungen an '{ $strategy }' verworfen
+check-config-unknown-key = unbekannter Schlüssel '{ $key }'
+check-config-ok = { $path }: OK ({ $strategies } Strategien)
+migrate-current = { $path } verwendet bereits die aktuellen Schlüsselnamen
+migrate-done = { $path } migriert
+strategy-exported = '{ $strategy }' nach { $path } exportiert
+strategy-imported = '{ $strategy }' in { $path } importiert; mit `fw-fanctrl reload` verwenden
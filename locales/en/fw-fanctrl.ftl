@@ -0,0 +1,120 @@
+# Natural output of fw-fanctrl. Every message here must also exist in the
+# other catalogs; a test checks that.
+
+## print all
+
+status-strategy = Strategy
+status-default = (default)
+status-since = Since
+status-profile = Profile
+status-active = Active
+status-active-yes = yes
+status-active-no = no (paused)
+status-mode = Mode
+status-fan = Fan
+status-fanless = none (fanless)
+status-fan-speed = Fan speed
+status-fan-rpm = Fan RPM
+status-speed-bias = Speed bias
+status-temperature = Temperature
+status-moving-average = Moving average
+status-effective = Effective
+status-window-fill = { $samples }/{ $window } samples
+status-trend = Trend
+status-trend-breakpoint = curve point { $temperature }°C in { $eta }
+status-trend-safety = safetyTemp { $temperature }°C in { $eta }
+status-sensors = Sensors
+status-sensors-value = { $aggregation } of all
+status-battery = Battery
+status-quiet-hours = Quiet hours
+status-quiet-hours-value = { $start }-{ $end }, max { $speed }%
+status-alerts = Alerts
+status-degraded = Degraded mode
+status-fan-control = Fan control
+status-monitor-only = monitor only, duty not written
+status-duty-drift = Duty drift
+status-duty-drift-value = EC reports { $duty }%, another process may be setting it
+status-power = Power
+status-power-ac = AC
+status-power-battery = battery
+status-power-battery-at = { $source }, battery at { $percent }%
+status-platform = Platform
+status-uptime = Uptime
+status-configuration = Configuration:
+status-default-strategy = Default strategy
+status-on-battery = On battery
+status-strategies = Strategies
+
+## Short replies
+
+reply-ok = OK
+reply-fan-speed = Fan speed: { $speed }%
+reply-fan-rpm = Fan RPM: { $rpm }
+reply-speed-bias = Speed bias: { $bias }%
+reply-active = Active: { $active }
+reply-battery-excluded = Battery sensor left out of the control temperature
+reply-battery-included = Battery sensor included in the control temperature
+reply-current-strategy = Current strategy: { $strategy }
+reply-pending-switch = Switching to { $strategy } in { $seconds }s (power source changed)
+reply-last-change = Last change: { $reason }
+reply-client-version = Client: { $version }
+reply-daemon-version = Daemon: { $version } (protocol { $protocol })
+reply-started-job = Started job { $id }
+
+reason-power-source = power source
+reason-battery-threshold = battery threshold
+reason-charge-state = charge state
+reason-quiet-hours = quiet hours
+
+error-label = Error:
+
+## sanity-check
+
+sanity-title = === Sanity Check ===
+sanity-done = === Done ===
+sanity-degraded = Mode:        Degraded (EC not accessible, hwmon temperatures only)
+sanity-firmware = Firmware:    { $status }
+sanity-failed = FAILED
+sanity-temperature = Temperature
+sanity-temperature-ok = Temperature: { $celsius }°C - OK
+sanity-power = Power
+sanity-power-ac = Power:       AC connected - OK
+sanity-power-battery = Power:       Battery - OK
+sanity-fan-skipped-degraded = Fan control: SKIPPED (not available in degraded mode)
+sanity-fan-skipped-fanless = Fan control: SKIPPED (fanless, the EC reports no fan)
+sanity-fan-skipped-quirk = Fan control: SKIPPED (EC firmware has a known fan-control quirk)
+sanity-fan-testing = Testing fan control...
+sanity-fan-ok = Fan control: OK (auto-restored)
+sanity-fan-mode = Fan mode
+sanity-fan-mode-auto = Fan mode: Auto
+
+## Commands
+
+warning-label = Warning:
+no-command = No command provided. Use --help for usage information.
+column-speed = Speed%
+column-rpm = RPM
+column-duty = Duty%
+column-temperature = Temp°C
+column-settled = Settled
+selftest-ok = Fan control: OK (handed back to the controller)
+export-done = Exported { $samples } samples to { $path }
+apply-done = Set fan duty to { $duty }% at { $temperature }°C ({ $strategy })
+characterize-start = Characterizing { $duties } duty levels (up to { $seconds }s each); press Ctrl+C to abort
+characterize-aborted = Aborted, restoring EC auto fan control
+characterize-required = Lowest duty holding { $temperature }°C: { $duty }%
+characterize-curve = Suggested speedCurve:
+autotune-analysis = Analyzed { $samples } active samples over { $seconds }s: { $oscillations } oscillations/h, { $overheat }% above { $limit }°C, peak { $peak }°C
+autotune-no-changes = No changes proposed
+autotune-applied = Applied to { $path }
+autotune-dry-run = Run again with --apply to write these changes
+commit-nothing = Nothing to commit, { $path } matches the daemon
+commit-saved = Saved to { $path }
+curve-saved = Saved '{ $strategy }' to { $path }
+curve-discarded = Discarded changes to '{ $strategy }'
+check-config-unknown-key = unknown key '{ $key }'
+check-config-ok = { $path }: OK ({ $strategies } strategies)
+migrate-current = { $path } already uses the current key names
+migrate-done = Migrated { $path }
+strategy-exported = Exported '{ $strategy }' to { $path }
+strategy-imported = Imported '{ $strategy }' into { $path }; run `fw-fanctrl reload` to use it
@@ -0,0 +1,133 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+
+use serde::Serialize;
+use tokio::net::UnixStream;
+
+use crate::controller::unix_now;
+use crate::protocol::is_state_changing;
+
+/// Who is on the other end of a socket connection, from `SO_PEERCRED`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Peer {
+    pub uid: Option<u32>,
+    pub pid: Option<i32>,
+    /// The client's binary, when `/proc` lets the daemon see it.
+    pub executable: Option<String>,
+}
+
+impl Peer {
+    pub fn of(stream: &UnixStream) -> Self {
+        let Ok(cred) = stream.peer_cred() else {
+            return Self::default();
+        };
+        let pid = cred.pid();
+        Self {
+            uid: Some(cred.uid()),
+            pid,
+            executable: pid
+                .and_then(|pid| std::fs::read_link(format!("/proc/{}/exe", pid)).ok())
+                .map(|path| path.display().to_string()),
+        }
+    }
+}
+
+/// One audited command.
+#[derive(Debug, Serialize)]
+struct AuditEntry<'a> {
+    /// Seconds since the Unix epoch.
+    timestamp: u64,
+    command: &'a str,
+    #[serde(flatten)]
+    peer: &'a Peer,
+    /// `success`, or why the command failed.
+    result: &'a str,
+}
+
+/// Logs state-changing socket commands with their sender, and optionally
+/// appends them to a file as JSON lines.
+#[derive(Debug, Default)]
+pub struct AuditLog {
+    path: Option<PathBuf>,
+}
+
+impl AuditLog {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self { path }
+    }
+
+    /// Records `command` from `peer` if it is state-changing; `result` is
+    /// `success` or the error it failed with.
+    pub fn record(&self, peer: &Peer, command: &str, result: &str) {
+        if !is_state_changing(command) {
+            return;
+        }
+        tracing::info!(
+            uid = ?peer.uid,
+            pid = ?peer.pid,
+            executable = ?peer.executable,
+            result,
+            "Command '{}'",
+            command
+        );
+
+        let Some(path) = &self.path else {
+            return;
+        };
+        let entry = AuditEntry {
+            timestamp: unix_now(),
+            command,
+            peer,
+            result,
+        };
+        let line = serde_json::to_string(&entry).unwrap_or_default();
+        let written = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| writeln!(file, "{}", line));
+        if let Err(e) = written {
+            tracing::warn!("Failed to append to {}: {}", path.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entries_are_appended_as_json_lines() {
+        let path =
+            std::env::temp_dir().join(format!("fw-fanctrl-audit-{}.log", std::process::id()));
+        let log = AuditLog::new(Some(path.clone()));
+        let peer = Peer {
+            uid: Some(1000),
+            pid: Some(42),
+            executable: Some("/usr/bin/fw-fanctrl".into()),
+        };
+        log.record(&peer, "pause", "success");
+        log.record(&peer, "print all", "success");
+        log.record(
+            &peer,
+            "shutdown",
+            "Permission denied: shutdown requires root",
+        );
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let entries: Vec<serde_json::Value> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["command"], "pause");
+        assert_eq!(entries[0]["uid"], 1000);
+        assert_eq!(entries[0]["executable"], "/usr/bin/fw-fanctrl");
+        assert_eq!(
+            entries[1]["result"],
+            "Permission denied: shutdown requires root"
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+}
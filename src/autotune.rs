@@ -0,0 +1,254 @@
+use serde::Serialize;
+
+use crate::config::Strategy;
+use crate::controller::HistoryEntry;
+use crate::error::{Error, Result};
+
+/// Speed direction reversals per hour above which the fan counts as oscillating.
+const OSCILLATION_THRESHOLD: f64 = 30.0;
+/// Share of samples above the limit (with the fan below full speed) that counts as overheating.
+const OVERHEAT_THRESHOLD: f64 = 0.01;
+const HYSTERESIS_STEP: u32 = 2;
+const MAX_HYSTERESIS: u32 = 10;
+const SPEED_STEP: u32 = 10;
+/// Curve points this far below the limit are raised when overheating.
+const OVERHEAT_WINDOW: u32 = 15;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Analysis {
+    pub samples: usize,
+    pub duration_seconds: u64,
+    pub oscillations_per_hour: f64,
+    /// Fraction of samples above the limit while the fan was not at full speed.
+    pub overheat_fraction: f64,
+    pub max_temperature: f64,
+}
+
+/// One proposed config edit, printed as `path: from -> to`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Change {
+    pub path: String,
+    pub from: String,
+    pub to: String,
+}
+
+impl std::fmt::Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} -> {}", self.path, self.from, self.to)
+    }
+}
+
+/// Reads history written by `export`, in either its JSON or CSV format.
+pub fn parse_history(content: &str) -> Result<Vec<HistoryEntry>> {
+    if content.trim_start().starts_with('[') {
+        return serde_json::from_str(content)
+            .map_err(|e| Error::Config(format!("Invalid history JSON: {}", e)));
+    }
+
+    content
+        .lines()
+        .skip(1)
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(i, line)| {
+            let invalid = || Error::Config(format!("Invalid history CSV at line {}", i + 2));
+            let fields: Vec<&str> = line.split(',').collect();
+            let [timestamp, temperature, speed, active] = fields[..] else {
+                return Err(invalid());
+            };
+            Ok(HistoryEntry {
+                timestamp: timestamp.trim().parse().map_err(|_| invalid())?,
+                temperature: temperature.trim().parse().map_err(|_| invalid())?,
+                speed: speed.trim().parse().map_err(|_| invalid())?,
+                active: active.trim().parse().map_err(|_| invalid())?,
+            })
+        })
+        .collect()
+}
+
+/// Looks for oscillation and overheating in the samples recorded while fan control was active.
+pub fn analyze(history: &[HistoryEntry], max_temp: f64) -> Analysis {
+    let active: Vec<&HistoryEntry> = history.iter().filter(|e| e.active).collect();
+
+    let duration_seconds = match (active.first(), active.last()) {
+        (Some(first), Some(last)) => last.timestamp.saturating_sub(first.timestamp),
+        _ => 0,
+    };
+
+    let mut reversals = 0;
+    let mut last_direction = 0i64;
+    for pair in active.windows(2) {
+        let direction = (pair[1].speed as i64 - pair[0].speed as i64).signum();
+        if direction == 0 {
+            continue;
+        }
+        if last_direction != 0 && direction != last_direction {
+            reversals += 1;
+        }
+        last_direction = direction;
+    }
+
+    let overheated = active
+        .iter()
+        .filter(|e| e.temperature > max_temp && e.speed < 100)
+        .count();
+
+    Analysis {
+        samples: active.len(),
+        duration_seconds,
+        oscillations_per_hour: if duration_seconds == 0 {
+            0.0
+        } else {
+            reversals as f64 * 3600.0 / duration_seconds as f64
+        },
+        overheat_fraction: if active.is_empty() {
+            0.0
+        } else {
+            overheated as f64 / active.len() as f64
+        },
+        max_temperature: active
+            .iter()
+            .map(|e| e.temperature)
+            .fold(f64::MIN, f64::max)
+            .max(0.0),
+    }
+}
+
+/// Proposes a tuned copy of `strategy`: more hysteresis when the fan oscillated,
+/// faster curve points just below `max_temp` when it overheated.
+pub fn propose(
+    name: &str,
+    strategy: &Strategy,
+    analysis: &Analysis,
+    max_temp: f64,
+) -> (Strategy, Vec<Change>) {
+    let mut tuned = strategy.clone();
+    let mut changes = Vec::new();
+
+    if analysis.oscillations_per_hour > OSCILLATION_THRESHOLD
+        && strategy.hysteresis < MAX_HYSTERESIS
+    {
+        tuned.hysteresis = (strategy.hysteresis + HYSTERESIS_STEP).min(MAX_HYSTERESIS);
+        changes.push(Change {
+            path: format!("strategies.{}.hysteresis", name),
+            from: strategy.hysteresis.to_string(),
+            to: tuned.hysteresis.to_string(),
+        });
+    }
+
+    if analysis.overheat_fraction > OVERHEAT_THRESHOLD {
+        let window_start = (max_temp as u32).saturating_sub(OVERHEAT_WINDOW);
+        for (i, point) in tuned.speed_curve.iter_mut().enumerate() {
+            if point.temp >= window_start && point.speed < 100 {
+                let from = point.speed;
+                point.speed = (point.speed + SPEED_STEP).min(100);
+                changes.push(Change {
+                    path: format!("strategies.{}.speedCurve[{}].speed", name, i),
+                    from: from.to_string(),
+                    to: point.speed.to_string(),
+                });
+            }
+        }
+    }
+
+    (tuned, changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CurvePoint, SensorAggregation, Smoothing};
+
+    fn entry(timestamp: u64, temperature: f64, speed: u32) -> HistoryEntry {
+        HistoryEntry {
+            timestamp,
+            temperature,
+            speed,
+            active: true,
+        }
+    }
+
+    fn strategy() -> Strategy {
+        Strategy {
+            description: None,
+            update_interval_seconds: 5,
+            moving_average_interval: 20,
+            speed_curve: vec![
+                CurvePoint { temp: 0, speed: 0 },
+                CurvePoint {
+                    temp: 60,
+                    speed: 30,
+                },
+                CurvePoint {
+                    temp: 75,
+                    speed: 60,
+                },
+                CurvePoint {
+                    temp: 90,
+                    speed: 100,
+                },
+            ],
+            compose: Vec::new(),
+            hysteresis: 0,
+            speed_decay_seconds: 0,
+            max_speed_changes_per_minute: 0,
+            safety_temp: 85,
+            smoothing: Smoothing::MovingAverage,
+            sensor_aggregation: SensorAggregation::Max,
+            sensor_curves: Vec::new(),
+            charge_rate_limit: None,
+            discharge_saver: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_history_csv_and_json() {
+        let csv = "timestamp,temperature,speed,active\n1,50.5,20,true\n2,51,25,false\n";
+        let entries = parse_history(csv).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].speed, 25);
+        assert!(!entries[1].active);
+
+        let json = r#"[{"timestamp":1,"temperature":50.5,"speed":20,"active":true}]"#;
+        assert_eq!(parse_history(json).unwrap()[0].temperature, 50.5);
+
+        assert!(parse_history("timestamp,temperature,speed,active\n1,2\n").is_err());
+    }
+
+    #[test]
+    fn test_oscillation_raises_hysteresis() {
+        // Speed flips every 10 seconds for an hour
+        let history: Vec<_> = (0..=360)
+            .map(|i| entry(i * 10, 60.0, if i % 2 == 0 { 30 } else { 35 }))
+            .collect();
+        let analysis = analyze(&history, 85.0);
+        assert!(analysis.oscillations_per_hour > OSCILLATION_THRESHOLD);
+
+        let (tuned, changes) = propose("lazy", &strategy(), &analysis, 85.0);
+        assert_eq!(tuned.hysteresis, 2);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].to_string(), "strategies.lazy.hysteresis: 0 -> 2");
+    }
+
+    #[test]
+    fn test_overheating_raises_upper_curve() {
+        let history: Vec<_> = (0..100).map(|i| entry(i, 88.0, 70)).collect();
+        let analysis = analyze(&history, 85.0);
+        assert_eq!(analysis.overheat_fraction, 1.0);
+
+        let (tuned, changes) = propose("lazy", &strategy(), &analysis, 85.0);
+        assert_eq!(tuned.speed_curve[1].speed, 30);
+        assert_eq!(tuned.speed_curve[2].speed, 70);
+        assert_eq!(tuned.speed_curve[3].speed, 100);
+        assert_eq!(changes.len(), 1);
+    }
+
+    #[test]
+    fn test_steady_history_proposes_nothing() {
+        let history: Vec<_> = (0..100).map(|i| entry(i, 65.0, 40)).collect();
+        let analysis = analyze(&history, 85.0);
+        let (_, changes) = propose("lazy", &strategy(), &analysis, 85.0);
+        assert!(changes.is_empty());
+    }
+}
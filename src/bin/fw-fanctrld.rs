@@ -0,0 +1,16 @@
+use clap::Parser;
+
+use fw_fanctrl::daemon::{self, RunArgs};
+use fw_fanctrl::error::Result;
+
+/// Framework laptop fan-control daemon; `fw-fanctrl` talks to it over its socket.
+#[derive(Parser, Debug)]
+#[clap(author, version, long_about = None)]
+struct Cli {
+    #[clap(flatten)]
+    run: RunArgs,
+}
+
+fn main() -> Result<()> {
+    daemon::run(Cli::parse().run)
+}
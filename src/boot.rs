@@ -0,0 +1,91 @@
+use std::fs;
+
+const CMDLINE_PATH: &str = "/proc/cmdline";
+const CMDLINE_KEY: &str = "fw_fanctrl.strategy";
+/// The EFI variable, as efivarfs exposes it; its vendor GUID is this project's own.
+pub const EFI_VARIABLE_PATH: &str =
+    "/sys/firmware/efi/efivars/FwFanctrlStrategy-5c7e1a1e-2f0b-4a8e-9d6c-3b1f0e4a7d21";
+
+/// Where a strategy hint came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HintSource {
+    KernelCmdline,
+    EfiVariable,
+}
+
+impl std::fmt::Display for HintSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::KernelCmdline => "kernel command line",
+            Self::EfiVariable => "EFI variable",
+        })
+    }
+}
+
+/// The strategy the boot asks for, if any: `fw_fanctrl.strategy=` on the
+/// kernel command line, or the `FwFanctrlStrategy` EFI variable. The command
+/// line wins over the EFI variable.
+pub fn strategy_hint() -> Option<(String, HintSource)> {
+    let cmdline = fs::read_to_string(CMDLINE_PATH).ok();
+    if let Some(name) = cmdline.as_deref().and_then(parse_cmdline) {
+        return Some((name, HintSource::KernelCmdline));
+    }
+    let variable = fs::read(EFI_VARIABLE_PATH).ok()?;
+    parse_efi_variable(&variable).map(|name| (name, HintSource::EfiVariable))
+}
+
+/// The value of the last `fw_fanctrl.strategy=` parameter, as the kernel
+/// itself lets later parameters override earlier ones.
+fn parse_cmdline(cmdline: &str) -> Option<String> {
+    cmdline
+        .split_whitespace()
+        .rev()
+        .find_map(|param| param.strip_prefix(CMDLINE_KEY)?.strip_prefix('='))
+        .map(|value| value.trim_matches('"'))
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+/// The name stored in the variable, after the 4-byte attributes efivarfs
+/// prefixes; UTF-8, or UTF-16LE as firmware tools tend to write strings.
+fn parse_efi_variable(data: &[u8]) -> Option<String> {
+    let value = data.get(4..)?;
+    let utf16 = value.len() >= 2 && value.len() % 2 == 0 && value[1] == 0;
+    let text = if utf16 {
+        let units: Vec<u16> = value
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .collect();
+        String::from_utf16(&units).ok()?
+    } else {
+        String::from_utf8(value.to_vec()).ok()?
+    };
+    let name = text.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cmdline_takes_the_last_strategy() {
+        let cmdline = "BOOT_IMAGE=/vmlinuz root=UUID=1234 fw_fanctrl.strategy=lazy quiet \
+                       fw_fanctrl.strategy=\"medium\" splash\n";
+        assert_eq!(parse_cmdline(cmdline).as_deref(), Some("medium"));
+        assert_eq!(parse_cmdline("quiet fw_fanctrl.strategy= splash"), None);
+        assert_eq!(parse_cmdline("fw_fanctrl.strategyx=lazy"), None);
+    }
+
+    #[test]
+    fn test_efi_variable_in_utf8_and_utf16() {
+        let attributes = [0x07, 0, 0, 0];
+        let utf8 = [&attributes[..], b"lazy\n"].concat();
+        assert_eq!(parse_efi_variable(&utf8).as_deref(), Some("lazy"));
+
+        let utf16: Vec<u8> = "lazy\0".encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let data = [&attributes[..], &utf16].concat();
+        assert_eq!(parse_efi_variable(&data).as_deref(), Some("lazy"));
+        assert_eq!(parse_efi_variable(&attributes), None);
+    }
+}
@@ -0,0 +1,278 @@
+use std::collections::VecDeque;
+use std::process::Child;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::config::CurvePoint;
+use crate::error::{Error, Result};
+use crate::hardware::HardwareController;
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(2);
+/// Temperatures must stay within this band over the settle window to count as equilibrium.
+const SETTLED_BAND: f64 = 1.0;
+const SETTLE_WINDOW: usize = 15;
+
+/// Equilibrium temperature reached at a fixed duty.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Measurement {
+    pub duty: u32,
+    pub temperature: f64,
+    pub settled: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Characterization {
+    pub measurements: Vec<Measurement>,
+    pub target_temperature: f64,
+    /// Lowest duty whose equilibrium stays at or below the target.
+    pub required_duty: u32,
+    pub speed_curve: Vec<CurvePoint>,
+}
+
+pub enum Load {
+    /// Spin one busy thread per CPU.
+    BusyLoop,
+    /// Run a shell command for the duration of the measurement.
+    Command(String),
+    /// The user provides the load externally.
+    External,
+}
+
+pub struct Options {
+    pub duties: Vec<u32>,
+    pub target_temperature: f64,
+    /// Any sample above this aborts the run.
+    pub max_temperature: f64,
+    pub max_settle: Duration,
+    pub load: Load,
+}
+
+/// Lowest duty (interpolated between measurements) whose equilibrium
+/// temperature is at or below `target`, or 100 if none is.
+pub fn required_duty(measurements: &[Measurement], target: f64) -> u32 {
+    let mut sorted = measurements.to_vec();
+    sorted.sort_by_key(|m| m.duty);
+
+    let Some(i) = sorted.iter().position(|m| m.temperature <= target) else {
+        return 100;
+    };
+    if i == 0 {
+        return sorted[0].duty;
+    }
+
+    let (hot, cool) = (sorted[i - 1], sorted[i]);
+    let fraction = (hot.temperature - target) / (hot.temperature - cool.temperature);
+    let duty = hot.duty as f64 + fraction * (cool.duty - hot.duty) as f64;
+    (duty.ceil() as u32).min(100)
+}
+
+/// Builds a curve that stays silent well below the target, reaches the
+/// required duty at the target and ramps to full speed 10°C above it.
+pub fn suggest_curve(required_duty: u32, target: f64) -> Vec<CurvePoint> {
+    let target = target.round().clamp(10.0, 90.0) as u32;
+    vec![
+        CurvePoint { temp: 0, speed: 0 },
+        CurvePoint {
+            temp: target - 10,
+            speed: 0,
+        },
+        CurvePoint {
+            temp: target,
+            speed: required_duty,
+        },
+        CurvePoint {
+            temp: target + 10,
+            speed: 100,
+        },
+    ]
+}
+
+fn is_settled(window: &VecDeque<f64>) -> bool {
+    if window.len() < SETTLE_WINDOW {
+        return false;
+    }
+    let max = window.iter().copied().fold(f64::MIN, f64::max);
+    let min = window.iter().copied().fold(f64::MAX, f64::min);
+    max - min <= SETTLED_BAND
+}
+
+struct LoadGuard {
+    stop: Arc<AtomicBool>,
+    child: Option<Child>,
+}
+
+impl LoadGuard {
+    fn start(load: &Load) -> Result<Self> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let mut child = None;
+
+        match load {
+            Load::BusyLoop => {
+                let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+                for _ in 0..threads {
+                    let stop = Arc::clone(&stop);
+                    std::thread::spawn(move || {
+                        while !stop.load(Ordering::Relaxed) {
+                            std::hint::spin_loop();
+                        }
+                    });
+                }
+            }
+            Load::Command(cmd) => {
+                child = Some(
+                    std::process::Command::new("sh")
+                        .arg("-c")
+                        .arg(cmd)
+                        .spawn()
+                        .map_err(|e| Error::Command(format!("Failed to start load: {}", e)))?,
+                );
+            }
+            Load::External => {}
+        }
+
+        Ok(Self { stop, child })
+    }
+}
+
+impl Drop for LoadGuard {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(child) = &mut self.child {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+async fn measure(hw: &HardwareController, duty: u32, options: &Options) -> Result<Measurement> {
+    hw.set_fan_speed(duty)?;
+    let started = Instant::now();
+    let mut window = VecDeque::with_capacity(SETTLE_WINDOW);
+
+    loop {
+        tokio::time::sleep(SAMPLE_INTERVAL).await;
+        let temperature = hw.get_temperature()?;
+        if temperature > options.max_temperature {
+            return Err(Error::Ec(format!(
+                "Temperature {:.1}°C exceeds the {:.0}°C limit at duty {}%, aborting",
+                temperature, options.max_temperature, duty
+            )));
+        }
+        window.push_back(temperature);
+        if window.len() > SETTLE_WINDOW {
+            window.pop_front();
+        }
+
+        let settled = is_settled(&window);
+        if settled || started.elapsed() >= options.max_settle {
+            let temperature = window.iter().sum::<f64>() / window.len() as f64;
+            tracing::info!(
+                "duty {}%: {:.1}°C ({})",
+                duty,
+                temperature,
+                if settled { "settled" } else { "timed out" }
+            );
+            return Ok(Measurement {
+                duty,
+                temperature,
+                settled,
+            });
+        }
+    }
+}
+
+/// Steps through `options.duties` under load, recording equilibrium temperatures.
+///
+/// The fan is always handed back to EC automatic control afterwards, also when
+/// a sample exceeds `options.max_temperature` and the run fails.
+pub async fn characterize(hw: &HardwareController, options: &Options) -> Result<Characterization> {
+    let result = async {
+        let _load = LoadGuard::start(&options.load)?;
+        let mut measurements = Vec::with_capacity(options.duties.len());
+        for &duty in &options.duties {
+            measurements.push(measure(hw, duty, options).await?);
+        }
+        Ok::<_, Error>(measurements)
+    }
+    .await;
+
+    let restore = hw.enable_auto_fan();
+    let measurements = result?;
+    restore?;
+
+    let required_duty = required_duty(&measurements, options.target_temperature);
+    Ok(Characterization {
+        speed_curve: suggest_curve(required_duty, options.target_temperature),
+        measurements,
+        target_temperature: options.target_temperature,
+        required_duty,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockEc;
+
+    fn m(duty: u32, temperature: f64) -> Measurement {
+        Measurement {
+            duty,
+            temperature,
+            settled: true,
+        }
+    }
+
+    #[test]
+    fn test_required_duty_interpolates() {
+        let measurements = [m(0, 95.0), m(50, 80.0), m(100, 70.0)];
+        assert_eq!(required_duty(&measurements, 75.0), 75);
+    }
+
+    #[test]
+    fn test_required_duty_when_passive_is_enough() {
+        let measurements = [m(20, 60.0), m(100, 50.0)];
+        assert_eq!(required_duty(&measurements, 75.0), 20);
+    }
+
+    #[test]
+    fn test_required_duty_when_target_unreachable() {
+        let measurements = [m(50, 95.0), m(100, 90.0)];
+        assert_eq!(required_duty(&measurements, 75.0), 100);
+    }
+
+    #[test]
+    fn test_run_aborts_above_max_temperature() {
+        let ec = MockEc::new();
+        ec.set_temperatures(&[95]);
+        let hw = HardwareController::with_backend(Box::new(ec.clone()), None, "mock");
+        let options = Options {
+            duties: vec![0, 100],
+            target_temperature: 80.0,
+            max_temperature: 85.0,
+            max_settle: Duration::from_secs(60),
+            load: Load::External,
+        };
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let result = runtime.block_on(characterize(&hw, &options));
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("exceeds the 85°C limit"));
+        assert_eq!(ec.duty_writes(), vec![0]);
+        assert_eq!(ec.duty(), None);
+    }
+
+    #[test]
+    fn test_suggest_curve_is_monotonic() {
+        let curve = suggest_curve(40, 80.0);
+        assert!(curve.windows(2).all(|w| w[0].temp < w[1].temp));
+        assert!(curve.windows(2).all(|w| w[0].speed <= w[1].speed));
+        assert_eq!(curve[2].temp, 80);
+        assert_eq!(curve[2].speed, 40);
+    }
+}
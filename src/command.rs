@@ -0,0 +1,613 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::error::{Error, Result};
+
+/// A socket command with its arguments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Use {
+        strategy: String,
+    },
+    Toggle {
+        strategy: String,
+        other: String,
+    },
+    Cycle,
+    Reset,
+    Reload {
+        path: Option<PathBuf>,
+    },
+    Curve {
+        strategy: String,
+        points: String,
+    },
+    Tune {
+        strategy: String,
+        field: String,
+        value: u32,
+    },
+    Adjust {
+        bias: i32,
+    },
+    /// `battery-sensor exclude` or `battery-sensor include`.
+    BatterySensor {
+        exclude: bool,
+    },
+    Pause,
+    Resume,
+    Print(Selection),
+    History {
+        seconds: Option<u64>,
+    },
+    ClearHistory,
+    Selftest,
+    Jobs(JobsAction),
+    Profile(ProfileAction),
+    Version,
+    Capabilities,
+    Shutdown,
+    RestartAuto,
+}
+
+/// What `print` reports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Selection {
+    All,
+    Active,
+    Current,
+    List {
+        detailed: bool,
+    },
+    Speed,
+    Platform,
+    Sensors,
+    Rpm,
+    Errors,
+    Stats,
+    /// A strategy's definition; the current one without a name.
+    Strategy(Option<String>),
+    /// The configuration in effect, profile and all.
+    Config,
+    /// A strategy's curve with composed strategies folded in; the current one
+    /// without a name.
+    Curve(Option<String>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobsAction {
+    List,
+    Status(u32),
+    Cancel(u32),
+    Start(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProfileAction {
+    List,
+    Use(String),
+    Auto,
+}
+
+impl Command {
+    /// The command's name on the wire, e.g. `restart-auto`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Use { .. } => "use",
+            Self::Toggle { .. } => "toggle",
+            Self::Cycle => "cycle",
+            Self::Reset => "reset",
+            Self::Reload { .. } => "reload",
+            Self::Curve { .. } => "curve",
+            Self::Tune { .. } => "tune",
+            Self::Adjust { .. } => "adjust",
+            Self::BatterySensor { .. } => "battery-sensor",
+            Self::Pause => "pause",
+            Self::Resume => "resume",
+            Self::Print(_) => "print",
+            Self::History { .. } => "history",
+            Self::ClearHistory => "clear-history",
+            Self::Selftest => "selftest",
+            Self::Jobs(_) => "jobs",
+            Self::Profile(_) => "profile",
+            Self::Version => "version",
+            Self::Capabilities => "capabilities",
+            Self::Shutdown => "shutdown",
+            Self::RestartAuto => "restart-auto",
+        }
+    }
+
+    /// Whether the command changes the daemon's state: refused on the query
+    /// socket and recorded in the audit log.
+    pub fn is_state_changing(&self) -> bool {
+        match self {
+            Self::Use { .. }
+            | Self::Toggle { .. }
+            | Self::Cycle
+            | Self::Reset
+            | Self::Reload { .. }
+            | Self::Curve { .. }
+            | Self::Tune { .. }
+            | Self::Adjust { .. }
+            | Self::BatterySensor { .. }
+            | Self::Pause
+            | Self::Resume
+            | Self::ClearHistory
+            | Self::Selftest
+            | Self::Shutdown
+            | Self::RestartAuto => true,
+            Self::Jobs(action) => matches!(action, JobsAction::Start(_) | JobsAction::Cancel(_)),
+            Self::Profile(action) => matches!(action, ProfileAction::Use(_) | ProfileAction::Auto),
+            Self::Print(_) | Self::History { .. } | Self::Version | Self::Capabilities => false,
+        }
+    }
+}
+
+impl Selection {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::All => "all",
+            Self::Active => "active",
+            Self::Current => "current",
+            Self::List { .. } => "list",
+            Self::Speed => "speed",
+            Self::Platform => "platform",
+            Self::Sensors => "sensors",
+            Self::Rpm => "rpm",
+            Self::Errors => "errors",
+            Self::Stats => "stats",
+            Self::Strategy(_) => "strategy",
+            Self::Config => "config",
+            Self::Curve(_) => "curve",
+        }
+    }
+}
+
+/// Writes the command in the form [`parse`] reads back, quoting arguments as needed.
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())?;
+        let mut arg = |value: &str| write!(f, " {}", quote(value));
+        match self {
+            Self::Use { strategy } => arg(strategy),
+            Self::Toggle { strategy, other } => arg(strategy).and_then(|_| arg(other)),
+            Self::Reload { path: Some(path) } => arg(&path.to_string_lossy()),
+            Self::Curve { strategy, points } => arg(strategy).and_then(|_| arg(points)),
+            Self::Tune {
+                strategy,
+                field,
+                value,
+            } => arg(strategy)
+                .and_then(|_| arg(field))
+                .and_then(|_| arg(&value.to_string())),
+            Self::Adjust { bias } => arg(&bias.to_string()),
+            Self::BatterySensor { exclude } => arg(if *exclude { "exclude" } else { "include" }),
+            Self::Print(selection) => {
+                arg(selection.name())?;
+                match selection {
+                    Selection::List { detailed: true } => arg("detailed"),
+                    Selection::Strategy(Some(name)) | Selection::Curve(Some(name)) => arg(name),
+                    _ => Ok(()),
+                }
+            }
+            Self::History {
+                seconds: Some(seconds),
+            } => arg(&seconds.to_string()),
+            Self::Jobs(action) => match action {
+                JobsAction::List => arg("list"),
+                JobsAction::Status(id) => arg("status").and_then(|_| arg(&id.to_string())),
+                JobsAction::Cancel(id) => arg("cancel").and_then(|_| arg(&id.to_string())),
+                JobsAction::Start(kind) => arg("start").and_then(|_| arg(kind)),
+            },
+            Self::Profile(action) => match action {
+                ProfileAction::List => arg("list"),
+                ProfileAction::Use(name) => arg("use").and_then(|_| arg(name)),
+                ProfileAction::Auto => arg("auto"),
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+/// `value` as one word of a command line, quoted if it would otherwise be
+/// split, unescaped or taken for an option.
+pub fn quote(value: &str) -> Cow<'_, str> {
+    let plain = !value.is_empty()
+        && !value.starts_with("--")
+        && !value
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '"' | '\'' | '\\'));
+    if plain {
+        return Cow::Borrowed(value);
+    }
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        if matches!(c, '"' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    Cow::Owned(quoted)
+}
+
+/// A word of a command line, and whether any of it was quoted or escaped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Word {
+    text: String,
+    literal: bool,
+}
+
+/// Splits `line` into words, see [`parse`] for the quoting rules.
+fn split_words(line: &str) -> Result<Vec<Word>> {
+    let mut words = Vec::new();
+    let mut current: Option<Word> = None;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            words.extend(current.take());
+            continue;
+        }
+        let word = current.get_or_insert_with(|| Word {
+            text: String::new(),
+            literal: false,
+        });
+        match c {
+            '\'' => {
+                word.literal = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.text.push(c),
+                        None => return Err(Error::Command("Unterminated quote".into())),
+                    }
+                }
+            }
+            '"' => {
+                word.literal = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\')) => word.text.push(c),
+                            Some(c) => {
+                                word.text.push('\\');
+                                word.text.push(c);
+                            }
+                            None => return Err(Error::Command("Unterminated quote".into())),
+                        },
+                        Some(c) => word.text.push(c),
+                        None => return Err(Error::Command("Unterminated quote".into())),
+                    }
+                }
+            }
+            '\\' => {
+                word.literal = true;
+                match chars.next() {
+                    Some(c) => word.text.push(c),
+                    None => return Err(Error::Command("Trailing backslash".into())),
+                }
+            }
+            c => word.text.push(c),
+        }
+    }
+    words.extend(current);
+    Ok(words)
+}
+
+/// The positional arguments of a command, consumed in order.
+struct Arguments {
+    words: std::vec::IntoIter<String>,
+}
+
+impl Arguments {
+    fn next(&mut self) -> Option<String> {
+        self.words.next()
+    }
+
+    fn required(&mut self, usage: &str) -> Result<String> {
+        self.next()
+            .ok_or_else(|| Error::Command(format!("Usage: {}", usage)))
+    }
+
+    fn number<T: std::str::FromStr>(value: &str, what: &str) -> Result<T> {
+        value
+            .parse()
+            .map_err(|_| Error::Command(format!("Invalid {}: {}", what, value)))
+    }
+
+    /// Fails if any argument is left over, rather than ignoring it.
+    fn finish<T>(mut self, command: T) -> Result<T> {
+        match self.next() {
+            Some(extra) => Err(Error::Command(format!("Unexpected argument: {}", extra))),
+            None => Ok(command),
+        }
+    }
+}
+
+/// Parses a command line sent over the socket into a typed [`Command`].
+///
+/// The line is split into words like a shell would: whitespace separates
+/// words, single quotes keep everything literally, double quotes allow `\"` and
+/// `\\`, and a backslash outside quotes escapes the next character. Unquoted
+/// words starting with `--` are options; the only one accepted is
+/// `--output-format`, which older clients append and which is ignored.
+pub fn parse(line: &str) -> Result<Command> {
+    let mut positional = Vec::new();
+    let mut options_done = false;
+    for word in split_words(line)? {
+        if options_done || word.literal || !word.text.starts_with("--") {
+            positional.push(word.text);
+        } else if word.text == "--" {
+            options_done = true;
+        } else if !word.text.starts_with("--output-format=") {
+            return Err(Error::Command(format!("Unknown option: {}", word.text)));
+        }
+    }
+
+    let mut args = Arguments {
+        words: positional.into_iter(),
+    };
+    let Some(name) = args.next() else {
+        return Err(Error::Command("Empty command".into()));
+    };
+
+    let command = match name.as_str() {
+        "use" => Command::Use {
+            strategy: args.required("use <strategy>")?,
+        },
+        "toggle" => {
+            let usage = "toggle <strategy> <strategy>";
+            Command::Toggle {
+                strategy: args.required(usage)?,
+                other: args.required(usage)?,
+            }
+        }
+        "cycle" => Command::Cycle,
+        "reset" => Command::Reset,
+        "reload" => Command::Reload {
+            path: args.next().map(PathBuf::from),
+        },
+        "curve" => {
+            let usage = "curve <strategy> <temp:speed,...>";
+            Command::Curve {
+                strategy: args.required(usage)?,
+                points: args.required(usage)?,
+            }
+        }
+        "tune" => {
+            let usage = "tune <strategy> <field> <value>";
+            let strategy = args.required(usage)?;
+            let field = args.required(usage)?;
+            let value = args.required(usage)?;
+            Command::Tune {
+                strategy,
+                field,
+                value: Arguments::number(&value, "value")?,
+            }
+        }
+        "adjust" => {
+            let bias = args.required("adjust <+/-percent>")?;
+            Command::Adjust {
+                bias: Arguments::number(&bias, "speed bias")?,
+            }
+        }
+        "battery-sensor" => Command::BatterySensor {
+            exclude: match args.required("battery-sensor exclude|include")?.as_str() {
+                "exclude" => true,
+                "include" => false,
+                other => {
+                    return Err(Error::Command(format!(
+                        "Unknown battery-sensor action: {}",
+                        other
+                    )))
+                }
+            },
+        },
+        "pause" => Command::Pause,
+        "resume" => Command::Resume,
+        "print" => Command::Print(parse_selection(&mut args)?),
+        "history" => Command::History {
+            seconds: args
+                .next()
+                .map(|s| Arguments::number(&s, "number of seconds"))
+                .transpose()?,
+        },
+        "clear-history" => Command::ClearHistory,
+        "selftest" => Command::Selftest,
+        "jobs" => Command::Jobs(parse_jobs_action(&mut args)?),
+        "profile" => Command::Profile(match args.next().as_deref() {
+            None | Some("list") => ProfileAction::List,
+            Some("use") => ProfileAction::Use(args.required("profile use <profile>")?),
+            Some("auto") => ProfileAction::Auto,
+            Some(other) => {
+                return Err(Error::Command(format!("Unknown profile action: {}", other)))
+            }
+        }),
+        "version" => Command::Version,
+        "capabilities" => Command::Capabilities,
+        "shutdown" => Command::Shutdown,
+        "restart-auto" => Command::RestartAuto,
+        other => return Err(Error::Command(format!("Unknown command: {}", other))),
+    };
+    args.finish(command)
+}
+
+fn parse_selection(args: &mut Arguments) -> Result<Selection> {
+    let Some(selection) = args.next() else {
+        return Ok(Selection::All);
+    };
+    Ok(match selection.as_str() {
+        "all" => Selection::All,
+        "active" => Selection::Active,
+        "current" => Selection::Current,
+        "list" => match args.next().as_deref() {
+            None => Selection::List { detailed: false },
+            Some("detailed") => Selection::List { detailed: true },
+            Some(other) => return Err(Error::Command(format!("Unexpected argument: {}", other))),
+        },
+        "speed" => Selection::Speed,
+        "platform" => Selection::Platform,
+        "sensors" => Selection::Sensors,
+        "rpm" => Selection::Rpm,
+        "errors" => Selection::Errors,
+        "stats" => Selection::Stats,
+        "strategy" => Selection::Strategy(args.next()),
+        "config" => Selection::Config,
+        "curve" => Selection::Curve(args.next()),
+        other => {
+            return Err(Error::Command(format!(
+                "Unknown print selection: {}",
+                other
+            )))
+        }
+    })
+}
+
+fn parse_jobs_action(args: &mut Arguments) -> Result<JobsAction> {
+    let id = |args: &mut Arguments, action: &str| -> Result<u32> {
+        let id = args.required(&format!("jobs {} <id>", action))?;
+        Arguments::number(&id, "job ID")
+    };
+    let action = match args.next().as_deref() {
+        None | Some("list") => JobsAction::List,
+        Some("status") => JobsAction::Status(id(args, "status")?),
+        Some("cancel") => JobsAction::Cancel(id(args, "cancel")?),
+        Some("start") => JobsAction::Start(args.required("jobs start <kind>")?),
+        Some(other) => return Err(Error::Command(format!("Unknown jobs action: {}", other))),
+    };
+    Ok(action)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_parses_commands_with_arguments() {
+        assert_eq!(
+            parse("use lazy").unwrap(),
+            Command::Use {
+                strategy: "lazy".into()
+            }
+        );
+        assert_eq!(
+            parse("print list detailed").unwrap(),
+            Command::Print(Selection::List { detailed: true })
+        );
+        assert_eq!(parse("print").unwrap(), Command::Print(Selection::All));
+        assert_eq!(
+            parse("jobs status 3").unwrap(),
+            Command::Jobs(JobsAction::Status(3))
+        );
+        assert_eq!(parse("adjust -5").unwrap(), Command::Adjust { bias: -5 });
+        assert_eq!(
+            parse("battery-sensor include").unwrap(),
+            Command::BatterySensor { exclude: false }
+        );
+        assert_eq!(
+            parse("print all --output-format=JSON").unwrap(),
+            Command::Print(Selection::All)
+        );
+    }
+
+    #[test]
+    fn test_quoted_arguments_keep_spaces() {
+        assert_eq!(
+            parse(r#"toggle "quiet desk" 'loud \ fan'"#).unwrap(),
+            Command::Toggle {
+                strategy: "quiet desk".into(),
+                other: r"loud \ fan".into()
+            }
+        );
+        assert_eq!(
+            parse(r"use my\ strategy").unwrap(),
+            Command::Use {
+                strategy: "my strategy".into()
+            }
+        );
+        assert_eq!(
+            parse(r#"use "--for=30m""#).unwrap(),
+            Command::Use {
+                strategy: "--for=30m".into()
+            }
+        );
+    }
+
+    #[test]
+    fn test_rejects_what_it_used_to_drop() {
+        let reason = |line: &str| parse(line).unwrap_err().to_string();
+        assert_eq!(
+            reason("use lazy --for=30m"),
+            "Invalid command: Unknown option: --for=30m"
+        );
+        assert_eq!(
+            reason("reset now"),
+            "Invalid command: Unexpected argument: now"
+        );
+        assert_eq!(reason("use \"lazy"), "Invalid command: Unterminated quote");
+        assert_eq!(reason("  "), "Invalid command: Empty command");
+        assert_eq!(reason("use"), "Invalid command: Usage: use <strategy>");
+    }
+
+    fn word() -> impl Strategy<Value = String> {
+        prop_oneof![
+            "[a-z0-9:,.-]{1,12}",
+            any::<String>(),
+            Just("--for=30m".to_string()),
+            Just(String::new()),
+        ]
+    }
+
+    fn command() -> impl Strategy<Value = Command> {
+        prop_oneof![
+            word().prop_map(|strategy| Command::Use { strategy }),
+            (word(), word()).prop_map(|(strategy, other)| Command::Toggle { strategy, other }),
+            proptest::option::of(word()).prop_map(|path| Command::Reload {
+                path: path.map(PathBuf::from)
+            }),
+            (word(), word(), any::<u32>()).prop_map(|(strategy, field, value)| Command::Tune {
+                strategy,
+                field,
+                value
+            }),
+            any::<i32>().prop_map(|bias| Command::Adjust { bias }),
+            any::<bool>().prop_map(|exclude| Command::BatterySensor { exclude }),
+            proptest::option::of(word()).prop_map(|name| Command::Print(Selection::Strategy(name))),
+            proptest::option::of(word()).prop_map(|name| Command::Print(Selection::Curve(name))),
+            any::<bool>().prop_map(|detailed| Command::Print(Selection::List { detailed })),
+            proptest::option::of(any::<u64>()).prop_map(|seconds| Command::History { seconds }),
+            any::<u32>().prop_map(|id| Command::Jobs(JobsAction::Cancel(id))),
+            word().prop_map(|name| Command::Profile(ProfileAction::Use(name))),
+            Just(Command::ClearHistory),
+            Just(Command::RestartAuto),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn prop_commands_survive_a_round_trip(command in command()) {
+            prop_assert_eq!(parse(&command.to_string()).unwrap(), command);
+        }
+
+        #[test]
+        fn prop_quoted_words_split_back(words in proptest::collection::vec(word(), 0..6)) {
+            let line: Vec<Cow<str>> = words.iter().map(|w| quote(w)).collect();
+            let split: Vec<String> = split_words(&line.join(" "))
+                .unwrap()
+                .into_iter()
+                .map(|w| w.text)
+                .collect();
+            prop_assert_eq!(split, words);
+        }
+
+        #[test]
+        fn prop_parse_never_panics(line in any::<String>()) {
+            let _ = parse(&line);
+        }
+    }
+}
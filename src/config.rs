@@ -1,49 +1,1173 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use serde::{Deserialize, Serialize};
+use chrono::NaiveTime;
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize};
 
 use crate::error::{Error, Result};
+use crate::hardware::EcLayout;
 
 pub const DEFAULT_CONFIG_PATH: &str = "/etc/fw-fanctrl/config.json";
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Config {
-    #[serde(rename = "defaultStrategy")]
+    #[serde(rename = "defaultStrategy", alias = "default_strategy")]
     pub default_strategy: String,
-    #[serde(rename = "strategyOnDischarging")]
+    /// Empty or omitted means the default strategy is used on battery too.
+    #[serde(
+        rename = "strategyOnDischarging",
+        alias = "strategy_on_discharging",
+        default
+    )]
     pub strategy_on_discharging: String,
+    /// Used on AC while the battery is charging, which heats the area around
+    /// the battery sensor; empty or omitted keeps the default strategy.
+    #[serde(
+        rename = "strategyWhileCharging",
+        alias = "strategy_while_charging",
+        default,
+        skip_serializing_if = "String::is_empty"
+    )]
+    pub strategy_while_charging: String,
     pub strategies: HashMap<String, Strategy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub telemetry: Option<TelemetryConfig>,
+    #[serde(
+        rename = "quietHours",
+        alias = "quiet_hours",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub quiet_hours: Option<QuietHours>,
+    /// Order used by `cycle`; empty means all strategies in name order.
+    #[serde(
+        rename = "strategyCycle",
+        alias = "strategy_cycle",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub strategy_cycle: Vec<String>,
+    /// EC memory map offsets, for firmware that differs from the built-in layout.
+    #[serde(
+        rename = "ecLayout",
+        alias = "ec_layout",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub ec_layout: Option<EcLayout>,
+    #[serde(
+        rename = "gpuBoost",
+        alias = "gpu_boost",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub gpu_boost: Option<GpuBoost>,
+    #[serde(
+        rename = "tripPoints",
+        alias = "trip_points",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub trip_points: Option<TripPoints>,
+    #[serde(
+        rename = "scheduledSelfTest",
+        alias = "scheduled_self_test",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub scheduled_self_test: Option<ScheduledSelfTest>,
+    /// Named sets of config values that replace the ones above while active.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,
+    /// How long a new power source must last before the AC/battery strategy follows it.
+    #[serde(
+        rename = "powerSwitchDelaySeconds",
+        alias = "power_switch_delay_seconds",
+        default,
+        skip_serializing_if = "is_default"
+    )]
+    pub power_switch_delay_seconds: u64,
+    /// Single readings this many degrees away from the recent median are
+    /// discarded as EC glitches; 0 disables the filter.
+    #[serde(
+        rename = "spikeFilterCelsius",
+        alias = "spike_filter_celsius",
+        default,
+        skip_serializing_if = "is_default"
+    )]
+    pub spike_filter_celsius: u32,
+    /// Battery charge (percent) from which the discharging strategy engages;
+    /// above it the default strategy stays. 0 switches right when unplugged.
+    #[serde(
+        rename = "dischargingStrategyBatteryThreshold",
+        alias = "discharging_strategy_battery_threshold",
+        default,
+        skip_serializing_if = "is_default"
+    )]
+    pub discharging_strategy_battery_threshold: u32,
+    /// What to assume about the power source when it can't be read.
+    #[serde(
+        rename = "acDetectionFallback",
+        alias = "ac_detection_fallback",
+        default,
+        skip_serializing_if = "is_default"
+    )]
+    pub ac_detection_fallback: AcDetectionFallback,
+    /// What drives the fan until the moving-average window first fills.
+    #[serde(
+        rename = "startupBehavior",
+        alias = "startup_behavior",
+        default,
+        skip_serializing_if = "is_default"
+    )]
+    pub startup_behavior: StartupBehavior,
+    /// Duty held during warm-up with `startupBehavior` `fixedSpeed`.
+    #[serde(
+        rename = "startupSpeed",
+        alias = "startup_speed",
+        default,
+        skip_serializing_if = "is_default"
+    )]
+    pub startup_speed: u32,
+    /// Leaves the platform's battery sensor out of the control temperature, or
+    /// keeps it in; unset leaves it to `--no-battery-sensors`.
+    #[serde(
+        rename = "excludeBatterySensor",
+        alias = "exclude_battery_sensor",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub exclude_battery_sensor: Option<bool>,
+    /// Sensors left out of the control temperature, in addition to the battery
+    /// sensor when it is excluded.
+    #[serde(
+        rename = "excludeSensors",
+        alias = "exclude_sensors",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub exclude_sensors: Vec<SensorSelector>,
+    /// Temperature sources besides the EC and hwmon, e.g. external commands.
+    #[serde(
+        rename = "sensorSources",
+        alias = "sensor_sources",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub sensor_sources: Vec<SensorSource>,
+    /// When set, only these sensors feed the control temperature.
+    #[serde(
+        rename = "includeSensors",
+        alias = "include_sensors",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub include_sensors: Vec<SensorSelector>,
+    /// Temperatures for which `print stats` counts the time spent above them.
+    #[serde(
+        rename = "temperatureBands",
+        alias = "temperature_bands",
+        default = "default_temperature_bands",
+        skip_serializing_if = "is_default_temperature_bands"
+    )]
+    pub temperature_bands: Vec<u32>,
+    /// Conditions that run a command when they have held for a while, and again
+    /// when they end.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alerts: Vec<Alert>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A temperature sensor, by index or by a label shown by `print sensors`:
+/// `ec3`, the platform's name for it like `battery`, or a hwmon label.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum SensorSelector {
+    Index(usize),
+    Label(String),
+}
+
+impl SensorSelector {
+    /// Whether this selects sensor `index`, known by any of `labels`.
+    pub fn matches(&self, index: usize, labels: &[&str]) -> bool {
+        match self {
+            Self::Index(i) => *i == index,
+            Self::Label(l) => labels.iter().any(|label| l.eq_ignore_ascii_case(label)),
+        }
+    }
+}
+
+/// A temperature source outside the EC and hwmon, e.g. an eGPU or a USB thermometer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum SensorSource {
+    /// Runs `cmd` every `interval` seconds and reads a temperature in °C from its output.
+    Exec {
+        /// Label in `print sensors` and sensor selectors; defaults to the file name of `cmd`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        cmd: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        args: Vec<String>,
+        #[serde(default = "default_exec_interval")]
+        interval: u64,
+    },
+}
+
+fn default_exec_interval() -> u64 {
+    5
+}
+
+impl SensorSource {
+    pub fn name(&self) -> String {
+        match self {
+            Self::Exec { name, cmd, .. } => name.clone().unwrap_or_else(|| {
+                std::path::Path::new(cmd)
+                    .file_name()
+                    .map_or_else(|| cmd.clone(), |n| n.to_string_lossy().into_owned())
+            }),
+        }
+    }
+
+    /// Seconds between readings.
+    pub fn interval(&self) -> u64 {
+        match self {
+            Self::Exec { interval, .. } => *interval,
+        }
+    }
+}
+
+/// Which sensors feed the control temperature.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SensorSelection {
+    /// Only these sensors, when not empty.
+    pub include: Vec<SensorSelector>,
+    pub exclude: Vec<SensorSelector>,
+}
+
+impl SensorSelection {
+    /// Whether sensor `index`, known by any of `labels`, is left out.
+    pub fn excludes(&self, index: usize, labels: &[&str]) -> bool {
+        let selected = |list: &[SensorSelector]| list.iter().any(|s| s.matches(index, labels));
+        (!self.include.is_empty() && !selected(&self.include)) || selected(&self.exclude)
+    }
+
+    /// Whether a sensor known only by `label`, e.g. a `sensorSources` entry, is left out.
+    pub fn excludes_label(&self, label: &str) -> bool {
+        let selected = |list: &[SensorSelector]| {
+            list.iter()
+                .any(|s| matches!(s, SensorSelector::Label(l) if l.eq_ignore_ascii_case(label)))
+        };
+        (!self.include.is_empty() && !selected(&self.include)) || selected(&self.exclude)
+    }
+}
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Strategy {
-    #[serde(rename = "fanSpeedUpdateFrequency")]
-    pub fan_speed_update_frequency: u32,
-    #[serde(rename = "movingAverageInterval")]
+    /// Human-readable summary shown by clients, e.g. as a tooltip.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Seconds between duty updates, however often temperatures are read.
+    /// Older configs name it `fanSpeedUpdateFrequency`, counted in one-second
+    /// ticks, which reads the same.
+    #[serde(
+        rename = "updateIntervalSeconds",
+        alias = "update_interval_seconds",
+        alias = "fanSpeedUpdateFrequency",
+        alias = "fan_speed_update_frequency",
+        default = "default_update_interval_seconds"
+    )]
+    pub update_interval_seconds: u32,
+    #[serde(
+        rename = "movingAverageInterval",
+        alias = "moving_average_interval",
+        default = "default_moving_average_interval"
+    )]
     pub moving_average_interval: u32,
-    #[serde(rename = "speedCurve")]
+    /// May be left out when `compose` names other strategies.
+    #[serde(
+        rename = "speedCurve",
+        alias = "speed_curve",
+        deserialize_with = "deserialize_curve",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    #[schemars(with = "Option<CurveForm>")]
     pub speed_curve: Vec<CurvePoint>,
+    /// Strategies whose curves also apply; the fan runs at the highest duty
+    /// any of them or this strategy's own curves ask for.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub compose: Vec<String>,
+    /// Degrees the effective temperature must fall below the point where the
+    /// current speed was set before the curves follow it down. Strategy changes,
+    /// `adjust`, quiet hours and the other caps and floors still apply right away.
+    #[serde(default)]
+    pub hysteresis: u32,
+    /// Seconds per percentage point the duty may drop while cooling down;
+    /// 0 follows the curve down immediately.
+    #[serde(rename = "speedDecaySeconds", alias = "speed_decay_seconds", default)]
+    pub speed_decay_seconds: u32,
+    /// Distinct speed changes allowed per minute; 0 means unlimited.
+    #[serde(
+        rename = "maxSpeedChangesPerMinute",
+        alias = "max_speed_changes_per_minute",
+        default
+    )]
+    pub max_speed_changes_per_minute: u32,
+    /// Above this effective temperature the change budget is ignored.
+    #[serde(
+        rename = "safetyTemp",
+        alias = "safety_temp",
+        default = "default_safety_temp"
+    )]
+    pub safety_temp: u32,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub smoothing: Smoothing,
+    #[serde(
+        rename = "sensorAggregation",
+        alias = "sensor_aggregation",
+        default,
+        skip_serializing_if = "is_default"
+    )]
+    pub sensor_aggregation: SensorAggregation,
+    /// Extra curves, each driven by a single sensor; the fan runs at the highest
+    /// duty any curve asks for.
+    #[serde(
+        rename = "sensorCurves",
+        alias = "sensor_curves",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub sensor_curves: Vec<SensorCurve>,
+    /// Battery charge rate, as a fraction of the battery capacity per hour,
+    /// the EC is held to while this strategy is in effect; charging heats the
+    /// machine on AC. Lifted again, to full rate rather than any earlier limit,
+    /// when another strategy takes over.
+    #[serde(
+        rename = "chargeRateLimit",
+        alias = "charge_rate_limit",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub charge_rate_limit: Option<f32>,
+    /// Lowers the duty on battery while it drains fast and the machine is
+    /// only moderately warm.
+    #[serde(
+        rename = "dischargeSaver",
+        alias = "discharge_saver",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub discharge_saver: Option<DischargeSaver>,
+}
+
+/// Trades a few degrees for battery runtime: while the battery discharges at
+/// `minWatts` or more and the effective temperature is below `maxTemp`, the
+/// duty is lowered by `speedReduction` points.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct DischargeSaver {
+    #[serde(
+        rename = "minWatts",
+        alias = "min_watts",
+        default = "default_discharge_min_watts"
+    )]
+    pub min_watts: f64,
+    #[serde(
+        rename = "maxTemp",
+        alias = "max_temp",
+        default = "default_discharge_max_temp"
+    )]
+    pub max_temp: u32,
+    #[serde(
+        rename = "speedReduction",
+        alias = "speed_reduction",
+        default = "default_discharge_speed_reduction"
+    )]
+    pub speed_reduction: u32,
+}
+
+fn default_discharge_min_watts() -> f64 {
+    15.0
+}
+
+fn default_discharge_max_temp() -> u32 {
+    70
+}
+
+fn default_discharge_speed_reduction() -> u32 {
+    10
 }
 
+/// A strategy on its own, as `strategy export` writes it and `strategy import`
+/// reads it: the same shape as the `print strategy` reply.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrategyExport {
+    pub name: String,
+    pub definition: Strategy,
+}
+
+impl StrategyExport {
+    /// Parses an exported strategy, or a bare definition as shared in forum
+    /// posts when `name` is given. `name` also renames an exported one.
+    /// Unknown keys are an error, since they are most likely typos.
+    pub fn parse(content: &str, name: Option<&str>) -> Result<Self> {
+        let invalid = |e: &dyn std::fmt::Display| Error::Config(format!("Invalid strategy: {}", e));
+        let value: serde_json::Value = serde_json::from_str(content).map_err(|e| invalid(&e))?;
+        let (exported_name, definition) = match value {
+            serde_json::Value::Object(mut export) if export.contains_key("definition") => (
+                export
+                    .get("name")
+                    .and_then(|n| n.as_str())
+                    .map(str::to_string),
+                export.remove("definition").unwrap_or_default(),
+            ),
+            definition => (None, definition),
+        };
+        let name = name.map(str::to_string).or(exported_name).ok_or_else(|| {
+            Error::Config("The file doesn't name the strategy, use --name".into())
+        })?;
+
+        let mut unknown = Vec::new();
+        let definition: Strategy =
+            serde_ignored::deserialize(definition, |path| unknown.push(path.to_string()))
+                .map_err(|e| invalid(&e))?;
+        if !unknown.is_empty() {
+            unknown.sort();
+            return Err(Error::Config(format!(
+                "Unknown strategy keys: {}",
+                unknown.join(", ")
+            )));
+        }
+        Ok(Self { name, definition })
+    }
+}
+
+/// A curve bound to one sensor, e.g. an NVMe drive or the dGPU.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct SensorCurve {
+    pub name: String,
+    pub sensor: SensorSelector,
+    #[serde(
+        rename = "speedCurve",
+        alias = "speed_curve",
+        deserialize_with = "deserialize_curve"
+    )]
+    #[schemars(with = "CurveForm")]
+    pub speed_curve: Vec<CurvePoint>,
+}
+
+/// How the readings of several sensors collapse into one temperature.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum SensorAggregation {
+    #[default]
+    Max,
+    Mean,
+    /// 90th percentile (nearest rank).
+    P90,
+    /// Ignores the hottest sensor, e.g. one that is stuck high.
+    SecondHighest,
+}
+
+impl SensorAggregation {
+    /// The aggregate of `temps`, or `None` without readings.
+    pub fn apply(self, temps: &[f64]) -> Option<f64> {
+        self.apply_in_place(&mut temps.to_vec())
+    }
+
+    /// Like [`apply`](Self::apply), but sorts `temps` in place instead of a copy.
+    pub fn apply_in_place(self, temps: &mut [f64]) -> Option<f64> {
+        temps.sort_by(f64::total_cmp);
+        let sorted = &*temps;
+        let highest = *sorted.last()?;
+        Some(match self {
+            Self::Max => highest,
+            Self::Mean => sorted.iter().sum::<f64>() / sorted.len() as f64,
+            Self::P90 => sorted[(sorted.len() * 9).div_ceil(10) - 1],
+            Self::SecondHighest => sorted.iter().rev().nth(1).copied().unwrap_or(highest),
+        })
+    }
+}
+
+/// The power source assumed when reading it from the hardware fails.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum AcDetectionFallback {
+    AssumeAc,
+    AssumeBattery,
+    /// Keeps the last known source; battery if it was never read.
+    #[default]
+    HoldLast,
+}
+
+impl AcDetectionFallback {
+    /// The assumed AC state, or `None` to keep the last one.
+    pub fn assumed_on_ac(self) -> Option<bool> {
+        match self {
+            Self::AssumeAc => Some(true),
+            Self::AssumeBattery => Some(false),
+            Self::HoldLast => None,
+        }
+    }
+}
+
+/// How the daemon drives the fan while the moving-average window is still filling.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum StartupBehavior {
+    /// Averages whatever readings there are so far.
+    #[default]
+    Average,
+    /// Fills the window with the first reading.
+    SeedHistory,
+    /// Leaves the fan to the EC until the window is full.
+    EcAuto,
+    /// Holds `startupSpeed` until the window is full.
+    FixedSpeed,
+}
+
+/// How readings are smoothed into the effective temperature the curve is applied to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Smoothing {
+    /// Two parts `movingAverageInterval` average to one part current reading.
+    #[default]
+    MovingAverage,
+    /// Single-pole low-pass filter; readings take about `timeConstantSeconds`
+    /// to move the output 63% of the way.
+    LowPass {
+        #[serde(rename = "timeConstantSeconds", alias = "time_constant_seconds")]
+        time_constant_seconds: f64,
+    },
+}
+
+impl Strategy {
+    /// Numeric settings `tune` can change at runtime, by config key.
+    pub const TUNABLE: &'static [&'static str] = &[
+        "updateIntervalSeconds",
+        "movingAverageInterval",
+        "hysteresis",
+        "speedDecaySeconds",
+        "maxSpeedChangesPerMinute",
+        "safetyTemp",
+    ];
+
+    /// The setting behind a [`Strategy::TUNABLE`] key; `fanSpeedUpdateFrequency`
+    /// still names the update interval.
+    pub fn parameter_mut(&mut self, field: &str) -> Option<&mut u32> {
+        match field {
+            "updateIntervalSeconds" | "fanSpeedUpdateFrequency" => {
+                Some(&mut self.update_interval_seconds)
+            }
+            "movingAverageInterval" => Some(&mut self.moving_average_interval),
+            "hysteresis" => Some(&mut self.hysteresis),
+            "speedDecaySeconds" => Some(&mut self.speed_decay_seconds),
+            "maxSpeedChangesPerMinute" => Some(&mut self.max_speed_changes_per_minute),
+            "safetyTemp" => Some(&mut self.safety_temp),
+            _ => None,
+        }
+    }
+
+    pub fn set_parameter(&mut self, field: &str, value: u32) -> Result<()> {
+        if matches!(field, "updateIntervalSeconds" | "fanSpeedUpdateFrequency") && value == 0 {
+            return Err(Error::Config(
+                "updateIntervalSeconds must be greater than 0".into(),
+            ));
+        }
+        let parameter = self.parameter_mut(field).ok_or_else(|| {
+            Error::Config(format!(
+                "Unknown parameter '{}', expected one of: {}",
+                field,
+                Self::TUNABLE.join(", ")
+            ))
+        })?;
+        *parameter = value;
+        Ok(())
+    }
+}
+
+/// Changes between two configs, as reported by `reload`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigDiff {
+    pub strategies_added: Vec<String>,
+    pub strategies_removed: Vec<String>,
+    pub strategies_modified: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_strategy: Option<StrategyChange>,
+    /// Every changed setting, see [`Config::changes`].
+    pub changes: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StrategyChange {
+    pub from: String,
+    pub to: String,
+}
+
+impl ConfigDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+fn diff_values(
+    path: &str,
+    old: &serde_json::Value,
+    new: &serde_json::Value,
+    changes: &mut Vec<String>,
+) {
+    use serde_json::Value;
+
+    let (Value::Object(old), Value::Object(new)) = (old, new) else {
+        if old != new {
+            changes.push(format!("{}: {} -> {}", path, old, new));
+        }
+        return;
+    };
+    let mut keys: Vec<&String> = old.keys().chain(new.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    for key in keys {
+        let child = if path.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", path, key)
+        };
+        match (old.get(key), new.get(key)) {
+            (Some(old), Some(new)) => diff_values(&child, old, new, changes),
+            (None, Some(_)) => changes.push(format!("{}: added", child)),
+            (Some(_), None) => changes.push(format!("{}: removed", child)),
+            (None, None) => {}
+        }
+    }
+}
+
+fn default_update_interval_seconds() -> u32 {
+    5
+}
+
+fn default_moving_average_interval() -> u32 {
+    30
+}
+
+fn default_safety_temp() -> u32 {
+    85
+}
+
+fn default_temperature_bands() -> Vec<u32> {
+    vec![70, 80, 90]
+}
+
+fn is_default_temperature_bands(bands: &[u32]) -> bool {
+    bands == default_temperature_bands()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub struct CurvePoint {
     pub temp: u32,
     pub speed: u32,
 }
 
+/// The forms a `speedCurve` may be written in, for the JSON Schema.
+#[allow(dead_code)]
+#[derive(JsonSchema)]
+#[serde(untagged)]
+enum CurveForm {
+    /// `[{"temp": 40, "speed": 0}, ...]` or `[[40, 0], ...]`.
+    Points(Vec<CurvePointForm>),
+    /// `{"40": 0, "60": 30, ...}`, from temperature to speed.
+    Map(HashMap<String, u32>),
+}
+
+#[allow(dead_code)]
+#[derive(JsonSchema)]
+#[serde(untagged)]
+enum CurvePointForm {
+    Object(CurvePoint),
+    Pair(u32, u32),
+}
+
+/// Reads a `speedCurve` in any of the [`CurveForm`]s; a map is sorted by temperature.
+fn deserialize_curve<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Vec<CurvePoint>, D::Error> {
+    use serde::de::Error as _;
+    use serde_json::Value;
+
+    let speed = |value: Value| serde_json::from_value::<u32>(value).map_err(D::Error::custom);
+    match Value::deserialize(deserializer)? {
+        Value::Object(map) => {
+            let mut points = map
+                .into_iter()
+                .map(|(temp, value)| {
+                    let temp = temp.trim().parse().map_err(|_| {
+                        D::Error::custom(format!("invalid curve temperature '{}'", temp))
+                    })?;
+                    Ok(CurvePoint {
+                        temp,
+                        speed: speed(value)?,
+                    })
+                })
+                .collect::<std::result::Result<Vec<_>, D::Error>>()?;
+            points.sort_by_key(|point| point.temp);
+            Ok(points)
+        }
+        Value::Array(items) => items
+            .into_iter()
+            .map(|item| match item {
+                Value::Array(pair) => match <[Value; 2]>::try_from(pair) {
+                    Ok([temp, value]) => Ok(CurvePoint {
+                        temp: serde_json::from_value(temp).map_err(D::Error::custom)?,
+                        speed: speed(value)?,
+                    }),
+                    Err(_) => Err(D::Error::custom("curve pairs must be [temp, speed]")),
+                },
+                other => serde_json::from_value(other).map_err(D::Error::custom),
+            })
+            .collect(),
+        _ => Err(D::Error::custom(
+            "expected a list of curve points or a map from temperature to speed",
+        )),
+    }
+}
+
+/// A daily window (local time, `HH:MM`) during which the fan speed is capped,
+/// except at or above the strategy's `safetyTemp`.
+///
+/// `end` before `start` means the window spans midnight.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct QuietHours {
+    #[serde(with = "clock_time")]
+    #[schemars(with = "String")]
+    pub start: NaiveTime,
+    #[serde(with = "clock_time")]
+    #[schemars(with = "String")]
+    pub end: NaiveTime,
+    #[serde(rename = "maxSpeed", alias = "max_speed")]
+    pub max_speed: u32,
+    /// Strategy used instead of the AC/battery default while the window is active.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strategy: Option<String>,
+}
+
+/// `HH:MM` times, parsed once when the config is read.
+mod clock_time {
+    use chrono::NaiveTime;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(time: &NaiveTime, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&time.format("%H:%M"))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveTime, D::Error> {
+        use serde::de::Error as _;
+
+        let value = String::deserialize(deserializer)?;
+        NaiveTime::parse_from_str(&value, "%H:%M")
+            .map_err(|_| D::Error::custom(format!("Invalid time '{}', expected HH:MM", value)))
+    }
+}
+
+impl QuietHours {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        let (start, end) = (self.start, self.end);
+        if start <= end {
+            start <= time && time < end
+        } else {
+            time >= start || time < end
+        }
+    }
+}
+
+/// Duty floor applied while a discrete GPU stays busy, ahead of its temperature.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GpuBoost {
+    /// `gpu_busy_percent` at or above which a sample counts as busy.
+    #[serde(
+        rename = "busyPercent",
+        alias = "busy_percent",
+        default = "default_gpu_busy_percent"
+    )]
+    pub busy_percent: u32,
+    /// Consecutive busy samples (one per second) before the floor applies.
+    #[serde(
+        rename = "sustainSeconds",
+        alias = "sustain_seconds",
+        default = "default_gpu_sustain_seconds"
+    )]
+    pub sustain_seconds: u32,
+    #[serde(rename = "minSpeed", alias = "min_speed")]
+    pub min_speed: u32,
+}
+
+/// Duty floor while a kernel thermal zone nears a passive or critical trip point,
+/// so the fan helps the kernel's throttling instead of working against it.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TripPoints {
+    /// Degrees below a trip point at which the floor applies.
+    #[serde(
+        rename = "marginCelsius",
+        alias = "margin_celsius",
+        default = "default_trip_margin_celsius"
+    )]
+    pub margin_celsius: u32,
+    #[serde(rename = "minSpeed", alias = "min_speed")]
+    pub min_speed: u32,
+}
+
+fn default_trip_margin_celsius() -> u32 {
+    5
+}
+
+fn default_gpu_busy_percent() -> u32 {
+    80
+}
+
+fn default_gpu_sustain_seconds() -> u32 {
+    3
+}
+
+/// Brief fan check run on a schedule while the machine is cool.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ScheduledSelfTest {
+    #[serde(
+        rename = "intervalHours",
+        alias = "interval_hours",
+        default = "default_self_test_interval_hours"
+    )]
+    pub interval_hours: u64,
+    /// Duty held during the test.
+    #[serde(default = "default_self_test_duty")]
+    pub duty: u32,
+    /// RPM the fan must reach at `duty` for the test to pass.
+    #[serde(
+        rename = "minRpm",
+        alias = "min_rpm",
+        default = "default_self_test_min_rpm"
+    )]
+    pub min_rpm: u16,
+    /// The test only starts while the average temperature is below this.
+    #[serde(
+        rename = "maxTemp",
+        alias = "max_temp",
+        default = "default_self_test_max_temp"
+    )]
+    pub max_temp: u32,
+    /// Command and arguments run when the test fails.
+    #[serde(
+        rename = "onFailure",
+        alias = "on_failure",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub on_failure: Vec<String>,
+}
+
+/// A condition on the temperature or duty, e.g. `effectiveTemperature > 85`,
+/// that trips once it has held for `forSeconds` and clears when it stops holding.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Alert {
+    /// Shown in `print`, logs and metrics, and passed to the commands.
+    pub name: String,
+    pub metric: AlertMetric,
+    pub op: AlertOp,
+    pub value: f64,
+    /// How long the condition must hold before the alert trips.
+    #[serde(rename = "forSeconds", alias = "for_seconds", default)]
+    pub for_seconds: u64,
+    /// Command and arguments run when the alert trips.
+    #[serde(
+        rename = "onTrip",
+        alias = "on_trip",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub on_trip: Vec<String>,
+    /// Command and arguments run when a tripped alert clears.
+    #[serde(
+        rename = "onClear",
+        alias = "on_clear",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub on_clear: Vec<String>,
+}
+
+impl Alert {
+    /// Whether `reading` of the alert's metric meets the condition.
+    pub fn holds(&self, reading: f64) -> bool {
+        match self.op {
+            AlertOp::Above => reading > self.value,
+            AlertOp::AtLeast => reading >= self.value,
+            AlertOp::Below => reading < self.value,
+            AlertOp::AtMost => reading <= self.value,
+            AlertOp::Equal => reading == self.value,
+        }
+    }
+}
+
+/// The reading an [`Alert`] watches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum AlertMetric {
+    /// The control temperature, before smoothing.
+    Temperature,
+    /// The smoothed temperature the curve sees.
+    #[serde(alias = "effective_temperature")]
+    EffectiveTemperature,
+    /// The commanded fan duty in percent.
+    Duty,
+}
+
+/// How an [`Alert`] compares its metric with `value`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum AlertOp {
+    #[serde(rename = ">")]
+    Above,
+    #[serde(rename = ">=")]
+    AtLeast,
+    #[serde(rename = "<")]
+    Below,
+    #[serde(rename = "<=")]
+    AtMost,
+    #[serde(rename = "=")]
+    Equal,
+}
+
+/// Config values that replace the top-level ones while the profile is active,
+/// e.g. a whole different set of `strategies` with its `defaultStrategy`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Profile {
+    /// When the profile activates by itself; without it only `profile use` selects it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<ProfileTrigger>,
+    /// The replaced top-level keys, spelled as in the config.
+    #[serde(flatten)]
+    pub values: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Conditions under which a profile activates; all that are set must hold.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ProfileTrigger {
+    /// A connected USB device as `vendor:product`, as listed by `lsusb`, e.g. a dock.
+    #[serde(
+        rename = "usbDevice",
+        alias = "usb_device",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub usb_device: Option<String>,
+    /// The power-profiles-daemon profile: `power-saver`, `balanced` or `performance`.
+    #[serde(
+        rename = "powerProfile",
+        alias = "power_profile",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub power_profile: Option<String>,
+}
+
+/// What profile triggers are matched against.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProfileEnvironment {
+    /// `vendor:product` of each connected USB device, in lowercase hex.
+    pub usb_devices: Vec<String>,
+    pub power_profile: Option<String>,
+}
+
+impl ProfileTrigger {
+    pub fn matches(&self, env: &ProfileEnvironment) -> bool {
+        let usb = self.usb_device.as_ref().map(|device| {
+            env.usb_devices
+                .iter()
+                .any(|connected| connected.eq_ignore_ascii_case(device))
+        });
+        let power = self
+            .power_profile
+            .as_ref()
+            .map(|profile| env.power_profile.as_ref() == Some(profile));
+        match (usb, power) {
+            (None, None) => false,
+            (usb, power) => usb.unwrap_or(true) && power.unwrap_or(true),
+        }
+    }
+}
+
+fn default_self_test_interval_hours() -> u64 {
+    168
+}
+
+fn default_self_test_duty() -> u32 {
+    30
+}
+
+fn default_self_test_min_rpm() -> u16 {
+    500
+}
+
+fn default_self_test_max_temp() -> u32 {
+    55
+}
+
+fn default_telemetry_interval() -> u64 {
+    10
+}
+
+fn default_statsd_prefix() -> String {
+    "fw_fanctrl".into()
+}
+
+/// Push-based telemetry, sent every `intervalSeconds` to each configured sink.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TelemetryConfig {
+    #[serde(
+        rename = "intervalSeconds",
+        alias = "interval_seconds",
+        default = "default_telemetry_interval"
+    )]
+    pub interval_seconds: u64,
+    pub sinks: Vec<TelemetrySinkConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type")]
+pub enum TelemetrySinkConfig {
+    /// InfluxDB line protocol over UDP, e.g. `"address": "127.0.0.1:8089"`.
+    #[serde(rename = "influxdb-udp")]
+    InfluxDbUdp { address: String },
+    /// InfluxDB line protocol POSTed to a plain `http://` write endpoint.
+    #[serde(rename = "influxdb-http")]
+    InfluxDbHttp {
+        url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        token: Option<String>,
+    },
+    /// statsd gauges over UDP.
+    #[serde(rename = "statsd")]
+    Statsd {
+        address: String,
+        #[serde(default = "default_statsd_prefix")]
+        prefix: String,
+    },
+}
+
 impl Config {
+    /// JSON Schema of the config file, for editors and tools that generate configs.
+    pub fn json_schema() -> serde_json::Value {
+        serde_json::to_value(schemars::schema_for!(Config)).unwrap_or_default()
+    }
+
+    /// The settings that differ in `new`, one `path: old -> new` line per dotted
+    /// path; whole strategies and lists are reported as added, removed or replaced.
+    pub fn changes(&self, new: &Config) -> Vec<String> {
+        let mut changes = Vec::new();
+        diff_values(
+            "",
+            &serde_json::to_value(self).unwrap_or_default(),
+            &serde_json::to_value(new).unwrap_or_default(),
+            &mut changes,
+        );
+        changes
+    }
+
+    /// What changes when `new` replaces this config, e.g. on `reload`.
+    pub fn diff(&self, new: &Config) -> ConfigDiff {
+        let mut diff = ConfigDiff {
+            changes: self.changes(new),
+            ..ConfigDiff::default()
+        };
+        for (name, strategy) in &new.strategies {
+            match self.strategies.get(name) {
+                None => diff.strategies_added.push(name.clone()),
+                Some(old)
+                    if serde_json::to_value(old).ok() != serde_json::to_value(strategy).ok() =>
+                {
+                    diff.strategies_modified.push(name.clone())
+                }
+                Some(_) => {}
+            }
+        }
+        diff.strategies_removed = self
+            .strategies
+            .keys()
+            .filter(|name| !new.strategies.contains_key(*name))
+            .cloned()
+            .collect();
+        diff.strategies_added.sort();
+        diff.strategies_removed.sort();
+        diff.strategies_modified.sort();
+        if self.default_strategy != new.default_strategy {
+            diff.default_strategy = Some(StrategyChange {
+                from: self.default_strategy.clone(),
+                to: new.default_strategy.clone(),
+            });
+        }
+        diff
+    }
+
+    /// Loads and validates a config, logging a warning for each unknown key.
     pub fn load(path: &PathBuf) -> Result<Self> {
+        let (config, unknown) = Self::load_with_unknown_keys(path)?;
+        for key in &unknown {
+            tracing::warn!("Ignoring unknown config key '{}'", key);
+        }
+        Ok(config)
+    }
+
+    /// Like [`Config::load`], but unknown keys are an error.
+    pub fn load_strict(path: &PathBuf) -> Result<Self> {
+        let (config, unknown) = Self::load_with_unknown_keys(path)?;
+        if !unknown.is_empty() {
+            return Err(Error::Config(format!(
+                "Unknown config keys: {}",
+                unknown.join(", ")
+            )));
+        }
+        Ok(config)
+    }
+
+    /// Loads and validates a config, also returning the dotted paths of keys
+    /// that don't correspond to any setting (typically typos like `speedcurve`).
+    pub fn load_with_unknown_keys(path: &PathBuf) -> Result<(Self, Vec<String>)> {
         let content = fs::read_to_string(path)
             .map_err(|e| Error::Config(format!("Failed to read config file: {}", e)))?;
+        Self::parse(&content)
+    }
 
-        let config: Config = serde_json::from_str(&content)
-            .map_err(|e| Error::Config(format!("Failed to parse config: {}", e)))?;
+    /// Validates the config and writes it to `path` as pretty JSON. The file is
+    /// replaced atomically, keeping its permissions, so a crash never leaves it
+    /// half-written.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        self.validate()?;
+        let rendered =
+            serde_json::to_string_pretty(self).map_err(|e| Error::Config(e.to_string()))?;
+        let temp = path.with_extension("json.tmp");
+        fs::write(&temp, rendered + "\n")?;
+        if let Ok(metadata) = fs::metadata(path) {
+            fs::set_permissions(&temp, metadata.permissions())?;
+        }
+        fs::rename(&temp, path)?;
+        Ok(())
+    }
+
+    /// Parses and validates config JSON, returning it with any unknown keys.
+    pub fn parse(content: &str) -> Result<(Self, Vec<String>)> {
+        let mut unknown = Vec::new();
+        let deserializer = &mut serde_json::Deserializer::from_str(content);
+        let config: Config =
+            serde_ignored::deserialize(deserializer, |path| unknown.push(path.to_string()))
+                .map_err(|e| Error::Config(format!("Failed to parse config: {}", e)))?;
+        unknown.sort();
 
         config.validate()?;
 
-        Ok(config)
+        Ok((config, unknown))
     }
 
     pub fn validate(&self) -> Result<()> {
@@ -63,28 +1187,343 @@ impl Config {
             )));
         }
 
+        if !self.strategy_while_charging.is_empty()
+            && !self.strategies.contains_key(&self.strategy_while_charging)
+        {
+            return Err(Error::Config(format!(
+                "Charging strategy '{}' is not a valid strategy",
+                self.strategy_while_charging
+            )));
+        }
+
+        if self.discharging_strategy_battery_threshold > 100 {
+            return Err(Error::Config(
+                "dischargingStrategyBatteryThreshold must be between 0 and 100".into(),
+            ));
+        }
+
+        if self.startup_speed > 100 {
+            return Err(Error::Config(
+                "startupSpeed must be between 0 and 100".into(),
+            ));
+        }
+
         for (name, strategy) in &self.strategies {
-            if strategy.speed_curve.is_empty() {
+            if strategy.speed_curve.is_empty() && strategy.compose.is_empty() {
                 return Err(Error::Config(format!(
                     "Strategy '{}' has an empty speed curve",
                     name
                 )));
             }
+            for component in &strategy.compose {
+                match self.strategies.get(component) {
+                    None => {
+                        return Err(Error::Config(format!(
+                            "Strategy '{}' composes unknown strategy '{}'",
+                            name, component
+                        )))
+                    }
+                    Some(composed) if !composed.compose.is_empty() => {
+                        return Err(Error::Config(format!(
+                            "Strategy '{}' composes '{}', which composes others in turn",
+                            name, component
+                        )))
+                    }
+                    Some(_) => {}
+                }
+            }
+            if strategy.update_interval_seconds == 0 {
+                return Err(Error::Config(format!(
+                    "Strategy '{}' must have an updateIntervalSeconds greater than 0",
+                    name
+                )));
+            }
+            for (i, curve) in strategy.sensor_curves.iter().enumerate() {
+                if curve.speed_curve.is_empty() {
+                    return Err(Error::Config(format!(
+                        "Sensor curve '{}' of strategy '{}' has an empty speed curve",
+                        curve.name, name
+                    )));
+                }
+                if strategy.sensor_curves[..i]
+                    .iter()
+                    .any(|c| c.name == curve.name)
+                {
+                    return Err(Error::Config(format!(
+                        "Strategy '{}' has more than one sensor curve named '{}'",
+                        name, curve.name
+                    )));
+                }
+            }
+            if let Smoothing::LowPass {
+                time_constant_seconds,
+            } = strategy.smoothing
+            {
+                if !time_constant_seconds.is_finite() || time_constant_seconds < 0.0 {
+                    return Err(Error::Config(format!(
+                        "Strategy '{}' must have a non-negative smoothing.timeConstantSeconds",
+                        name
+                    )));
+                }
+            }
+            if let Some(rate) = strategy.charge_rate_limit {
+                if rate.is_nan() || rate <= 0.0 || rate > 1.0 {
+                    return Err(Error::Config(format!(
+                        "Strategy '{}' must have a chargeRateLimit above 0 and at most 1",
+                        name
+                    )));
+                }
+            }
+            if let Some(saver) = strategy.discharge_saver {
+                if !saver.min_watts.is_finite() || saver.min_watts < 0.0 {
+                    return Err(Error::Config(format!(
+                        "Strategy '{}' must have a non-negative dischargeSaver.minWatts",
+                        name
+                    )));
+                }
+                if saver.max_temp >= strategy.safety_temp || saver.speed_reduction > 100 {
+                    return Err(Error::Config(format!(
+                        "Strategy '{}' must have a dischargeSaver.maxTemp below safetyTemp \
+                         and a speedReduction of at most 100",
+                        name
+                    )));
+                }
+            }
+        }
+
+        for name in &self.strategy_cycle {
+            if !self.strategies.contains_key(name) {
+                return Err(Error::Config(format!(
+                    "Cycle strategy '{}' is not a valid strategy",
+                    name
+                )));
+            }
+        }
+
+        if let Some(quiet) = &self.quiet_hours {
+            if quiet.max_speed > 100 {
+                return Err(Error::Config(
+                    "quietHours.maxSpeed must be between 0 and 100".into(),
+                ));
+            }
+            if let Some(name) = &quiet.strategy {
+                if !self.strategies.contains_key(name) {
+                    return Err(Error::Config(format!(
+                        "Quiet hours strategy '{}' is not a valid strategy",
+                        name
+                    )));
+                }
+            }
+        }
+
+        if let Some(layout) = &self.ec_layout {
+            layout.validate()?;
+        }
+
+        if let Some(boost) = &self.gpu_boost {
+            if boost.busy_percent > 100 || boost.min_speed > 100 {
+                return Err(Error::Config(
+                    "gpuBoost.busyPercent and gpuBoost.minSpeed must be between 0 and 100".into(),
+                ));
+            }
+        }
+
+        if self.trip_points.as_ref().is_some_and(|t| t.min_speed > 100) {
+            return Err(Error::Config(
+                "tripPoints.minSpeed must be between 0 and 100".into(),
+            ));
+        }
+
+        if let Some(test) = &self.scheduled_self_test {
+            if test.interval_hours == 0 {
+                return Err(Error::Config(
+                    "scheduledSelfTest.intervalHours must be greater than 0".into(),
+                ));
+            }
+            if test.duty == 0 || test.duty > 100 {
+                return Err(Error::Config(
+                    "scheduledSelfTest.duty must be between 1 and 100".into(),
+                ));
+            }
+        }
+
+        let mut source_names = Vec::new();
+        for source in &self.sensor_sources {
+            let SensorSource::Exec { cmd, interval, .. } = source;
+            if cmd.is_empty() || *interval == 0 {
+                return Err(Error::Config(
+                    "Exec sensor sources need a cmd and an interval above 0".into(),
+                ));
+            }
+            let name = source.name();
+            if source_names.contains(&name) {
+                return Err(Error::Config(format!(
+                    "Sensor source name '{}' is used twice",
+                    name
+                )));
+            }
+            source_names.push(name);
+        }
+
+        let mut alert_names = Vec::new();
+        for alert in &self.alerts {
+            if alert.name.is_empty() || !alert.value.is_finite() {
+                return Err(Error::Config(
+                    "Alerts need a name and a finite value".into(),
+                ));
+            }
+            if alert_names.contains(&&alert.name) {
+                return Err(Error::Config(format!(
+                    "Alert name '{}' is used twice",
+                    alert.name
+                )));
+            }
+            alert_names.push(&alert.name);
+        }
+
+        for (name, profile) in &self.profiles {
+            if profile.values.contains_key("profiles") {
+                return Err(Error::Config(format!(
+                    "Profile '{}' must not contain profiles",
+                    name
+                )));
+            }
+            if let Some(device) = profile.when.as_ref().and_then(|w| w.usb_device.as_ref()) {
+                let valid = device.split_once(':').is_some_and(|(vendor, product)| {
+                    [vendor, product]
+                        .iter()
+                        .all(|id| id.len() == 4 && id.chars().all(|c| c.is_ascii_hexdigit()))
+                });
+                if !valid {
+                    return Err(Error::Config(format!(
+                        "Profile '{}': usbDevice '{}' must be vendor:product, e.g. 17ef:a396",
+                        name, device
+                    )));
+                }
+            }
+            self.with_profile(name)?;
+        }
+
+        if let Some(telemetry) = &self.telemetry {
+            if telemetry.interval_seconds == 0 {
+                return Err(Error::Config(
+                    "telemetry.intervalSeconds must be greater than 0".into(),
+                ));
+            }
         }
 
         Ok(())
     }
 
+    /// This config with `name`'s values in place of its own. The result has no
+    /// profiles of its own.
+    pub fn with_profile(&self, name: &str) -> Result<Config> {
+        let profile = self
+            .profiles
+            .get(name)
+            .ok_or_else(|| Error::Config(format!("Unknown profile: {}", name)))?;
+        let mut value = serde_json::to_value(self)
+            .map_err(|e| Error::Config(format!("Failed to serialize config: {}", e)))?;
+        if let Some(map) = value.as_object_mut() {
+            map.remove("profiles");
+            map.extend(profile.values.clone());
+        }
+        let config: Config = serde_json::from_value(value)
+            .map_err(|e| Error::Config(format!("Profile '{}': {}", name, e)))?;
+        config
+            .validate()
+            .map_err(|e| Error::Config(format!("Profile '{}': {}", name, e)))?;
+        Ok(config)
+    }
+
+    /// The first profile, in name order, whose trigger matches `env`.
+    pub fn matching_profile(&self, env: &ProfileEnvironment) -> Option<&str> {
+        self.profiles
+            .iter()
+            .find(|(_, profile)| profile.when.as_ref().is_some_and(|when| when.matches(env)))
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Whether any profile activates by itself, so the environment is worth watching.
+    pub fn has_profile_triggers(&self) -> bool {
+        self.profiles.values().any(|profile| profile.when.is_some())
+    }
+
     pub fn get_strategy(&self, name: &str) -> Option<&Strategy> {
         self.strategies.get(name)
     }
 
+    /// The curve strategy `name` drives the fan by, with the curves of the
+    /// strategies it composes folded in. `sensorCurves` are left out, since
+    /// they follow other sensors.
+    pub fn resolved_curve(&self, name: &str) -> Option<Vec<CurvePoint>> {
+        let strategy = self.get_strategy(name)?;
+        let curves: Vec<&[CurvePoint]> = std::iter::once(strategy)
+            .chain(strategy.compose.iter().filter_map(|c| self.get_strategy(c)))
+            .map(|s| s.speed_curve.as_slice())
+            .collect();
+        Some(crate::curve::upper_envelope(&curves))
+    }
+
+    /// Strategy `name` as a file that stands alone: the curves of composed
+    /// strategies are folded into its own.
+    pub fn export_strategy(&self, name: &str) -> Result<StrategyExport> {
+        let strategy = self
+            .get_strategy(name)
+            .ok_or_else(|| Error::Strategy(format!("Unknown strategy: {}", name)))?;
+        let mut definition = strategy.clone();
+        if !definition.compose.is_empty() {
+            definition.speed_curve = self.resolved_curve(name).unwrap_or_default();
+            for composed in definition.compose.drain(..).collect::<Vec<_>>() {
+                let Some(composed) = self.get_strategy(&composed) else {
+                    continue;
+                };
+                for curve in &composed.sensor_curves {
+                    if !definition
+                        .sensor_curves
+                        .iter()
+                        .any(|c| c.name == curve.name)
+                    {
+                        definition.sensor_curves.push(curve.clone());
+                    }
+                }
+            }
+        }
+        Ok(StrategyExport {
+            name: name.to_string(),
+            definition,
+        })
+    }
+
+    /// Adds an exported strategy, replacing one of the same name only if
+    /// `replace` is set, and checks the result like a loaded config.
+    pub fn import_strategy(&mut self, export: StrategyExport, replace: bool) -> Result<()> {
+        if !replace && self.strategies.contains_key(&export.name) {
+            return Err(Error::Config(format!(
+                "Strategy '{}' already exists; use --force to replace it or --name to rename it",
+                export.name
+            )));
+        }
+        let mut config = self.clone();
+        config.strategies.insert(export.name, export.definition);
+        config.validate()?;
+        *self = config;
+        Ok(())
+    }
+
     pub fn get_default_strategy(&self) -> &Strategy {
         self.strategies
             .get(&self.default_strategy)
             .expect("Default strategy must exist")
     }
 
+    pub fn sensor_selection(&self) -> SensorSelection {
+        SensorSelection {
+            include: self.include_sensors.clone(),
+            exclude: self.exclude_sensors.clone(),
+        }
+    }
+
     pub fn get_discharging_strategy(&self) -> &Strategy {
         let name = if self.strategy_on_discharging.is_empty() {
             &self.default_strategy
@@ -99,4 +1538,14 @@ impl Config {
     pub fn strategy_names(&self) -> Vec<&String> {
         self.strategies.keys().collect()
     }
+
+    /// Strategies in the order `cycle` steps through them.
+    pub fn cycle_order(&self) -> Vec<&String> {
+        if !self.strategy_cycle.is_empty() {
+            return self.strategy_cycle.iter().collect();
+        }
+        let mut names = self.strategy_names();
+        names.sort();
+        names
+    }
 }
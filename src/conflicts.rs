@@ -0,0 +1,200 @@
+use std::fs;
+use std::path::Path;
+
+const PROC_PATH: &str = "/proc";
+
+/// Processes known to drive fans or otherwise fight over the EC, and whether
+/// they write fan duties themselves. thermald only throttles the CPU, which
+/// skews the curve but doesn't fight over the fan.
+const KNOWN_CONFLICTS: &[(&str, &str, bool)] = &[
+    ("thermald", "Intel thermal daemon", false),
+    ("fancontrol", "lm-sensors fancontrol", true),
+    ("fw-fanctrl", "another fw-fanctrl instance", true),
+    ("fw-fanctrld", "another fw-fanctrl daemon", true),
+    ("nbfc_service", "NoteBook FanControl", true),
+    ("coolercontrold", "CoolerControl", true),
+];
+
+/// A running process that may also be controlling the fan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    pub pid: u32,
+    pub name: String,
+    pub description: &'static str,
+    /// Whether it writes fan duties, rather than only interfering with the readings.
+    pub writes_fans: bool,
+}
+
+impl Conflict {
+    /// Whether this is an fw-fanctrl daemon, `fw-fanctrl run` or `fw-fanctrld`.
+    pub fn is_fw_fanctrl(&self) -> bool {
+        matches!(self.name.as_str(), "fw-fanctrl" | "fw-fanctrld")
+    }
+}
+
+impl std::fmt::Display for Conflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (pid {}, {})", self.name, self.pid, self.description)
+    }
+}
+
+/// Matches the executable and, for interpreted daemons such as the Python
+/// fw-fanctrl, the script name. fw-fanctrl only counts when it runs the service,
+/// not for one-shot client commands; fw-fanctrld always runs it.
+fn match_known(cmdline: &str) -> Option<(&'static str, &'static str, bool)> {
+    let args: Vec<&str> = cmdline.split('\0').collect();
+    args.iter()
+        .take(2)
+        .filter_map(|arg| Path::new(arg).file_name()?.to_str())
+        .find_map(|name| {
+            let name = name.strip_suffix(".py").unwrap_or(name);
+            KNOWN_CONFLICTS
+                .iter()
+                .copied()
+                .find(|(known, _, _)| *known == name)
+        })
+        .filter(|(name, _, _)| *name != "fw-fanctrl" || args.contains(&"run"))
+}
+
+fn scan(proc_root: &Path, own_pid: u32) -> Vec<Conflict> {
+    let Ok(entries) = fs::read_dir(proc_root) else {
+        return Vec::new();
+    };
+
+    let mut conflicts: Vec<Conflict> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let pid: u32 = entry.file_name().to_str()?.parse().ok()?;
+            if pid == own_pid {
+                return None;
+            }
+            let cmdline = fs::read_to_string(entry.path().join("cmdline")).ok()?;
+            let (name, description, writes_fans) = match_known(&cmdline)?;
+            Some(Conflict {
+                pid,
+                name: name.to_string(),
+                description,
+                writes_fans,
+            })
+        })
+        .collect();
+    conflicts.sort_by_key(|c| c.pid);
+    conflicts
+}
+
+/// Running processes, other than this one, that are known to control fans.
+pub fn find_conflicting_processes() -> Vec<Conflict> {
+    scan(Path::new(PROC_PATH), std::process::id())
+}
+
+/// Control samples at one duty before its RPM is taken as the baseline.
+const SETTLE_SAMPLES: u32 = 10;
+/// Consecutive off-baseline samples before reporting foreign writes.
+const MISMATCH_SAMPLES: u32 = 10;
+const RPM_TOLERANCE: f64 = 0.5;
+const MIN_RPM_DELTA: u16 = 1000;
+
+/// Spots fan RPM swings the controller did not command, which usually means
+/// another process is writing fan duties to the EC.
+#[derive(Debug, Default)]
+pub struct RpmWatch {
+    duty: Option<u32>,
+    stable_samples: u32,
+    baseline: Option<u16>,
+    mismatches: u32,
+}
+
+impl RpmWatch {
+    /// Feeds one sample; returns `true` once per episode of sustained mismatch.
+    pub fn observe(&mut self, duty: u32, rpm: u16) -> bool {
+        if self.duty != Some(duty) {
+            *self = Self {
+                duty: Some(duty),
+                ..Self::default()
+            };
+            return false;
+        }
+
+        self.stable_samples += 1;
+        if self.stable_samples < SETTLE_SAMPLES {
+            return false;
+        }
+        let Some(baseline) = self.baseline else {
+            self.baseline = Some(rpm);
+            return false;
+        };
+
+        let delta = rpm.abs_diff(baseline);
+        if delta > MIN_RPM_DELTA && delta as f64 > baseline as f64 * RPM_TOLERANCE {
+            self.mismatches += 1;
+        } else {
+            self.mismatches = 0;
+        }
+        self.mismatches == MISMATCH_SAMPLES
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_finds_known_daemons() {
+        let root = std::env::temp_dir().join(format!("fw-fanctrl-proc-{}", std::process::id()));
+        for (pid, cmdline) in [
+            ("100", "/usr/sbin/thermald\0--no-daemon\0"),
+            (
+                "200",
+                "/usr/bin/python3\0/usr/local/bin/fw-fanctrl.py\0run\0",
+            ),
+            ("300", "/usr/local/bin/fw-fanctrl\0print\0"),
+            ("400", "/usr/local/bin/fw-fanctrl\0run\0"),
+            (
+                "500",
+                "/usr/local/bin/fw-fanctrld\0--config\0/etc/fw-fanctrl/config.json\0",
+            ),
+        ] {
+            fs::create_dir_all(root.join(pid)).unwrap();
+            fs::write(root.join(pid).join("cmdline"), cmdline).unwrap();
+        }
+
+        let conflicts = scan(&root, 400);
+        fs::remove_dir_all(&root).unwrap();
+
+        let found: Vec<_> = conflicts
+            .iter()
+            .map(|c| (c.pid, c.name.as_str(), c.writes_fans))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (100, "thermald", false),
+                (200, "fw-fanctrl", true),
+                (500, "fw-fanctrld", true)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rpm_watch_flags_sustained_uncommanded_change() {
+        let mut watch = RpmWatch::default();
+        for _ in 0..=SETTLE_SAMPLES {
+            assert!(!watch.observe(40, 3000));
+        }
+        let flagged: Vec<bool> = (0..MISMATCH_SAMPLES + 2)
+            .map(|_| watch.observe(40, 6000))
+            .collect();
+        assert_eq!(flagged.iter().filter(|f| **f).count(), 1);
+    }
+
+    #[test]
+    fn test_rpm_watch_resets_on_commanded_change() {
+        let mut watch = RpmWatch::default();
+        for _ in 0..=SETTLE_SAMPLES {
+            watch.observe(40, 3000);
+        }
+        for _ in 0..MISMATCH_SAMPLES * 2 {
+            assert!(!watch.observe(80, 6000));
+        }
+    }
+}
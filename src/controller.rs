@@ -1,63 +1,512 @@
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use crate::config::{Config, Strategy};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{
+    Alert, AlertMetric, Config, ConfigDiff, CurvePoint, QuietHours, Smoothing, StartupBehavior,
+    Strategy, DEFAULT_CONFIG_PATH,
+};
 use crate::curve::interpolate;
 use crate::error::{Error, Result};
-use crate::hardware::HardwareController;
+use crate::hardware::{Capabilities, ChargeState, HardwareController, PlatformInfo, SensorReading};
+use crate::jobs::Jobs;
+use crate::selftest::SCHEDULED_SELF_TEST_JOB;
+use crate::stats::{SelfTestResult, ThermalStats};
+use crate::sysfs::ThermalTrip;
 
 const TEMP_HISTORY_MAX_LEN: usize = 100;
+/// One hour of samples at the 1s control tick.
+const SAMPLE_HISTORY_MAX_LEN: usize = 3600;
+const SPEED_CHANGE_WINDOW: Duration = Duration::from_secs(60);
+/// How often the cached AC state is re-read when no power supply event arrives.
+const POWER_POLL_INTERVAL: Duration = Duration::from_secs(60);
+/// Recent samples whose median a new reading is compared to by the spike filter.
+const SPIKE_FILTER_WINDOW: usize = 5;
+/// Errors kept for `print errors`.
+const ERROR_LOG_MAX_LEN: usize = 50;
+/// Seconds of samples the temperature trend is fitted to.
+const TREND_WINDOW_SECONDS: u64 = 60;
+/// Samples needed before a trend is reported.
+const TREND_MIN_SAMPLES: usize = 10;
+/// Slopes below this many °C per minute count as steady, with no ETA.
+const TREND_STEADY_SLOPE: f64 = 0.2;
+/// How often profile triggers are checked without a USB hotplug event.
+const PROFILE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// C-rate that lifts a charge-rate limit again.
+const FULL_CHARGE_RATE: f32 = 1.0;
+/// Period `step` is assumed to run at unless [`FanController::with_tick`] says otherwise.
+pub const DEFAULT_TICK: Duration = Duration::from_secs(1);
+
+/// A timestamped control-loop sample, kept for `export`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub temperature: f64,
+    pub speed: u32,
+    pub active: bool,
+}
+
+/// Summary of the recorded sample history.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryStats {
+    pub samples: usize,
+    pub min_temperature: Option<f64>,
+    pub max_temperature: Option<f64>,
+    pub mean_temperature: Option<f64>,
+    pub speed_changes: usize,
+}
+
+/// Where the temperature is heading, fitted to the last minute of samples.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Trend {
+    /// °C per minute; positive while heating up.
+    pub slope_per_minute: f64,
+    /// The next threshold in the direction of travel; `None` while steady or
+    /// with nothing left to cross.
+    pub target: Option<TrendTarget>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrendTarget {
+    pub kind: TrendTargetKind,
+    pub temperature: u32,
+    /// Estimated seconds until the temperature gets there at the current slope.
+    pub eta_seconds: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TrendTargetKind {
+    /// A point of the current strategy's curve, where the duty changes pace.
+    Breakpoint,
+    /// The strategy's `safetyTemp`.
+    SafetyTemp,
+}
+
+/// Least-squares slope of `(timestamp, temperature)` samples, in °C per minute.
+fn slope_per_minute(samples: &[(u64, f64)]) -> Option<f64> {
+    let first = samples.first()?.0;
+    let n = samples.len() as f64;
+    let points = samples.iter().map(|&(t, temp)| ((t - first) as f64, temp));
+    let (sum_t, sum_temp) = points
+        .clone()
+        .fold((0.0, 0.0), |(st, sv), (t, v)| (st + t, sv + v));
+    let (mean_t, mean_temp) = (sum_t / n, sum_temp / n);
+    let (covariance, variance) = points.fold((0.0, 0.0), |(c, v), (t, temp)| {
+        (
+            c + (t - mean_t) * (temp - mean_temp),
+            v + (t - mean_t) * (t - mean_t),
+        )
+    });
+    (variance > 0.0).then(|| covariance / variance * 60.0)
+}
+
+/// How long an alert's condition has held, and whether it tripped.
+#[derive(Debug, Clone, Copy, Default)]
+struct AlertState {
+    held: Duration,
+    tripped: bool,
+}
+
+/// An alert that tripped or cleared, waiting for the service to run its command.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertEvent {
+    pub alert: Alert,
+    pub tripped: bool,
+    /// The metric's reading when it happened.
+    pub reading: f64,
+}
+
+/// What the daemon was doing when a recorded error happened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorContext {
+    EcRead,
+    EcWrite,
+    ConfigReload,
+    Socket,
+}
+
+/// A timestamped error, kept for `print errors`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorEntry {
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub context: ErrorContext,
+    pub message: String,
+}
+
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// What made the strategy in effect change, for the log and `print current`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StrategyChangeReason {
+    /// `use`, `toggle` or `cycle`.
+    Override,
+    /// `reset` went back to the automatic choice.
+    Reset,
+    /// Plugged in or unplugged.
+    PowerSource,
+    /// The charge crossed `dischargingStrategyBatteryThreshold`.
+    BatteryThreshold,
+    /// The battery started or stopped charging, with `strategyWhileCharging` set.
+    ChargeState,
+    /// A quiet-hours window started or ended.
+    QuietHours,
+    /// A reload changed the strategies or removed the override.
+    Reload,
+    /// Another profile became active.
+    Profile,
+}
+
+impl std::fmt::Display for StrategyChangeReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Override => "override",
+            Self::Reset => "reset",
+            Self::PowerSource => "power source",
+            Self::BatteryThreshold => "battery threshold",
+            Self::ChargeState => "charge state",
+            Self::QuietHours => "quiet hours",
+            Self::Reload => "reload",
+            Self::Profile => "profile",
+        })
+    }
+}
+
+/// Who is driving the fan, for `print all`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FanMode {
+    /// The daemon writes duties from its curves.
+    Managed,
+    /// The EC's own fan control runs the fan.
+    EcAuto,
+    /// The daemon stopped writing duties but handing the fan back to the EC
+    /// failed, so the last duty stays.
+    Held,
+}
+
+impl FanMode {
+    /// The name used in `print all` replies.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Managed => "managed",
+            Self::EcAuto => "ecAuto",
+            Self::Held => "held",
+        }
+    }
+}
+
+/// How a control-interface client asked the daemon to exit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShutdownRequest {
+    /// Stop the same way as on SIGTERM.
+    Stop,
+    /// EC auto mode was already restored when the request was accepted.
+    RestartAuto,
+}
 
 pub struct FanController {
     hw: HardwareController,
+    /// The config in effect: `base_config` with the active profile applied.
     config: Config,
+    /// The config as loaded, with its `profiles`.
+    base_config: Config,
+    /// Active profile, if any, and whether `profile use` chose it rather than its trigger.
+    profile: Option<String>,
+    profile_pinned: bool,
+    profile_polled_at: Instant,
+    /// File `config` came from, read again by `reload`.
+    config_path: PathBuf,
     overwritten_strategy: Option<String>,
     temp_history: VecDeque<f64>,
+    /// Whether the previous reading was discarded by the spike filter.
+    spike_rejected: bool,
+    /// Output of the low-pass filter while the current strategy uses it.
+    low_pass: Option<f64>,
+    sample_history: VecDeque<HistoryEntry>,
+    /// The last [`ERROR_LOG_MAX_LEN`] errors, oldest first.
+    errors: VecDeque<ErrorEntry>,
+    /// Long-term counters for `print stats`, persisted by the service.
+    thermal_stats: ThermalStats,
     current_speed: u32,
+    /// Temperature the curves were last read at for `current_speed`, for hysteresis.
+    speed_set_at_temp: f64,
+    /// When the duty last stepped down or the curve last asked for at least
+    /// `current_speed`, for `speedDecaySeconds`.
+    decay_from: Instant,
+    /// When recent speed changes happened, for the per-minute change budget.
+    speed_changes: VecDeque<Instant>,
+    /// Temporary percentage added to the curve output, cleared by `reset`.
+    speed_bias: i32,
+    /// The duty read back from the EC while it disagrees with `current_speed`.
+    duty_drift: Option<u32>,
+    /// Times the EC duty was found changed behind the daemon's back.
+    duty_drift_events: u64,
+    /// Why the last hand-back to EC auto mode failed; cleared once one succeeds
+    /// or control resumes.
+    auto_fan_error: Option<String>,
+    /// Charge-rate limit set on the EC for the current strategy's `chargeRateLimit`.
+    charge_rate_limit: Option<f32>,
+    /// Progress of the config's `alerts`, by name.
+    alerts: BTreeMap<String, AlertState>,
+    /// Trips and clears not yet taken by the service loop.
+    alert_events: Vec<AlertEvent>,
+    /// Cached AC state; refreshed on power supply events and every [`POWER_POLL_INTERVAL`].
+    on_ac: bool,
+    /// Failed reads of the AC state, and whether the last one failed.
+    ac_detection_failures: u64,
+    ac_detection_failing: bool,
+    power_polled_at: Instant,
+    /// A power source change waiting out `powerSwitchDelaySeconds`, and when it was first seen.
+    pending_power: Option<(bool, Instant)>,
+    /// Battery charge in percent, refreshed along with `on_ac`.
+    battery_charge: Option<u32>,
+    charge_state: Option<ChargeState>,
+    /// Consecutive busy GPU samples, for `gpuBoost`.
+    gpu_busy_samples: u32,
+    /// The trip point nearest to being reached, while within `tripPoints.marginCelsius`.
+    near_trip: Option<ThermalTrip>,
+    /// Long-running operations started over the socket.
+    jobs: Jobs,
+    /// Set by the `shutdown` and `restart-auto` commands, picked up by the service loop.
+    shutdown_request: Option<ShutdownRequest>,
+    /// When the controller started, for `uptimeSeconds`.
+    started_at: Instant,
+    /// Strategy in effect at the last check, when it took over (Unix seconds) and why;
+    /// no reason until the first change.
+    strategy_seen: String,
+    strategy_changed_at: u64,
+    strategy_change_reason: Option<StrategyChangeReason>,
     active: bool,
-    timecount: u32,
+    /// Computes duties without ever writing them or switching the EC's fan mode.
+    monitor_only: bool,
+    /// Until the moving-average window first fills, `startupBehavior` may stand in for the curve.
+    warming_up: bool,
+    /// How often `step` runs, i.e. the service loop's tick.
+    tick: Duration,
+    /// Control time since the duty was last updated; `None` updates it on the next step.
+    since_update: Option<Duration>,
 }
 
 impl FanController {
-    pub fn new(hw: HardwareController, config: Config, initial_strategy: Option<String>) -> Self {
+    pub fn new(
+        mut hw: HardwareController,
+        config: Config,
+        initial_strategy: Option<String>,
+    ) -> Self {
+        hw.set_sensor_selection(config.sensor_selection());
+        if let Some(excluded) = config.exclude_battery_sensor {
+            hw.set_battery_excluded(excluded);
+        }
         let overwritten_strategy = initial_strategy.filter(|s| !s.is_empty());
-        Self {
+        let on_ac = hw.is_on_ac();
+        let battery_charge = hw.get_battery_charge();
+        let charge_state = hw.get_charge_state();
+        let mut controller = Self {
             hw,
+            base_config: config.clone(),
             config,
+            profile: None,
+            profile_pinned: false,
+            profile_polled_at: Instant::now(),
+            config_path: PathBuf::from(DEFAULT_CONFIG_PATH),
             overwritten_strategy,
             temp_history: VecDeque::with_capacity(TEMP_HISTORY_MAX_LEN),
+            spike_rejected: false,
+            low_pass: None,
+            sample_history: VecDeque::with_capacity(SAMPLE_HISTORY_MAX_LEN),
+            errors: VecDeque::with_capacity(ERROR_LOG_MAX_LEN),
+            thermal_stats: ThermalStats::new(unix_now()),
             current_speed: 0,
+            speed_set_at_temp: 0.0,
+            decay_from: Instant::now(),
+            speed_changes: VecDeque::new(),
+            speed_bias: 0,
+            duty_drift: None,
+            duty_drift_events: 0,
+            auto_fan_error: None,
+            charge_rate_limit: None,
+            alerts: BTreeMap::new(),
+            alert_events: Vec::new(),
+            on_ac: false,
+            ac_detection_failures: 0,
+            ac_detection_failing: false,
+            power_polled_at: Instant::now(),
+            pending_power: None,
+            battery_charge,
+            charge_state,
+            gpu_busy_samples: 0,
+            near_trip: None,
+            jobs: Jobs::default(),
+            shutdown_request: None,
+            started_at: Instant::now(),
+            strategy_seen: String::new(),
+            strategy_changed_at: unix_now(),
+            strategy_change_reason: None,
             active: true,
-            timecount: 0,
-        }
+            monitor_only: false,
+            warming_up: true,
+            tick: DEFAULT_TICK,
+            since_update: None,
+        };
+        controller.on_ac = match on_ac {
+            Ok(on_ac) => on_ac,
+            Err(e) => controller.ac_detection_failed(&e).unwrap_or(false),
+        };
+        controller.refresh_profile();
+        controller.strategy_seen = controller.get_current_strategy_name();
+        controller
+    }
+
+    /// Sets the file `reload` reads, when the config came from somewhere other
+    /// than [`DEFAULT_CONFIG_PATH`].
+    pub fn with_config_path(mut self, path: PathBuf) -> Self {
+        self.config_path = path;
+        self
+    }
+
+    /// Sets how often the service loop calls `step`, which update intervals are
+    /// counted in.
+    pub fn with_tick(mut self, tick: Duration) -> Self {
+        self.tick = tick;
+        self
+    }
+
+    /// Runs without fan control: duties are computed and reported but never
+    /// written, and the EC's fan mode is left alone.
+    pub fn with_monitor_only(mut self) -> Self {
+        self.hw.refuse_fan_control("monitor-only mode");
+        self.monitor_only = true;
+        self
+    }
+
+    pub fn is_monitor_only(&self) -> bool {
+        self.monitor_only
+    }
+
+    pub fn config_path(&self) -> &Path {
+        &self.config_path
     }
 
     pub fn get_current_strategy(&self) -> &Strategy {
-        if let Some(ref name) = self.overwritten_strategy {
-            self.config
-                .get_strategy(name)
-                .expect("Overwritten strategy must exist")
-        } else if self.hw.is_on_ac().unwrap_or(false) {
-            self.config.get_default_strategy()
-        } else {
-            self.config.get_discharging_strategy()
-        }
+        self.config
+            .get_strategy(self.current_strategy_name())
+            .expect("Current strategy must exist")
     }
 
     pub fn get_current_strategy_name(&self) -> String {
+        self.current_strategy_name().to_string()
+    }
+
+    /// Like [`get_current_strategy_name`](Self::get_current_strategy_name),
+    /// without copying the name.
+    pub fn current_strategy_name(&self) -> &str {
+        self.strategy_name_for(self.on_ac)
+    }
+
+    fn strategy_name_for(&self, on_ac: bool) -> &str {
         if let Some(ref name) = self.overwritten_strategy {
-            return name.clone();
+            return name;
+        }
+
+        if let Some(name) = self.quiet_hours_strategy() {
+            return name;
+        }
+
+        if on_ac && self.is_charging() && !self.config.strategy_while_charging.is_empty() {
+            return &self.config.strategy_while_charging;
         }
 
-        if self.hw.is_on_ac().unwrap_or(false) {
-            return self.config.default_strategy.clone();
+        if on_ac || self.battery_above_threshold() {
+            return &self.config.default_strategy;
         }
 
         let discharging = &self.config.strategy_on_discharging;
         if discharging.is_empty() {
-            return self.config.default_strategy.clone();
+            return &self.config.default_strategy;
+        }
+
+        discharging
+    }
+
+    /// The quiet-hours window, if it is active right now.
+    pub fn active_quiet_hours(&self) -> Option<&QuietHours> {
+        self.config
+            .quiet_hours
+            .as_ref()
+            .filter(|q| q.contains(Local::now().time()))
+    }
+
+    fn quiet_hours_strategy(&self) -> Option<&String> {
+        self.active_quiet_hours().and_then(|q| q.strategy.as_ref())
+    }
+
+    /// Notes and logs a change of the strategy in effect, caused by `reason`.
+    fn track_strategy(&mut self, reason: StrategyChangeReason) {
+        if self.current_strategy_name() == self.strategy_seen {
+            return;
         }
+        let name = self.get_current_strategy_name();
+        tracing::info!(
+            from = %self.strategy_seen,
+            to = %name,
+            reason = %reason,
+            "Strategy changed"
+        );
+        self.strategy_seen = name;
+        self.strategy_changed_at = unix_now();
+        self.strategy_change_reason = Some(reason);
+    }
+
+    /// When the current strategy took over, in seconds since the Unix epoch.
+    pub fn last_strategy_change(&self) -> u64 {
+        self.strategy_changed_at
+    }
+
+    /// Why the strategy last changed; `None` if it hasn't since startup.
+    pub fn last_strategy_change_reason(&self) -> Option<StrategyChangeReason> {
+        self.strategy_change_reason
+    }
+
+    pub fn uptime(&self) -> Duration {
+        self.started_at.elapsed()
+    }
+
+    pub fn is_on_ac(&self) -> bool {
+        self.on_ac
+    }
+
+    /// Battery charge in percent, as of the last power state refresh.
+    pub fn battery_charge(&self) -> Option<u32> {
+        self.battery_charge
+    }
+
+    /// Battery charge state, as of the last power state refresh.
+    pub fn charge_state(&self) -> Option<ChargeState> {
+        self.charge_state
+    }
 
-        discharging.clone()
+    fn is_charging(&self) -> bool {
+        self.charge_state == Some(ChargeState::Charging)
+    }
+
+    pub fn platform_name(&self) -> &str {
+        self.hw.platform_name()
     }
 
     pub fn is_overwritten(&self) -> bool {
@@ -69,44 +518,120 @@ impl FanController {
             return Err(Error::Strategy(format!("Unknown strategy: {}", name)));
         }
         self.overwritten_strategy = Some(name.to_string());
-        self.timecount = 0;
+        self.track_strategy(StrategyChangeReason::Override);
+        self.apply_now_or_next_tick();
         Ok(())
     }
 
+    /// Switches to `b` if `a` is the current strategy, otherwise to `a`.
+    pub fn toggle_strategy(&mut self, a: &str, b: &str) -> Result<()> {
+        let target = if self.current_strategy_name() == a {
+            b
+        } else {
+            a
+        };
+        self.overwrite_strategy(target)
+    }
+
+    /// Advances to the strategy after the current one in the configured cycle order.
+    pub fn cycle_strategy(&mut self) -> Result<()> {
+        let current = self.get_current_strategy_name();
+        let order = self.config.cycle_order();
+        let next = match order.iter().position(|name| **name == current) {
+            Some(i) => order[(i + 1) % order.len()],
+            None => order[0],
+        }
+        .clone();
+        self.overwrite_strategy(&next)
+    }
+
     pub fn clear_overwritten_strategy(&mut self) {
         self.overwritten_strategy = None;
-        self.timecount = 0;
+        self.speed_bias = 0;
+        self.track_strategy(StrategyChangeReason::Reset);
+        self.apply_now_or_next_tick();
+    }
+
+    /// Adds `bias` to the curve output and applies it right away, releasing
+    /// any hysteresis hold.
+    pub fn set_speed_bias(&mut self, bias: i32) {
+        self.speed_bias = bias.clamp(-100, 100);
+        self.speed_set_at_temp = 0.0;
+        self.apply_now_or_next_tick();
+    }
+
+    pub fn get_speed_bias(&self) -> i32 {
+        self.speed_bias
+    }
+
+    /// Leaves the battery sensor out of the control temperature or takes it
+    /// back in, until a reload or profile switch sets `excludeBatterySensor`.
+    pub fn set_battery_excluded(&mut self, excluded: bool) -> Result<()> {
+        if self.hw.battery_sensor_index().is_none() {
+            return Err(Error::Command(
+                "This platform has no known battery sensor".into(),
+            ));
+        }
+        self.hw.set_battery_excluded(excluded);
+        self.since_update = None;
+        tracing::info!(
+            "Battery sensor {} the control temperature",
+            if excluded {
+                "left out of"
+            } else {
+                "included in"
+            }
+        );
+        Ok(())
+    }
+
+    pub fn is_battery_excluded(&self) -> bool {
+        self.hw.is_battery_excluded()
     }
 
     pub fn get_actual_temperature(&self) -> Result<f64> {
-        self.hw.get_temperature()
+        self.hw
+            .get_aggregated_temperature(self.get_current_strategy().sensor_aggregation)
     }
 
     pub fn get_moving_average_temperature(&self, interval: u32) -> f64 {
-        let positive_temps: Vec<f64> = self
+        // Newest first, so the window is a `take` instead of a slice of a copy.
+        let window = match interval {
+            0 => usize::MAX,
+            n => n as usize,
+        };
+        let (sum, count) = self
             .temp_history
             .iter()
+            .rev()
             .filter(|&&t| t > 0.0)
-            .copied()
-            .collect();
+            .take(window)
+            .fold((0.0, 0usize), |(sum, count), t| (sum + t, count + 1));
 
-        if positive_temps.is_empty() {
+        if count == 0 {
             return self.get_actual_temperature().unwrap_or(50.0);
         }
 
-        let slice_start = positive_temps.len().saturating_sub(interval as usize);
-        let slice = &positive_temps[slice_start..];
-
-        if slice.is_empty() {
-            return positive_temps.iter().sum::<f64>() / positive_temps.len() as f64;
-        }
+        sum / count as f64
+    }
 
-        slice.iter().sum::<f64>() / slice.len() as f64
+    /// How many samples back the moving average over `interval` seconds, and
+    /// how many it takes to fill the window. Until it is full, e.g. right after
+    /// startup or `clear-history`, the average leans on the few readings there are.
+    pub fn moving_average_fill(&self, interval: u32) -> (usize, usize) {
+        let window = match interval {
+            0 => TEMP_HISTORY_MAX_LEN,
+            n => (n as usize).min(TEMP_HISTORY_MAX_LEN),
+        };
+        let samples = self.temp_history.iter().filter(|&&t| t > 0.0).count();
+        (samples.min(window), window)
     }
 
     pub fn get_effective_temperature(&self, current_temp: f64, interval: u32) -> f64 {
-        let moving_avg = self.get_moving_average_temperature(interval);
-        let effective = (moving_avg * 2.0 + current_temp) / 3.0;
+        let effective = match (self.get_current_strategy().smoothing, self.low_pass) {
+            (Smoothing::LowPass { .. }, Some(filtered)) => filtered,
+            _ => (self.get_moving_average_temperature(interval) * 2.0 + current_temp) / 3.0,
+        };
         (effective * 100.0).round() / 100.0
     }
 
@@ -115,41 +640,509 @@ impl FanController {
         let effective_temp =
             self.get_effective_temperature(current_temp, strategy.moving_average_interval);
 
-        let new_speed = interpolate(&strategy.speed_curve, effective_temp as u32);
+        // Only follow the temperature down once it has dropped by the hysteresis
+        // margin. Until then the curves are read at the temperature the duty was
+        // set at, so a new strategy, bias, cap or floor still applies right away.
+        let held = effective_temp < self.speed_set_at_temp
+            && effective_temp > self.speed_set_at_temp - strategy.hysteresis as f64;
+        let curve_temp = if held {
+            self.speed_set_at_temp
+        } else {
+            effective_temp
+        };
+        let curve_speed = self.curve_speed(strategy, curve_temp);
+        let mut new_speed = (curve_speed as i32 + self.speed_bias).clamp(0, 100) as u32;
+        if let Some(reduction) = self.discharge_saving(strategy, effective_temp) {
+            new_speed = new_speed.saturating_sub(reduction);
+        }
+        if let Some(floor) = self.gpu_boost_floor() {
+            new_speed = new_speed.max(floor);
+        }
+        let max_changes = strategy.max_speed_changes_per_minute as usize;
+        let safety_temp = strategy.safety_temp as f64;
+        // Quiet hours give way once the machine reaches the safety temperature
+        let quiet_cap = self
+            .active_quiet_hours()
+            .filter(|_| effective_temp < safety_temp)
+            .map(|quiet| quiet.max_speed);
+        if let Some(cap) = quiet_cap {
+            new_speed = new_speed.min(cap);
+        }
+        // Near a trip point the kernel is about to throttle, so quiet hours give way
+        if let Some(floor) = self.trip_point_floor() {
+            new_speed = new_speed.max(floor);
+        }
+
+        // While cooling down, step the duty down by one point every `speedDecaySeconds`
+        let decaying = new_speed < self.current_speed && strategy.speed_decay_seconds > 0;
+        if decaying {
+            let steps = self.decay_from.elapsed().as_secs() / strategy.speed_decay_seconds as u64;
+            let decayed = self
+                .current_speed
+                .saturating_sub(steps.min(100) as u32)
+                .min(quiet_cap.unwrap_or(100));
+            new_speed = new_speed.max(decayed);
+        }
+
+        let changed = new_speed != self.current_speed;
+        if changed && max_changes > 0 {
+            let now = Instant::now();
+            while self
+                .speed_changes
+                .front()
+                .is_some_and(|t| now.duration_since(*t) > SPEED_CHANGE_WINDOW)
+            {
+                self.speed_changes.pop_front();
+            }
+            if self.speed_changes.len() >= max_changes {
+                if effective_temp < safety_temp {
+                    return Ok(());
+                }
+                self.thermal_stats.record_failsafe();
+            }
+        }
 
-        if self.active {
-            self.hw.set_fan_speed(new_speed)?;
+        // In monitor-only mode the duty is tracked as if it had been written
+        let writable = self.hw.can_control_fan();
+        if self.active && (writable || self.monitor_only) {
+            if writable {
+                self.hw.set_fan_speed(new_speed)?;
+            }
             self.current_speed = new_speed;
+            self.speed_set_at_temp = curve_temp;
+            if changed {
+                self.speed_changes.push_back(Instant::now());
+            }
+            if changed || !decaying {
+                self.decay_from = Instant::now();
+            }
         }
 
         Ok(())
     }
 
-    #[allow(dead_code)]
+    /// Reads the temperature once and sets the duty the current strategy's curves
+    /// give for it, without smoothing, hysteresis or the change budget. Returns the
+    /// temperature and the duty.
+    pub fn apply_once(&mut self) -> Result<(f64, u32)> {
+        if !self.hw.can_control_fan() {
+            return Err(Error::Ec("Fan control is unavailable".into()));
+        }
+        let temp = self.get_actual_temperature()?;
+        let strategy = self.get_current_strategy();
+        let mut speed = self.curve_speed(strategy, temp);
+        if let Some(quiet) = self
+            .active_quiet_hours()
+            .filter(|_| temp < strategy.safety_temp as f64)
+        {
+            speed = speed.min(quiet.max_speed);
+        }
+        self.set_speed(speed)?;
+        Ok((temp, speed))
+    }
+
+    /// Points the strategy's `dischargeSaver` takes off the duty at `effective_temp`:
+    /// only on battery, below its `maxTemp` and while discharging at `minWatts` or more.
+    fn discharge_saving(&self, strategy: &Strategy, effective_temp: f64) -> Option<u32> {
+        let saver = strategy.discharge_saver?;
+        if self.on_ac || effective_temp >= saver.max_temp as f64 {
+            return None;
+        }
+        let watts = self.hw.get_battery_discharge_watts()?;
+        (watts >= saver.min_watts).then_some(saver.speed_reduction)
+    }
+
+    /// Power the battery is discharging at in watts, when it can be read.
+    pub fn battery_discharge_watts(&self) -> Option<f64> {
+        self.hw.get_battery_discharge_watts()
+    }
+
+    /// Highest duty the strategy's curves ask for at `temp`, including those of the
+    /// strategies it composes.
+    fn curve_speed(&self, strategy: &Strategy, temp: f64) -> u32 {
+        let own =
+            interpolate(&strategy.speed_curve, temp as u32).max(self.sensor_curves_speed(strategy));
+        strategy
+            .compose
+            .iter()
+            .filter_map(|name| self.config.strategies.get(name))
+            .map(|composed| {
+                interpolate(&composed.speed_curve, temp as u32)
+                    .max(self.sensor_curves_speed(composed))
+            })
+            .fold(own, u32::max)
+    }
+
+    /// Highest duty the strategy's `sensorCurves` ask for; curves whose sensor
+    /// can't be read are skipped.
+    fn sensor_curves_speed(&self, strategy: &Strategy) -> u32 {
+        strategy
+            .sensor_curves
+            .iter()
+            .filter_map(
+                |curve| match self.hw.get_sensor_temperature(&curve.sensor) {
+                    Ok(Some(temp)) => Some(interpolate(&curve.speed_curve, temp as u32)),
+                    Ok(None) => {
+                        tracing::debug!("No reading for sensor curve '{}'", curve.name);
+                        None
+                    }
+                    Err(e) => {
+                        tracing::debug!("Failed to read sensor for curve '{}': {}", curve.name, e);
+                        None
+                    }
+                },
+            )
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Reads the duty back, where the backend reports one, and when another
+    /// process has overwritten it, logs the discrepancy and writes the duty
+    /// again. Backends without a duty source are never checked.
+    pub fn verify_duty(&mut self) -> Result<()> {
+        if !self.active || !self.hw.can_control_fan() {
+            return Ok(());
+        }
+
+        let Some(reported) = self.hw.get_fan_duty()? else {
+            return Ok(());
+        };
+        if reported == self.current_speed {
+            self.duty_drift = None;
+            return Ok(());
+        }
+
+        if self.duty_drift != Some(reported) {
+            self.duty_drift_events += 1;
+            tracing::warn!(
+                "EC reports fan duty {}% but {}% was set; another process may be writing \
+                 fan duties, re-asserting",
+                reported,
+                self.current_speed
+            );
+        }
+        self.duty_drift = Some(reported);
+        self.hw.set_fan_speed(self.current_speed)
+    }
+
+    /// The duty the EC reports while it differs from the one last set.
+    pub fn duty_drift(&self) -> Option<u32> {
+        self.duty_drift
+    }
+
+    /// Times since startup that the EC duty was found changed by someone else.
+    pub fn duty_drift_events(&self) -> u64 {
+        self.duty_drift_events
+    }
+
     pub fn set_speed(&mut self, speed: u32) -> Result<()> {
         self.hw.set_fan_speed(speed)?;
         self.current_speed = speed;
         Ok(())
     }
 
+    /// Forgets the readings behind the moving average and the low-pass filter,
+    /// so the next ones start from the same state as after startup, warm-up
+    /// included. The sample history for `history` and `export` is kept.
+    pub fn clear_history(&mut self) {
+        self.temp_history.clear();
+        self.spike_rejected = false;
+        self.low_pass = None;
+        self.warming_up = true;
+        self.since_update = None;
+    }
+
     pub fn pause(&mut self) -> Result<()> {
         self.active = false;
-        self.hw.enable_auto_fan()
+        self.enable_auto_fan()
     }
 
+    /// Takes the fan back from the EC and sets the current duty right away.
     pub fn resume(&mut self) -> Result<()> {
-        self.active = true;
+        let was_active = std::mem::replace(&mut self.active, true);
+        self.auto_fan_error = None;
+        if !was_active {
+            // The EC drove the fan while paused, so neither hysteresis nor the
+            // change budget should hold back the first duty
+            self.current_speed = 0;
+            self.speed_changes.clear();
+            self.apply_now_or_next_tick();
+        }
+        Ok(())
+    }
+
+    pub fn enable_auto_fan(&mut self) -> Result<()> {
+        if self.monitor_only {
+            return Ok(());
+        }
+        let result = self.hw.enable_auto_fan();
+        self.auto_fan_error = result.as_ref().err().map(|e| e.to_string());
+        result
+    }
+
+    /// Sets the EC charge-rate limit to the current strategy's `chargeRateLimit`,
+    /// or back to full rate once a strategy without one takes over. The limit in
+    /// place before is not read, so full rate is forced rather than restored.
+    fn apply_charge_rate_limit(&mut self) {
+        let wanted = self.get_current_strategy().charge_rate_limit;
+        if wanted == self.charge_rate_limit || self.monitor_only {
+            return;
+        }
+        // Only tried once per change, so an EC without support is not asked every tick
+        self.charge_rate_limit = wanted;
+        match self
+            .hw
+            .set_charge_rate_limit(wanted.unwrap_or(FULL_CHARGE_RATE))
+        {
+            Ok(()) => match wanted {
+                Some(rate) => tracing::info!("Limited the charge rate to {}C", rate),
+                None => tracing::info!("Lifted the charge rate limit"),
+            },
+            Err(e) => {
+                tracing::warn!("Failed to set the charge rate limit: {}", e);
+                self.record_error(ErrorContext::EcWrite, &e);
+            }
+        }
+    }
+
+    /// Lifts a charge-rate limit set for the current strategy, e.g. on shutdown,
+    /// by forcing full rate.
+    pub fn lift_charge_rate_limit(&mut self) -> Result<()> {
+        if self.charge_rate_limit.take().is_none() {
+            return Ok(());
+        }
+        self.hw.set_charge_rate_limit(FULL_CHARGE_RATE)
+    }
+
+    /// Who is driving the fan right now, and why when it isn't the daemon.
+    pub fn fan_mode(&self) -> (FanMode, Option<String>) {
+        let ec_auto = |reason: &str| (FanMode::EcAuto, Some(reason.to_string()));
+        if self.hw.is_degraded() {
+            return ec_auto("degraded, no EC access");
+        }
+        if let Some(reason) = self.hw.fan_control_refused() {
+            return ec_auto(reason);
+        }
+        if self.hw.is_fanless() {
+            return ec_auto("the EC reports no fan");
+        }
+        if !self.active {
+            return match &self.auto_fan_error {
+                Some(e) => (
+                    FanMode::Held,
+                    Some(format!("paused, switching to EC auto failed: {}", e)),
+                ),
+                None => ec_auto("paused"),
+            };
+        }
+        if self.warming_up && self.config.startup_behavior == StartupBehavior::EcAuto {
+            return ec_auto("starting up, until the moving average fills");
+        }
+        (FanMode::Managed, None)
+    }
+
+    /// Re-reads the AC state from the hardware; returns what changed, if anything.
+    /// A change only takes effect once it has lasted `powerSwitchDelaySeconds`.
+    /// On battery, a charge crossing `dischargingStrategyBatteryThreshold` also
+    /// counts as a change, as does charging starting or stopping on AC while
+    /// `strategyWhileCharging` is set.
+    pub fn refresh_power_state(&mut self) -> Option<StrategyChangeReason> {
+        self.power_polled_at = Instant::now();
+        let on_ac = match self.hw.is_on_ac() {
+            Ok(on_ac) => {
+                if std::mem::take(&mut self.ac_detection_failing) {
+                    tracing::info!("Power state readable again");
+                }
+                on_ac
+            }
+            Err(e) => self.ac_detection_failed(&e)?,
+        };
+        let source = if on_ac { "AC" } else { "battery" };
+        let was_above_threshold = self.battery_above_threshold();
+        let was_charging = self.is_charging();
+        self.battery_charge = self.hw.get_battery_charge();
+        self.charge_state = self.hw.get_charge_state();
+
+        if on_ac == self.on_ac {
+            if self.pending_power.take().is_some() {
+                tracing::info!("Power source back on {}, keeping strategy", source);
+            }
+            let charging_changed = on_ac
+                && was_charging != self.is_charging()
+                && !self.config.strategy_while_charging.is_empty();
+            if charging_changed {
+                let state = self.charge_state.map(|s| s.to_string());
+                tracing::info!(
+                    "Battery {}, switching strategy",
+                    state.as_deref().unwrap_or("state unknown")
+                );
+                return Some(StrategyChangeReason::ChargeState);
+            }
+            let crossed = !on_ac && was_above_threshold != self.battery_above_threshold();
+            if crossed {
+                tracing::info!(
+                    "Battery charge at {}%, switching strategy",
+                    self.battery_charge.unwrap_or(0)
+                );
+            }
+            return crossed.then_some(StrategyChangeReason::BatteryThreshold);
+        }
+
+        let delay = Duration::from_secs(self.config.power_switch_delay_seconds);
+        let since = match self.pending_power {
+            Some((pending, since)) if pending == on_ac => since,
+            _ => {
+                if !delay.is_zero() {
+                    tracing::info!(
+                        "Power source changed to {}, switching strategy in {}s",
+                        source,
+                        delay.as_secs()
+                    );
+                }
+                let now = Instant::now();
+                self.pending_power = Some((on_ac, now));
+                now
+            }
+        };
+        if since.elapsed() < delay {
+            return None;
+        }
+
+        tracing::info!("Power source changed: {}", source);
+        self.pending_power = None;
+        self.on_ac = on_ac;
+        Some(StrategyChangeReason::PowerSource)
+    }
+
+    /// Counts a failed read of the AC state and returns the state
+    /// `acDetectionFallback` assumes instead, if any.
+    fn ac_detection_failed(&mut self, error: &Error) -> Option<bool> {
+        self.ac_detection_failures += 1;
+        let assumed = self.config.ac_detection_fallback.assumed_on_ac();
+        if self.ac_detection_failing {
+            tracing::debug!("Failed to read power state: {}", error);
+        } else {
+            let fallback = match assumed {
+                Some(true) => "assuming AC",
+                Some(false) => "assuming battery",
+                None => "keeping the last known state",
+            };
+            tracing::warn!("Failed to read power state: {}; {}", error, fallback);
+        }
+        self.ac_detection_failing = true;
+        assumed
+    }
+
+    /// Failed reads of the AC state since startup.
+    pub fn ac_detection_failures(&self) -> u64 {
+        self.ac_detection_failures
+    }
+
+    /// Whether the battery is charged enough to keep the default strategy
+    /// while discharging. An unknown charge does not hold the switch back.
+    fn battery_above_threshold(&self) -> bool {
+        let threshold = self.config.discharging_strategy_battery_threshold;
+        threshold > 0
+            && self
+                .battery_charge
+                .is_some_and(|charge| charge >= threshold)
+    }
+
+    /// The strategy a pending power source change will switch to, and the
+    /// seconds left until it does.
+    pub fn pending_strategy_switch(&self) -> Option<(String, u64)> {
+        let (on_ac, since) = self.pending_power?;
+        let name = self.strategy_name_for(on_ac);
+        if name == self.current_strategy_name() {
+            return None;
+        }
+        let delay = Duration::from_secs(self.config.power_switch_delay_seconds);
+        Some((
+            name.to_string(),
+            delay.saturating_sub(since.elapsed()).as_secs(),
+        ))
+    }
+
+    /// Handles a power supply event: applies the strategy for the new power
+    /// source right away instead of on the next update tick.
+    pub fn handle_power_change(&mut self) -> Result<()> {
+        let Some(reason) = self.refresh_power_state() else {
+            return Ok(());
+        };
+        self.track_strategy(reason);
+        self.apply_now()
+    }
+
+    /// Reads the temperature and sets the duty the way the next update tick
+    /// would, restarting the update interval from here.
+    fn apply_now(&mut self) -> Result<()> {
+        let temp = self
+            .get_actual_temperature()
+            .inspect_err(|e| self.record_error(ErrorContext::EcRead, e))?;
+        self.update_speed(temp)
+            .inspect_err(|e| self.record_error(ErrorContext::EcWrite, e))?;
+        self.since_update = Some(Duration::ZERO);
+        Ok(())
+    }
+
+    /// Like [`Self::apply_now`] for commands whose change stands either way;
+    /// a failure leaves the duty to the next tick.
+    fn apply_now_or_next_tick(&mut self) {
+        if let Err(e) = self.apply_now() {
+            tracing::warn!("Failed to apply fan duty right away: {}", e);
+            self.since_update = None;
+        }
+    }
+
+    /// Asks the service loop to exit; `RestartAuto` hands the fan back to the EC right away.
+    pub fn request_shutdown(&mut self, request: ShutdownRequest) -> Result<()> {
+        if request == ShutdownRequest::RestartAuto {
+            self.pause()?;
+        }
+        self.shutdown_request = Some(request);
         Ok(())
     }
 
-    pub fn enable_auto_fan(&self) -> Result<()> {
-        self.hw.enable_auto_fan()
+    pub fn take_shutdown_request(&mut self) -> Option<ShutdownRequest> {
+        self.shutdown_request.take()
+    }
+
+    pub fn jobs(&self) -> &Jobs {
+        &self.jobs
     }
 
     pub fn is_active(&self) -> bool {
         self.active
     }
 
+    pub fn is_degraded(&self) -> bool {
+        self.hw.is_degraded()
+    }
+
+    pub fn capabilities(&self) -> Capabilities {
+        self.hw.capabilities()
+    }
+
+    pub fn get_fan_rpm(&self) -> Result<u16> {
+        self.hw.get_fan_rpm()
+    }
+
+    pub fn get_fan_rpms(&self) -> Result<Vec<u16>> {
+        self.hw.get_fan_rpms()
+    }
+
+    pub fn platform_info(&self) -> PlatformInfo {
+        self.hw.platform_info()
+    }
+
+    pub fn get_battery_temperature(&self) -> Result<Option<f64>> {
+        self.hw.get_battery_temperature()
+    }
+
+    pub fn get_sensor_temperatures(&self) -> Result<Vec<SensorReading>> {
+        self.hw.get_sensor_temperatures()
+    }
+
     pub fn get_current_speed(&self) -> u32 {
         self.current_speed
     }
@@ -158,32 +1151,1797 @@ impl FanController {
         &self.config
     }
 
+    /// Duty floor while the GPU has been busy for `gpuBoost.sustainSeconds`.
+    pub fn gpu_boost_floor(&self) -> Option<u32> {
+        self.config
+            .gpu_boost
+            .as_ref()
+            .filter(|boost| self.gpu_busy_samples >= boost.sustain_seconds.max(1))
+            .map(|boost| boost.min_speed)
+    }
+
+    /// Duty floor while a thermal zone is within `tripPoints.marginCelsius` of a trip point.
+    pub fn trip_point_floor(&self) -> Option<u32> {
+        self.config
+            .trip_points
+            .as_ref()
+            .filter(|_| self.near_trip.is_some())
+            .map(|trip_points| trip_points.min_speed)
+    }
+
+    /// The trip point the floor is held for, if any.
+    pub fn near_trip(&self) -> Option<&ThermalTrip> {
+        self.near_trip.as_ref()
+    }
+
+    /// The recent median when `temp` is a spike to discard, per `spikeFilterCelsius`.
+    ///
+    /// Only single readings are discarded: one that follows a discarded reading is
+    /// taken as a real change, so a sudden load still reaches the fan a tick later.
+    fn reject_spike(&mut self, temp: f64) -> Option<f64> {
+        let threshold = self.config.spike_filter_celsius;
+        if threshold == 0 || self.temp_history.len() < SPIKE_FILTER_WINDOW || self.spike_rejected {
+            self.spike_rejected = false;
+            return None;
+        }
+        let mut recent = [0.0; SPIKE_FILTER_WINDOW];
+        for (slot, t) in recent.iter_mut().zip(self.temp_history.iter().rev()) {
+            *slot = *t;
+        }
+        recent.sort_by(f64::total_cmp);
+        let median = recent[recent.len() / 2];
+        self.spike_rejected = (temp - median).abs() > threshold as f64;
+        self.spike_rejected.then_some(median)
+    }
+
+    /// Advances the low-pass filter by one control tick, or drops its state
+    /// when the current strategy smooths with the moving average.
+    fn update_low_pass(&mut self, temp: f64) {
+        let Smoothing::LowPass {
+            time_constant_seconds,
+        } = self.get_current_strategy().smoothing
+        else {
+            self.low_pass = None;
+            return;
+        };
+        let alpha = 1.0 / (time_constant_seconds + 1.0);
+        self.low_pass = Some(match self.low_pass {
+            Some(filtered) => filtered + alpha * (temp - filtered),
+            None => temp,
+        });
+    }
+
+    /// Counts a GPU utilization sample; returns whether the boost just started.
+    fn observe_gpu(&mut self, busy_percent: Option<u32>) -> bool {
+        let Some(boost) = &self.config.gpu_boost else {
+            return false;
+        };
+        let was_boosted = self.gpu_boost_floor().is_some();
+        self.gpu_busy_samples = match busy_percent {
+            Some(busy) if busy >= boost.busy_percent => self.gpu_busy_samples.saturating_add(1),
+            _ => 0,
+        };
+        !was_boosted && self.gpu_boost_floor().is_some()
+    }
+
+    /// Keeps the nearest of `trips` within `tripPoints.marginCelsius`; returns
+    /// whether a zone just came that close.
+    fn observe_trip_points(&mut self, trips: Vec<ThermalTrip>) -> bool {
+        let Some(trip_points) = &self.config.trip_points else {
+            self.near_trip = None;
+            return false;
+        };
+        let margin = trip_points.margin_celsius as f64;
+        let near = trips
+            .into_iter()
+            .filter(|trip| trip.margin() <= margin)
+            .min_by(|a, b| a.margin().total_cmp(&b.margin()));
+        let started = self.near_trip.is_none() && near.is_some();
+        match &near {
+            Some(trip) if started => tracing::info!(
+                "Thermal zone {} at {:.1}°C, near its {} trip point at {:.1}°C; raising fan duty floor",
+                trip.zone,
+                trip.zone_celsius,
+                trip.kind,
+                trip.trip_celsius
+            ),
+            None if self.near_trip.is_some() => {
+                tracing::info!("Thermal zones clear of their trip points again")
+            }
+            _ => {}
+        }
+        self.near_trip = near;
+        started
+    }
+
+    #[tracing::instrument(name = "control_step", skip(self))]
     pub fn step(&mut self) -> Result<f64> {
-        let temp = self.get_actual_temperature()?;
+        let reading = self
+            .get_actual_temperature()
+            .inspect_err(|e| self.record_error(ErrorContext::EcRead, e))?;
+        let spike = self.reject_spike(reading);
+        if spike.is_some() {
+            tracing::debug!("Discarded temperature spike: {:.1}°C", reading);
+        }
+        let temp = spike.unwrap_or(reading);
+        self.update_low_pass(temp);
+        self.warm_up(temp);
 
-        let strategy = self.get_current_strategy();
-        if self.timecount % strategy.fan_speed_update_frequency == 0 {
-            self.adapt_speed(temp)?;
-            self.timecount = 0;
+        // Events can be missed, e.g. without netlink access or across suspend;
+        // a pending switch is polled every tick until it settles
+        let poll_power =
+            self.pending_power.is_some() || self.power_polled_at.elapsed() >= POWER_POLL_INTERVAL;
+        // Without a power change, only a quiet-hours window can switch strategies here
+        let mut reason = StrategyChangeReason::QuietHours;
+        if let Some(power_reason) = poll_power.then(|| self.refresh_power_state()).flatten() {
+            reason = power_reason;
+            self.since_update = None;
+        }
+        self.track_strategy(reason);
+        self.apply_charge_rate_limit();
+        if self.profile_polled_at.elapsed() >= PROFILE_POLL_INTERVAL {
+            self.refresh_profile();
         }
 
-        self.temp_history.push_back(temp);
+        let gpu_busy = self
+            .config
+            .gpu_boost
+            .as_ref()
+            .and_then(|_| self.hw.get_gpu_busy_percent());
+        // React right away instead of waiting for the next update tick
+        let boost_started = self.observe_gpu(gpu_busy);
+        if boost_started {
+            tracing::debug!("GPU busy, raising fan duty floor");
+        }
+        let trips = match self.config.trip_points {
+            Some(_) => self.hw.get_thermal_trips(),
+            None => Vec::new(),
+        };
+        let trip_near = self.observe_trip_points(trips);
+
+        // One step per control tick, i.e. per second
+        let safety_temp = self.get_current_strategy().safety_temp;
+        self.thermal_stats
+            .record(temp, 1, &self.config.temperature_bands, safety_temp);
+
+        let update_interval =
+            Duration::from_secs(self.get_current_strategy().update_interval_seconds as u64);
+        self.since_update = self.since_update.map(|since| since + self.tick);
+        let update_due = self
+            .since_update
+            .map_or(true, |since| since >= update_interval);
+        let startup = self.startup_override();
+        if boost_started || trip_near || update_due {
+            self.update_speed(temp)
+                .inspect_err(|e| self.record_error(ErrorContext::EcWrite, e))?;
+            self.since_update = Some(Duration::ZERO);
+        } else if startup == Some(StartupBehavior::EcAuto) {
+            // Nothing was written, so there is no duty to verify
+        } else if let Err(e) = self.verify_duty() {
+            tracing::debug!("Fan duty readback failed: {}", e);
+        }
+
+        if spike.is_none() {
+            self.temp_history.push_back(temp);
+        }
 
         if self.temp_history.len() > TEMP_HISTORY_MAX_LEN {
             self.temp_history.pop_front();
         }
 
-        self.timecount += 1;
+        self.sample_history.push_back(HistoryEntry {
+            timestamp: unix_now(),
+            temperature: temp,
+            speed: self.current_speed,
+            active: self.active,
+        });
+
+        if self.sample_history.len() > SAMPLE_HISTORY_MAX_LEN {
+            self.sample_history.pop_front();
+        }
+        self.evaluate_alerts(temp);
 
         Ok(temp)
     }
 
-    pub fn reload_config(&mut self, config: Config) {
-        self.config = config;
-        if let Some(ref name) = self.overwritten_strategy {
-            if self.config.get_strategy(name).is_none() {
-                self.overwritten_strategy = None;
+    /// Advances each alert by one tick: it trips once its condition has held
+    /// for `forSeconds` and clears on the first tick it no longer holds.
+    fn evaluate_alerts(&mut self, temp: f64) {
+        if self.config.alerts.is_empty() {
+            return;
+        }
+        let interval = self.get_current_strategy().moving_average_interval;
+        let effective = self.get_effective_temperature(temp, interval);
+        for alert in &self.config.alerts {
+            let reading = match alert.metric {
+                AlertMetric::Temperature => temp,
+                AlertMetric::EffectiveTemperature => effective,
+                AlertMetric::Duty => self.current_speed as f64,
+            };
+            let state = self.alerts.entry(alert.name.clone()).or_default();
+            let tripped = if alert.holds(reading) {
+                state.held += self.tick;
+                state.tripped || state.held >= Duration::from_secs(alert.for_seconds)
+            } else {
+                state.held = Duration::ZERO;
+                false
+            };
+            if tripped == state.tripped {
+                continue;
             }
+            state.tripped = tripped;
+            if tripped {
+                tracing::warn!("Alert {} tripped at {:.1}", alert.name, reading);
+            } else {
+                tracing::info!("Alert {} cleared at {:.1}", alert.name, reading);
+            }
+            self.alert_events.push(AlertEvent {
+                alert: alert.clone(),
+                tripped,
+                reading,
+            });
         }
     }
+
+    /// Names of the alerts currently tripped, in config order.
+    pub fn active_alerts(&self) -> Vec<String> {
+        self.config
+            .alerts
+            .iter()
+            .filter(|alert| self.alerts.get(&alert.name).is_some_and(|s| s.tripped))
+            .map(|alert| alert.name.clone())
+            .collect()
+    }
+
+    /// Trips and clears since the last call, oldest first.
+    pub fn take_alert_events(&mut self) -> Vec<AlertEvent> {
+        std::mem::take(&mut self.alert_events)
+    }
+
+    /// Seeds the history on the first reading with `startupBehavior` `seedHistory`,
+    /// and ends warm-up once the moving-average window is full or the temperature
+    /// reaches `safetyTemp`.
+    fn warm_up(&mut self, temp: f64) {
+        if !self.warming_up {
+            return;
+        }
+        let strategy = self.get_current_strategy();
+        let interval = (strategy.moving_average_interval as usize).min(TEMP_HISTORY_MAX_LEN);
+        let too_hot = temp >= strategy.safety_temp as f64;
+        if self.temp_history.is_empty()
+            && self.config.startup_behavior == StartupBehavior::SeedHistory
+        {
+            self.temp_history
+                .extend(std::iter::repeat(temp).take(interval));
+        }
+        if self.temp_history.len() >= interval || too_hot {
+            self.warming_up = false;
+        }
+    }
+
+    /// The `startupBehavior` standing in for the curve while warming up, if any.
+    fn startup_override(&self) -> Option<StartupBehavior> {
+        self.warming_up
+            .then_some(self.config.startup_behavior)
+            .filter(|b| matches!(b, StartupBehavior::EcAuto | StartupBehavior::FixedSpeed))
+    }
+
+    /// Sets the duty for `temp`, or what `startupBehavior` asks for during warm-up.
+    fn update_speed(&mut self, temp: f64) -> Result<()> {
+        match self.startup_override() {
+            // The EC keeps the fan from startup; nothing to write yet
+            Some(StartupBehavior::EcAuto) => Ok(()),
+            Some(_) => self.hold_startup_speed(),
+            None => self.adapt_speed(temp),
+        }
+    }
+
+    /// Sets `startupSpeed` during warm-up, or tracks it in monitor-only mode.
+    fn hold_startup_speed(&mut self) -> Result<()> {
+        let speed = self.config.startup_speed;
+        let writable = self.hw.can_control_fan();
+        if !self.active || !(writable || self.monitor_only) {
+            return Ok(());
+        }
+        if writable {
+            self.hw.set_fan_speed(speed)?;
+        }
+        self.current_speed = speed;
+        // No curve chose this duty, so hysteresis has nothing to hold it to
+        self.speed_set_at_temp = 0.0;
+        Ok(())
+    }
+
+    /// Samples recorded at or after `since` (seconds since the Unix epoch).
+    pub fn history_since(&self, since: u64) -> Vec<HistoryEntry> {
+        self.sample_history
+            .iter()
+            .filter(|e| e.timestamp >= since)
+            .copied()
+            .collect()
+    }
+
+    /// Keeps `error` for `print errors`, dropping the oldest once the log is full.
+    pub fn record_error(&mut self, context: ErrorContext, error: &Error) {
+        self.errors.push_back(ErrorEntry {
+            timestamp: unix_now(),
+            context,
+            message: error.to_string(),
+        });
+        if self.errors.len() > ERROR_LOG_MAX_LEN {
+            self.errors.pop_front();
+        }
+    }
+
+    /// Recorded errors, oldest first.
+    pub fn recent_errors(&self) -> Vec<ErrorEntry> {
+        self.errors.iter().cloned().collect()
+    }
+
+    pub fn thermal_stats(&self) -> &ThermalStats {
+        &self.thermal_stats
+    }
+
+    /// Continues counting from `stats`, e.g. loaded from the previous run.
+    pub fn set_thermal_stats(&mut self, stats: ThermalStats) {
+        self.thermal_stats = stats;
+    }
+
+    pub fn record_self_test(&mut self, result: SelfTestResult) {
+        self.thermal_stats.record_self_test(result);
+    }
+
+    /// Whether the `scheduledSelfTest` should start now: its interval has passed
+    /// since the last one, the controller drives the fan and the machine is cool.
+    pub fn self_test_due(&self, now: u64) -> bool {
+        let Some(schedule) = &self.config.scheduled_self_test else {
+            return false;
+        };
+        let last = self.thermal_stats.last_self_test.map(|result| result.at);
+        if last.is_some_and(|at| now < at + schedule.interval_hours * 3600) {
+            return false;
+        }
+        if !self.active
+            || self.monitor_only
+            || self.warming_up
+            || !self.hw.can_control_fan()
+            || self.jobs.is_running(SCHEDULED_SELF_TEST_JOB)
+        {
+            return false;
+        }
+        let interval = self.get_current_strategy().moving_average_interval;
+        self.get_moving_average_temperature(interval) < schedule.max_temp as f64
+    }
+
+    pub fn history_stats(&self) -> HistoryStats {
+        let temps = self.sample_history.iter().map(|e| e.temperature);
+        let samples = self.sample_history.len();
+        HistoryStats {
+            samples,
+            min_temperature: temps.clone().reduce(f64::min),
+            max_temperature: temps.clone().reduce(f64::max),
+            mean_temperature: (samples > 0).then(|| temps.sum::<f64>() / samples as f64),
+            speed_changes: self
+                .sample_history
+                .iter()
+                .zip(self.sample_history.iter().skip(1))
+                .filter(|(a, b)| a.speed != b.speed)
+                .count(),
+        }
+    }
+
+    /// The temperature slope over the last minute and the next curve
+    /// breakpoint or `safetyTemp` it is heading for; `None` until enough
+    /// samples have come in.
+    pub fn temperature_trend(&self) -> Option<Trend> {
+        let newest = self.sample_history.back()?;
+        let since = newest.timestamp.saturating_sub(TREND_WINDOW_SECONDS);
+        let mut samples: Vec<(u64, f64)> = self
+            .sample_history
+            .iter()
+            .rev()
+            .take_while(|e| e.timestamp >= since)
+            .map(|e| (e.timestamp, e.temperature))
+            .collect();
+        if samples.len() < TREND_MIN_SAMPLES {
+            return None;
+        }
+        samples.reverse();
+        let slope = slope_per_minute(&samples)?;
+        let temp = newest.temperature;
+
+        let target = (slope.abs() >= TREND_STEADY_SLOPE).then(|| {
+            let rising = slope > 0.0;
+            let curve = self
+                .config
+                .resolved_curve(self.current_strategy_name())
+                .unwrap_or_default();
+            let breakpoint = crate::curve::next_breakpoint(&curve, temp, rising)
+                .map(|t| (TrendTargetKind::Breakpoint, t));
+            let safety_temp = self.get_current_strategy().safety_temp;
+            let safety = (rising && safety_temp as f64 > temp)
+                .then_some((TrendTargetKind::SafetyTemp, safety_temp));
+            breakpoint
+                .into_iter()
+                .chain(safety)
+                .min_by(|a, b| {
+                    (a.1 as f64 - temp)
+                        .abs()
+                        .total_cmp(&(b.1 as f64 - temp).abs())
+                })
+                .map(|(kind, temperature)| TrendTarget {
+                    kind,
+                    temperature,
+                    eta_seconds: ((temperature as f64 - temp) / slope * 60.0).round() as u64,
+                })
+        });
+        Some(Trend {
+            slope_per_minute: (slope * 100.0).round() / 100.0,
+            target: target.flatten(),
+        })
+    }
+
+    /// Replaces a strategy's speed curve in the running config, without
+    /// touching the config file.
+    pub fn set_strategy_curve(&mut self, name: &str, curve: Vec<CurvePoint>) -> Result<()> {
+        if curve.is_empty() {
+            return Err(Error::Config("Speed curve must not be empty".into()));
+        }
+        let strategy = self
+            .config
+            .strategies
+            .get_mut(name)
+            .ok_or_else(|| Error::Strategy(format!("Unknown strategy: {}", name)))?;
+        strategy.speed_curve = curve;
+        Ok(())
+    }
+
+    /// Changes one numeric strategy setting in memory only; `reload` undoes it.
+    pub fn tune_strategy(&mut self, name: &str, field: &str, value: u32) -> Result<&Strategy> {
+        let strategy = self
+            .config
+            .strategies
+            .get_mut(name)
+            .ok_or_else(|| Error::Strategy(format!("Unknown strategy: {}", name)))?;
+        strategy.set_parameter(field, value)?;
+        self.since_update = None;
+        Ok(strategy)
+    }
+
+    /// Loads the config file again, or `path` from now on, and applies it.
+    /// Returns what changed; failures are recorded for `print errors`.
+    pub fn reload_from(&mut self, path: Option<PathBuf>) -> Result<ConfigDiff> {
+        let path = path.unwrap_or_else(|| self.config_path.clone());
+        let config = Config::load(&path)
+            .inspect_err(|e| self.record_error(ErrorContext::ConfigReload, e))?;
+        let diff = self.base_config.diff(&config);
+        self.reload_config(config);
+        if diff.is_empty() {
+            tracing::info!("Reloaded {}, nothing changed", path.display());
+        } else {
+            tracing::info!("Reloaded {}: {}", path.display(), diff.changes.join(", "));
+        }
+        self.config_path = path;
+        Ok(diff)
+    }
+
+    pub fn reload_config(&mut self, config: Config) {
+        self.base_config = config;
+        let profile = self
+            .profile
+            .take()
+            .filter(|name| self.base_config.profiles.contains_key(name));
+        self.profile_pinned &= profile.is_some();
+        let effective = match &profile {
+            Some(name) => self.base_config.with_profile(name),
+            None => Ok(self.base_config.clone()),
+        };
+        self.profile = profile;
+        // Profiles were validated along with the config, so this doesn't fail
+        self.apply_config(effective.unwrap_or_else(|_| self.base_config.clone()));
+        self.track_strategy(StrategyChangeReason::Reload);
+        self.refresh_profile();
+    }
+
+    /// Replaces the config in effect, e.g. with another profile's.
+    fn apply_config(&mut self, config: Config) {
+        self.hw.set_sensor_selection(config.sensor_selection());
+        if let Some(excluded) = config.exclude_battery_sensor {
+            self.hw.set_battery_excluded(excluded);
+        }
+        self.alerts
+            .retain(|name, _| config.alerts.iter().any(|alert| &alert.name == name));
+        self.config = config;
+        if let Some(ref name) = self.overwritten_strategy {
+            if self.config.get_strategy(name).is_none() {
+                self.overwritten_strategy = None;
+            }
+        }
+    }
+
+    /// The active profile, if any.
+    pub fn profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+
+    /// Whether the active profile was chosen with `profile use` rather than by its trigger.
+    pub fn is_profile_pinned(&self) -> bool {
+        self.profile_pinned
+    }
+
+    /// Profiles in the config, in name order.
+    pub fn profile_names(&self) -> Vec<String> {
+        self.base_config.profiles.keys().cloned().collect()
+    }
+
+    /// Activates `name` until `profile auto` or a reload that removes it; `None`
+    /// goes back to the profile whose trigger matches, if any.
+    pub fn use_profile(&mut self, name: Option<&str>) -> Result<()> {
+        match name {
+            Some(name) => {
+                self.switch_profile(Some(name.to_string()))?;
+                self.profile_pinned = true;
+            }
+            None => {
+                self.profile_pinned = false;
+                self.refresh_profile();
+            }
+        }
+        Ok(())
+    }
+
+    /// Switches to the profile whose trigger matches now, unless one is pinned.
+    pub fn refresh_profile(&mut self) {
+        self.profile_polled_at = Instant::now();
+        if self.profile_pinned {
+            return;
+        }
+        // Sysfs is only read when some profile can activate by itself
+        let matching = self
+            .base_config
+            .has_profile_triggers()
+            .then(|| self.hw.profile_environment())
+            .and_then(|env| self.base_config.matching_profile(&env).map(str::to_string));
+        if matching != self.profile {
+            if let Err(e) = self.switch_profile(matching) {
+                tracing::error!("Failed to switch profile: {}", e);
+            }
+        }
+    }
+
+    fn switch_profile(&mut self, name: Option<String>) -> Result<()> {
+        let config = match &name {
+            Some(name) => self.base_config.with_profile(name)?,
+            None => self.base_config.clone(),
+        };
+        tracing::info!(
+            from = self.profile.as_deref().unwrap_or("none"),
+            to = name.as_deref().unwrap_or("none"),
+            "Profile changed"
+        );
+        self.profile = name;
+        self.apply_config(config);
+        self.since_update = None;
+        self.track_strategy(StrategyChangeReason::Profile);
+        Ok(())
+    }
+}
+
+impl AsRef<HardwareController> for FanController {
+    fn as_ref(&self) -> &HardwareController {
+        &self.hw
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockEc;
+
+    fn controller(ec: &MockEc, strategy: &str) -> FanController {
+        let config: Config = serde_json::from_str(&format!(
+            r#"{{
+                "defaultStrategy": "linear",
+                "strategyOnDischarging": "",
+                "strategies": {{ "linear": {} }}
+            }}"#,
+            strategy
+        ))
+        .unwrap();
+        let hw = HardwareController::with_backend(Box::new(ec.clone()), None, "mock");
+        FanController::new(hw, config, None)
+    }
+
+    #[test]
+    fn test_hysteresis_delays_slowdown() {
+        let ec = MockEc::new();
+        let mut ctrl = controller(
+            &ec,
+            r#"{"updateIntervalSeconds": 1, "movingAverageInterval": 1, "hysteresis": 5,
+                "speedCurve": [{"temp": 0, "speed": 0}, {"temp": 100, "speed": 100}]}"#,
+        );
+
+        ec.set_temperatures(&[60]);
+        ctrl.adapt_speed(60.0).unwrap();
+        assert_eq!(ec.duty(), Some(60));
+
+        ec.set_temperatures(&[57]);
+        ctrl.adapt_speed(57.0).unwrap();
+        assert_eq!(ctrl.get_current_speed(), 60);
+
+        ec.set_temperatures(&[54]);
+        ctrl.adapt_speed(54.0).unwrap();
+        assert_eq!(ctrl.get_current_speed(), 54);
+    }
+
+    #[test]
+    fn test_hysteresis_lets_a_floor_end() {
+        let ec = MockEc::new();
+        let mut ctrl = controller(
+            &ec,
+            r#"{"updateIntervalSeconds": 1, "movingAverageInterval": 1, "hysteresis": 3,
+                "speedCurve": [{"temp": 0, "speed": 0}, {"temp": 100, "speed": 100}]}"#,
+        );
+        ctrl.config.gpu_boost = Some(crate::config::GpuBoost {
+            busy_percent: 80,
+            sustain_seconds: 1,
+            min_speed: 60,
+        });
+        ctrl.config.trip_points = Some(crate::config::TripPoints {
+            margin_celsius: 5,
+            min_speed: 70,
+        });
+        ec.set_temperatures(&[40]);
+
+        ctrl.observe_gpu(Some(90));
+        ctrl.adapt_speed(40.0).unwrap();
+        assert_eq!(ctrl.get_current_speed(), 60);
+        ctrl.observe_gpu(Some(10));
+        ctrl.adapt_speed(40.0).unwrap();
+        assert_eq!(ctrl.get_current_speed(), 40);
+
+        ctrl.observe_trip_points(vec![ThermalTrip {
+            zone: "acpitz".into(),
+            kind: "passive".into(),
+            trip_celsius: 95.0,
+            zone_celsius: 92.0,
+        }]);
+        ctrl.adapt_speed(40.0).unwrap();
+        assert_eq!(ctrl.get_current_speed(), 70);
+        ctrl.observe_trip_points(Vec::new());
+        ctrl.adapt_speed(40.0).unwrap();
+        assert_eq!(ctrl.get_current_speed(), 40);
+    }
+
+    #[test]
+    fn test_hysteresis_measures_from_where_the_speed_was_set() {
+        let ec = MockEc::new();
+        let mut ctrl = controller(
+            &ec,
+            r#"{"updateIntervalSeconds": 1, "movingAverageInterval": 1, "hysteresis": 5,
+                "speedCurve": [{"temp": 0, "speed": 0}, {"temp": 100, "speed": 100}]}"#,
+        );
+
+        ec.set_temperatures(&[70]);
+        ctrl.adapt_speed(70.0).unwrap();
+        assert_eq!(ctrl.get_current_speed(), 70);
+
+        // A slow drift down never slips past the margin one reading at a time
+        for temp in [69, 68, 67, 66] {
+            ec.set_temperatures(&[temp]);
+            ctrl.adapt_speed(temp as f64).unwrap();
+            assert_eq!(ctrl.get_current_speed(), 70);
+        }
+        ec.set_temperatures(&[64]);
+        ctrl.adapt_speed(64.0).unwrap();
+        assert_eq!(ctrl.get_current_speed(), 64);
+
+        // Rising temperatures are never held back
+        ec.set_temperatures(&[66]);
+        ctrl.adapt_speed(66.0).unwrap();
+        assert_eq!(ctrl.get_current_speed(), 66);
+    }
+
+    #[test]
+    fn test_speed_decays_while_cooling() {
+        let ec = MockEc::new();
+        let mut ctrl = controller(
+            &ec,
+            r#"{"updateIntervalSeconds": 1, "movingAverageInterval": 1, "speedDecaySeconds": 2,
+                "speedCurve": [{"temp": 0, "speed": 0}, {"temp": 100, "speed": 100}]}"#,
+        );
+
+        ec.set_temperatures(&[80]);
+        ctrl.adapt_speed(80.0).unwrap();
+        assert_eq!(ec.duty(), Some(80));
+
+        // The burst is over, but the duty holds until a decay step has passed
+        ec.set_temperatures(&[40]);
+        ctrl.adapt_speed(40.0).unwrap();
+        assert_eq!(ctrl.get_current_speed(), 80);
+
+        ctrl.decay_from -= Duration::from_secs(5);
+        ctrl.adapt_speed(40.0).unwrap();
+        assert_eq!(ctrl.get_current_speed(), 78);
+
+        ctrl.decay_from -= Duration::from_secs(300);
+        ctrl.adapt_speed(40.0).unwrap();
+        assert_eq!(ctrl.get_current_speed(), 40);
+
+        // Rising temperatures are still followed immediately
+        ec.set_temperatures(&[90]);
+        ctrl.adapt_speed(90.0).unwrap();
+        assert_eq!(ctrl.get_current_speed(), 90);
+    }
+
+    #[test]
+    fn test_speed_bias_is_clamped_and_cleared_by_reset() {
+        let ec = MockEc::new();
+        let mut ctrl = controller(
+            &ec,
+            r#"{"updateIntervalSeconds": 1, "movingAverageInterval": 1,
+                "speedCurve": [{"temp": 0, "speed": 0}, {"temp": 100, "speed": 100}]}"#,
+        );
+        ec.set_temperatures(&[95]);
+
+        ctrl.set_speed_bias(10);
+        ctrl.adapt_speed(95.0).unwrap();
+        assert_eq!(ctrl.get_current_speed(), 100);
+
+        ctrl.set_speed_bias(-5);
+        ctrl.adapt_speed(95.0).unwrap();
+        assert_eq!(ctrl.get_current_speed(), 90);
+
+        ctrl.clear_overwritten_strategy();
+        assert_eq!(ctrl.get_speed_bias(), 0);
+        ctrl.adapt_speed(95.0).unwrap();
+        assert_eq!(ctrl.get_current_speed(), 95);
+    }
+
+    #[test]
+    fn test_speed_bias_applies_right_away() {
+        let ec = MockEc::new();
+        let mut ctrl = controller(
+            &ec,
+            r#"{"updateIntervalSeconds": 5, "movingAverageInterval": 1, "hysteresis": 5,
+                "speedCurve": [{"temp": 0, "speed": 0}, {"temp": 100, "speed": 100}]}"#,
+        );
+        ec.set_temperatures(&[50]);
+        ctrl.step().unwrap();
+        assert_eq!(ctrl.get_current_speed(), 50);
+
+        ctrl.set_speed_bias(-10);
+        assert_eq!(ec.duty(), Some(40));
+        ctrl.step().unwrap();
+        assert_eq!(ctrl.get_current_speed(), 40);
+    }
+
+    #[test]
+    fn test_power_change_switches_strategy_immediately() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "defaultStrategy": "ac",
+                "strategyOnDischarging": "battery",
+                "strategies": {
+                    "ac": {"updateIntervalSeconds": 30, "speedCurve": [{"temp": 0, "speed": 60}]},
+                    "battery": {"updateIntervalSeconds": 30, "speedCurve": [{"temp": 0, "speed": 20}]}
+                }
+            }"#,
+        )
+        .unwrap();
+        let ec = MockEc::new();
+        ec.set_temperatures(&[50]);
+        let hw = HardwareController::with_backend(Box::new(ec.clone()), None, "mock");
+        let mut ctrl = FanController::new(hw, config, None);
+        ctrl.step().unwrap();
+        assert_eq!(ec.duty(), Some(60));
+
+        // Cached until an event or the periodic poll
+        ec.set_ac_present(false);
+        assert_eq!(ctrl.get_current_strategy_name(), "ac");
+        ctrl.strategy_changed_at = 0;
+
+        ctrl.handle_power_change().unwrap();
+        assert_eq!(ctrl.get_current_strategy_name(), "battery");
+        assert_eq!(ec.duty(), Some(20));
+        assert!(!ctrl.is_on_ac());
+        assert!(ctrl.last_strategy_change() > 0);
+        assert_eq!(
+            ctrl.last_strategy_change_reason(),
+            Some(StrategyChangeReason::PowerSource)
+        );
+    }
+
+    #[test]
+    fn test_discharging_strategy_waits_for_battery_threshold() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "defaultStrategy": "ac",
+                "strategyOnDischarging": "battery",
+                "dischargingStrategyBatteryThreshold": 60,
+                "strategies": {
+                    "ac": {"speedCurve": [{"temp": 0, "speed": 60}]},
+                    "battery": {"speedCurve": [{"temp": 0, "speed": 20}]}
+                }
+            }"#,
+        )
+        .unwrap();
+        config.validate().unwrap();
+        let ec = MockEc::new();
+        ec.set_battery_charge(80);
+        let hw = HardwareController::with_backend(Box::new(ec.clone()), None, "mock");
+        let mut ctrl = FanController::new(hw, config, None);
+
+        // Unplugged with plenty of charge keeps the default strategy
+        ec.set_ac_present(false);
+        ctrl.refresh_power_state();
+        assert_eq!(ctrl.get_current_strategy_name(), "ac");
+        assert_eq!(ctrl.pending_strategy_switch(), None);
+
+        ec.set_battery_charge(59);
+        assert_eq!(
+            ctrl.refresh_power_state(),
+            Some(StrategyChangeReason::BatteryThreshold)
+        );
+        assert_eq!(ctrl.get_current_strategy_name(), "battery");
+    }
+
+    #[test]
+    fn test_charging_strategy_follows_charge_state() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "defaultStrategy": "ac",
+                "strategyWhileCharging": "charging",
+                "strategies": {
+                    "ac": {"speedCurve": [{"temp": 0, "speed": 30}]},
+                    "charging": {"speedCurve": [{"temp": 0, "speed": 50}]}
+                }
+            }"#,
+        )
+        .unwrap();
+        config.validate().unwrap();
+        let ec = MockEc::new();
+        ec.set_charge_state(ChargeState::Charging);
+        let hw = HardwareController::with_backend(Box::new(ec.clone()), None, "mock");
+        let mut ctrl = FanController::new(hw, config, None);
+        assert_eq!(ctrl.get_current_strategy_name(), "charging");
+        assert_eq!(ctrl.get_current_strategy().speed_curve[0].speed, 50);
+
+        ec.set_charge_state(ChargeState::ChargeLimited);
+        assert_eq!(
+            ctrl.refresh_power_state(),
+            Some(StrategyChangeReason::ChargeState)
+        );
+        assert_eq!(ctrl.get_current_strategy_name(), "ac");
+        assert_eq!(ctrl.charge_state(), Some(ChargeState::ChargeLimited));
+
+        ec.set_charge_state(ChargeState::Full);
+        assert_eq!(ctrl.refresh_power_state(), None);
+    }
+
+    #[test]
+    fn test_charge_rate_limit_follows_strategy() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "defaultStrategy": "normal",
+                "strategyOnDischarging": "",
+                "strategies": {
+                    "normal": {"speedCurve": [{"temp": 0, "speed": 40}]},
+                    "quiet": {"speedCurve": [{"temp": 0, "speed": 20}], "chargeRateLimit": 0.5}
+                }
+            }"#,
+        )
+        .unwrap();
+        let ec = MockEc::new();
+        ec.set_temperatures(&[50]);
+        let hw = HardwareController::with_backend(Box::new(ec.clone()), None, "mock");
+        let mut ctrl = FanController::new(hw, config, None);
+        ctrl.step().unwrap();
+        assert_eq!(ec.charge_rate_limit(), None);
+
+        ctrl.overwrite_strategy("quiet").unwrap();
+        ctrl.step().unwrap();
+        assert_eq!(ec.charge_rate_limit(), Some(0.5));
+
+        ctrl.clear_overwritten_strategy();
+        ctrl.step().unwrap();
+        assert_eq!(ec.charge_rate_limit(), Some(FULL_CHARGE_RATE));
+
+        ctrl.overwrite_strategy("quiet").unwrap();
+        ctrl.step().unwrap();
+        ctrl.lift_charge_rate_limit().unwrap();
+        assert_eq!(ec.charge_rate_limit(), Some(FULL_CHARGE_RATE));
+    }
+
+    #[test]
+    fn test_alert_trips_after_holding_and_clears() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "defaultStrategy": "flat",
+                "strategyOnDischarging": "",
+                "strategies": {
+                    "flat": {"speedCurve": [{"temp": 0, "speed": 40}], "movingAverageInterval": 1}
+                },
+                "alerts": [
+                    {"name": "hot", "metric": "temperature", "op": ">", "value": 80, "forSeconds": 3},
+                    {"name": "duty", "metric": "duty", "op": "=", "value": 40}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let ec = MockEc::new();
+        let hw = HardwareController::with_backend(Box::new(ec.clone()), None, "mock");
+        let mut ctrl = FanController::new(hw, config, None);
+
+        ec.set_temperatures(&[90]);
+        ctrl.step().unwrap();
+        ctrl.step().unwrap();
+        assert_eq!(ctrl.active_alerts(), vec!["duty"]);
+        ec.set_temperatures(&[70]);
+        ctrl.step().unwrap();
+        ec.set_temperatures(&[90]);
+        ctrl.step().unwrap();
+        ctrl.step().unwrap();
+        assert_eq!(ctrl.active_alerts(), vec!["duty"]);
+        ctrl.step().unwrap();
+        assert_eq!(ctrl.active_alerts(), vec!["hot", "duty"]);
+
+        ec.set_temperatures(&[60]);
+        ctrl.step().unwrap();
+        assert_eq!(ctrl.active_alerts(), vec!["duty"]);
+        let events: Vec<(String, bool)> = ctrl
+            .take_alert_events()
+            .into_iter()
+            .map(|e| (e.alert.name, e.tripped))
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                ("duty".to_string(), true),
+                ("hot".to_string(), true),
+                ("hot".to_string(), false)
+            ]
+        );
+        assert!(ctrl.take_alert_events().is_empty());
+    }
+
+    #[test]
+    fn test_discharge_saver_lowers_duty_while_draining() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "defaultStrategy": "flat",
+                "strategyOnDischarging": "",
+                "strategies": {
+                    "flat": {
+                        "speedCurve": [{"temp": 0, "speed": 40}],
+                        "movingAverageInterval": 1,
+                        "dischargeSaver": {"minWatts": 20, "maxTemp": 60, "speedReduction": 15}
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        let ec = MockEc::new();
+        ec.set_temperatures(&[50]);
+        ec.set_ac_present(false);
+        ec.set_battery_discharge_watts(25.0);
+        let hw = HardwareController::with_backend(Box::new(ec.clone()), None, "mock");
+        let mut ctrl = FanController::new(hw, config, None);
+        ctrl.step().unwrap();
+        assert_eq!(ctrl.get_current_speed(), 25);
+
+        ec.set_battery_discharge_watts(10.0);
+        ctrl.adapt_speed(50.0).unwrap();
+        assert_eq!(ctrl.get_current_speed(), 40);
+
+        ec.set_battery_discharge_watts(25.0);
+        ec.set_temperatures(&[70]);
+        ctrl.step().unwrap();
+        ctrl.adapt_speed(70.0).unwrap();
+        assert_eq!(ctrl.get_current_speed(), 40);
+    }
+
+    #[test]
+    fn test_battery_sensor_exclusion_at_runtime() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "defaultStrategy": "linear",
+                "strategyOnDischarging": "",
+                "excludeBatterySensor": true,
+                "strategies": {"linear": {"speedCurve": [{"temp": 0, "speed": 0}]}}
+            }"#,
+        )
+        .unwrap();
+        let ec = MockEc::new();
+        ec.set_temperatures(&[45, 50, 38, 70]);
+        let hw = HardwareController::with_backend(Box::new(ec.clone()), Some(3), "mock");
+        let mut ctrl = FanController::new(hw, config.clone(), None);
+        assert!(ctrl.is_battery_excluded());
+        assert_eq!(ctrl.get_actual_temperature().unwrap(), 50.0);
+
+        ctrl.set_battery_excluded(false).unwrap();
+        assert_eq!(ctrl.get_actual_temperature().unwrap(), 70.0);
+
+        let hw = HardwareController::with_backend(Box::new(ec), None, "mock");
+        let mut ctrl = FanController::new(hw, config, None);
+        assert!(ctrl.set_battery_excluded(true).is_err());
+    }
+
+    #[test]
+    fn test_ac_detection_fallback() {
+        let json = |fallback: &str| {
+            format!(
+                r#"{{
+                    "defaultStrategy": "ac",
+                    "strategyOnDischarging": "battery",
+                    "acDetectionFallback": "{}",
+                    "strategies": {{
+                        "ac": {{"speedCurve": [{{"temp": 0, "speed": 50}}]}},
+                        "battery": {{"speedCurve": [{{"temp": 0, "speed": 20}}]}}
+                    }}
+                }}"#,
+                fallback
+            )
+        };
+        let ec = MockEc::new();
+        ec.set_ac_unreadable(true);
+        for (fallback, strategy) in [("assumeAc", "ac"), ("assumeBattery", "battery")] {
+            let config: Config = serde_json::from_str(&json(fallback)).unwrap();
+            let hw = HardwareController::with_backend(Box::new(ec.clone()), None, "mock");
+            let ctrl = FanController::new(hw, config, None);
+            assert_eq!(ctrl.get_current_strategy_name(), strategy);
+            assert_eq!(ctrl.ac_detection_failures(), 1);
+        }
+
+        ec.set_ac_unreadable(false);
+        let config: Config = serde_json::from_str(&json("holdLast")).unwrap();
+        let hw = HardwareController::with_backend(Box::new(ec.clone()), None, "mock");
+        let mut ctrl = FanController::new(hw, config, None);
+        ec.set_ac_unreadable(true);
+        assert_eq!(ctrl.refresh_power_state(), None);
+        assert_eq!(ctrl.refresh_power_state(), None);
+        assert_eq!(ctrl.get_current_strategy_name(), "ac");
+        assert_eq!(ctrl.ac_detection_failures(), 2);
+    }
+
+    #[test]
+    fn test_apply_once_uses_the_raw_curve() {
+        let ec = MockEc::new();
+        let mut ctrl = controller(
+            &ec,
+            r#"{"hysteresis": 10, "speedCurve": [{"temp": 0, "speed": 0}, {"temp": 100, "speed": 100}]}"#,
+        );
+        ec.set_temperatures(&[60]);
+        assert_eq!(ctrl.apply_once().unwrap(), (60.0, 60));
+        ec.set_temperatures(&[40]);
+        assert_eq!(ctrl.apply_once().unwrap(), (40.0, 40));
+        assert_eq!(ec.duty(), Some(40));
+    }
+
+    #[test]
+    fn test_monitor_only_never_writes() {
+        let ec = MockEc::new();
+        let mut ctrl = controller(
+            &ec,
+            r#"{"movingAverageInterval": 1, "speedCurve": [{"temp": 0, "speed": 0}, {"temp": 100, "speed": 100}]}"#,
+        )
+        .with_monitor_only();
+        ec.set_temperatures(&[60]);
+        ctrl.step().unwrap();
+        ctrl.pause().unwrap();
+
+        assert_eq!(ctrl.get_current_speed(), 60);
+        assert!(ec.duty_writes().is_empty());
+        assert!(ctrl.apply_once().is_err());
+    }
+
+    #[test]
+    fn test_update_interval_counts_seconds_not_ticks() {
+        let ec = MockEc::new();
+        let mut ctrl = controller(
+            &ec,
+            r#"{"updateIntervalSeconds": 2, "movingAverageInterval": 1,
+                "speedCurve": [{"temp": 0, "speed": 0}, {"temp": 100, "speed": 100}]}"#,
+        )
+        .with_tick(Duration::from_millis(500));
+
+        ec.set_temperatures(&[40]);
+        let mut updated_at = Vec::new();
+        for tick in 1..=9 {
+            let writes = ec.duty_writes().len();
+            ctrl.step().unwrap();
+            if ec.duty_writes().len() > writes {
+                updated_at.push(tick);
+            }
+        }
+        // The first tick, then every four half-second ticks
+        assert_eq!(updated_at, vec![1, 5, 9]);
+    }
+
+    #[test]
+    fn test_moving_average_skips_empty_samples() {
+        let ec = MockEc::new();
+        let mut ctrl = controller(&ec, r#"{"speedCurve": [{"temp": 0, "speed": 0}]}"#);
+        ctrl.temp_history.extend([40.0, 0.0, 50.0, 0.0, 60.0]);
+
+        assert_eq!(ctrl.get_moving_average_temperature(2), 55.0);
+        assert_eq!(ctrl.get_moving_average_temperature(10), 50.0);
+        // An interval of zero averages everything recorded
+        assert_eq!(ctrl.get_moving_average_temperature(0), 50.0);
+    }
+
+    #[test]
+    fn test_moving_average_fill_counts_toward_the_window() {
+        let ec = MockEc::new();
+        ec.set_temperatures(&[50]);
+        let mut ctrl = controller(
+            &ec,
+            r#"{"movingAverageInterval": 3, "speedCurve": [{"temp": 0, "speed": 0}]}"#,
+        );
+        assert_eq!(ctrl.moving_average_fill(3), (0, 3));
+        ctrl.step().unwrap();
+        ctrl.step().unwrap();
+        assert_eq!(ctrl.moving_average_fill(3), (2, 3));
+        ctrl.step().unwrap();
+        ctrl.step().unwrap();
+        assert_eq!(ctrl.moving_average_fill(3), (3, 3));
+        assert_eq!(ctrl.moving_average_fill(500), (4, TEMP_HISTORY_MAX_LEN));
+
+        ctrl.clear_history();
+        assert_eq!(ctrl.moving_average_fill(3), (0, 3));
+        assert!(ctrl.warming_up);
+        ec.set_temperatures(&[80]);
+        ctrl.step().unwrap();
+        assert_eq!(ctrl.get_moving_average_temperature(3), 80.0);
+    }
+
+    #[test]
+    fn test_resume_and_use_apply_duty_right_away() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "defaultStrategy": "lazy",
+                "strategies": {
+                    "lazy": {"updateIntervalSeconds": 10, "hysteresis": 10,
+                             "speedCurve": [{"temp": 0, "speed": 30}]},
+                    "agile": {"updateIntervalSeconds": 10,
+                              "speedCurve": [{"temp": 0, "speed": 70}]}
+                }
+            }"#,
+        )
+        .unwrap();
+        let ec = MockEc::new();
+        ec.set_temperatures(&[50]);
+        let hw = HardwareController::with_backend(Box::new(ec.clone()), None, "mock");
+        let mut ctrl = FanController::new(hw, config, None);
+        ctrl.step().unwrap();
+        assert_eq!(ec.duty(), Some(30));
+
+        ctrl.overwrite_strategy("agile").unwrap();
+        assert_eq!(ec.duty(), Some(70));
+        // So does a lower strategy, even one with hysteresis
+        ctrl.overwrite_strategy("lazy").unwrap();
+        assert_eq!(ec.duty(), Some(30));
+        ctrl.overwrite_strategy("agile").unwrap();
+        assert_eq!(ec.duty(), Some(70));
+
+        ctrl.pause().unwrap();
+        assert_eq!(ec.duty(), None);
+        ctrl.clear_overwritten_strategy();
+        assert_eq!(ec.duty(), None);
+        // Hysteresis would otherwise hold the 70% from before the pause
+        ctrl.resume().unwrap();
+        assert_eq!(ec.duty(), Some(30));
+    }
+
+    #[test]
+    fn test_fan_mode_reports_who_drives_the_fan() {
+        let ec = MockEc::new();
+        let mut ctrl = controller(&ec, r#"{"speedCurve": [{"temp": 0, "speed": 40}]}"#);
+        assert_eq!(ctrl.fan_mode(), (FanMode::Managed, None));
+
+        ctrl.pause().unwrap();
+        assert_eq!(ctrl.fan_mode(), (FanMode::EcAuto, Some("paused".into())));
+
+        ctrl.resume().unwrap();
+        ec.set_autofanctrl_failing(true);
+        assert!(ctrl.pause().is_err());
+        assert_eq!(ctrl.fan_mode().0, FanMode::Held);
+
+        ctrl.resume().unwrap();
+        let ctrl = ctrl.with_monitor_only();
+        assert_eq!(
+            ctrl.fan_mode(),
+            (FanMode::EcAuto, Some("monitor-only mode".into()))
+        );
+    }
+
+    #[test]
+    fn test_power_switch_waits_for_delay() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "defaultStrategy": "ac",
+                "strategyOnDischarging": "battery",
+                "powerSwitchDelaySeconds": 30,
+                "strategies": {
+                    "ac": {"speedCurve": [{"temp": 0, "speed": 60}]},
+                    "battery": {"speedCurve": [{"temp": 0, "speed": 20}]}
+                }
+            }"#,
+        )
+        .unwrap();
+        config.validate().unwrap();
+        let ec = MockEc::new();
+        let hw = HardwareController::with_backend(Box::new(ec.clone()), None, "mock");
+        let mut ctrl = FanController::new(hw, config, None);
+
+        // A brief unplug is ignored
+        ec.set_ac_present(false);
+        assert_eq!(ctrl.refresh_power_state(), None);
+        let (pending, seconds) = ctrl.pending_strategy_switch().unwrap();
+        assert_eq!(pending, "battery");
+        assert!(seconds > 25);
+        ec.set_ac_present(true);
+        assert_eq!(ctrl.refresh_power_state(), None);
+        assert_eq!(ctrl.pending_strategy_switch(), None);
+
+        // A lasting one switches once the delay has passed
+        ec.set_ac_present(false);
+        ctrl.refresh_power_state();
+        ctrl.pending_power = Some((false, Instant::now() - Duration::from_secs(31)));
+        assert_eq!(
+            ctrl.refresh_power_state(),
+            Some(StrategyChangeReason::PowerSource)
+        );
+        assert_eq!(ctrl.get_current_strategy_name(), "battery");
+        assert_eq!(ctrl.pending_strategy_switch(), None);
+    }
+
+    #[test]
+    fn test_toggle_and_cycle_strategies() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "defaultStrategy": "a",
+                "strategyOnDischarging": "",
+                "strategyCycle": ["a", "b", "c"],
+                "strategies": {
+                    "a": {"updateIntervalSeconds": 1, "movingAverageInterval": 1, "speedCurve": [{"temp": 0, "speed": 0}]},
+                    "b": {"updateIntervalSeconds": 1, "movingAverageInterval": 1, "speedCurve": [{"temp": 0, "speed": 0}]},
+                    "c": {"updateIntervalSeconds": 1, "movingAverageInterval": 1, "speedCurve": [{"temp": 0, "speed": 0}]}
+                }
+            }"#,
+        )
+        .unwrap();
+        let hw = HardwareController::with_backend(Box::new(MockEc::new()), None, "mock");
+        let mut ctrl = FanController::new(hw, config, None);
+
+        ctrl.toggle_strategy("a", "c").unwrap();
+        assert_eq!(ctrl.get_current_strategy_name(), "c");
+        ctrl.toggle_strategy("a", "c").unwrap();
+        assert_eq!(ctrl.get_current_strategy_name(), "a");
+
+        ctrl.cycle_strategy().unwrap();
+        assert_eq!(ctrl.get_current_strategy_name(), "b");
+        ctrl.cycle_strategy().unwrap();
+        ctrl.cycle_strategy().unwrap();
+        assert_eq!(ctrl.get_current_strategy_name(), "a");
+
+        assert!(ctrl.toggle_strategy("missing", "a").is_err());
+    }
+
+    #[test]
+    fn test_temperature_trend_heads_for_next_threshold() {
+        let ec = MockEc::new();
+        let ctrl_with = |temps: &[f64]| {
+            let mut ctrl = controller(
+                &ec,
+                r#"{"safetyTemp": 62, "speedCurve": [{"temp": 40, "speed": 0}, {"temp": 65, "speed": 50}, {"temp": 80, "speed": 100}]}"#,
+            );
+            ctrl.sample_history
+                .extend(
+                    temps
+                        .iter()
+                        .enumerate()
+                        .map(|(i, &temperature)| HistoryEntry {
+                            timestamp: 1_000 + i as u64,
+                            temperature,
+                            speed: 0,
+                            active: true,
+                        }),
+                );
+            ctrl
+        };
+
+        // Too few samples to tell
+        assert_eq!(ctrl_with(&[50.0, 51.0]).temperature_trend(), None);
+
+        // 0.5°C per second up to 59.5°C: safetyTemp comes before the 65°C point
+        let rising: Vec<f64> = (0..20).map(|i| 50.0 + i as f64 * 0.5).collect();
+        let trend = ctrl_with(&rising).temperature_trend().unwrap();
+        assert_eq!(trend.slope_per_minute, 30.0);
+        assert_eq!(
+            trend.target,
+            Some(TrendTarget {
+                kind: TrendTargetKind::SafetyTemp,
+                temperature: 62,
+                eta_seconds: 5
+            })
+        );
+
+        // Cooling from 59.5°C heads for the 40°C point
+        let falling: Vec<f64> = rising.iter().rev().copied().collect();
+        let target = ctrl_with(&falling)
+            .temperature_trend()
+            .unwrap()
+            .target
+            .unwrap();
+        assert_eq!(target.kind, TrendTargetKind::Breakpoint);
+        assert_eq!((target.temperature, target.eta_seconds), (40, 20));
+
+        let steady = ctrl_with(&[55.0; 20]).temperature_trend().unwrap();
+        assert_eq!((steady.slope_per_minute, steady.target), (0.0, None));
+    }
+
+    #[test]
+    fn test_history_stats() {
+        let ec = MockEc::new();
+        let mut ctrl = controller(
+            &ec,
+            r#"{"updateIntervalSeconds": 1, "movingAverageInterval": 1,
+                "speedCurve": [{"temp": 0, "speed": 0}, {"temp": 100, "speed": 100}]}"#,
+        );
+        assert_eq!(ctrl.history_stats().mean_temperature, None);
+
+        for temp in [40, 40, 60] {
+            ec.set_temperatures(&[temp]);
+            ctrl.step().unwrap();
+        }
+        let stats = ctrl.history_stats();
+        assert_eq!(stats.samples, 3);
+        assert_eq!(stats.min_temperature, Some(40.0));
+        assert_eq!(stats.max_temperature, Some(60.0));
+        assert!(stats.speed_changes >= 1);
+    }
+
+    #[test]
+    fn test_single_spike_is_discarded() {
+        let ec = MockEc::new();
+        let mut ctrl = controller(
+            &ec,
+            r#"{"updateIntervalSeconds": 1, "movingAverageInterval": 30,
+                "speedCurve": [{"temp": 0, "speed": 0}, {"temp": 100, "speed": 100}]}"#,
+        );
+        ctrl.config.spike_filter_celsius = 20;
+        ec.set_temperatures(&[50]);
+        for _ in 0..SPIKE_FILTER_WINDOW {
+            ctrl.step().unwrap();
+        }
+
+        ec.set_temperatures(&[120]);
+        assert_eq!(ctrl.step().unwrap(), 50.0);
+        assert_eq!(ctrl.temp_history.back(), Some(&50.0));
+
+        // Still there on the next tick: a real change
+        assert_eq!(ctrl.step().unwrap(), 120.0);
+        assert_eq!(ctrl.temp_history.back(), Some(&120.0));
+    }
+
+    #[test]
+    fn test_sensor_curve_raises_duty() {
+        let ec = MockEc::new();
+        let mut ctrl = controller(
+            &ec,
+            r#"{"updateIntervalSeconds": 1,
+                "sensorAggregation": "mean",
+                "speedCurve": [{"temp": 0, "speed": 0}, {"temp": 100, "speed": 100}],
+                "sensorCurves": [
+                    {"name": "gpu", "sensor": "ec1", "speedCurve": [{"temp": 60, "speed": 0}, {"temp": 80, "speed": 100}]},
+                    {"name": "missing", "sensor": 9, "speedCurve": [{"temp": 0, "speed": 100}]}
+                ]}"#,
+        );
+        ec.set_temperatures(&[40, 50]);
+        ctrl.step().unwrap();
+        assert_eq!(ec.duty(), Some(45));
+
+        ec.set_temperatures(&[40, 75]);
+        ctrl.temp_history.clear();
+        ctrl.step().unwrap();
+        assert_eq!(ec.duty(), Some(75));
+    }
+
+    #[test]
+    fn test_composed_strategy_takes_highest_duty() {
+        let ec = MockEc::new();
+        let config: Config = serde_json::from_str(
+            r#"{
+                "defaultStrategy": "combined",
+                "strategyOnDischarging": "",
+                "strategies": {
+                    "cpu": {"speedCurve": [{"temp": 50, "speed": 20}, {"temp": 90, "speed": 100}]},
+                    "ssd": {"speedCurve": [{"temp": 0, "speed": 0}],
+                            "sensorCurves": [{"name": "ssd", "sensor": "ec1",
+                                              "speedCurve": [{"temp": 40, "speed": 0}, {"temp": 70, "speed": 60}]}]},
+                    "combined": {"updateIntervalSeconds": 1, "compose": ["cpu", "ssd"]}
+                }
+            }"#,
+        )
+        .unwrap();
+        config.validate().unwrap();
+        let hw = HardwareController::with_backend(Box::new(ec.clone()), None, "mock");
+        let mut ctrl = FanController::new(hw, config, None);
+
+        ec.set_temperatures(&[50, 40]);
+        ctrl.step().unwrap();
+        assert_eq!(ec.duty(), Some(20));
+
+        ec.set_temperatures(&[50, 65]);
+        ctrl.step().unwrap();
+        assert_eq!(ec.duty(), Some(50));
+    }
+
+    #[test]
+    fn test_low_pass_smoothing() {
+        let ec = MockEc::new();
+        let mut ctrl = controller(
+            &ec,
+            r#"{"updateIntervalSeconds": 1,
+                "smoothing": {"type": "lowPass", "timeConstantSeconds": 4},
+                "speedCurve": [{"temp": 0, "speed": 0}, {"temp": 100, "speed": 100}]}"#,
+        );
+        ec.set_temperatures(&[50]);
+        ctrl.step().unwrap();
+        assert_eq!(ctrl.get_effective_temperature(50.0, 30), 50.0);
+
+        // A fifth of the way per tick with a 4s time constant
+        ec.set_temperatures(&[80]);
+        ctrl.step().unwrap();
+        assert_eq!(ctrl.get_effective_temperature(80.0, 30), 56.0);
+        ctrl.step().unwrap();
+        assert_eq!(ctrl.get_effective_temperature(80.0, 30), 60.8);
+        assert_eq!(ctrl.get_current_speed(), 60);
+    }
+
+    #[test]
+    fn test_startup_behavior_until_window_fills() {
+        let run = |behavior: &str, celsius: u8| {
+            let config: Config = serde_json::from_str(&format!(
+                r#"{{
+                    "defaultStrategy": "linear",
+                    "strategyOnDischarging": "",
+                    "startupBehavior": "{}",
+                    "startupSpeed": 40,
+                    "strategies": {{ "linear": {{
+                        "updateIntervalSeconds": 1,
+                        "movingAverageInterval": 3,
+                        "speedCurve": [{{"temp": 0, "speed": 0}}, {{"temp": 100, "speed": 100}}]
+                    }} }}
+                }}"#,
+                behavior
+            ))
+            .unwrap();
+            let ec = MockEc::new();
+            ec.set_temperatures(&[celsius]);
+            let hw = HardwareController::with_backend(Box::new(ec.clone()), None, "mock");
+            let mut ctrl = FanController::new(hw, config, None);
+            for _ in 0..5 {
+                ctrl.step().unwrap();
+            }
+            (ec.duty_writes(), ctrl.temp_history.len())
+        };
+
+        assert_eq!(run("fixedSpeed", 60), (vec![40, 40, 40, 60, 60], 5));
+        assert_eq!(run("ecAuto", 60), (vec![60, 60], 5));
+        assert_eq!(run("seedHistory", 60), (vec![60, 60, 60, 60, 60], 8));
+        // Once warmed up, the curve takes over even below `startupSpeed`
+        assert_eq!(run("fixedSpeed", 30), (vec![40, 40, 40, 30, 30], 5));
+    }
+
+    #[test]
+    fn test_error_log_is_capped() {
+        let ec = MockEc::new();
+        let mut ctrl = controller(
+            &ec,
+            r#"{"speedCurve": [{"temp": 0, "speed": 0}, {"temp": 100, "speed": 100}]}"#,
+        );
+        ctrl.record_error(ErrorContext::EcRead, &Error::Ec("first".into()));
+        assert_eq!(ctrl.recent_errors()[0].context, ErrorContext::EcRead);
+
+        for i in 0..ERROR_LOG_MAX_LEN {
+            ctrl.record_error(ErrorContext::Socket, &Error::Socket(format!("error {}", i)));
+        }
+        let errors = ctrl.recent_errors();
+        assert_eq!(errors.len(), ERROR_LOG_MAX_LEN);
+        assert_eq!(errors[0].message, "Socket error: error 0");
+        assert_eq!(errors[0].context, ErrorContext::Socket);
+    }
+
+    #[test]
+    fn test_external_duty_write_is_reasserted() {
+        let ec = MockEc::new();
+        let mut ctrl = controller(
+            &ec,
+            r#"{"updateIntervalSeconds": 5, "movingAverageInterval": 1,
+                "speedCurve": [{"temp": 0, "speed": 0}, {"temp": 100, "speed": 100}]}"#,
+        );
+        ec.set_temperatures(&[40]);
+        ec.set_fan_rpm(0, 2400);
+        ctrl.step().unwrap();
+        assert_eq!(ec.duty(), Some(40));
+
+        ec.override_duty(100);
+        assert_eq!(ctrl.get_fan_rpms().unwrap(), vec![2400]);
+        ctrl.step().unwrap();
+        assert_eq!(ec.duty(), Some(40));
+        assert_eq!(ec.duty_writes(), vec![40, 40]);
+
+        ctrl.step().unwrap();
+        assert_eq!(ec.duty_writes(), vec![40, 40]);
+        assert_eq!(ctrl.duty_drift(), None);
+        assert_eq!(ctrl.duty_drift_events(), 1);
+
+        // Without a valid duty to read, drift can't be told apart and is left alone
+        ec.set_stuck_duty(Some(0xFF));
+        ec.override_duty(100);
+        ctrl.step().unwrap();
+        assert_eq!(ec.duty_writes(), vec![40, 40]);
+        assert_eq!(ctrl.duty_drift_events(), 1);
+    }
+
+    #[test]
+    fn test_self_test_waits_for_interval_and_cool_machine() {
+        let ec = MockEc::new();
+        let mut ctrl = controller(
+            &ec,
+            r#"{"updateIntervalSeconds": 1, "movingAverageInterval": 1,
+                "speedCurve": [{"temp": 0, "speed": 0}, {"temp": 100, "speed": 100}]}"#,
+        );
+        ec.set_temperatures(&[40]);
+        ctrl.step().unwrap();
+        ctrl.step().unwrap();
+        assert!(!ctrl.self_test_due(1000));
+
+        ctrl.config.scheduled_self_test = serde_json::from_str(r#"{"maxTemp": 50}"#).unwrap();
+        assert!(ctrl.self_test_due(1000));
+
+        ctrl.record_self_test(SelfTestResult {
+            at: 1000,
+            duty: 30,
+            rpm: 2000,
+            passed: true,
+        });
+        assert!(!ctrl.self_test_due(1000 + 3600));
+        assert!(ctrl.self_test_due(1000 + 168 * 3600));
+
+        ec.set_temperatures(&[60]);
+        ctrl.step().unwrap();
+        assert!(!ctrl.self_test_due(1000 + 168 * 3600));
+    }
+
+    #[test]
+    fn test_profile_use_survives_reload() {
+        let ec = MockEc::new();
+        let mut ctrl = controller(&ec, r#"{"speedCurve": [{"temp": 0, "speed": 10}]}"#);
+        let mut config = ctrl.get_config().clone();
+        config.profiles = serde_json::from_str(
+            r#"{"docked": {"defaultStrategy": "loud",
+                "strategies": {"loud": {"speedCurve": [{"temp": 0, "speed": 60}]}}}}"#,
+        )
+        .unwrap();
+        ctrl.reload_config(config.clone());
+        assert_eq!(ctrl.profile(), None);
+
+        ctrl.use_profile(Some("docked")).unwrap();
+        assert_eq!(ctrl.get_current_strategy_name(), "loud");
+        assert_eq!(
+            ctrl.last_strategy_change_reason(),
+            Some(StrategyChangeReason::Profile)
+        );
+        assert!(ctrl.use_profile(Some("office")).is_err());
+
+        ctrl.reload_config(config);
+        assert_eq!(ctrl.profile(), Some("docked"));
+        assert!(ctrl.is_profile_pinned());
+
+        ctrl.use_profile(None).unwrap();
+        assert!(!ctrl.is_profile_pinned());
+        assert_eq!(ctrl.profile(), None);
+        assert_eq!(ctrl.get_current_strategy_name(), "linear");
+    }
+
+    #[test]
+    fn test_speed_change_budget_holds_duty_below_safety_temp() {
+        let ec = MockEc::new();
+        let mut ctrl = controller(
+            &ec,
+            r#"{"updateIntervalSeconds": 1, "movingAverageInterval": 1,
+                "maxSpeedChangesPerMinute": 1, "safetyTemp": 85,
+                "speedCurve": [{"temp": 0, "speed": 0}, {"temp": 100, "speed": 100}]}"#,
+        );
+
+        ec.set_temperatures(&[40]);
+        ctrl.adapt_speed(40.0).unwrap();
+        assert_eq!(ctrl.get_current_speed(), 40);
+
+        ec.set_temperatures(&[50]);
+        ctrl.adapt_speed(50.0).unwrap();
+        assert_eq!(ctrl.get_current_speed(), 40);
+
+        ec.set_temperatures(&[90]);
+        ctrl.adapt_speed(90.0).unwrap();
+        assert_eq!(ctrl.get_current_speed(), 90);
+        assert_eq!(ctrl.thermal_stats().failsafe_activations, 1);
+    }
+
+    #[test]
+    fn test_gpu_boost_floor_after_sustained_load() {
+        let ec = MockEc::new();
+        let mut ctrl = controller(
+            &ec,
+            r#"{"updateIntervalSeconds": 1, "movingAverageInterval": 1,
+                "speedCurve": [{"temp": 0, "speed": 0}, {"temp": 100, "speed": 100}]}"#,
+        );
+        ctrl.config.gpu_boost = Some(crate::config::GpuBoost {
+            busy_percent: 80,
+            sustain_seconds: 2,
+            min_speed: 60,
+        });
+
+        assert!(!ctrl.observe_gpu(Some(95)));
+        assert!(!ctrl.observe_gpu(None));
+        assert!(!ctrl.observe_gpu(Some(95)));
+        assert!(ctrl.observe_gpu(Some(90)));
+        assert!(!ctrl.observe_gpu(Some(90)));
+        ctrl.adapt_speed(40.0).unwrap();
+        assert_eq!(ec.duty(), Some(60));
+
+        ctrl.observe_gpu(Some(10));
+        assert_eq!(ctrl.gpu_boost_floor(), None);
+    }
+
+    #[test]
+    fn test_quiet_hours_cap_yields_at_safety_temp() {
+        let ec = MockEc::new();
+        let mut ctrl = controller(
+            &ec,
+            r#"{"updateIntervalSeconds": 1, "movingAverageInterval": 1, "safetyTemp": 85,
+                "hysteresis": 20,
+                "speedCurve": [{"temp": 0, "speed": 0}, {"temp": 100, "speed": 100}]}"#,
+        );
+        let now = Local::now();
+        ctrl.config.quiet_hours = Some(QuietHours {
+            start: (now - chrono::Duration::hours(1)).time(),
+            end: (now + chrono::Duration::hours(1)).time(),
+            max_speed: 30,
+            strategy: None,
+        });
+
+        ec.set_temperatures(&[60]);
+        ctrl.adapt_speed(60.0).unwrap();
+        assert_eq!(ec.duty(), Some(30));
+
+        ec.set_temperatures(&[90]);
+        ctrl.adapt_speed(90.0).unwrap();
+        assert_eq!(ec.duty(), Some(90));
+
+        // Back under the safety temperature the cap returns, hysteresis or not
+        ec.set_temperatures(&[76]);
+        ctrl.adapt_speed(76.0).unwrap();
+        assert_eq!(ec.duty(), Some(30));
+    }
+
+    #[test]
+    fn test_trip_point_floor_overrides_quiet_hours() {
+        let ec = MockEc::new();
+        let mut ctrl = controller(
+            &ec,
+            r#"{"updateIntervalSeconds": 1, "movingAverageInterval": 1,
+                "speedCurve": [{"temp": 0, "speed": 0}, {"temp": 100, "speed": 100}]}"#,
+        );
+        ctrl.config.trip_points = Some(crate::config::TripPoints {
+            margin_celsius: 5,
+            min_speed: 70,
+        });
+        let now = Local::now();
+        ctrl.config.quiet_hours = Some(QuietHours {
+            start: (now - chrono::Duration::hours(1)).time(),
+            end: (now + chrono::Duration::hours(1)).time(),
+            max_speed: 30,
+            strategy: None,
+        });
+        let trip = |zone_celsius| ThermalTrip {
+            zone: "acpitz".into(),
+            kind: "passive".into(),
+            trip_celsius: 95.0,
+            zone_celsius,
+        };
+
+        assert!(!ctrl.observe_trip_points(vec![trip(85.0)]));
+        ctrl.adapt_speed(40.0).unwrap();
+        assert_eq!(ec.duty(), Some(30));
+
+        assert!(ctrl.observe_trip_points(vec![trip(85.0), trip(91.0)]));
+        assert!(!ctrl.observe_trip_points(vec![trip(92.0)]));
+        ctrl.adapt_speed(40.0).unwrap();
+        assert_eq!(ec.duty(), Some(70));
+        assert_eq!(ctrl.near_trip().map(|t| t.zone_celsius), Some(92.0));
+
+        ctrl.observe_trip_points(vec![trip(80.0)]);
+        assert_eq!(ctrl.trip_point_floor(), None);
+    }
+
+    #[test]
+    fn test_reload_reads_the_daemon_config_path() {
+        let ec = MockEc::new();
+        let write = |name: &str, hysteresis: u32| {
+            let path = std::env::temp_dir().join(format!(
+                "fw-fanctrl-reload-{}-{}.json",
+                name,
+                std::process::id()
+            ));
+            std::fs::write(
+                &path,
+                format!(
+                    r#"{{"defaultStrategy": "linear", "strategies": {{"linear":
+                        {{"hysteresis": {}, "speedCurve": [{{"temp": 0, "speed": 0}}]}}}}}}"#,
+                    hysteresis
+                ),
+            )
+            .unwrap();
+            path
+        };
+        let first = write("first", 2);
+        let second = write("second", 5);
+        let mut ctrl = controller(&ec, r#"{"speedCurve": [{"temp": 0, "speed": 0}]}"#)
+            .with_config_path(first.clone());
+
+        let diff = ctrl.reload_from(None).unwrap();
+        assert_eq!(diff.changes, vec!["strategies.linear.hysteresis: 0 -> 2"]);
+        assert_eq!(diff.strategies_modified, vec!["linear"]);
+
+        let diff = ctrl.reload_from(Some(second.clone())).unwrap();
+        assert_eq!(diff.changes, vec!["strategies.linear.hysteresis: 2 -> 5"]);
+        assert_eq!(ctrl.config_path(), second);
+        assert!(ctrl.reload_from(None).unwrap().is_empty());
+
+        std::fs::remove_file(&first).unwrap();
+        std::fs::remove_file(&second).unwrap();
+        assert!(ctrl.reload_from(None).is_err());
+        assert_eq!(ctrl.config_path(), second);
+        assert_eq!(ctrl.recent_errors().len(), 1);
+    }
+
+    #[test]
+    fn test_tune_strategy_changes_memory_only() {
+        let ec = MockEc::new();
+        let mut ctrl = controller(
+            &ec,
+            r#"{"updateIntervalSeconds": 5, "movingAverageInterval": 30,
+                "speedCurve": [{"temp": 0, "speed": 0}, {"temp": 100, "speed": 100}]}"#,
+        );
+        let tuned = ctrl.tune_strategy("linear", "hysteresis", 4).unwrap();
+        assert_eq!(tuned.hysteresis, 4);
+        assert_eq!(ctrl.get_current_strategy().hysteresis, 4);
+
+        assert!(ctrl
+            .tune_strategy("linear", "updateIntervalSeconds", 0)
+            .is_err());
+        assert!(ctrl.tune_strategy("linear", "speedCurve", 1).is_err());
+        assert!(ctrl.tune_strategy("missing", "hysteresis", 1).is_err());
+    }
+
+    #[test]
+    fn test_set_strategy_curve_applies_live() {
+        let ec = MockEc::new();
+        let mut ctrl = controller(
+            &ec,
+            r#"{"updateIntervalSeconds": 1, "movingAverageInterval": 1,
+                "speedCurve": [{"temp": 0, "speed": 0}, {"temp": 100, "speed": 100}]}"#,
+        );
+        ctrl.set_strategy_curve("linear", crate::curve::parse_points("0:70").unwrap())
+            .unwrap();
+        ctrl.adapt_speed(40.0).unwrap();
+        assert_eq!(ec.duty(), Some(70));
+
+        assert!(ctrl.set_strategy_curve("missing", Vec::new()).is_err());
+        assert!(ctrl.set_strategy_curve("linear", Vec::new()).is_err());
+    }
 }
@@ -1,4 +1,5 @@
 use crate::config::CurvePoint;
+use crate::error::{Error, Result};
 
 pub fn interpolate(curve: &[CurvePoint], temp: u32) -> u32 {
     if curve.is_empty() {
@@ -29,6 +30,95 @@ pub fn interpolate(curve: &[CurvePoint], temp: u32) -> u32 {
     new_speed.clamp(0, 100) as u32
 }
 
+/// The nearest point temperature above `temp` when `rising`, below it otherwise.
+pub fn next_breakpoint(curve: &[CurvePoint], temp: f64, rising: bool) -> Option<u32> {
+    let temps = curve.iter().map(|point| point.temp);
+    if rising {
+        temps.filter(|&t| t as f64 > temp).min()
+    } else {
+        temps.filter(|&t| (t as f64) < temp).max()
+    }
+}
+
+/// Formats a curve as `temp:speed` pairs, e.g. `0:0,50:20,85:100`, the
+/// whitespace-free form used by the `curve` socket command.
+pub fn format_points(curve: &[CurvePoint]) -> String {
+    curve
+        .iter()
+        .map(|p| format!("{}:{}", p.temp, p.speed))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Parses the output of [`format_points`], checking speeds and temperature order.
+pub fn parse_points(value: &str) -> Result<Vec<CurvePoint>> {
+    let invalid = |point: &str| Error::Command(format!("Invalid curve point: {}", point));
+    let curve = value
+        .split(',')
+        .map(|point| {
+            let (temp, speed) = point.split_once(':').ok_or_else(|| invalid(point))?;
+            Ok(CurvePoint {
+                temp: temp.trim().parse().map_err(|_| invalid(point))?,
+                speed: speed.trim().parse().map_err(|_| invalid(point))?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    if let Some(point) = curve.iter().find(|p| p.speed > 100) {
+        return Err(Error::Command(format!(
+            "Curve speed {} is above 100",
+            point.speed
+        )));
+    }
+    if curve.windows(2).any(|w| w[0].temp >= w[1].temp) {
+        return Err(Error::Command(
+            "Curve temperatures must be strictly increasing".into(),
+        ));
+    }
+    Ok(curve)
+}
+
+/// The curve [`interpolate`] follows when the fan runs at the highest duty any
+/// of `curves` asks for, as the fewest points that reproduce it.
+pub fn upper_envelope(curves: &[&[CurvePoint]]) -> Vec<CurvePoint> {
+    let Some(last) = curves.iter().filter_map(|c| c.last()).map(|p| p.temp).max() else {
+        return Vec::new();
+    };
+    let first = curves
+        .iter()
+        .filter_map(|c| c.first())
+        .map(|p| p.temp)
+        .min()
+        .unwrap_or(last);
+    let speed = |temp: u32| {
+        curves
+            .iter()
+            .map(|curve| interpolate(curve, temp))
+            .max()
+            .unwrap_or(0)
+    };
+
+    // Slopes are whole numbers, so sampling every degree and keeping the
+    // points where the slope changes loses nothing
+    let mut points = vec![CurvePoint {
+        temp: first,
+        speed: speed(first),
+    }];
+    for temp in first + 1..last {
+        let (before, at, after) = (speed(temp - 1), speed(temp), speed(temp + 1));
+        if at as i64 - before as i64 != after as i64 - at as i64 {
+            points.push(CurvePoint { temp, speed: at });
+        }
+    }
+    if last > first {
+        points.push(CurvePoint {
+            temp: last,
+            speed: speed(last),
+        });
+    }
+    points
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -108,4 +198,35 @@ mod tests {
         assert_eq!(interpolate(&curve, 30), 50);
         assert_eq!(interpolate(&curve, 70), 50);
     }
+
+    #[test]
+    fn test_points_round_trip() {
+        let curve = parse_points("0:0,50:20,85:100").unwrap();
+        assert_eq!(curve.len(), 3);
+        assert_eq!(curve[1].temp, 50);
+        assert_eq!(curve[1].speed, 20);
+        assert_eq!(format_points(&curve), "0:0,50:20,85:100");
+    }
+
+    #[test]
+    fn test_parse_points_rejects_invalid_curves() {
+        assert!(parse_points("0:0,50").is_err());
+        assert!(parse_points("0:0,50:120").is_err());
+        assert!(parse_points("50:0,40:20").is_err());
+    }
+
+    #[test]
+    fn test_upper_envelope_takes_the_higher_curve() {
+        let quiet = parse_points("40:0,80:80").unwrap();
+        let floor = parse_points("0:30,100:30").unwrap();
+        let envelope = upper_envelope(&[&quiet, &floor]);
+        assert_eq!(format_points(&envelope), "0:30,55:30,80:80,100:80");
+        for temp in 0..=100 {
+            assert_eq!(
+                interpolate(&envelope, temp),
+                interpolate(&quiet, temp).max(interpolate(&floor, temp))
+            );
+        }
+        assert!(upper_envelope(&[]).is_empty());
+    }
 }
@@ -0,0 +1,772 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::Arc;
+
+use clap::Args;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::{watch, Mutex};
+use tokio::task::JoinHandle;
+use tokio::time::{interval, Duration, Instant, MissedTickBehavior};
+
+use crate::audit::AuditLog;
+use crate::boot;
+use crate::config::{Config, SensorSource, DEFAULT_CONFIG_PATH};
+use crate::conflicts;
+use crate::controller::{unix_now, AlertEvent, FanController, ShutdownRequest};
+use crate::error::{Error, Result};
+use crate::external::{parse_reading, ExternalSensors};
+use crate::firmware::{self, FirmwareStatus};
+use crate::hardware::HardwareController;
+#[cfg(feature = "sandbox")]
+use crate::protocol::SOCKET_FOLDER_PATH;
+use crate::replay::{RecordingEc, ReplayEc};
+#[cfg(feature = "sandbox")]
+use crate::sandbox::Sandbox;
+use crate::selftest::{self, SCHEDULED_SELF_TEST_JOB};
+use crate::socket::{start_socket_server, ControllerHandle};
+use crate::stats::{ThermalStats, STATS_PATH};
+use crate::uevent::{DeviceEvent, DeviceEvents};
+
+/// Period of the control loop, which reads the temperature every tick.
+const CONTROL_TICK: Duration = Duration::from_secs(1);
+/// Control-loop ticks between scans for conflicting processes.
+const CONFLICT_SCAN_INTERVAL: u64 = 60;
+/// Control-loop ticks between saves of the thermal counters.
+const STATS_SAVE_INTERVAL: u64 = 300;
+
+#[derive(Args, Debug)]
+pub struct RunArgs {
+    #[clap(short, long, default_value = DEFAULT_CONFIG_PATH)]
+    config: PathBuf,
+
+    /// Strategy to start with, until `reset`; without it, `fw_fanctrl.strategy=` on the
+    /// kernel command line or the `FwFanctrlStrategy` EFI variable is used if set
+    #[clap(short, long)]
+    strategy: Option<String>,
+
+    #[clap(long)]
+    silent: bool,
+
+    /// Leave the battery sensor out of the control temperature unless the config sets
+    /// `excludeBatterySensor`; `excludeSensors` in the config leaves out others
+    #[clap(long)]
+    no_battery_sensors: bool,
+
+    /// Run without a usable EC: hwmon temperatures only, no fan control
+    #[clap(long)]
+    allow_unsupported: bool,
+
+    /// Start even if another fan-control daemon appears to be running
+    #[clap(long)]
+    force: bool,
+
+    /// Observe only: run the sensor loop, socket and telemetry but never write fan duties
+    #[clap(long)]
+    monitor_only: bool,
+
+    /// Refuse to start when the config contains unknown keys instead of warning
+    #[clap(long)]
+    strict_config: bool,
+
+    /// Leave the fan to the EC on firmware with known fan-control quirks instead of warning
+    #[clap(long)]
+    strict_firmware: bool,
+
+    /// Periodically write Prometheus metrics to this file for node_exporter's textfile collector
+    #[clap(long, value_name = "PATH")]
+    textfile_path: Option<PathBuf>,
+
+    /// Seconds between textfile metric writes
+    #[clap(long, default_value = "15", value_name = "SECS")]
+    textfile_interval: u64,
+
+    /// Where the `print stats` counters are kept across restarts
+    #[clap(long, default_value = STATS_PATH, value_name = "PATH")]
+    stats_path: PathBuf,
+
+    /// Also append state-changing socket commands and their senders to this file
+    #[clap(long, value_name = "PATH")]
+    audit_log: Option<PathBuf>,
+
+    /// Also serve read-only commands on a world-accessible socket, and restrict
+    /// the command socket to the daemon's user and group
+    #[clap(long)]
+    query_socket: bool,
+
+    /// Serve the gRPC interface on `unix:<path>` or a loopback address like 127.0.0.1:50051
+    #[cfg(feature = "grpc")]
+    #[clap(long, value_name = "ADDR")]
+    grpc: Option<String>,
+
+    /// File containing the bearer token fan-changing gRPC calls over TCP need
+    #[cfg(feature = "grpc")]
+    #[clap(long, value_name = "PATH", requires = "grpc")]
+    grpc_token_file: Option<PathBuf>,
+
+    /// Serve the REST API on a loopback address like 127.0.0.1:8780
+    #[cfg(feature = "http")]
+    #[clap(long, value_name = "ADDR", requires = "http_token_file")]
+    http: Option<String>,
+
+    /// File containing the bearer token required by the REST API
+    #[cfg(feature = "http")]
+    #[clap(long, value_name = "PATH")]
+    http_token_file: Option<PathBuf>,
+
+    /// Export traces and metrics to this OTLP/gRPC collector, e.g. http://localhost:4317
+    #[cfg(feature = "otel")]
+    #[clap(long, value_name = "URL")]
+    otlp_endpoint: Option<String>,
+
+    /// Log every EC read and write with its result to this file, for `--replay-ec`
+    #[clap(long, value_name = "PATH", conflicts_with = "replay_ec")]
+    record_ec: Option<PathBuf>,
+
+    /// Feed a session saved with `--record-ec` to the daemon instead of using the EC
+    #[clap(long, value_name = "PATH")]
+    replay_ec: Option<PathBuf>,
+
+    /// Run without the Landlock filesystem rules and the seccomp syscall filter
+    #[cfg(feature = "sandbox")]
+    #[clap(long)]
+    no_sandbox: bool,
+}
+
+/// Runs the daemon until it is asked to exit, then hands the fan back to the EC.
+pub fn run(args: RunArgs) -> Result<()> {
+    let RunArgs {
+        config: config_path,
+        strategy,
+        silent,
+        no_battery_sensors,
+        allow_unsupported,
+        force,
+        monitor_only,
+        strict_config,
+        strict_firmware,
+        ..
+    } = args;
+
+    #[cfg(feature = "sandbox")]
+    let sandbox = (!args.no_sandbox).then(|| {
+        let mut sandbox = Sandbox::new(&config_path);
+        if let Some(folder) = args
+            .textfile_path
+            .as_deref()
+            .and_then(std::path::Path::parent)
+        {
+            sandbox = sandbox.allow_write(folder);
+        }
+        if let Some(folder) = args.stats_path.parent() {
+            sandbox = sandbox.allow_write(folder);
+        }
+        if let Some(folder) = args.audit_log.as_deref().and_then(std::path::Path::parent) {
+            sandbox = sandbox.allow_write(folder);
+        }
+        if let Some(folder) = args.record_ec.as_deref().and_then(std::path::Path::parent) {
+            sandbox = sandbox.allow_write(folder);
+        }
+        #[cfg(feature = "grpc")]
+        if let Some(folder) = args
+            .grpc
+            .as_deref()
+            .and_then(|address| address.strip_prefix("unix:"))
+            .and_then(|path| std::path::Path::new(path).parent())
+        {
+            sandbox = sandbox.allow_write(folder);
+        }
+        sandbox
+    });
+
+    let mut runtime = tokio::runtime::Builder::new_multi_thread();
+    runtime.enable_all();
+    // Runtime threads start unrestricted before the main thread is done initializing,
+    // so each one restricts itself; the socket folder must exist to be allowed
+    #[cfg(feature = "sandbox")]
+    if let Some(sandbox) = sandbox.clone() {
+        let _ = std::fs::create_dir_all(SOCKET_FOLDER_PATH);
+        if let Some(folder) = args.stats_path.parent() {
+            let _ = std::fs::create_dir_all(folder);
+        }
+        if let Some(folder) = args.audit_log.as_deref().and_then(std::path::Path::parent) {
+            let _ = std::fs::create_dir_all(folder);
+        }
+        runtime.on_thread_start(move || {
+            if let Err(e) = sandbox.restrict_thread() {
+                tracing::error!("Failed to sandbox runtime thread: {}", e);
+            }
+        });
+    }
+    let rt = runtime.build().expect("Failed to create tokio runtime");
+
+    #[cfg(feature = "otel")]
+    let otel = rt.block_on(async { crate::otel::init(args.otlp_endpoint.as_deref()) })?;
+    #[cfg(not(feature = "otel"))]
+    tracing_subscriber::fmt::init();
+
+    let config = if strict_config {
+        Config::load_strict(&config_path)?
+    } else {
+        Config::load(&config_path)?
+    };
+    let telemetry_config = config.telemetry.clone();
+    let strategy = strategy.or_else(|| {
+        let (name, source) = boot::strategy_hint()?;
+        if config.get_strategy(&name).is_none() {
+            tracing::warn!(
+                "Ignoring strategy '{}' from the {}: it is not in the config",
+                name,
+                source
+            );
+            return None;
+        }
+        tracing::info!("Starting with strategy '{}' from the {}", name, source);
+        Some(name)
+    });
+
+    #[cfg(feature = "grpc")]
+    let grpc_token = args
+        .grpc_token_file
+        .as_ref()
+        .map(std::fs::read_to_string)
+        .transpose()?
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty());
+
+    #[cfg(feature = "http")]
+    let http_token = args
+        .http_token_file
+        .as_ref()
+        .map(std::fs::read_to_string)
+        .transpose()?
+        .map(|t| t.trim().to_string());
+
+    let hw = match &args.replay_ec {
+        Some(path) => {
+            let (replay, session) = ReplayEc::load(path)?;
+            tracing::info!(
+                "Replaying EC session from {} (platform: {})",
+                path.display(),
+                session.platform
+            );
+            HardwareController::with_backend(
+                Box::new(replay),
+                session.battery_sensor_index,
+                session.platform,
+            )
+            .with_battery_excluded(no_battery_sensors)
+        }
+        None => HardwareController::new(no_battery_sensors, allow_unsupported)?,
+    };
+    let mut hw = match &args.record_ec {
+        Some(path) => {
+            let info = hw.platform_info();
+            tracing::info!("Recording EC calls to {}", path.display());
+            hw.map_backend(|ec| Ok(Box::new(RecordingEc::create(path, ec, &info)?)))?
+        }
+        None => hw,
+    }
+    .with_layout(config.ec_layout.unwrap_or_default());
+
+    if let Some(version) = hw.ec_version() {
+        match firmware::check(&version) {
+            FirmwareStatus::KnownGood => tracing::info!("EC firmware: {}", version),
+            FirmwareStatus::Untested => tracing::warn!(
+                "EC firmware {} is not in the known-good table; \
+                 include `print platform` output when reporting fan-control problems",
+                version
+            ),
+            FirmwareStatus::Quirk(note) if strict_firmware => {
+                tracing::warn!(
+                    "EC firmware {} has a known fan-control quirk ({}); \
+                     leaving the fan to the EC",
+                    version,
+                    note
+                );
+                hw = hw.with_fan_control_refused(format!("EC firmware {}: {}", version, note));
+            }
+            FirmwareStatus::Quirk(note) => tracing::warn!(
+                "EC firmware {} has a known fan-control quirk ({}); \
+                 pass --strict-firmware to leave the fan to the EC",
+                version,
+                note
+            ),
+        }
+    }
+
+    if hw.is_fanless() {
+        tracing::info!("EC reports no fan; reading temperatures without writing duties");
+    }
+
+    let mut known_conflicts = conflicts::find_conflicting_processes();
+    let (writers, others): (Vec<_>, Vec<_>) = known_conflicts.iter().partition(|c| c.writes_fans);
+    let list = |conflicts: &[&conflicts::Conflict]| {
+        conflicts
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    if !writers.is_empty() {
+        if !force && !monitor_only {
+            return Err(Error::Conflict(format!(
+                "Other fan-control processes are running: {}; stop them or pass --force",
+                list(&writers)
+            )));
+        }
+        tracing::warn!("Starting despite conflicting processes: {}", list(&writers));
+    }
+    if !others.is_empty() {
+        tracing::warn!(
+            "Running alongside {}, which may throttle the CPU and skew the fan curve",
+            list(&others)
+        );
+    }
+
+    let external_sensors = ExternalSensors::default();
+    let hw = hw.with_external_sensors(external_sensors.clone());
+    let mut controller = FanController::new(hw, config, strategy)
+        .with_config_path(config_path)
+        .with_tick(CONTROL_TICK);
+    if monitor_only {
+        tracing::info!("Monitor-only mode: fan duties are computed but not written");
+        controller = controller.with_monitor_only();
+    }
+    let stats_path = args.stats_path;
+    controller.set_thermal_stats(ThermalStats::load(&stats_path));
+
+    #[cfg(feature = "sandbox")]
+    if let Some(mut sandbox) = sandbox.clone() {
+        // Commands added by a later reload stay blocked
+        let config = controller.get_config();
+        if !config.sensor_sources.is_empty()
+            || config
+                .scheduled_self_test
+                .as_ref()
+                .is_some_and(|test| !test.on_failure.is_empty())
+            || config
+                .alerts
+                .iter()
+                .any(|alert| !alert.on_trip.is_empty() || !alert.on_clear.is_empty())
+        {
+            sandbox = sandbox.allow_exec();
+        }
+        sandbox.apply()?;
+    }
+
+    let controller_handle: ControllerHandle = Arc::new(Mutex::new(controller));
+
+    #[cfg(feature = "otel")]
+    let otel_metrics = otel.as_ref();
+
+    let result = rt.block_on(async move {
+        {
+            let mut ctrl = controller_handle.lock().await;
+            ctrl.enable_auto_fan()?;
+        }
+
+        let (shutdown, shutdown_signal) = watch::channel(false);
+        let server_handle = Arc::clone(&controller_handle);
+        let shutdown_clone = shutdown_signal.clone();
+        let audit = Arc::new(AuditLog::new(args.audit_log));
+        let query_socket = args.query_socket;
+        let socket_task = tokio::spawn(async move {
+            if let Err(e) =
+                start_socket_server(server_handle, audit, query_socket, shutdown_clone).await
+            {
+                tracing::error!("Socket server error: {}", e);
+            }
+        });
+
+        #[cfg(feature = "grpc")]
+        let grpc_task = args.grpc.map(|address| {
+            let grpc_handle = Arc::clone(&controller_handle);
+            let shutdown_clone = shutdown_signal.clone();
+            tokio::spawn(async move {
+                if let Err(e) =
+                    crate::grpc::start_grpc_server(grpc_handle, address, grpc_token, shutdown_clone)
+                        .await
+                {
+                    tracing::error!("gRPC server error: {}", e);
+                }
+            })
+        });
+
+        #[cfg(feature = "http")]
+        let http_task = args.http.zip(http_token).map(|(address, token)| {
+            let http_handle = Arc::clone(&controller_handle);
+            let shutdown_clone = shutdown_signal.clone();
+            tokio::spawn(async move {
+                if let Err(e) =
+                    crate::http::start_http_server(http_handle, address, token, shutdown_clone)
+                        .await
+                {
+                    tracing::error!("HTTP API error: {}", e);
+                }
+            })
+        });
+
+        let textfile_task = args.textfile_path.map(|path| {
+            let textfile_handle = Arc::clone(&controller_handle);
+            let interval = Duration::from_secs(args.textfile_interval.max(1));
+            tokio::spawn(async move {
+                crate::metrics::run_textfile_exporter(textfile_handle, &path, interval).await
+            })
+        });
+
+        let telemetry_task = telemetry_config.map(|telemetry| {
+            let telemetry_handle = Arc::clone(&controller_handle);
+            tokio::spawn(async move {
+                if let Err(e) = crate::telemetry::run_telemetry(telemetry_handle, telemetry).await {
+                    tracing::error!("Telemetry error: {}", e);
+                }
+            })
+        });
+
+        let mut sources_task = None;
+        sync_sensor_sources(
+            &mut sources_task,
+            &*controller_handle.lock().await,
+            &controller_handle,
+            &external_sensors,
+        );
+
+        if !silent {
+            println!(
+                "{:<15} {:<10} {:<10} {:<10}",
+                "Strategy", "Temp", "Speed", "Active"
+            );
+        }
+
+        let mut sigterm = signal(SignalKind::terminate())?;
+        let mut sigint = std::pin::pin!(tokio::signal::ctrl_c());
+        let mut sigusr1 = signal(SignalKind::user_defined1())?;
+        let mut sigusr2 = signal(SignalKind::user_defined2())?;
+        let mut loop_errors: u64 = 0;
+        let mut ticks: u64 = 0;
+        let mut rpm_watch = conflicts::RpmWatch::default();
+        // Aligned ticks that don't drift when other events wake the loop
+        let mut control_tick = interval(CONTROL_TICK);
+        control_tick.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut device_events = match DeviceEvents::open() {
+            Ok(events) => Some(events),
+            Err(e) => {
+                tracing::debug!("No device events, relying on the control tick: {}", e);
+                None
+            }
+        };
+
+        loop {
+            tokio::select! {
+                _ = &mut sigint => {
+                    tracing::info!("Received SIGINT, switching fan to auto mode before exit");
+                    break;
+                }
+                _ = sigterm.recv() => {
+                    tracing::info!("Received SIGTERM, switching fan to auto mode before exit");
+                    break;
+                }
+                _ = sigusr1.recv() => {
+                    let ctrl = controller_handle.lock().await;
+                    let mut snapshot = match crate::socket::status_json(&ctrl) {
+                        Ok(snapshot) => snapshot,
+                        Err(e) => serde_json::json!({ "statusError": e.to_string() }),
+                    };
+                    snapshot["history"] = serde_json::json!(ctrl.history_stats());
+                    snapshot["health"] = serde_json::json!({
+                        "controlLoopErrors": loop_errors,
+                        "acDetectionFailures": ctrl.ac_detection_failures(),
+                        "dutyDriftEvents": ctrl.duty_drift_events()
+                    });
+                    tracing::info!("State dump (SIGUSR1): {}", snapshot);
+                }
+                _ = sigusr2.recv() => {
+                    let mut ctrl = controller_handle.lock().await;
+                    let result = if ctrl.is_active() { ctrl.pause() } else { ctrl.resume() };
+                    match result {
+                        Ok(()) => tracing::info!(
+                            "SIGUSR2: fan control {}",
+                            if ctrl.is_active() { "resumed" } else { "paused" }
+                        ),
+                        Err(e) => tracing::error!("SIGUSR2: failed to toggle pause: {}", e),
+                    }
+                }
+                event = next_device_event(&mut device_events) => {
+                    match event {
+                        Ok(DeviceEvent::PowerSupply) => {
+                            let mut ctrl = controller_handle.lock().await;
+                            if let Err(e) = ctrl.handle_power_change() {
+                                tracing::error!("Failed to apply power change: {}", e);
+                            }
+                        }
+                        Ok(DeviceEvent::UsbDevice) => {
+                            controller_handle.lock().await.refresh_profile();
+                        }
+                        Err(e) => {
+                            tracing::warn!("Device events stopped: {}", e);
+                            device_events = None;
+                        }
+                    }
+                }
+                _ = control_tick.tick() => {
+                    ticks += 1;
+                    if ticks % CONFLICT_SCAN_INTERVAL == 0 {
+                        for conflict in conflicts::find_conflicting_processes() {
+                            if !known_conflicts.contains(&conflict) {
+                                tracing::warn!("Conflicting process started: {}", conflict);
+                                known_conflicts.push(conflict);
+                            }
+                        }
+                    }
+
+                    let mut ctrl = controller_handle.lock().await;
+                    if ticks % STATS_SAVE_INTERVAL == 0 {
+                        if let Err(e) = ctrl.thermal_stats().save(&stats_path) {
+                            tracing::warn!("Failed to save thermal stats: {}", e);
+                        }
+                    }
+                    match ctrl.take_shutdown_request() {
+                        Some(ShutdownRequest::Stop) => {
+                            tracing::info!(
+                                "Shutdown requested, switching fan to auto mode before exit"
+                            );
+                            break;
+                        }
+                        Some(ShutdownRequest::RestartAuto) => {
+                            tracing::info!("restart-auto requested, fan in auto mode, exiting");
+                            break;
+                        }
+                        None => {}
+                    }
+                    // A reload may have added the first source or removed the last
+                    sync_sensor_sources(
+                        &mut sources_task,
+                        &ctrl,
+                        &controller_handle,
+                        &external_sensors,
+                    );
+                    match ctrl.step() {
+                        Ok(temp) => {
+                            for event in ctrl.take_alert_events() {
+                                tokio::spawn(run_alert_hook(event));
+                            }
+                            if ctrl.self_test_due(unix_now()) {
+                                start_scheduled_self_test(&ctrl, &controller_handle);
+                            }
+                            if ctrl.is_active() && ctrl.capabilities().fan_control {
+                                if let Ok(rpm) = ctrl.get_fan_rpm() {
+                                    if rpm_watch.observe(ctrl.get_current_speed(), rpm) {
+                                        tracing::warn!(
+                                            "Fan RPM changed to {} without a new duty being set; \
+                                             another process may be writing to the EC",
+                                            rpm
+                                        );
+                                    }
+                                }
+                            }
+                            #[cfg(feature = "otel")]
+                            if let Some(otel) = otel_metrics {
+                                otel.record(
+                                    temp,
+                                    ctrl.get_current_speed(),
+                                    &ctrl.get_current_strategy_name(),
+                                );
+                            }
+                            if !silent {
+                                let strategy_name = ctrl.get_current_strategy_name();
+                                let speed = ctrl.get_current_speed();
+                                let active = ctrl.is_active();
+                                println!(
+                                    "{:<15} {:<10.1} {:<10} {:<10}",
+                                    strategy_name,
+                                    temp,
+                                    speed,
+                                    active
+                                );
+                            }
+                        }
+                        Err(e) => {
+                            loop_errors += 1;
+                            tracing::error!("Error in control loop: {}", e);
+                        }
+                    }
+                }
+            }
+        }
+
+        for task in [textfile_task, telemetry_task].into_iter().flatten() {
+            task.abort();
+        }
+
+        tracing::info!("Shutting down socket server...");
+        let _ = shutdown.send(true);
+        let _ = socket_task.await;
+        #[cfg(feature = "grpc")]
+        if let Some(grpc_task) = grpc_task {
+            let _ = grpc_task.await;
+        }
+        #[cfg(feature = "http")]
+        if let Some(http_task) = http_task {
+            let _ = http_task.await;
+        }
+        tracing::info!("Socket server shut down");
+
+        let cleanup_result = {
+            let mut ctrl = controller_handle.lock().await;
+            if let Err(e) = ctrl.thermal_stats().save(&stats_path) {
+                tracing::warn!("Failed to save thermal stats: {}", e);
+            }
+            if let Err(e) = ctrl.lift_charge_rate_limit() {
+                tracing::warn!("Failed to lift the charge rate limit: {}", e);
+            }
+            ctrl.enable_auto_fan()
+        };
+
+        if let Err(e) = cleanup_result {
+            tracing::error!("Failed to restore auto fan control on shutdown: {}", e);
+            return Err(e);
+        }
+
+        Ok(())
+    });
+
+    #[cfg(feature = "otel")]
+    if let Some(otel) = otel {
+        otel.shutdown();
+    }
+
+    result
+}
+
+/// The next power supply or USB device change; never resolves without a subscription.
+async fn next_device_event(events: &mut Option<DeviceEvents>) -> Result<DeviceEvent> {
+    match events {
+        Some(events) => events.next().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Starts the `scheduledSelfTest` as a job, like `fan-test` over the socket.
+fn start_scheduled_self_test(ctrl: &FanController, handle: &ControllerHandle) {
+    let Some(schedule) = ctrl.get_config().scheduled_self_test.clone() else {
+        return;
+    };
+    let handle = Arc::clone(handle);
+    if let Err(e) = ctrl.jobs().spawn(SCHEDULED_SELF_TEST_JOB, |ctx| {
+        selftest::scheduled_self_test_job(handle, schedule, ctx)
+    }) {
+        tracing::warn!("Failed to start scheduled self-test: {}", e);
+    }
+}
+
+/// Keeps [`poll_sensor_sources`] running while the config has `sensorSources`,
+/// so without any the daemon doesn't wake up for them.
+fn sync_sensor_sources(
+    task: &mut Option<JoinHandle<()>>,
+    ctrl: &FanController,
+    handle: &ControllerHandle,
+    sensors: &ExternalSensors,
+) {
+    let wanted = !ctrl.get_config().sensor_sources.is_empty();
+    match task.take() {
+        Some(running) if !wanted => {
+            running.abort();
+            sensors.retain(&[]);
+        }
+        None if wanted => {
+            *task = Some(tokio::spawn(poll_sensor_sources(
+                Arc::clone(handle),
+                sensors.clone(),
+            )));
+        }
+        running => *task = running,
+    }
+}
+
+/// Runs each of the config's `sensorSources` every `interval` and records what
+/// it prints. A command still running after its interval is killed.
+async fn poll_sensor_sources(controller: ControllerHandle, sensors: ExternalSensors) {
+    let mut due: HashMap<String, Instant> = HashMap::new();
+    let mut tick = interval(Duration::from_secs(1));
+    loop {
+        tick.tick().await;
+        let sources = controller.lock().await.get_config().sensor_sources.clone();
+        let names: Vec<String> = sources.iter().map(SensorSource::name).collect();
+        sensors.retain(&names);
+        due.retain(|name, _| names.contains(name));
+
+        for (source, name) in sources.into_iter().zip(names) {
+            let now = Instant::now();
+            if due.get(&name).is_some_and(|at| now < *at) {
+                continue;
+            }
+            let period = Duration::from_secs(source.interval());
+            due.insert(name.clone(), now + period);
+            let sensors = sensors.clone();
+            tokio::spawn(async move {
+                match read_sensor_source(&source, period).await {
+                    Ok(celsius) => sensors.record(&name, celsius, period),
+                    Err(e) => tracing::warn!("Sensor source {}: {}", name, e),
+                }
+            });
+        }
+    }
+}
+
+/// Runs the `onTrip` or `onClear` command of an alert that changed state, with
+/// `FW_FANCTRL_ALERT`, `FW_FANCTRL_ALERT_STATE` and `FW_FANCTRL_ALERT_VALUE` set.
+async fn run_alert_hook(event: AlertEvent) {
+    let (command, state) = if event.tripped {
+        (&event.alert.on_trip, "tripped")
+    } else {
+        (&event.alert.on_clear, "cleared")
+    };
+    let Some((program, args)) = command.split_first() else {
+        return;
+    };
+    let status = tokio::process::Command::new(program)
+        .args(args)
+        .env("FW_FANCTRL_ALERT", &event.alert.name)
+        .env("FW_FANCTRL_ALERT_STATE", state)
+        .env("FW_FANCTRL_ALERT_VALUE", format!("{:.1}", event.reading))
+        .stdin(Stdio::null())
+        .status()
+        .await;
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => tracing::warn!(
+            "Alert {} command {} exited with {}",
+            event.alert.name,
+            program,
+            status
+        ),
+        Err(e) => tracing::warn!(
+            "Failed to run alert {} command {}: {}",
+            event.alert.name,
+            program,
+            e
+        ),
+    }
+}
+
+async fn read_sensor_source(source: &SensorSource, timeout: Duration) -> Result<f64> {
+    let SensorSource::Exec { cmd, args, .. } = source;
+    let child = tokio::process::Command::new(cmd)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()?;
+    let output = tokio::time::timeout(timeout, child.wait_with_output())
+        .await
+        .map_err(|_| Error::Sensor(format!("{} did not finish within {:?}", cmd, timeout)))??;
+    if !output.status.success() {
+        return Err(Error::Sensor(format!(
+            "{} exited with {}",
+            cmd, output.status
+        )));
+    }
+    parse_reading(&String::from_utf8_lossy(&output.stdout))
+}
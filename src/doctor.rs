@@ -0,0 +1,388 @@
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::process::Command;
+
+use crate::config::Config;
+use crate::conflicts::Conflict;
+use crate::error::{Error, Result};
+use crate::firmware::{self, FirmwareStatus};
+use crate::hardware::HardwareController;
+use crate::output::{paint, Color};
+use crate::service::EC_DEVICE_PATH;
+
+const LOCKDOWN_PATH: &str = "/sys/kernel/security/lockdown";
+const SYSTEMD_RUNTIME_PATH: &str = "/run/systemd/system";
+const SERVICE_NAME: &str = "fw-fanctrl";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+/// The outcome of one environment check, with a fix when it didn't pass.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub check: &'static str,
+    pub status: Status,
+    pub detail: String,
+    pub fix: Option<String>,
+}
+
+impl Finding {
+    fn ok(check: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            check,
+            status: Status::Ok,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn warn(check: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            check,
+            status: Status::Warn,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn fail(check: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            check,
+            status: Status::Fail,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// The active mode in the contents of `/sys/kernel/security/lockdown`,
+/// e.g. `integrity` for `none [integrity] confidentiality`.
+fn lockdown_mode(contents: &str) -> Option<&str> {
+    let start = contents.find('[')? + 1;
+    let end = start + contents[start..].find(']')?;
+    Some(&contents[start..end])
+}
+
+fn can_read_write(path: &Path) -> bool {
+    let Ok(path) = CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    // SAFETY: `path` is a valid NUL-terminated string for the duration of the call.
+    unsafe { libc::access(path.as_ptr(), libc::R_OK | libc::W_OK) == 0 }
+}
+
+/// Whether the EC can be reached: through the `cros_ec` device, or through port I/O as root.
+pub fn check_ec_driver(device: &Path, is_root: bool) -> Finding {
+    const CHECK: &str = "EC driver";
+    if device.exists() {
+        if can_read_write(device) {
+            Finding::ok(
+                CHECK,
+                format!("cros_ec, {} is read-write", device.display()),
+            )
+        } else {
+            Finding::fail(
+                CHECK,
+                format!("cros_ec, but {} is not read-write", device.display()),
+                "Run as root, or run `sudo fw-fanctrl setup-permissions --user $USER` \
+                 and log in again",
+            )
+        }
+    } else if is_root {
+        Finding::ok(CHECK, "no cros_ec device, using port I/O")
+    } else {
+        Finding::fail(
+            CHECK,
+            "no cros_ec device, and port I/O needs root",
+            "Run as root, or load the cros_ec_lpcs module on a recent kernel for a device \
+             that `setup-permissions` can open up",
+        )
+    }
+}
+
+/// Kernel lockdown blocks port I/O, leaving only the `cros_ec` device.
+pub fn check_lockdown(contents: Option<&str>, has_device: bool) -> Finding {
+    const CHECK: &str = "Kernel lockdown";
+    match contents.and_then(lockdown_mode) {
+        None => Finding::ok(CHECK, "not available"),
+        Some("none") => Finding::ok(CHECK, "none"),
+        Some(mode) if has_device => Finding::ok(
+            CHECK,
+            format!("{} (port I/O blocked, cros_ec device used)", mode),
+        ),
+        Some(mode) => Finding::fail(
+            CHECK,
+            format!("{} blocks port I/O and there is no cros_ec device", mode),
+            "Load the cros_ec_lpcs module on a recent kernel, or disable Secure Boot \
+             to lift the lockdown",
+        ),
+    }
+}
+
+/// Platform detection and EC access through framework_lib (or the hwmon fallback).
+pub fn check_platform(hw: &HardwareController) -> Finding {
+    const CHECK: &str = "Platform";
+    let info = hw.platform_info();
+    let Some(driver) = info.ec_driver else {
+        return Finding::fail(
+            CHECK,
+            format!("{}, EC not accessible", info.platform),
+            "Fix the EC driver check first; on other hardware only \
+             `run --allow-unsupported` (no fan control) works",
+        );
+    };
+    let detail = format!("{}, EC via {}", info.platform, driver);
+    match info.ec_version.as_deref().map(firmware::check) {
+        Some(FirmwareStatus::Quirk(note)) => Finding::warn(
+            CHECK,
+            format!("{}, firmware has a fan-control quirk: {}", detail, note),
+            "Update the EC firmware, or run with --strict-firmware to leave the fan to the EC",
+        ),
+        _ if info.capabilities.fanless => Finding::ok(CHECK, format!("{}, fanless", detail)),
+        _ if !info.capabilities.fan_control => Finding::warn(
+            CHECK,
+            format!("{}, no fan reported", detail),
+            "Fan control is unavailable on this machine; only temperatures are shown",
+        ),
+        _ => Finding::ok(CHECK, detail),
+    }
+}
+
+pub fn check_config(path: &Path) -> Finding {
+    const CHECK: &str = "Config";
+    match Config::load_strict(&path.to_path_buf()) {
+        Ok(_) => Finding::ok(CHECK, format!("{} is valid", path.display())),
+        Err(e) if !path.exists() => Finding::fail(
+            CHECK,
+            e.to_string(),
+            "Run `sudo fw-fanctrl install` to write the default config",
+        ),
+        Err(e) => Finding::fail(
+            CHECK,
+            e.to_string(),
+            format!(
+                "Fix the file, then check it with `fw-fanctrl check-config -c {}`",
+                path.display()
+            ),
+        ),
+    }
+}
+
+fn systemd_state() -> Option<String> {
+    if !Path::new(SYSTEMD_RUNTIME_PATH).exists() {
+        return None;
+    }
+    let output = Command::new("systemctl")
+        .args(["is-active", SERVICE_NAME])
+        .output()
+        .ok()?;
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Whether a daemon is running, from the `fw-fanctrl run` and `fw-fanctrld`
+/// processes found.
+pub fn check_service(daemons: &[&Conflict]) -> Finding {
+    const CHECK: &str = "Service";
+    if let Some(daemon) = daemons.first() {
+        return Finding::ok(CHECK, format!("running (pid {})", daemon.pid));
+    }
+    match systemd_state().as_deref() {
+        Some("failed") => Finding::fail(
+            CHECK,
+            "failed",
+            "See why with `journalctl -u fw-fanctrl -b`, then `sudo systemctl restart fw-fanctrl`",
+        ),
+        Some(state) if !state.is_empty() && state != "unknown" => Finding::fail(
+            CHECK,
+            format!("not running ({})", state),
+            "Start it with `sudo systemctl enable --now fw-fanctrl`",
+        ),
+        _ => Finding::fail(
+            CHECK,
+            "not running",
+            "Install and start it with `sudo fw-fanctrl install`, \
+             or start `fw-fanctrl run` yourself",
+        ),
+    }
+}
+
+/// Whether the daemon answered `version` over the socket.
+pub fn check_socket(reply: Result<String>) -> Finding {
+    const CHECK: &str = "Socket";
+    let reply = reply.and_then(|reply| {
+        serde_json::from_str::<serde_json::Value>(&reply)
+            .map_err(|e| Error::Socket(format!("Invalid reply: {}", e)))
+    });
+    match reply {
+        Ok(reply) => match crate::protocol::version_mismatch(&reply) {
+            Some(mismatch) => Finding::warn(
+                CHECK,
+                mismatch,
+                "Restart the daemon after upgrading so client and daemon match",
+            ),
+            None => Finding::ok(CHECK, "daemon replied"),
+        },
+        Err(e) => Finding::fail(
+            CHECK,
+            e.to_string(),
+            "Check the service; if it is running, make sure `/run/fw-fanctrl` exists \
+             and the daemon is not stuck (`kill -USR1` logs its state)",
+        ),
+    }
+}
+
+/// Other fan controllers, and extra fw-fanctrl daemons beyond the first; processes
+/// that don't write fan duties, such as thermald, only warn.
+pub fn check_conflicts(conflicts: &[Conflict]) -> Finding {
+    const CHECK: &str = "Conflicts";
+    let others: Vec<&Conflict> = conflicts
+        .iter()
+        .filter(|c| !c.is_fw_fanctrl())
+        .chain(conflicts.iter().filter(|c| c.is_fw_fanctrl()).skip(1))
+        .collect();
+    let detail = others
+        .iter()
+        .map(|c| c.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    if others.is_empty() {
+        Finding::ok(CHECK, "no other fan-control processes")
+    } else if others.iter().any(|c| c.writes_fans) {
+        Finding::fail(
+            CHECK,
+            detail,
+            "Stop and disable them, e.g. `sudo systemctl disable --now fancontrol`",
+        )
+    } else {
+        Finding::warn(
+            CHECK,
+            detail,
+            "They don't write fan duties, but CPU throttling can skew the fan curve",
+        )
+    }
+}
+
+/// Runs every check except the socket, whose reply the caller passes in.
+pub fn diagnose(config_path: &Path, socket_reply: Result<String>) -> Vec<Finding> {
+    let device = Path::new(EC_DEVICE_PATH);
+    // SAFETY: geteuid has no preconditions.
+    let is_root = unsafe { libc::geteuid() } == 0;
+    let lockdown = std::fs::read_to_string(LOCKDOWN_PATH).ok();
+    let conflicts = crate::conflicts::find_conflicting_processes();
+    let daemons: Vec<&Conflict> = conflicts.iter().filter(|c| c.is_fw_fanctrl()).collect();
+
+    let mut findings = vec![
+        check_ec_driver(device, is_root),
+        check_lockdown(lockdown.as_deref(), device.exists()),
+    ];
+    findings.push(match HardwareController::new(false, true) {
+        Ok(hw) => check_platform(&hw),
+        Err(e) => Finding::fail("Platform", e.to_string(), "Fix the EC driver check first"),
+    });
+    findings.push(check_config(config_path));
+    findings.push(check_service(&daemons));
+    findings.push(check_socket(socket_reply));
+    findings.push(check_conflicts(&conflicts));
+    findings
+}
+
+/// One line per check, followed by its fix when it didn't pass.
+pub fn render(findings: &[Finding], color: bool) -> String {
+    findings
+        .iter()
+        .map(|finding| {
+            let label = match finding.status {
+                Status::Ok => paint("OK  ", Color::Green, color),
+                Status::Warn => paint("WARN", Color::Yellow, color),
+                Status::Fail => paint("FAIL", Color::Red, color),
+            };
+            let mut line = format!("[{}] {:<16} {}", label, finding.check, finding.detail);
+            if let Some(fix) = &finding.fix {
+                line.push_str(&format!("\n       Fix: {}", fix));
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lockdown_mode() {
+        assert_eq!(
+            lockdown_mode("none [integrity] confidentiality\n"),
+            Some("integrity")
+        );
+        assert_eq!(
+            lockdown_mode("[none] integrity confidentiality"),
+            Some("none")
+        );
+        assert_eq!(lockdown_mode(""), None);
+
+        let blocked = check_lockdown(Some("none [integrity] confidentiality"), false);
+        assert_eq!(blocked.status, Status::Fail);
+        let with_device = check_lockdown(Some("none [integrity] confidentiality"), true);
+        assert_eq!(with_device.status, Status::Ok);
+    }
+
+    #[test]
+    fn test_ec_driver_without_device() {
+        let missing = Path::new("/nonexistent/cros_ec");
+        assert_eq!(check_ec_driver(missing, true).status, Status::Ok);
+        let finding = check_ec_driver(missing, false);
+        assert_eq!(finding.status, Status::Fail);
+        assert!(finding.fix.unwrap().contains("cros_ec_lpcs"));
+    }
+
+    #[test]
+    fn test_one_daemon_is_not_a_conflict() {
+        let daemon = |pid| Conflict {
+            pid,
+            name: "fw-fanctrl".into(),
+            description: "another fw-fanctrl instance",
+            writes_fans: true,
+        };
+        assert_eq!(check_conflicts(&[daemon(10)]).status, Status::Ok);
+
+        let finding = check_conflicts(&[daemon(10), daemon(20)]);
+        assert_eq!(finding.status, Status::Fail);
+        assert!(finding.detail.contains("pid 20"));
+
+        let split = Conflict {
+            pid: 30,
+            name: "fw-fanctrld".into(),
+            description: "another fw-fanctrl daemon",
+            writes_fans: true,
+        };
+        assert_eq!(check_conflicts(&[split]).status, Status::Ok);
+
+        let thermald = Conflict {
+            pid: 40,
+            name: "thermald".into(),
+            description: "Intel thermal daemon",
+            writes_fans: false,
+        };
+        assert_eq!(check_conflicts(&[thermald]).status, Status::Warn);
+    }
+
+    #[test]
+    fn test_render_shows_fixes() {
+        let findings = [
+            Finding::ok("Config", "valid"),
+            Finding::fail("Socket", "refused", "Start the service"),
+        ];
+        assert_eq!(
+            render(&findings, false),
+            "[OK  ] Config           valid\n\
+             [FAIL] Socket           refused\n       Fix: Start the service"
+        );
+    }
+}
@@ -11,6 +11,9 @@ pub enum Error {
     #[error("EC error: {0}")]
     Ec(String),
 
+    #[error("Sensor error: {0}")]
+    Sensor(String),
+
     #[error("Socket error: {0}")]
     Socket(String),
 
@@ -19,6 +22,12 @@ pub enum Error {
 
     #[error("Invalid command: {0}")]
     Command(String),
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("Sandbox error: {0}")]
+    Sandbox(String),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
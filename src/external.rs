@@ -0,0 +1,105 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crate::error::{Error, Result};
+
+/// Readings older than this many intervals of their source are ignored.
+const STALE_INTERVALS: u32 = 3;
+
+struct Reading {
+    celsius: f64,
+    at: Instant,
+    max_age: Duration,
+}
+
+/// Latest readings of the `sensorSources`, by name. The service polls the
+/// sources and records here; the hardware layer folds the fresh readings into
+/// its sensor set. Cloning shares the readings.
+#[derive(Clone, Default)]
+pub struct ExternalSensors {
+    readings: Arc<Mutex<BTreeMap<String, Reading>>>,
+}
+
+impl ExternalSensors {
+    fn readings_mut(&self) -> MutexGuard<'_, BTreeMap<String, Reading>> {
+        self.readings.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Stores a reading of a source polled every `interval`.
+    pub fn record(&self, name: &str, celsius: f64, interval: Duration) {
+        self.readings_mut().insert(
+            name.to_string(),
+            Reading {
+                celsius,
+                at: Instant::now(),
+                max_age: interval * STALE_INTERVALS,
+            },
+        );
+    }
+
+    /// Forgets sources that are no longer configured.
+    pub fn retain(&self, names: &[String]) {
+        self.readings_mut().retain(|name, _| names.contains(name));
+    }
+
+    /// Fresh readings as `(name, °C)`, in name order.
+    pub fn readings(&self) -> Vec<(String, f64)> {
+        self.readings_mut()
+            .iter()
+            .filter(|(_, reading)| reading.at.elapsed() <= reading.max_age)
+            .map(|(name, reading)| (name.clone(), reading.celsius))
+            .collect()
+    }
+
+    /// Calls `f` with each fresh reading as `(name, °C)`, in name order,
+    /// without copying the names.
+    pub fn for_each_reading(&self, mut f: impl FnMut(&str, f64)) {
+        for (name, reading) in self.readings_mut().iter() {
+            if reading.at.elapsed() <= reading.max_age {
+                f(name, reading.celsius);
+            }
+        }
+    }
+}
+
+/// The temperature an exec source printed: the first number on stdout, in °C.
+pub fn parse_reading(stdout: &str) -> Result<f64> {
+    let text = stdout.split_whitespace().next().unwrap_or_default();
+    let celsius: f64 = text
+        .parse()
+        .map_err(|_| Error::Sensor(format!("Expected a temperature, got '{}'", text)))?;
+    if !(-40.0..=150.0).contains(&celsius) {
+        return Err(Error::Sensor(format!(
+            "Implausible temperature: {}°C",
+            celsius
+        )));
+    }
+    Ok(celsius)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_first_number() {
+        assert_eq!(parse_reading("48.5\n").unwrap(), 48.5);
+        assert_eq!(parse_reading("  61 C").unwrap(), 61.0);
+        assert!(parse_reading("").is_err());
+        assert!(parse_reading("N/A").is_err());
+        assert!(parse_reading("48500").is_err());
+    }
+
+    #[test]
+    fn test_stale_and_removed_readings_are_dropped() {
+        let sensors = ExternalSensors::default();
+        sensors.record("egpu", 70.0, Duration::from_secs(5));
+        sensors.record("usb", 30.0, Duration::ZERO);
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(sensors.readings(), vec![("egpu".to_string(), 70.0)]);
+
+        sensors.retain(&[]);
+        assert!(sensors.readings().is_empty());
+    }
+}
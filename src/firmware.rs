@@ -0,0 +1,71 @@
+/// EC firmware families manual fan control has been tested on, by version prefix.
+const KNOWN_GOOD: &[&str] = &["hx20", "hx30", "azalea", "lotus"];
+
+/// Firmware versions with known fan-control quirks, by version prefix, with a
+/// note for the log. Matched before [`KNOWN_GOOD`], so single releases of a
+/// good family can be listed here.
+const KNOWN_QUIRKS: &[(&str, &str)] = &[];
+
+/// How far an EC firmware version is trusted for manual fan control.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FirmwareStatus {
+    KnownGood,
+    /// Not in either table; most likely fine, but nobody has checked.
+    Untested,
+    Quirk(&'static str),
+}
+
+impl std::fmt::Display for FirmwareStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::KnownGood => write!(f, "known good"),
+            Self::Untested => write!(f, "untested"),
+            Self::Quirk(note) => write!(f, "known fan-control quirk: {}", note),
+        }
+    }
+}
+
+fn classify(version: &str, known_good: &[&str], quirks: &[(&str, &'static str)]) -> FirmwareStatus {
+    let version = version.trim().to_ascii_lowercase();
+    if let Some((_, note)) = quirks
+        .iter()
+        .find(|(prefix, _)| version.starts_with(prefix))
+    {
+        return FirmwareStatus::Quirk(note);
+    }
+    if known_good.iter().any(|prefix| version.starts_with(prefix)) {
+        FirmwareStatus::KnownGood
+    } else {
+        FirmwareStatus::Untested
+    }
+}
+
+/// Looks an EC version string, e.g. `hx30_v0.0.1-7a61a89`, up in the built-in tables.
+pub fn check(version: &str) -> FirmwareStatus {
+    classify(version, KNOWN_GOOD, KNOWN_QUIRKS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_families() {
+        assert_eq!(check("hx30_v0.0.1-7a61a89"), FirmwareStatus::KnownGood);
+        assert_eq!(check("Lotus-3.0.3-abcdef"), FirmwareStatus::KnownGood);
+        assert_eq!(check("marigold_v0.1"), FirmwareStatus::Untested);
+    }
+
+    #[test]
+    fn test_quirks_take_precedence() {
+        let quirks = [("hx30_v0.0.1", "duty writes ignored on battery")];
+        assert_eq!(
+            classify("hx30_v0.0.1-7a61a89", KNOWN_GOOD, &quirks),
+            FirmwareStatus::Quirk("duty writes ignored on battery")
+        );
+        assert_eq!(
+            classify("hx30_v0.0.2-1234567", KNOWN_GOOD, &quirks),
+            FirmwareStatus::KnownGood
+        );
+    }
+}
@@ -0,0 +1,346 @@
+use std::net::SocketAddr;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::watch;
+use tokio_stream::wrappers::{ReceiverStream, UnixListenerStream};
+use tokio_stream::Stream;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status as RpcStatus};
+
+use crate::controller::FanController;
+use crate::error::{Error, Result};
+use crate::socket::ControllerHandle;
+
+pub mod proto {
+    tonic::include_proto!("fw_fanctrl");
+}
+
+use proto::fan_control_server::{FanControl, FanControlServer};
+use proto::{
+    ActiveReply, Empty, Status, StrategyList, StrategyReply, TelemetryRequest, UseStrategyRequest,
+};
+
+const DEFAULT_TELEMETRY_INTERVAL_MS: u32 = 1000;
+
+fn to_rpc_status(error: Error) -> RpcStatus {
+    match error {
+        Error::Strategy(_) | Error::Command(_) => RpcStatus::invalid_argument(error.to_string()),
+        Error::Config(_) => RpcStatus::failed_precondition(error.to_string()),
+        _ => RpcStatus::internal(error.to_string()),
+    }
+}
+
+fn strategy_reply(controller: &FanController) -> StrategyReply {
+    StrategyReply {
+        strategy: controller.get_current_strategy_name(),
+        default: !controller.is_overwritten(),
+    }
+}
+
+fn status(controller: &FanController) -> Result<Status> {
+    let temp = controller.get_actual_temperature()?;
+    let interval = controller.get_current_strategy().moving_average_interval;
+    let (mode, mode_reason) = controller.fan_mode();
+
+    Ok(Status {
+        strategy: controller.get_current_strategy_name(),
+        default: !controller.is_overwritten(),
+        speed: controller.get_current_speed(),
+        temperature: temp,
+        moving_average_temperature: controller.get_moving_average_temperature(interval),
+        effective_temperature: controller.get_effective_temperature(temp, interval),
+        active: controller.is_active(),
+        degraded: controller.is_degraded(),
+        mode: mode.as_str().to_string(),
+        mode_reason: mode_reason.unwrap_or_default(),
+    })
+}
+
+/// Who may call the RPCs that change fan control.
+enum RpcAuth {
+    /// A unix socket only the daemon's user can connect to.
+    Socket,
+    /// TCP, where any local user can connect: callers must send
+    /// `authorization: Bearer <token>`, and without a token only the
+    /// read-only RPCs are served.
+    Token(Option<String>),
+}
+
+pub struct FanControlService {
+    controller: ControllerHandle,
+    auth: RpcAuth,
+}
+
+impl FanControlService {
+    fn authorize<T>(&self, request: &Request<T>) -> std::result::Result<(), RpcStatus> {
+        let expected = match &self.auth {
+            RpcAuth::Socket => return Ok(()),
+            RpcAuth::Token(None) => {
+                return Err(RpcStatus::permission_denied(
+                    "Fan-changing RPCs over TCP need --grpc-token-file",
+                ))
+            }
+            RpcAuth::Token(Some(token)) => token,
+        };
+        let authorized = request
+            .metadata()
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .is_some_and(|token| token == expected);
+        if authorized {
+            Ok(())
+        } else {
+            Err(RpcStatus::unauthenticated("Missing or wrong bearer token"))
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl FanControl for FanControlService {
+    async fn use_strategy(
+        &self,
+        request: Request<UseStrategyRequest>,
+    ) -> std::result::Result<Response<StrategyReply>, RpcStatus> {
+        self.authorize(&request)?;
+        let mut controller = self.controller.lock().await;
+        controller
+            .overwrite_strategy(&request.into_inner().strategy)
+            .map_err(to_rpc_status)?;
+        Ok(Response::new(strategy_reply(&controller)))
+    }
+
+    async fn reset(
+        &self,
+        request: Request<Empty>,
+    ) -> std::result::Result<Response<StrategyReply>, RpcStatus> {
+        self.authorize(&request)?;
+        let mut controller = self.controller.lock().await;
+        controller.clear_overwritten_strategy();
+        Ok(Response::new(strategy_reply(&controller)))
+    }
+
+    async fn reload(
+        &self,
+        request: Request<Empty>,
+    ) -> std::result::Result<Response<Empty>, RpcStatus> {
+        self.authorize(&request)?;
+        let mut controller = self.controller.lock().await;
+        controller.reload_from(None).map_err(to_rpc_status)?;
+        Ok(Response::new(Empty {}))
+    }
+
+    async fn pause(
+        &self,
+        request: Request<Empty>,
+    ) -> std::result::Result<Response<ActiveReply>, RpcStatus> {
+        self.authorize(&request)?;
+        let mut controller = self.controller.lock().await;
+        controller.pause().map_err(to_rpc_status)?;
+        Ok(Response::new(ActiveReply {
+            active: controller.is_active(),
+        }))
+    }
+
+    async fn resume(
+        &self,
+        request: Request<Empty>,
+    ) -> std::result::Result<Response<ActiveReply>, RpcStatus> {
+        self.authorize(&request)?;
+        let mut controller = self.controller.lock().await;
+        controller.resume().map_err(to_rpc_status)?;
+        Ok(Response::new(ActiveReply {
+            active: controller.is_active(),
+        }))
+    }
+
+    async fn get_status(
+        &self,
+        _request: Request<Empty>,
+    ) -> std::result::Result<Response<Status>, RpcStatus> {
+        let controller = self.controller.lock().await;
+        Ok(Response::new(status(&controller).map_err(to_rpc_status)?))
+    }
+
+    async fn list_strategies(
+        &self,
+        _request: Request<Empty>,
+    ) -> std::result::Result<Response<StrategyList>, RpcStatus> {
+        let controller = self.controller.lock().await;
+        let strategies = controller
+            .get_config()
+            .strategy_names()
+            .into_iter()
+            .cloned()
+            .collect();
+        Ok(Response::new(StrategyList { strategies }))
+    }
+
+    type TelemetryStream =
+        Pin<Box<dyn Stream<Item = std::result::Result<Status, RpcStatus>> + Send + 'static>>;
+
+    async fn telemetry(
+        &self,
+        request: Request<TelemetryRequest>,
+    ) -> std::result::Result<Response<Self::TelemetryStream>, RpcStatus> {
+        let interval_ms = match request.into_inner().interval_ms {
+            0 => DEFAULT_TELEMETRY_INTERVAL_MS,
+            ms => ms,
+        };
+        let controller = Arc::clone(&self.controller);
+        let (tx, rx) = tokio::sync::mpsc::channel(4);
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_millis(interval_ms as u64));
+            loop {
+                interval.tick().await;
+                let snapshot = status(&*controller.lock().await).map_err(to_rpc_status);
+                if tx.send(snapshot).await.is_err() {
+                    // Client went away
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(Box::pin(ReceiverStream::new(rx))))
+    }
+}
+
+/// Serves the gRPC interface on `address` until `shutdown` turns true.
+///
+/// `address` is either `unix:<path>`, a socket only the daemon's user can use,
+/// or a loopback TCP address such as `127.0.0.1:50051`. Over TCP the RPCs that
+/// change fan control need `token`, and are refused without one.
+pub async fn start_grpc_server(
+    controller: ControllerHandle,
+    address: String,
+    token: Option<String>,
+    mut shutdown: watch::Receiver<bool>,
+) -> Result<()> {
+    let shutdown_signal = async move {
+        let _ = shutdown.wait_for(|stop| *stop).await;
+    };
+
+    if let Some(path) = address.strip_prefix("unix:") {
+        let path = PathBuf::from(path);
+        if path.exists() {
+            std::fs::remove_file(&path)?;
+        }
+        let listener = tokio::net::UnixListener::bind(&path)
+            .map_err(|e| Error::Socket(format!("Failed to bind gRPC socket: {}", e)))?;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+        tracing::info!("gRPC server listening on {}", path.display());
+
+        let service = FanControlService {
+            controller,
+            auth: RpcAuth::Socket,
+        };
+        let router = Server::builder().add_service(FanControlServer::new(service));
+
+        let result = router
+            .serve_with_incoming_shutdown(UnixListenerStream::new(listener), shutdown_signal)
+            .await;
+        let _ = std::fs::remove_file(&path);
+        result.map_err(|e| Error::Socket(format!("gRPC server error: {}", e)))
+    } else {
+        let addr: SocketAddr = address
+            .parse()
+            .map_err(|e| Error::Socket(format!("Invalid gRPC address '{}': {}", address, e)))?;
+        if !addr.ip().is_loopback() {
+            return Err(Error::Socket(format!(
+                "Refusing to serve gRPC on non-loopback address {}; use a loopback or unix: address",
+                addr
+            )));
+        }
+        if token.is_none() {
+            tracing::warn!(
+                "No --grpc-token-file, serving only read-only RPCs on {}",
+                addr
+            );
+        }
+        tracing::info!("gRPC server listening on {}", addr);
+
+        let service = FanControlService {
+            controller,
+            auth: RpcAuth::Token(token),
+        };
+        Server::builder()
+            .add_service(FanControlServer::new(service))
+            .serve_with_shutdown(addr, shutdown_signal)
+            .await
+            .map_err(|e| Error::Socket(format!("gRPC server error: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::Mutex;
+    use tonic::Code;
+
+    use super::*;
+    use crate::config::Config;
+    use crate::hardware::HardwareController;
+    use crate::mock::MockEc;
+
+    fn service(auth: RpcAuth) -> FanControlService {
+        let config: Config = serde_json::from_str(
+            r#"{"defaultStrategy": "lazy", "strategies": {"lazy": {
+                "speedCurve": [{"temp": 0, "speed": 20}, {"temp": 100, "speed": 100}]}}}"#,
+        )
+        .unwrap();
+        let ec = MockEc::new();
+        ec.set_temperatures(&[45]);
+        let hw = HardwareController::with_backend(Box::new(ec), None, "mock");
+        FanControlService {
+            controller: Arc::new(Mutex::new(FanController::new(hw, config, None))),
+            auth,
+        }
+    }
+
+    fn bearer(token: &str) -> Request<Empty> {
+        let mut request = Request::new(Empty {});
+        request.metadata_mut().insert(
+            "authorization",
+            format!("Bearer {}", token).parse().unwrap(),
+        );
+        request
+    }
+
+    #[test]
+    fn test_fan_changing_rpcs_over_tcp_need_the_token() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let open = service(RpcAuth::Token(None));
+            let denied = open.pause(Request::new(Empty {})).await.unwrap_err();
+            assert_eq!(denied.code(), Code::PermissionDenied);
+            assert!(open.get_status(Request::new(Empty {})).await.is_ok());
+
+            let guarded = service(RpcAuth::Token(Some("secret".into())));
+            let denied = guarded.pause(bearer("wrong")).await.unwrap_err();
+            assert_eq!(denied.code(), Code::Unauthenticated);
+            let reply = guarded.pause(bearer("secret")).await.unwrap();
+            assert!(!reply.into_inner().active);
+
+            let socket = service(RpcAuth::Socket);
+            assert!(socket.reset(Request::new(Empty {})).await.is_ok());
+        });
+    }
+
+    #[test]
+    fn test_tcp_must_be_loopback() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let (_shutdown, signal) = watch::channel(false);
+        let controller = service(RpcAuth::Socket).controller;
+        let result = runtime.block_on(start_grpc_server(
+            controller,
+            "0.0.0.0:0".into(),
+            Some("secret".into()),
+            signal,
+        ));
+        assert!(result.unwrap_err().to_string().contains("non-loopback"));
+    }
+}
@@ -1,157 +1,1030 @@
+#[cfg(feature = "framework")]
 use framework_lib::chromium_ec::{CrosEc, CrosEcDriver};
+#[cfg(feature = "framework")]
 use framework_lib::power;
+#[cfg(feature = "framework")]
 use framework_lib::smbios::Platform;
+use std::borrow::Cow;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
 
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{ProfileEnvironment, SensorAggregation, SensorSelection, SensorSelector};
 use crate::error::{Error, Result};
+use crate::external::ExternalSensors;
+use crate::sysfs::{self, ThermalTrip};
+
+pub(crate) const EC_MEMMAP_TEMP_SENSOR: u16 = 0x00;
+const EC_TEMP_SENSOR_COUNT: u16 = 0x0F;
+pub(crate) const EC_MEMMAP_FAN: u16 = 0x10;
+const EC_MEMMAP_SIZE: u16 = 0x100;
+/// Four little-endian u16 fan RPM slots.
+const EC_FAN_BLOCK_SIZE: u16 = 8;
+/// RPM the EC reports for an empty fan slot.
+pub(crate) const EC_FAN_SPEED_NOT_PRESENT: u16 = 0xFFFF;
+/// RPM the EC reports for a fan that should spin but doesn't.
+const EC_FAN_SPEED_STALLED: u16 = 0xFFFE;
+
+/// Advisory `flock` held around every EC fan write. Scripts and other tools
+/// that write fan duties can take the same lock to avoid interleaving with the daemon.
+pub const EC_LOCK_PATH: &str = "/run/fw-fanctrl/ec.lock";
+const EC_LOCK_ATTEMPTS: u32 = 10;
+const EC_LOCK_RETRY: Duration = Duration::from_millis(20);
+
+/// Takes the EC write lock at `path`, retrying briefly while another process holds it.
+///
+/// Returns `Ok(None)` when the lock file cannot be created (e.g. not running
+/// as root), since the lock is purely advisory.
+fn lock_ec_writes(path: &Path) -> Result<Option<File>> {
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    let file = match OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)
+    {
+        Ok(file) => file,
+        Err(e) => {
+            tracing::debug!("EC write lock {} unavailable: {}", path.display(), e);
+            return Ok(None);
+        }
+    };
+
+    for _ in 0..EC_LOCK_ATTEMPTS {
+        // SAFETY: the descriptor belongs to `file`, which outlives the call.
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0 {
+            // Released when the file is closed
+            return Ok(Some(file));
+        }
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::WouldBlock {
+            tracing::debug!("Failed to lock {}: {}", path.display(), err);
+            return Ok(None);
+        }
+        std::thread::sleep(EC_LOCK_RETRY);
+    }
 
-const EC_MEMMAP_TEMP_SENSOR: u16 = 0x00;
+    Err(Error::Ec(format!(
+        "EC write lock {} is held by another process",
+        path.display()
+    )))
+}
+
+/// Labels of the EC sensors, so matching them needs no formatting per read.
+const EC_LABELS: [&str; EC_TEMP_SENSOR_COUNT as usize] = [
+    "ec0", "ec1", "ec2", "ec3", "ec4", "ec5", "ec6", "ec7", "ec8", "ec9", "ec10", "ec11", "ec12",
+    "ec13", "ec14",
+];
+
+/// The selector label of EC sensor `index`, e.g. `ec3`.
+fn ec_label(index: usize) -> Cow<'static, str> {
+    match EC_LABELS.get(index) {
+        Some(label) => Cow::Borrowed(label),
+        None => Cow::Owned(format!("ec{}", index)),
+    }
+}
 
-fn get_battery_sensor_index(platform: Option<Platform>) -> Option<usize> {
+/// The readings in raw EC temperature bytes as `(index, °C)`. Skips the
+/// markers 0xFC-0xFF (not present, error, not powered, not calibrated) and
+/// converts the rest from the EC's offset of 73.
+fn valid_temperatures(raw: &[u8]) -> impl Iterator<Item = (usize, u8)> + '_ {
+    raw.iter()
+        .copied()
+        .enumerate()
+        .filter(|(_, t)| *t < 0xFC)
+        .map(|(i, t)| (i, t.saturating_sub(73)))
+        .filter(|(_, t)| *t > 0)
+}
+
+/// Names of the EC temperature sensors by index, where the platform's layout is known.
+#[cfg(feature = "framework")]
+fn platform_sensor_names(platform: Option<Platform>) -> &'static [&'static str] {
+    // Based on framework_lib/src/power.rs sensor mappings
     match platform {
-        // Based on framework_lib/src/power.rs sensor mappings
-        // Index 3 = Battery for Intel Gen 11/12/13
-        Some(Platform::IntelGen11 | Platform::IntelGen12 | Platform::IntelGen13) => Some(3),
-        // Index 2 = Battery for Intel Core Ultra 1
-        Some(Platform::IntelCoreUltra1) => Some(2),
-        // Index 3 = Battery for Framework 12 Gen 13
-        Some(Platform::Framework12IntelGen13) => Some(3),
-        // AMD platforms (7040/AI300): No separate battery sensor in EC memory map
-        // Framework 16: Different sensor layout with dGPU
-        // These platforms should use max temp from all sensors
-        _ => None,
+        Some(Platform::IntelGen11) => &["local", "cpu", "ddr", "battery", "peci"],
+        Some(Platform::IntelGen12 | Platform::IntelGen13) => {
+            &["local", "cpu", "ddr", "battery", "peci", "vccgt"]
+        }
+        Some(Platform::IntelCoreUltra1) => &["local", "cpu", "battery", "ddr", "peci"],
+        Some(Platform::Framework12IntelGen13) => {
+            &["cpu", "skin", "local", "battery", "peci", "charger"]
+        }
+        // AMD 7040 and Ryzen AI 300: no battery sensor in the EC memory map
+        Some(Platform::Framework13Amd7080 | Platform::Framework13AmdAi300) => {
+            &["local", "cpu", "ddr", "apu"]
+        }
+        // Framework 16: the dGPU module adds its own sensors
+        Some(Platform::Framework16Amd7080 | Platform::Framework16AmdAi300) => &[
+            "local",
+            "cpu",
+            "ddr",
+            "apu",
+            "dgpu-vr",
+            "dgpu-vram",
+            "dgpu-ambient",
+            "dgpu",
+        ],
+        _ => &[],
     }
 }
 
+/// Where temperatures and fan data live in the EC memory map.
+///
+/// The defaults match current Framework firmware; the `ecLayout` config
+/// section overrides them for firmware that moves things around.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct EcLayout {
+    #[serde(rename = "temperatureOffset", alias = "temperature_offset")]
+    pub temperature_offset: u16,
+    #[serde(rename = "temperatureSensorCount", alias = "temperature_sensor_count")]
+    pub temperature_sensor_count: u16,
+    #[serde(rename = "fanOffset", alias = "fan_offset")]
+    pub fan_offset: u16,
+}
+
+impl Default for EcLayout {
+    fn default() -> Self {
+        Self {
+            temperature_offset: EC_MEMMAP_TEMP_SENSOR,
+            temperature_sensor_count: EC_TEMP_SENSOR_COUNT,
+            fan_offset: EC_MEMMAP_FAN,
+        }
+    }
+}
+
+impl EcLayout {
+    pub fn validate(&self) -> Result<()> {
+        let temperature_end = self.temperature_offset as u32 + self.temperature_sensor_count as u32;
+        if self.temperature_sensor_count == 0 || temperature_end > EC_MEMMAP_SIZE as u32 {
+            return Err(Error::Config(format!(
+                "ecLayout temperature sensors must lie within the {:#x}-byte memory map",
+                EC_MEMMAP_SIZE
+            )));
+        }
+        if self.fan_offset as u32 + EC_FAN_BLOCK_SIZE as u32 > EC_MEMMAP_SIZE as u32 {
+            return Err(Error::Config(format!(
+                "ecLayout fan block must lie within the {:#x}-byte memory map",
+                EC_MEMMAP_SIZE
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Low-level embedded controller operations used by [`HardwareController`].
+///
+/// Implemented for framework_lib's `CrosEc` (behind the `framework` feature)
+/// and for [`crate::mock::MockEc`].
+pub trait EcBackend: Send {
+    fn read_memory(&self, offset: u16, length: u16) -> Option<Vec<u8>>;
+
+    /// Like [`read_memory`](Self::read_memory), but fills `buf` so that
+    /// backends able to avoid an allocation per read can do so. framework_lib
+    /// only returns fresh buffers, so the `CrosEc` backend still allocates.
+    fn read_memory_into(&self, offset: u16, length: u16, buf: &mut Vec<u8>) -> bool {
+        match self.read_memory(offset, length) {
+            Some(memory) => {
+                *buf = memory;
+                true
+            }
+            None => false,
+        }
+    }
+    fn fan_set_duty(&self, percent: u32) -> Result<()>;
+
+    /// The duty the fan is driven at, when the backend can report it. The
+    /// memory map only holds fan RPMs, so the EC itself reports none.
+    fn fan_duty(&self) -> Option<u32> {
+        None
+    }
+    fn autofanctrl(&self) -> Result<()>;
+    fn ac_present(&self) -> Result<bool>;
+
+    /// Battery charge in percent, when the EC reports a battery.
+    fn battery_charge(&self) -> Option<u32> {
+        None
+    }
+
+    /// Whether the battery is charging, when the EC reports a battery.
+    fn charge_state(&self) -> Option<ChargeState> {
+        None
+    }
+
+    /// Power the battery is discharging at in watts, 0 while it isn't, when the
+    /// EC reports a battery.
+    fn battery_discharge_watts(&self) -> Option<f64> {
+        None
+    }
+
+    /// Caps the battery charge current at `rate` times the battery capacity
+    /// (a C-rate); 1.0 is full rate.
+    fn set_charge_rate_limit(&self, _rate: f32) -> Result<()> {
+        Err(Error::Ec("Charge rate limiting is not supported".into()))
+    }
+
+    /// Transport used to reach the EC.
+    fn driver(&self) -> String {
+        "unknown".into()
+    }
+
+    /// EC firmware version, when it can be queried.
+    fn version(&self) -> Option<String> {
+        None
+    }
+}
+
+#[cfg(feature = "framework")]
+impl EcBackend for CrosEc {
+    fn read_memory(&self, offset: u16, length: u16) -> Option<Vec<u8>> {
+        CrosEcDriver::read_memory(self, offset, length)
+    }
+
+    fn fan_set_duty(&self, percent: u32) -> Result<()> {
+        CrosEc::fan_set_duty(self, None, percent).map_err(|e| Error::Ec(format!("{:?}", e)))
+    }
+
+    fn autofanctrl(&self) -> Result<()> {
+        CrosEc::autofanctrl(self, None).map_err(|e| Error::Ec(format!("{:?}", e)))
+    }
+
+    fn ac_present(&self) -> Result<bool> {
+        let info = power::power_info(self)
+            .ok_or_else(|| Error::Ec("Failed to read power info from EC".into()))?;
+        Ok(info.ac_present)
+    }
+
+    fn battery_charge(&self) -> Option<u32> {
+        power::power_info(self)?
+            .battery
+            .map(|battery| battery.charge_percentage)
+    }
+
+    fn charge_state(&self) -> Option<ChargeState> {
+        let info = power::power_info(self)?;
+        let battery = info.battery?;
+        Some(ChargeState::from_battery(
+            info.ac_present,
+            battery.charging,
+            battery.charge_percentage,
+        ))
+    }
+
+    fn battery_discharge_watts(&self) -> Option<f64> {
+        let battery = power::power_info(self)?.battery?;
+        // present_rate is in mA, present_voltage in mV
+        Some(if battery.discharging {
+            battery.present_rate as f64 * battery.present_voltage as f64 / 1_000_000.0
+        } else {
+            0.0
+        })
+    }
+
+    fn set_charge_rate_limit(&self, rate: f32) -> Result<()> {
+        CrosEc::set_charge_rate_limit(self, rate, None).map_err(|e| Error::Ec(format!("{:?}", e)))
+    }
+
+    fn driver(&self) -> String {
+        format!("{:?}", self.driver)
+    }
+
+    fn version(&self) -> Option<String> {
+        self.version_info().ok().map(|v| v.trim().to_string())
+    }
+}
+
+/// What the battery is doing, finer-grained than the power source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ChargeState {
+    Charging,
+    Discharging,
+    /// On AC with the battery full.
+    Full,
+    /// On AC and not charging below full, held by the charge limit.
+    ChargeLimited,
+}
+
+impl ChargeState {
+    /// Charge from which an idle battery on AC counts as full rather than
+    /// held by a charge limit; batteries rarely stop at exactly 100%.
+    const FULL_PERCENT: u32 = 98;
+
+    pub fn from_battery(ac_present: bool, charging: bool, percent: u32) -> Self {
+        match (ac_present, charging) {
+            (_, true) => Self::Charging,
+            (false, false) => Self::Discharging,
+            (true, false) if percent >= Self::FULL_PERCENT => Self::Full,
+            (true, false) => Self::ChargeLimited,
+        }
+    }
+}
+
+impl std::fmt::Display for ChargeState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Charging => "charging",
+            Self::Discharging => "discharging",
+            Self::Full => "full",
+            Self::ChargeLimited => "charge-limited",
+        })
+    }
+}
+
+/// One temperature sensor reading.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SensorReading {
+    pub label: String,
+    /// What the sensor measures on this platform, e.g. `cpu`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub celsius: f64,
+    /// Whether this is the platform's battery sensor.
+    pub battery: bool,
+    /// Whether the sensor is left out of the control temperature.
+    pub excluded: bool,
+}
+
+/// Detected hardware details, for `print platform` and bug reports.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlatformInfo {
+    pub platform: String,
+    /// EC transport, or `None` in degraded mode.
+    pub ec_driver: Option<String>,
+    pub ec_version: Option<String>,
+    pub fan_count: usize,
+    pub battery_sensor_index: Option<usize>,
+    pub battery_excluded: bool,
+    pub sensors: Vec<SensorReading>,
+    pub ec_layout: EcLayout,
+    pub capabilities: Capabilities,
+}
+
+/// What the hardware layer is able to do on this machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Capabilities {
+    pub fan_control: bool,
+    pub ec_temperatures: bool,
+    pub power_status: bool,
+    /// The EC reports no fan, e.g. on fanless mods.
+    pub fanless: bool,
+}
+
 pub struct HardwareController {
-    ec: CrosEc,
+    /// `None` when running degraded on hardware without a usable EC.
+    ec: Option<Box<dyn EcBackend>>,
+    /// The platform's battery sensor, when known.
     battery_sensor_index: Option<usize>,
+    /// Leave the battery sensor out of the control temperature.
+    exclude_battery: bool,
+    /// EC sensor names by index; empty when the platform's layout is unknown.
+    sensor_names: Vec<&'static str>,
+    /// `includeSensors` and `excludeSensors` from the config.
+    sensor_selection: SensorSelection,
     platform_name: String,
+    layout: EcLayout,
+    /// Advisory lock taken around fan writes; `None` disables locking.
+    lock_path: Option<PathBuf>,
+    /// Why duty writes are refused although the EC is reachable.
+    fan_control_refused: Option<String>,
+    /// Readings of the `sensorSources`, added to the sensor set.
+    external: ExternalSensors,
+    /// Reused by every temperature read so the control loop doesn't allocate.
+    buffers: Mutex<TemperatureBuffers>,
+    /// Whether the EC reports a fan, from the first successful read; fans
+    /// don't come and go at runtime.
+    fan_presence: OnceLock<bool>,
 }
 
-impl HardwareController {
-    pub fn new(no_battery_sensors: bool) -> Result<Self> {
-        let ec = CrosEc::new();
+#[derive(Default)]
+struct TemperatureBuffers {
+    raw: Vec<u8>,
+    celsius: Vec<f64>,
+}
 
+impl HardwareController {
+    #[cfg(feature = "framework")]
+    pub fn new(no_battery_sensors: bool, allow_unsupported: bool) -> Result<Self> {
         let platform = framework_lib::smbios::get_platform();
         let platform_name = format!("{:?}", platform);
 
-        // Only determine battery sensor index if flag is set
-        let battery_index = if no_battery_sensors {
-            get_battery_sensor_index(platform)
-        } else {
-            None
-        };
+        let ec = CrosEc::new();
+        let ec: Option<Box<dyn EcBackend>> =
+            if EcBackend::read_memory(&ec, EC_MEMMAP_TEMP_SENSOR, EC_TEMP_SENSOR_COUNT).is_some() {
+                Some(Box::new(ec))
+            } else if allow_unsupported {
+                tracing::warn!(
+                    "EC not accessible (platform: {}), running in degraded mode: \
+                     hwmon temperatures only, no fan control",
+                    platform_name
+                );
+                None
+            } else {
+                return Err(Error::Ec(format!(
+                    "EC not accessible (platform: {}); \
+                     use --allow-unsupported to run in degraded mode",
+                    platform_name
+                )));
+            };
+
+        // Known even when not excluded, so the battery can be reported on its own
+        let sensor_names = platform_sensor_names(platform);
+        let battery_index = sensor_names.iter().position(|name| *name == "battery");
 
         tracing::info!(
-            "Platform: {}, Battery sensor index to exclude: {:?}",
+            "Platform: {}, Battery sensor index: {:?}, excluded from control: {}",
             platform_name,
-            battery_index
+            battery_index,
+            no_battery_sensors
         );
 
         Ok(Self {
             ec,
             battery_sensor_index: battery_index,
+            exclude_battery: no_battery_sensors,
+            sensor_names: sensor_names.to_vec(),
+            sensor_selection: SensorSelection::default(),
             platform_name,
+            layout: EcLayout::default(),
+            lock_path: Some(PathBuf::from(EC_LOCK_PATH)),
+            fan_control_refused: None,
+            external: ExternalSensors::default(),
+            buffers: Mutex::default(),
+            fan_presence: OnceLock::new(),
         })
     }
 
-    pub fn get_temperature(&self) -> Result<f64> {
-        let temps = self
-            .ec
-            .read_memory(EC_MEMMAP_TEMP_SENSOR, 0x0F)
-            .ok_or_else(|| Error::Ec("Failed to read temperature from EC".into()))?;
-
-        // Filter invalid values (0xFF=NotPresent, 0xFE=Error, 0xFD=NotPowered, 0xFC=NotCalibrated)
-        // and convert from EC raw value to Celsius (subtract 73)
-        let valid_temps: Vec<(usize, u8)> = temps
-            .iter()
+    #[cfg(not(feature = "framework"))]
+    pub fn new(_no_battery_sensors: bool, allow_unsupported: bool) -> Result<Self> {
+        if !allow_unsupported {
+            return Err(Error::Ec(
+                "Built without the `framework` feature; \
+                 use --allow-unsupported to run in degraded mode"
+                    .into(),
+            ));
+        }
+
+        tracing::warn!("Built without EC support, running in degraded mode");
+
+        Ok(Self {
+            ec: None,
+            battery_sensor_index: None,
+            exclude_battery: false,
+            sensor_names: Vec::new(),
+            sensor_selection: SensorSelection::default(),
+            platform_name: "unknown".into(),
+            layout: EcLayout::default(),
+            lock_path: None,
+            fan_control_refused: None,
+            external: ExternalSensors::default(),
+            buffers: Mutex::default(),
+            fan_presence: OnceLock::new(),
+        })
+    }
+
+    /// Builds a controller on top of an arbitrary EC backend, e.g. [`crate::mock::MockEc`].
+    ///
+    /// A `battery_sensor_index` is only reported; [`Self::with_battery_excluded`]
+    /// leaves it out of control.
+    pub fn with_backend(
+        ec: Box<dyn EcBackend>,
+        battery_sensor_index: Option<usize>,
+        platform_name: impl Into<String>,
+    ) -> Self {
+        Self {
+            ec: Some(ec),
+            battery_sensor_index,
+            exclude_battery: false,
+            sensor_names: Vec::new(),
+            sensor_selection: SensorSelection::default(),
+            platform_name: platform_name.into(),
+            layout: EcLayout::default(),
+            lock_path: None,
+            fan_control_refused: None,
+            external: ExternalSensors::default(),
+            buffers: Mutex::default(),
+            fan_presence: OnceLock::new(),
+        }
+    }
+
+    /// Wraps the EC backend, e.g. in a [`crate::replay::RecordingEc`]; does nothing
+    /// in degraded mode.
+    pub fn map_backend(
+        mut self,
+        wrap: impl FnOnce(Box<dyn EcBackend>) -> Result<Box<dyn EcBackend>>,
+    ) -> Result<Self> {
+        self.ec = self.ec.map(wrap).transpose()?;
+        self.fan_presence = OnceLock::new();
+        Ok(self)
+    }
+
+    /// Adds the readings recorded in `sensors` to the sensor set.
+    pub fn with_external_sensors(mut self, sensors: ExternalSensors) -> Self {
+        self.external = sensors;
+        self
+    }
+
+    /// External readings that feed the control temperature.
+    fn external_temperatures(&self) -> impl Iterator<Item = f64> + '_ {
+        self.external
+            .readings()
+            .into_iter()
+            .filter(|(name, _)| !self.sensor_selection.excludes_label(name))
+            .map(|(_, celsius)| celsius)
+    }
+
+    /// Leaves the battery sensor out of the control temperature.
+    pub fn with_battery_excluded(mut self, excluded: bool) -> Self {
+        self.exclude_battery = excluded;
+        self
+    }
+
+    /// Like [`with_battery_excluded`](Self::with_battery_excluded), at runtime.
+    pub fn set_battery_excluded(&mut self, excluded: bool) {
+        self.exclude_battery = excluded;
+    }
+
+    /// Whether the battery sensor is left out, independent of the sensor selection.
+    pub fn is_battery_excluded(&self) -> bool {
+        self.exclude_battery
+    }
+
+    /// The platform's battery sensor, when known.
+    pub fn battery_sensor_index(&self) -> Option<usize> {
+        self.battery_sensor_index
+    }
+
+    /// Picks the sensors that feed the control temperature; an excluded battery
+    /// sensor stays excluded either way.
+    pub fn set_sensor_selection(&mut self, selection: SensorSelection) {
+        self.sensor_selection = selection;
+    }
+
+    /// What EC sensor `index` measures, where known.
+    fn sensor_name(&self, index: usize) -> Option<&'static str> {
+        self.sensor_names
+            .get(index)
             .copied()
-            .enumerate()
-            .filter(|(_, t)| *t < 0xFC)
-            .map(|(i, t)| (i, t.saturating_sub(73)))
-            .filter(|(_, t)| *t > 0)
-            .collect();
+            .or_else(|| (Some(index) == self.battery_sensor_index).then_some("battery"))
+    }
+
+    /// Whether EC sensor `index` is left out of the control temperature.
+    fn is_excluded(&self, index: usize) -> bool {
+        if self.exclude_battery && Some(index) == self.battery_sensor_index {
+            return true;
+        }
+        let label = ec_label(index);
+        match self.sensor_name(index) {
+            Some(name) => self.sensor_selection.excludes(index, &[&label, name]),
+            None => self.sensor_selection.excludes(index, &[&label]),
+        }
+    }
+
+    /// Reads the EC memory map using `layout` instead of the default offsets.
+    pub fn with_layout(mut self, layout: EcLayout) -> Self {
+        self.layout = layout;
+        self.fan_presence = OnceLock::new();
+        self
+    }
+
+    /// Guards fan writes with an advisory lock at `path`.
+    pub fn with_lock_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.lock_path = Some(path.into());
+        self
+    }
+
+    fn lock(&self) -> Result<Option<File>> {
+        match &self.lock_path {
+            Some(path) => lock_ec_writes(path),
+            None => Ok(None),
+        }
+    }
+
+    /// The detected model, e.g. `Framework13Amd7080`, or `unknown`.
+    pub fn platform_name(&self) -> &str {
+        &self.platform_name
+    }
+
+    pub fn is_degraded(&self) -> bool {
+        self.ec.is_none()
+    }
+
+    /// Leaves the fan to the EC while still reading temperatures from it,
+    /// e.g. on firmware with known fan-control quirks.
+    pub fn with_fan_control_refused(mut self, reason: impl Into<String>) -> Self {
+        self.refuse_fan_control(reason);
+        self
+    }
+
+    pub fn refuse_fan_control(&mut self, reason: impl Into<String>) {
+        self.fan_control_refused = Some(reason.into());
+    }
+
+    /// Why fan control was refused, if it was.
+    pub fn fan_control_refused(&self) -> Option<&str> {
+        self.fan_control_refused.as_deref()
+    }
+
+    /// Whether duty writes are possible and allowed.
+    pub fn can_control_fan(&self) -> bool {
+        self.ec.is_some() && self.fan_control_refused.is_none() && !self.is_fanless()
+    }
+
+    /// Whether the EC is reachable but reports no fan; a failed read doesn't count.
+    pub fn is_fanless(&self) -> bool {
+        self.ec.is_some() && matches!(self.fan_present(), Ok(false))
+    }
+
+    pub fn ec_version(&self) -> Option<String> {
+        self.ec.as_ref().and_then(|ec| ec.version())
+    }
+
+    pub fn capabilities(&self) -> Capabilities {
+        let has_ec = self.ec.is_some();
+        Capabilities {
+            fan_control: self.can_control_fan(),
+            ec_temperatures: has_ec,
+            power_status: has_ec || sysfs::read_ac_online().is_some(),
+            fanless: self.is_fanless(),
+        }
+    }
+
+    /// Gathers what was detected; readings that fail are left out.
+    pub fn platform_info(&self) -> PlatformInfo {
+        PlatformInfo {
+            platform: self.platform_name.clone(),
+            ec_driver: self.ec.as_ref().map(|ec| ec.driver()),
+            ec_version: self.ec_version(),
+            fan_count: self.get_fan_rpms().map_or(0, |rpms| rpms.len()),
+            battery_sensor_index: self.battery_sensor_index,
+            battery_excluded: self
+                .battery_sensor_index
+                .is_some_and(|index| self.is_excluded(index)),
+            sensors: self.get_sensor_temperatures().unwrap_or_default(),
+            ec_layout: self.layout,
+            capabilities: self.capabilities(),
+        }
+    }
 
-        tracing::debug!("Raw temperature sensors: {:02x?}", temps);
+    fn ec(&self) -> Result<&dyn EcBackend> {
+        self.ec
+            .as_deref()
+            .ok_or_else(|| Error::Ec("EC not available in degraded mode".into()))
+    }
+
+    /// Valid EC sensor readings as `(index, °C)`.
+    fn read_ec_temperatures(&self, ec: &dyn EcBackend) -> Result<Vec<(usize, u8)>> {
+        let mut raw = Vec::new();
+        self.read_raw_temperatures(ec, &mut raw)?;
+        let valid_temps: Vec<(usize, u8)> = valid_temperatures(&raw).collect();
         tracing::debug!(
             "Valid temperature sensors (index, Celsius): {:?}",
             valid_temps
         );
+        Ok(valid_temps)
+    }
 
-        if valid_temps.is_empty() {
-            return Ok(50.0);
+    /// Reads the raw EC temperature bytes into `raw`.
+    fn read_raw_temperatures(&self, ec: &dyn EcBackend, raw: &mut Vec<u8>) -> Result<()> {
+        if !ec.read_memory_into(
+            self.layout.temperature_offset,
+            self.layout.temperature_sensor_count,
+            raw,
+        ) {
+            return Err(Error::Ec("Failed to read temperature from EC".into()));
         }
+        tracing::debug!("Raw temperature sensors: {:02x?}", raw);
+        Ok(())
+    }
 
-        let max_temp = if let Some(battery_idx) = self.battery_sensor_index {
-            // Exclude the battery sensor at the known index
-            let non_battery: Vec<u8> = valid_temps
-                .iter()
-                .filter(|(i, _)| *i != battery_idx)
-                .map(|(_, t)| *t)
-                .collect();
+    /// The hottest sensor reading.
+    pub fn get_temperature(&self) -> Result<f64> {
+        self.get_aggregated_temperature(SensorAggregation::Max)
+    }
 
-            if non_battery.is_empty() {
-                // If all sensors were filtered out, fall back to max of all
-                *valid_temps.iter().map(|(_, t)| t).max().unwrap()
-            } else {
-                *non_battery.iter().max().unwrap()
+    /// Collapses the sensor readings into one temperature. Excluded sensors are
+    /// left out, unless no other sensor reports.
+    pub fn get_aggregated_temperature(&self, aggregation: SensorAggregation) -> Result<f64> {
+        let Some(ec) = &self.ec else {
+            return self.get_hwmon_temperature(aggregation);
+        };
+
+        let mut buffers = self.buffers.lock().unwrap_or_else(|e| e.into_inner());
+        let TemperatureBuffers { raw, celsius } = &mut *buffers;
+        self.read_raw_temperatures(ec.as_ref(), raw)?;
+
+        celsius.clear();
+        celsius.extend(
+            valid_temperatures(raw)
+                .filter(|(i, _)| !self.is_excluded(*i))
+                .map(|(_, t)| t as f64),
+        );
+        self.external.for_each_reading(|name, temp| {
+            if !self.sensor_selection.excludes_label(name) {
+                celsius.push(temp);
             }
-        } else {
-            // No battery exclusion (unknown platform or flag not set) - use max of all
-            *valid_temps.iter().map(|(_, t)| t).max().unwrap()
+        });
+        if celsius.is_empty() {
+            celsius.extend(valid_temperatures(raw).map(|(_, t)| t as f64));
+        }
+        let Some(temp) = aggregation.apply_in_place(celsius) else {
+            return Ok(50.0);
         };
 
         tracing::debug!(
-            "Selected max temperature: {}°C (platform: {})",
-            max_temp,
+            "Selected {:?} temperature: {}°C (platform: {})",
+            aggregation,
+            temp,
             self.platform_name
         );
 
-        Ok(max_temp as f64)
+        Ok(temp)
     }
 
+    /// Hottest reading of the sensors `sensor` selects, whether or not they are
+    /// excluded from control. Labels that match no EC sensor are looked up in
+    /// hwmon, e.g. `Composite` for an NVMe drive.
+    pub fn get_sensor_temperature(&self, sensor: &SensorSelector) -> Result<Option<f64>> {
+        if let Some(ec) = &self.ec {
+            let temp = self
+                .read_ec_temperatures(ec.as_ref())?
+                .into_iter()
+                .filter(|(i, _)| {
+                    let label = ec_label(*i);
+                    match self.sensor_name(*i) {
+                        Some(name) => sensor.matches(*i, &[&label, name]),
+                        None => sensor.matches(*i, &[&label]),
+                    }
+                })
+                .map(|(_, t)| t as f64)
+                .reduce(f64::max);
+            if temp.is_some() || matches!(sensor, SensorSelector::Index(_)) {
+                return Ok(temp);
+            }
+        }
+
+        let external = self
+            .external
+            .readings()
+            .into_iter()
+            .filter(|(name, _)| matches!(sensor, SensorSelector::Label(l) if l.eq_ignore_ascii_case(name)))
+            .map(|(_, celsius)| celsius)
+            .reduce(f64::max);
+        if external.is_some() {
+            return Ok(external);
+        }
+
+        Ok(sysfs::read_hwmon_temperatures()
+            .into_iter()
+            .enumerate()
+            .filter(|(i, s)| sensor.matches(*i, &[&s.label]))
+            .map(|(_, s)| s.celsius)
+            .reduce(f64::max))
+    }
+
+    /// Battery temperature, whether or not it is excluded from control.
+    pub fn get_battery_temperature(&self) -> Result<Option<f64>> {
+        let (Some(ec), Some(index)) = (&self.ec, self.battery_sensor_index) else {
+            return Ok(None);
+        };
+        Ok(self
+            .read_ec_temperatures(ec.as_ref())?
+            .into_iter()
+            .find(|(i, _)| *i == index)
+            .map(|(_, t)| t as f64))
+    }
+
+    /// Every sensor reading: EC sensors by index, or hwmon sensors in degraded mode,
+    /// followed by the `sensorSources`.
+    pub fn get_sensor_temperatures(&self) -> Result<Vec<SensorReading>> {
+        let mut readings = self.get_builtin_sensor_temperatures()?;
+        readings.extend(
+            self.external
+                .readings()
+                .into_iter()
+                .map(|(label, celsius)| SensorReading {
+                    excluded: self.sensor_selection.excludes_label(&label),
+                    label,
+                    name: None,
+                    celsius,
+                    battery: false,
+                }),
+        );
+        Ok(readings)
+    }
+
+    fn get_builtin_sensor_temperatures(&self) -> Result<Vec<SensorReading>> {
+        let Some(ec) = &self.ec else {
+            return Ok(sysfs::read_hwmon_temperatures()
+                .into_iter()
+                .enumerate()
+                .map(|(i, s)| SensorReading {
+                    excluded: self.sensor_selection.excludes(i, &[&s.label]),
+                    label: s.label,
+                    name: None,
+                    celsius: s.celsius,
+                    battery: false,
+                })
+                .collect());
+        };
+        Ok(self
+            .read_ec_temperatures(ec.as_ref())?
+            .into_iter()
+            .map(|(i, t)| SensorReading {
+                label: ec_label(i).into_owned(),
+                name: self.sensor_name(i).map(String::from),
+                celsius: t as f64,
+                battery: Some(i) == self.battery_sensor_index,
+                excluded: self.is_excluded(i),
+            })
+            .collect())
+    }
+
+    fn get_hwmon_temperature(&self, aggregation: SensorAggregation) -> Result<f64> {
+        let sensors = sysfs::read_hwmon_temperatures();
+        tracing::debug!("hwmon temperature sensors: {:?}", sensors);
+
+        let temps: Vec<f64> = sensors
+            .iter()
+            .enumerate()
+            .filter(|(i, s)| !self.sensor_selection.excludes(*i, &[&s.label]))
+            .map(|(_, s)| s.celsius)
+            .filter(|t| *t > 0.0)
+            .chain(self.external_temperatures())
+            .collect();
+        aggregation
+            .apply(&temps)
+            .ok_or_else(|| Error::Ec("No hwmon temperature sensors available".into()))
+    }
+
+    /// Writes a fan duty and, where the backend reports the duty, reads it
+    /// back, retrying once if it reports something else.
+    ///
+    /// Refuses duties above 100% and writes while the EC reports no fan.
     pub fn set_fan_speed(&self, speed: u32) -> Result<()> {
-        self.ec
-            .fan_set_duty(None, speed)
-            .map_err(|e| Error::Ec(format!("{:?}", e)))
+        if speed > 100 {
+            return Err(Error::Ec(format!(
+                "Refusing to set fan duty {}%, must be 0-100",
+                speed
+            )));
+        }
+        let ec = self.ec()?;
+        if let Some(reason) = &self.fan_control_refused {
+            return Err(Error::Ec(format!("Fan control refused: {}", reason)));
+        }
+        if !self.fan_present()? {
+            return Err(Error::Ec(
+                "EC reports no fan present, not writing duty".into(),
+            ));
+        }
+
+        let _lock = self.lock()?;
+        ec.fan_set_duty(speed)?;
+        match self.get_fan_duty()? {
+            Some(duty) if duty != speed => {
+                tracing::warn!(
+                    "EC reports duty {}% after setting {}%, retrying",
+                    duty,
+                    speed
+                );
+                ec.fan_set_duty(speed)?;
+                match self.get_fan_duty()? {
+                    Some(duty) if duty != speed => Err(Error::Ec(format!(
+                        "Fan duty write not applied: set {}%, EC reports {}%",
+                        speed, duty
+                    ))),
+                    _ => Ok(()),
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn read_fan_block(&self) -> Result<Vec<u8>> {
+        self.ec()?
+            .read_memory(self.layout.fan_offset, EC_FAN_BLOCK_SIZE)
+            .ok_or_else(|| Error::Ec("Failed to read fan info from EC".into()))
+    }
+
+    /// The duty the backend reports, or `None` when it doesn't report a valid one.
+    pub fn get_fan_duty(&self) -> Result<Option<u32>> {
+        Ok(self.ec()?.fan_duty().filter(|duty| *duty <= 100))
     }
 
     pub fn get_fan_speed(&self) -> Result<u32> {
-        let fans = self
-            .ec
-            .read_memory(0x10, 8)
-            .ok_or_else(|| Error::Ec("Failed to read fan info from EC".into()))?;
+        Ok(self.get_fan_duty()?.unwrap_or(0))
+    }
 
-        let duty = fans[4];
-        if duty > 100 {
-            return Ok(0);
+    /// Whether the EC reports a fan in the first slot. Only read until a read
+    /// succeeds, so the per-tick capability checks don't each go to the EC.
+    pub fn fan_present(&self) -> Result<bool> {
+        if let Some(present) = self.fan_presence.get() {
+            return Ok(*present);
+        }
+        let fans = self.read_fan_block()?;
+        let present = u16::from_le_bytes([fans[0], fans[1]]) != EC_FAN_SPEED_NOT_PRESENT;
+        Ok(*self.fan_presence.get_or_init(|| present))
+    }
+
+    /// Passive and critical trip points of the kernel's thermal zones.
+    pub fn get_thermal_trips(&self) -> Vec<ThermalTrip> {
+        sysfs::read_thermal_trips()
+    }
+
+    /// Busiest GPU's utilization, where the driver reports it.
+    pub fn get_gpu_busy_percent(&self) -> Option<u32> {
+        sysfs::read_gpu_busy_percent()
+    }
+
+    /// Connected USB devices and the power profile, for profile triggers.
+    pub fn profile_environment(&self) -> ProfileEnvironment {
+        ProfileEnvironment {
+            usb_devices: sysfs::read_usb_devices(),
+            power_profile: sysfs::read_power_profile(),
         }
-        Ok(duty as u32)
     }
 
     pub fn is_on_ac(&self) -> Result<bool> {
-        let info = power::power_info(&self.ec)
-            .ok_or_else(|| Error::Ec("Failed to read power info from EC".into()))?;
-        Ok(info.ac_present)
+        let Some(ec) = &self.ec else {
+            return sysfs::read_ac_online()
+                .ok_or_else(|| Error::Ec("No mains power supply found in sysfs".into()));
+        };
+
+        ec.ac_present()
     }
 
-    pub fn enable_auto_fan(&self) -> Result<()> {
+    /// Battery charge in percent from the EC, or sysfs where the EC has none.
+    pub fn get_battery_charge(&self) -> Option<u32> {
+        self.ec
+            .as_ref()
+            .and_then(|ec| ec.battery_charge())
+            .or_else(sysfs::read_battery_capacity)
+    }
+
+    /// Battery charge state from the EC, or sysfs where the EC has none.
+    pub fn get_charge_state(&self) -> Option<ChargeState> {
+        self.ec
+            .as_ref()
+            .and_then(|ec| ec.charge_state())
+            .or_else(sysfs::read_charge_state)
+    }
+
+    /// Battery discharge power in watts from the EC, or sysfs where the EC has none.
+    pub fn get_battery_discharge_watts(&self) -> Option<f64> {
         self.ec
-            .autofanctrl(None)
-            .map_err(|e| Error::Ec(format!("{:?}", e)))
+            .as_ref()
+            .and_then(|ec| ec.battery_discharge_watts())
+            .or_else(sysfs::read_battery_discharge_watts)
+    }
+
+    pub fn enable_auto_fan(&self) -> Result<()> {
+        let Some(ec) = &self.ec else {
+            // Nothing to hand back to the EC when we never controlled the fan
+            return Ok(());
+        };
+        if self.is_fanless() {
+            return Ok(());
+        }
+
+        let _lock = self.lock()?;
+        ec.autofanctrl()
+    }
+
+    /// Caps the battery charge rate, see [`EcBackend::set_charge_rate_limit`].
+    pub fn set_charge_rate_limit(&self, rate: f32) -> Result<()> {
+        let ec = self.ec()?;
+        let _lock = self.lock()?;
+        ec.set_charge_rate_limit(rate)
     }
 
     #[allow(dead_code)]
     pub fn get_fan_rpm(&self) -> Result<u16> {
-        let fans = self
-            .ec
-            .read_memory(0x10, 8)
-            .ok_or_else(|| Error::Ec("Failed to read fan RPM from EC".into()))?;
-
+        let fans = self.read_fan_block()?;
         let rpm = u16::from_le_bytes([fans[0], fans[1]]);
         Ok(rpm)
     }
 
+    /// RPM of every fan the EC reports, in slot order; stalled fans read 0.
+    pub fn get_fan_rpms(&self) -> Result<Vec<u16>> {
+        let fans = self.read_fan_block()?;
+        Ok(fans
+            .chunks_exact(2)
+            .map(|slot| u16::from_le_bytes([slot[0], slot[1]]))
+            .filter(|rpm| *rpm != EC_FAN_SPEED_NOT_PRESENT)
+            .map(|rpm| if rpm == EC_FAN_SPEED_STALLED { 0 } else { rpm })
+            .collect())
+    }
+
     pub fn check_temperature(&self) -> Result<f64> {
         let temp = self.get_temperature()?;
         if !(0.0..=100.0).contains(&temp) {
@@ -163,30 +1036,290 @@ impl HardwareController {
         Ok(temp)
     }
 
-    pub fn test_fan_control(&self, steps: u32) -> Result<Vec<(u32, u16)>> {
-        let original_speed = self.get_fan_speed().unwrap_or(0);
-        let mut results = Vec::new();
+    #[allow(dead_code)]
+    pub fn restore_fan(&self, speed: u32) -> Result<()> {
+        self.set_fan_speed(speed)
+    }
+}
 
-        let speed_step = 100 / steps.max(1);
+impl AsRef<HardwareController> for HardwareController {
+    fn as_ref(&self) -> &HardwareController {
+        self
+    }
+}
 
-        for i in 1..=steps {
-            let speed = (speed_step * i).min(100);
-            if let Err(e) = self.set_fan_speed(speed) {
-                let _ = self.set_fan_speed(original_speed.min(100));
-                return Err(e);
-            }
-            std::thread::sleep(std::time::Duration::from_secs(2));
-            let rpm = self.get_fan_rpm().unwrap_or(0);
-            results.push((speed, rpm));
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SensorSelector;
+    use crate::mock::MockEc;
+
+    #[test]
+    fn test_temperature_is_max_of_valid_sensors() {
+        let ec = MockEc::new();
+        ec.set_temperatures(&[45, 62, 38]);
+        let hw = HardwareController::with_backend(Box::new(ec), None, "mock");
+        assert_eq!(hw.get_temperature().unwrap(), 62.0);
+    }
 
-        let _ = self.set_fan_speed(original_speed.min(100));
+    #[test]
+    fn test_temperature_excludes_battery_sensor() {
+        let ec = MockEc::new();
+        ec.set_temperatures(&[45, 50, 38, 70]);
+        let hw = HardwareController::with_backend(Box::new(ec), Some(3), "mock")
+            .with_battery_excluded(true);
+        assert_eq!(hw.get_temperature().unwrap(), 50.0);
 
-        Ok(results)
+        // Knowing which sensor is the battery doesn't leave it out by itself
+        let mut hw = hw.with_battery_excluded(false);
+        assert_eq!(hw.get_temperature().unwrap(), 70.0);
+        hw.set_battery_excluded(true);
+        assert!(hw.is_battery_excluded());
+        assert_eq!(hw.get_temperature().unwrap(), 50.0);
     }
 
-    #[allow(dead_code)]
-    pub fn restore_fan(&self, speed: u32) -> Result<()> {
-        self.set_fan_speed(speed)
+    #[test]
+    fn test_sensor_aggregation_skips_battery_sensor() {
+        let ec = MockEc::new();
+        ec.set_temperatures(&[40, 50, 60, 90, 80]);
+        let hw = HardwareController::with_backend(Box::new(ec), Some(3), "mock")
+            .with_battery_excluded(true);
+        let temp = |aggregation| hw.get_aggregated_temperature(aggregation).unwrap();
+        assert_eq!(temp(SensorAggregation::Max), 80.0);
+        assert_eq!(temp(SensorAggregation::Mean), 57.5);
+        assert_eq!(temp(SensorAggregation::P90), 80.0);
+        assert_eq!(temp(SensorAggregation::SecondHighest), 60.0);
+    }
+
+    #[test]
+    fn test_external_sensors_join_the_sensor_set() {
+        let ec = MockEc::new();
+        ec.set_temperatures(&[45, 50]);
+        let external = ExternalSensors::default();
+        external.record("egpu", 72.0, Duration::from_secs(5));
+        let mut hw = HardwareController::with_backend(Box::new(ec), None, "mock")
+            .with_external_sensors(external);
+        assert_eq!(hw.get_temperature().unwrap(), 72.0);
+        let egpu = SensorSelector::Label("EGPU".into());
+        assert_eq!(hw.get_sensor_temperature(&egpu).unwrap(), Some(72.0));
+
+        hw.set_sensor_selection(SensorSelection {
+            include: Vec::new(),
+            exclude: vec![egpu],
+        });
+        assert_eq!(hw.get_temperature().unwrap(), 50.0);
+        let sensors = hw.get_sensor_temperatures().unwrap();
+        assert_eq!(sensors[2].label, "egpu");
+        assert!(sensors[2].excluded);
+    }
+
+    #[test]
+    fn test_excluded_sensors_by_index_or_label() {
+        let ec = MockEc::new();
+        ec.set_temperatures(&[45, 50, 38, 70, 90]);
+        let mut hw = HardwareController::with_backend(Box::new(ec), Some(3), "mock")
+            .with_battery_excluded(true);
+        hw.set_sensor_selection(SensorSelection {
+            include: Vec::new(),
+            exclude: vec![
+                SensorSelector::Index(4),
+                SensorSelector::Label("EC1".into()),
+            ],
+        });
+        assert_eq!(hw.get_temperature().unwrap(), 45.0);
+
+        let sensors = hw.get_sensor_temperatures().unwrap();
+        let excluded: Vec<bool> = sensors.iter().map(|s| s.excluded).collect();
+        assert_eq!(excluded, vec![false, true, false, true, true]);
+        assert_eq!(sensors[3].name.as_deref(), Some("battery"));
+
+        // The battery's own name works when the flag leaves it in
+        let mut hw = hw.with_battery_excluded(false);
+        hw.set_sensor_selection(SensorSelection {
+            include: Vec::new(),
+            exclude: vec![SensorSelector::Label("battery".into())],
+        });
+        assert_eq!(hw.get_temperature().unwrap(), 90.0);
+        assert!(hw.platform_info().battery_excluded);
+    }
+
+    #[test]
+    fn test_included_sensors_only() {
+        let ec = MockEc::new();
+        ec.set_temperatures(&[45, 50, 38, 70, 90]);
+        let mut hw = HardwareController::with_backend(Box::new(ec), None, "mock");
+        hw.set_sensor_selection(SensorSelection {
+            include: vec![
+                SensorSelector::Label("ec0".into()),
+                SensorSelector::Index(2),
+            ],
+            exclude: Vec::new(),
+        });
+        assert_eq!(hw.get_temperature().unwrap(), 45.0);
+
+        // A list that matches nothing falls back to every sensor
+        hw.set_sensor_selection(SensorSelection {
+            include: vec![SensorSelector::Label("apu".into())],
+            exclude: Vec::new(),
+        });
+        assert_eq!(hw.get_temperature().unwrap(), 90.0);
+    }
+
+    #[test]
+    fn test_battery_temperature_reported_even_when_included() {
+        let ec = MockEc::new();
+        ec.set_temperatures(&[45, 50, 38, 70]);
+        let hw = HardwareController::with_backend(Box::new(ec), Some(3), "mock")
+            .with_battery_excluded(false);
+        assert_eq!(hw.get_temperature().unwrap(), 70.0);
+        assert_eq!(hw.get_battery_temperature().unwrap(), Some(70.0));
+
+        let sensors = hw.get_sensor_temperatures().unwrap();
+        assert_eq!(sensors.len(), 4);
+        assert!(sensors[3].battery && !sensors[0].battery);
+        assert_eq!(sensors[1].label, "ec1");
+    }
+
+    #[test]
+    fn test_refused_fan_control_leaves_ec_auto() {
+        let ec = MockEc::new();
+        ec.set_temperatures(&[50]);
+        let hw = HardwareController::with_backend(Box::new(ec.clone()), None, "mock")
+            .with_fan_control_refused("quirky firmware");
+
+        assert!(!hw.can_control_fan());
+        assert!(!hw.capabilities().fan_control);
+        assert!(hw.set_fan_speed(40).is_err());
+        assert!(ec.duty_writes().is_empty());
+        assert_eq!(hw.get_temperature().unwrap(), 50.0);
+    }
+
+    #[test]
+    fn test_platform_info_describes_backend() {
+        let ec = MockEc::new();
+        ec.set_temperatures(&[45, 50]);
+        ec.set_fan_rpm(0, 2000);
+        ec.set_fan_rpm(1, 2100);
+        ec.set_fan_rpm(2, EC_FAN_SPEED_NOT_PRESENT);
+        ec.set_fan_rpm(3, EC_FAN_SPEED_NOT_PRESENT);
+        let info = HardwareController::with_backend(Box::new(ec), None, "mock").platform_info();
+        assert_eq!(info.ec_driver.as_deref(), Some("mock"));
+        assert_eq!(info.fan_count, 2);
+        assert_eq!(info.sensors.len(), 2);
+        assert!(info.capabilities.fan_control);
+    }
+
+    #[test]
+    fn test_custom_layout_moves_sensors_and_fans() {
+        let ec = MockEc::new();
+        // Raw EC temperatures are offset by 73
+        ec.write_memory(0x70, &[45 + 73, 66 + 73, 0xFF]);
+        ec.write_memory(0x40, &[0xB8, 0x0B]);
+        let hw =
+            HardwareController::with_backend(Box::new(ec), None, "mock").with_layout(EcLayout {
+                temperature_offset: 0x70,
+                temperature_sensor_count: 3,
+                fan_offset: 0x40,
+            });
+        assert_eq!(hw.get_temperature().unwrap(), 66.0);
+        assert_eq!(hw.get_fan_rpm().unwrap(), 3000);
+
+        let outside = EcLayout {
+            temperature_offset: 0xF8,
+            ..EcLayout::default()
+        };
+        assert!(outside.validate().is_err());
+    }
+
+    #[test]
+    fn test_fan_rpms_skip_empty_slots() {
+        let ec = MockEc::new();
+        ec.set_fan_rpm(0, 2400);
+        ec.set_fan_rpm(1, EC_FAN_SPEED_STALLED);
+        ec.set_fan_rpm(2, EC_FAN_SPEED_NOT_PRESENT);
+        ec.set_fan_rpm(3, EC_FAN_SPEED_NOT_PRESENT);
+        let hw = HardwareController::with_backend(Box::new(ec), None, "mock");
+        assert_eq!(hw.get_fan_rpms().unwrap(), vec![2400, 0]);
+    }
+
+    #[test]
+    fn test_set_fan_speed_reaches_backend() {
+        let ec = MockEc::new();
+        let hw = HardwareController::with_backend(Box::new(ec.clone()), None, "mock");
+        hw.set_fan_speed(40).unwrap();
+        assert_eq!(ec.duty(), Some(40));
+        hw.enable_auto_fan().unwrap();
+        assert_eq!(ec.duty(), None);
+    }
+
+    #[test]
+    fn test_set_fan_speed_rejects_invalid_writes() {
+        let ec = MockEc::new();
+        let hw = HardwareController::with_backend(Box::new(ec.clone()), None, "mock");
+        assert!(hw.set_fan_speed(101).is_err());
+
+        ec.set_fan_rpm(0, EC_FAN_SPEED_NOT_PRESENT);
+        assert!(hw.set_fan_speed(50).is_err());
+        assert!(ec.duty_writes().is_empty());
+    }
+
+    #[test]
+    fn test_fanless_ec_is_never_written() {
+        let ec = MockEc::new();
+        ec.set_temperatures(&[50]);
+        ec.set_fan_rpm(0, EC_FAN_SPEED_NOT_PRESENT);
+        let hw = HardwareController::with_backend(Box::new(ec.clone()), None, "mock");
+
+        assert!(hw.is_fanless());
+        assert!(!hw.can_control_fan());
+        assert!(hw.capabilities().fanless);
+        hw.enable_auto_fan().unwrap();
+        assert!(ec.duty_writes().is_empty());
+        assert_eq!(hw.get_temperature().unwrap(), 50.0);
+    }
+
+    #[test]
+    fn test_fan_presence_is_read_once() {
+        let ec = MockEc::new();
+        let hw = HardwareController::with_backend(Box::new(ec.clone()), None, "mock");
+        assert!(hw.can_control_fan());
+        ec.set_fan_rpm(0, EC_FAN_SPEED_NOT_PRESENT);
+        assert!(hw.can_control_fan());
+        assert!(!hw.capabilities().fanless);
+    }
+
+    #[test]
+    fn test_set_fan_speed_retries_once_on_readback_mismatch() {
+        let ec = MockEc::new();
+        let hw = HardwareController::with_backend(Box::new(ec.clone()), None, "mock");
+        ec.set_fan_rpm(0, 2400);
+        ec.set_stuck_duty(Some(20));
+        assert!(hw.set_fan_speed(50).is_err());
+        assert_eq!(ec.duty_writes(), vec![50, 50]);
+        // The duty is reported apart from the RPM slots
+        assert_eq!(hw.get_fan_rpms().unwrap(), vec![2400]);
+
+        // A duty the EC doesn't report can't be verified
+        ec.set_stuck_duty(Some(0xFF));
+        hw.set_fan_speed(60).unwrap();
+        assert_eq!(hw.get_fan_duty().unwrap(), None);
+    }
+
+    #[test]
+    fn test_fan_write_fails_while_lock_is_held_elsewhere() {
+        let path = std::env::temp_dir().join(format!("fw-fanctrl-ec-{}.lock", std::process::id()));
+        let ec = MockEc::new();
+        let hw = HardwareController::with_backend(Box::new(ec.clone()), None, "mock")
+            .with_lock_path(&path);
+
+        hw.set_fan_speed(30).unwrap();
+        // flock locks belong to the open file description, so a second open conflicts
+        let held = lock_ec_writes(&path).unwrap().unwrap();
+        assert!(hw.set_fan_speed(60).is_err());
+        drop(held);
+        hw.set_fan_speed(60).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(ec.duty_writes(), vec![30, 60]);
     }
 }
@@ -0,0 +1,243 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::extract::{Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+#[cfg(feature = "web-ui")]
+use axum::response::Html;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post, put};
+use axum::{Json, Router};
+use serde::Deserialize;
+use tokio::sync::watch;
+
+use crate::error::{Error, Result};
+use crate::metrics;
+use crate::socket::{status_json, ControllerHandle};
+
+#[derive(Clone)]
+struct AppState {
+    controller: ControllerHandle,
+    token: Arc<str>,
+}
+
+struct ApiError(Error);
+
+impl From<Error> for ApiError {
+    fn from(error: Error) -> Self {
+        Self(error)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = match self.0 {
+            Error::Strategy(_) | Error::Command(_) => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        let body = serde_json::json!({
+            "status": "error",
+            "reason": self.0.to_string()
+        });
+        (status, Json(body)).into_response()
+    }
+}
+
+type ApiResult = std::result::Result<Json<serde_json::Value>, ApiError>;
+
+#[derive(Deserialize)]
+struct StrategyRequest {
+    strategy: String,
+}
+
+/// Compares the tokens in time that depends only on their lengths, so a
+/// wrong guess gives away nothing about how much of it was right.
+fn tokens_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+async fn require_token(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let authorized = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .is_some_and(|token| tokens_match(token, &state.token));
+
+    if !authorized {
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(serde_json::json!({
+                "status": "error",
+                "reason": "Missing or invalid bearer token"
+            })),
+        )
+            .into_response();
+    }
+
+    next.run(request).await
+}
+
+async fn get_status(State(state): State<AppState>) -> ApiResult {
+    let controller = state.controller.lock().await;
+    Ok(Json(status_json(&controller)?))
+}
+
+async fn get_strategies(State(state): State<AppState>) -> ApiResult {
+    let controller = state.controller.lock().await;
+    Ok(Json(serde_json::json!({
+        "status": "success",
+        "strategies": controller.get_config().strategy_names(),
+        "current": controller.get_current_strategy_name()
+    })))
+}
+
+async fn put_strategy(
+    State(state): State<AppState>,
+    Json(request): Json<StrategyRequest>,
+) -> ApiResult {
+    let mut controller = state.controller.lock().await;
+    controller.overwrite_strategy(&request.strategy)?;
+    Ok(Json(serde_json::json!({
+        "status": "success",
+        "strategy": controller.get_current_strategy_name(),
+        "default": !controller.is_overwritten()
+    })))
+}
+
+async fn post_pause(State(state): State<AppState>) -> ApiResult {
+    let mut controller = state.controller.lock().await;
+    controller.pause()?;
+    Ok(Json(serde_json::json!({
+        "status": "success",
+        "active": controller.is_active()
+    })))
+}
+
+async fn post_resume(State(state): State<AppState>) -> ApiResult {
+    let mut controller = state.controller.lock().await;
+    controller.resume()?;
+    Ok(Json(serde_json::json!({
+        "status": "success",
+        "active": controller.is_active()
+    })))
+}
+
+async fn get_metrics(State(state): State<AppState>) -> std::result::Result<Response, ApiError> {
+    let controller = state.controller.lock().await;
+    let body = metrics::render(&controller)?;
+    Ok(([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body).into_response())
+}
+
+#[cfg(feature = "web-ui")]
+const DASHBOARD_HTML: &str = include_str!("../web/dashboard.html");
+
+/// Served without authentication; the page itself asks for the token and
+/// sends it with every API call.
+#[cfg(feature = "web-ui")]
+async fn get_dashboard() -> Html<&'static str> {
+    Html(DASHBOARD_HTML)
+}
+
+fn router(controller: ControllerHandle, token: String) -> Router {
+    let state = AppState {
+        controller,
+        token: token.into(),
+    };
+
+    let router = Router::new()
+        .route("/status", get(get_status))
+        .route("/strategies", get(get_strategies))
+        .route("/strategy", put(put_strategy))
+        .route("/pause", post(post_pause))
+        .route("/resume", post(post_resume))
+        .route("/metrics", get(get_metrics))
+        .layer(middleware::from_fn_with_state(state.clone(), require_token));
+
+    // Routes added after the auth layer are not wrapped by it
+    #[cfg(feature = "web-ui")]
+    let router = router.route("/", get(get_dashboard));
+
+    router.with_state(state)
+}
+
+/// Serves the REST API on `address` until `shutdown` turns true.
+///
+/// Every request must carry `Authorization: Bearer <token>`.
+pub async fn start_http_server(
+    controller: ControllerHandle,
+    address: String,
+    token: String,
+    mut shutdown: watch::Receiver<bool>,
+) -> Result<()> {
+    if token.is_empty() {
+        return Err(Error::Config("HTTP API token must not be empty".into()));
+    }
+
+    let addr: SocketAddr = address
+        .parse()
+        .map_err(|e| Error::Socket(format!("Invalid HTTP address '{}': {}", address, e)))?;
+    if !addr.ip().is_loopback() {
+        return Err(Error::Socket(format!(
+            "Refusing to serve the HTTP API on non-loopback address {}",
+            addr
+        )));
+    }
+
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .map_err(|e| Error::Socket(format!("Failed to bind HTTP API: {}", e)))?;
+    tracing::info!("HTTP API listening on {}", addr);
+
+    axum::serve(listener, router(controller, token))
+        .with_graceful_shutdown(async move {
+            let _ = shutdown.wait_for(|stop| *stop).await;
+        })
+        .await
+        .map_err(|e| Error::Socket(format!("HTTP API error: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::Mutex;
+
+    use super::*;
+    use crate::config::Config;
+    use crate::controller::FanController;
+    use crate::hardware::HardwareController;
+    use crate::mock::MockEc;
+
+    #[test]
+    fn test_tokens_match() {
+        assert!(tokens_match("secret", "secret"));
+        assert!(!tokens_match("secreT", "secret"));
+        assert!(!tokens_match("secret2", "secret"));
+        assert!(!tokens_match("", "secret"));
+    }
+
+    #[test]
+    fn test_address_must_be_loopback() {
+        let config: Config = serde_json::from_str(
+            r#"{"defaultStrategy": "lazy", "strategies": {"lazy": {
+                "speedCurve": [{"temp": 0, "speed": 20}]}}}"#,
+        )
+        .unwrap();
+        let hw = HardwareController::with_backend(Box::new(MockEc::new()), None, "mock");
+        let controller = Arc::new(Mutex::new(FanController::new(hw, config, None)));
+        let (_shutdown, signal) = watch::channel(false);
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let result = runtime.block_on(start_http_server(
+            controller,
+            "0.0.0.0:0".into(),
+            "secret".into(),
+            signal,
+        ));
+        assert!(result.unwrap_err().to_string().contains("non-loopback"));
+    }
+}
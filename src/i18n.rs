@@ -0,0 +1,180 @@
+use std::sync::OnceLock;
+
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource, FluentValue};
+use unic_langid::LanguageIdentifier;
+
+/// The language every catalog is checked against, and the fallback for
+/// messages a translation lacks.
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+/// Fluent catalogs built into the binary from `locales/`, by language.
+const CATALOGS: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en/fw-fanctrl.ftl")),
+    ("de", include_str!("../locales/de/fw-fanctrl.ftl")),
+];
+
+static CATALOG: OnceLock<Catalog> = OnceLock::new();
+
+/// The messages of one language, backed by the English catalog.
+pub struct Catalog {
+    language: &'static str,
+    bundle: FluentBundle<FluentResource>,
+    fallback: Option<FluentBundle<FluentResource>>,
+}
+
+impl Catalog {
+    /// The catalog for `language`, e.g. `de`, `de-AT` or `de_DE.UTF-8`;
+    /// English if there is none.
+    pub fn new(language: &str) -> Self {
+        let language = available(language).unwrap_or(DEFAULT_LANGUAGE);
+        let fallback = (language != DEFAULT_LANGUAGE).then(|| bundle(DEFAULT_LANGUAGE));
+        Self {
+            language,
+            bundle: bundle(language),
+            fallback,
+        }
+    }
+
+    pub fn language(&self) -> &'static str {
+        self.language
+    }
+
+    /// Formats message `id`, or returns `id` itself if no catalog has it.
+    pub fn format(&self, id: &str, args: Option<&FluentArgs>) -> String {
+        std::iter::once(&self.bundle)
+            .chain(&self.fallback)
+            .find_map(|bundle| {
+                let pattern = bundle.get_message(id)?.value()?;
+                let mut errors = Vec::new();
+                let text = bundle.format_pattern(pattern, args, &mut errors);
+                if !errors.is_empty() {
+                    tracing::debug!("Formatting message '{}': {:?}", id, errors);
+                }
+                Some(text.into_owned())
+            })
+            .unwrap_or_else(|| {
+                tracing::debug!("No translation for '{}'", id);
+                id.to_string()
+            })
+    }
+}
+
+/// The built-in catalog that best matches a locale name, if any.
+fn available(locale: &str) -> Option<&'static str> {
+    // POSIX locale names look like `de_DE.UTF-8@euro`
+    let name = locale.split(['.', '@']).next().unwrap_or_default();
+    let id: LanguageIdentifier = name.replace('_', "-").parse().ok()?;
+    CATALOGS
+        .iter()
+        .map(|(language, _)| *language)
+        .find(|language| *language == id.language.as_str())
+}
+
+fn bundle(language: &'static str) -> FluentBundle<FluentResource> {
+    let source = CATALOGS
+        .iter()
+        .find(|(name, _)| *name == language)
+        .map_or("", |(_, source)| source);
+    let resource =
+        FluentResource::try_new(source.to_string()).unwrap_or_else(|(resource, errors)| {
+            tracing::warn!("Errors in the '{}' catalog: {:?}", language, errors);
+            resource
+        });
+    let id: LanguageIdentifier = language.parse().unwrap_or_default();
+    let mut bundle = FluentBundle::new_concurrent(vec![id]);
+    // Isolation marks would show up as stray characters in many terminals
+    bundle.set_use_isolating(false);
+    if let Err(errors) = bundle.add_resource(resource) {
+        tracing::warn!(
+            "Duplicate messages in the '{}' catalog: {:?}",
+            language,
+            errors
+        );
+    }
+    bundle
+}
+
+/// The language the locale environment asks for: `LC_ALL`, `LC_MESSAGES`,
+/// then `LANG`, the first one set.
+pub fn detect() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .filter(|value| value != "C" && value != "POSIX")
+}
+
+/// Picks the language of the natural output: `language` if given (`--lang`),
+/// otherwise the locale's. Only the first call has an effect.
+pub fn init(language: Option<&str>) {
+    let language = language.map(str::to_string).or_else(detect);
+    CATALOG.get_or_init(|| Catalog::new(language.as_deref().unwrap_or(DEFAULT_LANGUAGE)));
+}
+
+fn catalog() -> &'static Catalog {
+    CATALOG.get_or_init(|| Catalog::new(DEFAULT_LANGUAGE))
+}
+
+/// Message `id` in the chosen language; English until [`init`] runs.
+///
+/// Only text rendered for people goes through here. JSON output, logs and
+/// socket replies stay in English.
+pub fn tr(id: &str) -> String {
+    catalog().format(id, None)
+}
+
+/// Message `id` in the chosen language, with its `{ $name }` placeholders filled in.
+pub fn tr_with<'a>(id: &str, args: &[(&str, FluentValue<'a>)]) -> String {
+    let mut fluent_args = FluentArgs::new();
+    for (name, value) in args {
+        fluent_args.set(*name, value.clone());
+    }
+    catalog().format(id, Some(&fluent_args))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Message IDs as they appear at the start of a line, `id = ...`.
+    fn message_ids(source: &str) -> Vec<&str> {
+        source
+            .lines()
+            .filter_map(|line| line.split_once(" ="))
+            .map(|(id, _)| id)
+            .filter(|id| id.starts_with(|c: char| c.is_ascii_lowercase()))
+            .collect()
+    }
+
+    #[test]
+    fn test_catalogs_parse_and_cover_english() {
+        let english = message_ids(CATALOGS[0].1);
+        for (language, source) in CATALOGS {
+            assert!(FluentResource::try_new(source.to_string()).is_ok());
+            let ids = message_ids(source);
+            let missing: Vec<&&str> = english.iter().filter(|id| !ids.contains(id)).collect();
+            assert!(missing.is_empty(), "'{}' lacks {:?}", language, missing);
+        }
+    }
+
+    #[test]
+    fn test_locale_names_pick_a_catalog() {
+        assert_eq!(Catalog::new("de_DE.UTF-8").language(), "de");
+        assert_eq!(Catalog::new("de-AT").language(), "de");
+        assert_eq!(Catalog::new("fr_FR").language(), "en");
+        assert_eq!(Catalog::new("").language(), "en");
+    }
+
+    #[test]
+    fn test_formats_with_arguments_and_falls_back() {
+        let german = Catalog::new("de");
+        let mut args = FluentArgs::new();
+        args.set("speed", 40);
+        assert_eq!(
+            german.format("reply-fan-speed", Some(&args)),
+            "Lüfterdrehzahl: 40%"
+        );
+        assert_eq!(german.format("no-such-message", None), "no-such-message");
+    }
+}
@@ -0,0 +1,266 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use serde::Serialize;
+use tokio::sync::watch;
+
+use crate::controller::unix_now;
+use crate::error::{Error, Result};
+
+/// Finished jobs kept for `jobs status`; the oldest are dropped first.
+const FINISHED_JOBS_MAX_LEN: usize = 20;
+
+pub type JobId = u32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum JobState {
+    Running,
+    Finished,
+    Failed,
+    Cancelled,
+}
+
+/// What a job is doing or did, as reported by `jobs status`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobInfo {
+    pub id: JobId,
+    pub kind: String,
+    pub state: JobState,
+    /// Seconds since the Unix epoch.
+    pub started_at: u64,
+    /// Updates the job reported so far, oldest first.
+    pub progress: Vec<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+struct Job {
+    info: watch::Sender<JobInfo>,
+    cancel: watch::Sender<bool>,
+}
+
+#[derive(Default)]
+struct Registry {
+    next_id: JobId,
+    jobs: BTreeMap<JobId, Job>,
+}
+
+/// Long-running operations started over a control interface.
+///
+/// Each job runs as its own task next to the control loop, so it only holds the
+/// controller lock while it needs it. Cloning shares the same jobs.
+#[derive(Clone, Default)]
+pub struct Jobs {
+    registry: Arc<Mutex<Registry>>,
+}
+
+/// Handed to a running job to report progress and notice cancellation.
+pub struct JobContext {
+    info: watch::Sender<JobInfo>,
+    cancel: watch::Receiver<bool>,
+}
+
+impl JobContext {
+    pub fn report(&self, progress: impl Serialize) {
+        let value = serde_json::to_value(progress).unwrap_or_default();
+        self.info.send_modify(|info| info.progress.push(value));
+    }
+
+    /// Resolves once `jobs cancel` was requested. The job should then clean up
+    /// and return; its result is discarded.
+    pub async fn cancelled(&self) {
+        let mut cancel = self.cancel.clone();
+        let _ = cancel.wait_for(|cancelled| *cancelled).await;
+    }
+}
+
+impl Jobs {
+    fn registry(&self) -> MutexGuard<'_, Registry> {
+        self.registry.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Starts `job` on the runtime and returns its ID. Only one job of each
+    /// kind runs at a time.
+    pub fn spawn<F, Fut>(&self, kind: &str, job: F) -> Result<JobId>
+    where
+        F: FnOnce(JobContext) -> Fut,
+        Fut: Future<Output = Result<serde_json::Value>> + Send + 'static,
+    {
+        let mut registry = self.registry();
+        if registry.is_running(kind) {
+            return Err(Error::Command(format!("A {} job is already running", kind)));
+        }
+
+        registry.next_id += 1;
+        let id = registry.next_id;
+        let (info, _) = watch::channel(JobInfo {
+            id,
+            kind: kind.to_string(),
+            state: JobState::Running,
+            started_at: unix_now(),
+            progress: Vec::new(),
+            result: None,
+            error: None,
+        });
+        let (cancel, cancel_rx) = watch::channel(false);
+        let future = job(JobContext {
+            info: info.clone(),
+            cancel: cancel_rx.clone(),
+        });
+
+        let done = info.clone();
+        tokio::spawn(async move {
+            let result = future.await;
+            let cancelled = *cancel_rx.borrow();
+            done.send_modify(|info| match result {
+                _ if cancelled => info.state = JobState::Cancelled,
+                Ok(value) => {
+                    info.state = JobState::Finished;
+                    info.result = Some(value);
+                }
+                Err(e) => {
+                    info.state = JobState::Failed;
+                    info.error = Some(e.to_string());
+                }
+            });
+            tracing::info!(
+                "Job {} ({}) ended: {:?}",
+                id,
+                done.borrow().kind,
+                done.borrow().state
+            );
+        });
+
+        registry.jobs.insert(id, Job { info, cancel });
+        registry.prune();
+        tracing::info!("Job {} ({}) started", id, kind);
+        Ok(id)
+    }
+
+    pub fn is_running(&self, kind: &str) -> bool {
+        self.registry().is_running(kind)
+    }
+
+    /// All kept jobs, oldest first.
+    pub fn list(&self) -> Vec<JobInfo> {
+        self.registry()
+            .jobs
+            .values()
+            .map(|job| job.info.borrow().clone())
+            .collect()
+    }
+
+    pub fn status(&self, id: JobId) -> Result<JobInfo> {
+        Ok(self.subscribe(id)?.borrow().clone())
+    }
+
+    /// Watches a job's progress and state as it runs.
+    pub fn subscribe(&self, id: JobId) -> Result<watch::Receiver<JobInfo>> {
+        self.registry()
+            .jobs
+            .get(&id)
+            .map(|job| job.info.subscribe())
+            .ok_or_else(|| Error::Command(format!("Unknown job: {}", id)))
+    }
+
+    /// Asks a running job to stop; cancelling a job that already ended does nothing.
+    pub fn cancel(&self, id: JobId) -> Result<()> {
+        let registry = self.registry();
+        let job = registry
+            .jobs
+            .get(&id)
+            .ok_or_else(|| Error::Command(format!("Unknown job: {}", id)))?;
+        job.cancel.send_replace(true);
+        Ok(())
+    }
+}
+
+impl Registry {
+    fn is_running(&self, kind: &str) -> bool {
+        self.jobs.values().any(|job| {
+            job.info.borrow().kind == kind && job.info.borrow().state == JobState::Running
+        })
+    }
+
+    fn prune(&mut self) {
+        let finished: Vec<JobId> = self
+            .jobs
+            .iter()
+            .filter(|(_, job)| job.info.borrow().state != JobState::Running)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in finished
+            .iter()
+            .take(finished.len().saturating_sub(FINISHED_JOBS_MAX_LEN))
+        {
+            self.jobs.remove(id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn finished(jobs: &Jobs, id: JobId) -> JobInfo {
+        let mut info = jobs.subscribe(id).unwrap();
+        let ended = info
+            .wait_for(|info| info.state != JobState::Running)
+            .await
+            .unwrap()
+            .clone();
+        ended
+    }
+
+    #[test]
+    fn test_job_reports_progress_and_result() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let jobs = Jobs::default();
+        let info = runtime.block_on(async {
+            let id = jobs
+                .spawn("count", |ctx| async move {
+                    ctx.report(1);
+                    ctx.report(2);
+                    Ok(serde_json::json!("done"))
+                })
+                .unwrap();
+            finished(&jobs, id).await
+        });
+
+        assert_eq!(info.state, JobState::Finished);
+        assert_eq!(
+            info.progress,
+            vec![serde_json::json!(1), serde_json::json!(2)]
+        );
+        assert_eq!(info.result, Some(serde_json::json!("done")));
+        assert_eq!(jobs.list().len(), 1);
+    }
+
+    #[test]
+    fn test_cancelled_job_stops() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let jobs = Jobs::default();
+        let info = runtime.block_on(async {
+            let id = jobs
+                .spawn("wait", |ctx| async move {
+                    ctx.cancelled().await;
+                    Ok(serde_json::Value::Null)
+                })
+                .unwrap();
+            // One job of a kind at a time
+            assert!(jobs
+                .spawn("wait", |_| async { Ok(serde_json::Value::Null) })
+                .is_err());
+            jobs.cancel(id).unwrap();
+            finished(&jobs, id).await
+        });
+
+        assert_eq!(info.state, JobState::Cancelled);
+        assert!(jobs.cancel(99).is_err());
+    }
+}
@@ -1,6 +1,54 @@
+#[cfg(feature = "daemon")]
+pub mod audit;
+#[cfg(feature = "daemon")]
+pub mod autotune;
+#[cfg(feature = "daemon")]
+pub mod boot;
+#[cfg(feature = "daemon")]
+pub mod characterize;
+pub mod command;
 pub mod config;
+pub mod conflicts;
+#[cfg(feature = "daemon")]
 pub mod controller;
 pub mod curve;
+#[cfg(feature = "daemon")]
+pub mod daemon;
+pub mod doctor;
 pub mod error;
+pub mod external;
+pub mod firmware;
+#[cfg(feature = "grpc")]
+pub mod grpc;
 pub mod hardware;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod i18n;
+#[cfg(feature = "daemon")]
+pub mod jobs;
+#[cfg(feature = "daemon")]
+pub mod metrics;
+pub mod mock;
+#[cfg(feature = "otel")]
+pub mod otel;
+pub mod output;
+pub mod protocol;
+pub mod replay;
+#[cfg(feature = "sandbox")]
+pub mod sandbox;
+#[cfg(feature = "daemon")]
+pub mod selftest;
+pub mod service;
+#[cfg(feature = "daemon")]
 pub mod socket;
+#[cfg(feature = "daemon")]
+pub mod stats;
+pub mod sysfs;
+#[cfg(feature = "daemon")]
+pub mod telemetry;
+#[cfg(feature = "daemon")]
+pub mod testing;
+#[cfg(feature = "tui")]
+pub mod tui;
+#[cfg(feature = "daemon")]
+pub mod uevent;
@@ -1,18 +1,34 @@
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::time::Duration;
 
-use clap::{Parser, Subcommand, ValueEnum};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use serde::{Deserialize, Serialize};
-use tokio::signal::unix::{signal, SignalKind};
+#[cfg(feature = "daemon")]
 use tokio::sync::Mutex;
-use tokio::time::{sleep, Duration};
 
-use fw_fanctrl::config::{Config, DEFAULT_CONFIG_PATH};
+#[cfg(feature = "daemon")]
+use fw_fanctrl::autotune;
+#[cfg(feature = "daemon")]
+use fw_fanctrl::characterize::{characterize, Load, Options as CharacterizeOptions};
+use fw_fanctrl::command::quote;
+use fw_fanctrl::config::{Config, Strategy, StrategyExport, DEFAULT_CONFIG_PATH};
+#[cfg(feature = "daemon")]
 use fw_fanctrl::controller::FanController;
-use fw_fanctrl::error::Result;
+#[cfg(feature = "daemon")]
+use fw_fanctrl::daemon::{self, RunArgs};
+use fw_fanctrl::doctor;
+use fw_fanctrl::error::{Error, Result};
+#[cfg(feature = "daemon")]
+use fw_fanctrl::firmware::{self, FirmwareStatus};
+#[cfg(feature = "daemon")]
 use fw_fanctrl::hardware::HardwareController;
-use fw_fanctrl::socket::{start_socket_server, ControllerHandle};
+use fw_fanctrl::i18n::{self, tr};
+use fw_fanctrl::output::{self, paint, Color};
+use fw_fanctrl::protocol::{self, STEP_SETTLE};
+#[cfg(feature = "daemon")]
+use fw_fanctrl::selftest;
+use fw_fanctrl::service::{self, InitSystem, InstallOptions, PermissionsOptions};
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
@@ -23,6 +39,18 @@ struct Cli {
     #[clap(long, value_enum, default_value = "natural")]
     output_format: OutputFormat,
 
+    /// Colorize natural output; `auto` honors NO_COLOR and only colors terminals
+    #[clap(long, value_enum, default_value = "auto")]
+    color: ColorChoice,
+
+    /// Seconds to wait for the daemon to accept a connection and to reply
+    #[clap(long, default_value = "5", value_name = "SECS")]
+    timeout: u64,
+
+    /// Language of natural output, e.g. `de`; defaults to the locale (LC_ALL, LC_MESSAGES, LANG)
+    #[clap(long, value_name = "LANG")]
+    lang: Option<String>,
+
     #[clap(subcommand)]
     command: Option<Command>,
 }
@@ -34,31 +62,257 @@ enum OutputFormat {
     Json,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ExportFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum BatterySensorAction {
+    Exclude,
+    Include,
+}
+
 #[derive(Subcommand, Debug)]
 enum Command {
-    Run {
+    /// Run the daemon in the foreground, like `fw-fanctrld`
+    #[cfg(feature = "daemon")]
+    Run(Box<RunArgs>),
+    Use {
+        strategy: String,
+    },
+    /// Switch between two strategies
+    Toggle {
+        a: String,
+        b: String,
+    },
+    /// Advance to the next strategy in `strategyCycle`
+    Cycle,
+    Reset,
+    /// Add a temporary percentage bias to the curve output, e.g. +10 or -5 (cleared by reset)
+    Adjust {
+        #[clap(allow_negative_numbers = true)]
+        bias: i32,
+    },
+    /// Leave the battery sensor out of the control temperature, or take it back in,
+    /// until a reload
+    BatterySensor {
+        #[clap(value_enum)]
+        action: BatterySensorAction,
+    },
+    /// Forget the readings behind the moving average, e.g. between benchmark runs
+    ClearHistory,
+    /// Reload the daemon's config file, or switch it to another one
+    Reload {
+        /// Config file to load instead, used by later reloads too
+        path: Option<PathBuf>,
+    },
+    /// Change a strategy setting in the running daemon only; `reload` reverts it
+    Tune {
+        #[clap(required_unless_present = "commit")]
+        strategy: Option<String>,
+
+        /// updateIntervalSeconds, movingAverageInterval, hysteresis,
+        /// speedDecaySeconds, maxSpeedChangesPerMinute or safetyTemp
+        #[clap(required_unless_present = "commit")]
+        field: Option<String>,
+
+        #[clap(required_unless_present = "commit")]
+        value: Option<u32>,
+
+        /// Write the daemon's tuned settings to the config file
+        #[clap(long, conflicts_with_all = ["strategy", "field", "value"])]
+        commit: bool,
+
+        /// Config file `--commit` writes to
         #[clap(short, long, default_value = DEFAULT_CONFIG_PATH)]
         config: PathBuf,
+    },
+    Pause,
+    Resume,
+    /// Step the fan through 25-100% in the running daemon, showing the RPM at each step
+    Selftest,
+    /// Long-running operations in the daemon, e.g. the fan test
+    Jobs {
+        #[clap(subcommand)]
+        action: Option<JobsCommand>,
+    },
+    /// Show the config profiles, or switch between them
+    Profile {
+        #[clap(subcommand)]
+        action: Option<ProfileCommand>,
+    },
+    /// Print status: all, active, current, list, speed, config, strategy [name] or curve [name]
+    Print {
+        selection: Option<String>,
+
+        /// Strategy to show with `print strategy` or `print curve` (default: the current one)
+        name: Option<String>,
+
+        /// With `list`, include each strategy's full definition
+        #[clap(long)]
+        detailed: bool,
+
+        /// With `all`, add temperature and duty graphs of the last minute
+        #[clap(long, conflicts_with_all = ["name", "detailed"])]
+        graph: bool,
+    },
+    /// Show the status with temperature and duty graphs, refreshing until interrupted
+    Watch {
+        /// Seconds between refreshes
+        #[clap(long, default_value = "1", value_name = "SECS")]
+        interval: u64,
+    },
+    /// Export recorded temperature/speed history from the daemon
+    Export {
+        /// How far back to export, e.g. 90s, 30m, 1h
+        #[clap(long, default_value = "1h")]
+        since: String,
 
+        #[clap(long, value_enum, default_value = "csv")]
+        format: ExportFormat,
+
+        /// Write to this file instead of stdout
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Set the curve duty for the current temperature once and exit, without the daemon
+    #[cfg(feature = "daemon")]
+    Apply {
+        /// Strategy whose curve to apply (default: the one for the power source)
         #[clap(short, long)]
         strategy: Option<String>,
 
+        #[clap(short, long, default_value = DEFAULT_CONFIG_PATH)]
+        config: PathBuf,
+
+        #[clap(long)]
+        no_battery_sensors: bool,
+    },
+    /// Step through fan duties under load and suggest a speedCurve
+    #[cfg(feature = "daemon")]
+    Characterize {
+        /// Temperature the suggested curve should hold under the load
+        #[clap(long, default_value = "80")]
+        target: f64,
+
+        /// Abort and restore EC auto fan control once a reading exceeds this temperature
+        #[clap(long, default_value = "85")]
+        max_temp: f64,
+
+        /// Duty levels to measure, comma-separated
+        #[clap(long, value_delimiter = ',', default_value = "0,20,40,60,80,100")]
+        duties: Vec<u32>,
+
+        /// Maximum seconds to wait for equilibrium at each duty
+        #[clap(long, default_value = "180")]
+        settle: u64,
+
+        /// Shell command generating the load (default: internal busy loop)
+        #[clap(long, conflicts_with = "external_load")]
+        load: Option<String>,
+
+        /// Generate no load; run your own workload alongside
         #[clap(long)]
-        silent: bool,
+        external_load: bool,
 
         #[clap(long)]
         no_battery_sensors: bool,
     },
-    Use {
+    /// Edit speed curves interactively
+    #[cfg(feature = "tui")]
+    Curve {
+        #[clap(subcommand)]
+        action: CurveCommand,
+    },
+    /// Propose strategy tweaks from history recorded with `export`
+    #[cfg(feature = "daemon")]
+    Autotune {
+        /// Strategy to tune
         strategy: String,
+
+        /// History file written by `export` (CSV or JSON)
+        #[clap(long)]
+        history: PathBuf,
+
+        #[clap(short, long, default_value = DEFAULT_CONFIG_PATH)]
+        config: PathBuf,
+
+        /// Temperature the fan should keep the system below
+        #[clap(long, default_value = "85")]
+        max_temp: f64,
+
+        /// Write the proposed changes back to the config file
+        #[clap(long)]
+        apply: bool,
     },
-    Reset,
-    Reload,
-    Pause,
-    Resume,
-    Print {
-        selection: Option<String>,
+    /// Validate a config file, treating unknown keys as errors
+    CheckConfig {
+        #[clap(short, long, default_value = DEFAULT_CONFIG_PATH)]
+        config: PathBuf,
+    },
+    /// Config file tooling
+    Config {
+        #[clap(subcommand)]
+        action: ConfigCommand,
     },
+    /// Share strategies between machines as standalone JSON files
+    Strategy {
+        #[clap(subcommand)]
+        action: StrategyCommand,
+    },
+    /// Install the service for an init system, with the default config and socket directory
+    Install {
+        #[clap(flatten)]
+        target: ServiceTarget,
+
+        /// Binary the service runs (default: this executable)
+        #[clap(long, value_name = "PATH")]
+        bin: Option<PathBuf>,
+
+        /// Extra arguments for `run`, e.g. `-- --no-battery-sensors`
+        #[clap(last = true)]
+        run_args: Vec<String>,
+    },
+    /// Stop and remove the service installed by `install`
+    Uninstall {
+        #[clap(flatten)]
+        target: ServiceTarget,
+
+        /// Also remove the config, and its fw-fanctrl directory once that is empty
+        #[clap(long)]
+        purge: bool,
+    },
+    /// Install a udev rule and group giving non-root access to the EC device, and verify it
+    SetupPermissions {
+        /// Also add this user to the group
+        #[clap(long, value_name = "NAME")]
+        user: Option<String>,
+
+        /// Write the udev rule under this directory instead of `/` and change nothing else
+        #[clap(long, value_name = "DIR")]
+        root: Option<PathBuf>,
+    },
+    /// Show the client and daemon versions
+    Version,
+    /// Stop the daemon cleanly, restoring EC auto fan control (root only)
+    Shutdown,
+    /// Re-enable EC auto fan control right away and stop the daemon (root only)
+    RestartAuto,
+    /// Diagnose the environment: EC access, platform, config, service, socket and conflicts
+    Doctor {
+        #[clap(short, long, default_value = DEFAULT_CONFIG_PATH)]
+        config: PathBuf,
+    },
+    #[cfg(feature = "daemon")]
     SanityCheck {
         #[clap(long)]
         fan: bool,
@@ -71,55 +325,408 @@ enum Command {
     },
 }
 
-fn run_socket_command(cmd: &str, args: Option<&str>, format: OutputFormat) -> Result<()> {
+#[derive(Subcommand, Debug)]
+enum JobsCommand {
+    /// List running and recently finished jobs
+    List,
+    /// Show a job's progress and result
+    Status { id: u32 },
+    /// Stop a running job
+    Cancel { id: u32 },
+    /// Start a job in the background: fan-test
+    Start { kind: String },
+}
+
+#[derive(Subcommand, Debug)]
+enum ProfileCommand {
+    /// Show the active profile and the configured ones
+    List,
+    /// Switch to a profile until `profile auto`, overriding its trigger
+    Use { profile: String },
+    /// Follow the profile triggers again
+    Auto,
+}
+
+#[cfg(feature = "tui")]
+#[derive(Subcommand, Debug)]
+enum CurveCommand {
+    /// Full-screen editor that applies changes to the running daemon as you go
+    Edit {
+        strategy: String,
+
+        /// Config file the curve is saved to on confirm
+        #[clap(short, long, default_value = DEFAULT_CONFIG_PATH)]
+        config: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigCommand {
+    /// Print the JSON Schema of the config file, for editor completion and validation
+    Schema,
+    /// Show how a config file differs from what the daemon is running
+    Diff {
+        /// Config file to compare (default: the one the daemon was started with)
+        path: Option<PathBuf>,
+    },
+    /// Rewrite a config file with the current key names, e.g. `fanSpeedUpdateFrequency`
+    /// as `updateIntervalSeconds`
+    Migrate {
+        #[clap(short, long, default_value = DEFAULT_CONFIG_PATH)]
+        config: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum StrategyCommand {
+    /// Write a strategy from the config file, with composed curves folded in
+    Export {
+        name: String,
+
+        /// Write to this file instead of stdout
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+
+        #[clap(short, long, default_value = DEFAULT_CONFIG_PATH)]
+        config: PathBuf,
+    },
+    /// Add an exported strategy, or a bare strategy definition, to the config file
+    Import {
+        file: PathBuf,
+
+        /// Save it under this name; required for a bare definition
+        #[clap(long)]
+        name: Option<String>,
+
+        /// Replace a strategy of the same name
+        #[clap(long)]
+        force: bool,
+
+        #[clap(short, long, default_value = DEFAULT_CONFIG_PATH)]
+        config: PathBuf,
+    },
+}
+
+#[derive(Args, Debug)]
+struct ServiceTarget {
+    #[clap(long, value_enum, default_value = "systemd")]
+    init: InitSystem,
+
+    #[clap(short, long, default_value = DEFAULT_CONFIG_PATH)]
+    config: PathBuf,
+
+    /// Write files under this directory instead of `/` (implies --no-enable)
+    #[clap(long, value_name = "DIR")]
+    root: Option<PathBuf>,
+
+    /// Only write files; don't enable, start, stop or disable the service
+    #[clap(long)]
+    no_enable: bool,
+}
+
+impl ServiceTarget {
+    fn into_options(self) -> InstallOptions {
+        let enable = !self.no_enable && self.root.is_none();
+        InstallOptions {
+            init: self.init,
+            config_path: self.config,
+            root: self.root.unwrap_or_else(|| PathBuf::from("/")),
+            enable,
+            ..InstallOptions::default()
+        }
+    }
+}
+
+/// History `print --graph` and `watch` draw, in seconds.
+const GRAPH_SECONDS: u64 = 60;
+
+/// How the CLI talks to the daemon and renders replies.
+struct ClientOptions {
+    format: OutputFormat,
+    timeout: Duration,
+    color: bool,
+}
+
+fn run_socket_command(cmd: &str, args: Option<&str>, client: &ClientOptions) -> Result<()> {
     let full_cmd = match args {
         Some(a) => format!("{} {}", cmd, a),
         None => cmd.to_string(),
     };
-    let result = send_command(&full_cmd)?;
-    print_result(&result, format);
+    let result = send_command(&full_cmd, client.timeout)?;
+    print_result(&result, client.format, client.color);
     Ok(())
 }
 
 fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    // The daemon installs its own subscriber, which may also export over OTLP
+    #[cfg(feature = "daemon")]
+    if let Some(Command::Run(args)) = cli.command {
+        return daemon::run(*args);
+    }
     tracing_subscriber::fmt::init();
+    i18n::init(cli.lang.as_deref());
 
-    let cli = Cli::parse();
+    let client = ClientOptions {
+        format: cli.output_format,
+        timeout: Duration::from_secs(cli.timeout.max(1)),
+        color: match cli.color {
+            ColorChoice::Auto => output::auto_color(),
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        },
+    };
 
     match cli.command {
-        Some(Command::Run {
+        #[cfg(feature = "daemon")]
+        Some(Command::Run(_)) => unreachable!("handled above"),
+        Some(Command::Use { strategy }) => {
+            run_socket_command("use", Some(&quote(&strategy)), &client)?;
+        }
+        Some(Command::Reset) => {
+            run_socket_command("reset", None, &client)?;
+        }
+        Some(Command::Toggle { a, b }) => {
+            let args = format!("{} {}", quote(&a), quote(&b));
+            run_socket_command("toggle", Some(&args), &client)?;
+        }
+        Some(Command::Cycle) => {
+            run_socket_command("cycle", None, &client)?;
+        }
+        Some(Command::Adjust { bias }) => {
+            run_socket_command("adjust", Some(&bias.to_string()), &client)?;
+        }
+        Some(Command::ClearHistory) => {
+            run_socket_command("clear-history", None, &client)?;
+        }
+        Some(Command::BatterySensor { action }) => {
+            let action = match action {
+                BatterySensorAction::Exclude => "exclude",
+                BatterySensorAction::Include => "include",
+            };
+            run_socket_command("battery-sensor", Some(action), &client)?;
+        }
+        Some(Command::Reload { path }) => {
+            let path = path
+                .map(std::fs::canonicalize)
+                .transpose()?
+                .map(|path| quote(&path.display().to_string()).into_owned());
+            run_socket_command("reload", path.as_deref(), &client)?;
+        }
+        Some(Command::Tune {
+            strategy,
+            field,
+            value,
+            commit,
             config,
+        }) => {
+            if commit {
+                run_tune_commit(&config, client.timeout)?;
+            } else if let (Some(strategy), Some(field), Some(value)) = (strategy, field, value) {
+                let args = format!("{} {} {}", quote(&strategy), quote(&field), value);
+                run_socket_command("tune", Some(&args), &client)?;
+            }
+        }
+        Some(Command::Pause) => {
+            run_socket_command("pause", None, &client)?;
+        }
+        Some(Command::Resume) => {
+            run_socket_command("resume", None, &client)?;
+        }
+        Some(Command::Selftest) => {
+            run_selftest(&client)?;
+        }
+        Some(Command::Jobs { action }) => {
+            let args = match action.unwrap_or(JobsCommand::List) {
+                JobsCommand::List => "list".to_string(),
+                JobsCommand::Status { id } => format!("status {}", id),
+                JobsCommand::Cancel { id } => format!("cancel {}", id),
+                JobsCommand::Start { kind } => format!("start {}", kind),
+            };
+            run_socket_command("jobs", Some(&args), &client)?;
+        }
+        Some(Command::Profile { action }) => {
+            let args = match action.unwrap_or(ProfileCommand::List) {
+                ProfileCommand::List => "list".to_string(),
+                ProfileCommand::Use { profile } => format!("use {}", quote(&profile)),
+                ProfileCommand::Auto => "auto".to_string(),
+            };
+            run_socket_command("profile", Some(&args), &client)?;
+        }
+        Some(Command::Version) => {
+            run_socket_command("version", None, &client)?;
+        }
+        Some(Command::Shutdown) => {
+            run_socket_command("shutdown", None, &client)?;
+        }
+        Some(Command::RestartAuto) => {
+            run_socket_command("restart-auto", None, &client)?;
+        }
+        Some(Command::Print {
+            selection,
+            name,
+            detailed,
+            graph,
+        }) => {
+            let mut args = selection.unwrap_or_else(|| "all".to_string());
+            if let Some(name) = name {
+                args = format!("{} {}", args, quote(&name));
+            } else if detailed {
+                args.push_str(" detailed");
+            }
+            if graph && args == "all" && client.format == OutputFormat::Natural {
+                print_status_with_graphs(&client)?;
+            } else {
+                run_socket_command("print", Some(&args), &client)?;
+            }
+        }
+        Some(Command::Watch { interval }) => {
+            run_watch(Duration::from_secs(interval.max(1)), &client)?;
+        }
+        Some(Command::Export {
+            since,
+            format,
+            output,
+        }) => {
+            run_export(&since, format, output, client.timeout)?;
+        }
+        #[cfg(feature = "daemon")]
+        Some(Command::Apply {
             strategy,
-            silent,
+            config,
             no_battery_sensors,
         }) => {
-            run_service(config, strategy, silent, no_battery_sensors)?;
+            run_apply(strategy, &config, no_battery_sensors, cli.output_format)?;
         }
-        Some(Command::Use { strategy }) => {
-            run_socket_command("use", Some(&strategy), cli.output_format)?;
+        #[cfg(feature = "daemon")]
+        Some(Command::Characterize {
+            target,
+            max_temp,
+            duties,
+            settle,
+            load,
+            external_load,
+            no_battery_sensors,
+        }) => {
+            let load = match (load, external_load) {
+                (Some(cmd), _) => Load::Command(cmd),
+                (None, true) => Load::External,
+                (None, false) => Load::BusyLoop,
+            };
+            let options = CharacterizeOptions {
+                duties,
+                target_temperature: target,
+                max_temperature: max_temp,
+                max_settle: Duration::from_secs(settle),
+                load,
+            };
+            run_characterize(options, no_battery_sensors, cli.output_format)?;
         }
-        Some(Command::Reset) => {
-            run_socket_command("reset", None, cli.output_format)?;
+        #[cfg(feature = "tui")]
+        Some(Command::Curve {
+            action: CurveCommand::Edit { strategy, config },
+        }) => {
+            run_curve_edit(&strategy, &config, client.timeout)?;
         }
-        Some(Command::Reload) => {
-            run_socket_command("reload", None, cli.output_format)?;
+        #[cfg(feature = "daemon")]
+        Some(Command::Autotune {
+            strategy,
+            history,
+            config,
+            max_temp,
+            apply,
+        }) => {
+            run_autotune(&strategy, &history, &config, max_temp, apply)?;
         }
-        Some(Command::Pause) => {
-            run_socket_command("pause", None, cli.output_format)?;
+        Some(Command::CheckConfig { config }) => {
+            run_check_config(&config, client.color)?;
         }
-        Some(Command::Resume) => {
-            run_socket_command("resume", None, cli.output_format)?;
+        Some(Command::Config {
+            action: ConfigCommand::Schema,
+        }) => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&Config::json_schema()).unwrap_or_default()
+            );
+        }
+        Some(Command::Config {
+            action: ConfigCommand::Diff { path },
+        }) => {
+            run_config_diff(path, &client)?;
+        }
+        Some(Command::Config {
+            action: ConfigCommand::Migrate { config },
+        }) => {
+            run_config_migrate(&config)?;
+        }
+        Some(Command::Strategy {
+            action:
+                StrategyCommand::Export {
+                    name,
+                    output,
+                    config,
+                },
+        }) => {
+            run_strategy_export(&name, &config, output)?;
         }
-        Some(Command::Print { selection }) => {
-            let args = selection.unwrap_or_else(|| "all".to_string());
-            run_socket_command("print", Some(&args), cli.output_format)?;
+        Some(Command::Strategy {
+            action:
+                StrategyCommand::Import {
+                    file,
+                    name,
+                    force,
+                    config,
+                },
+        }) => {
+            run_strategy_import(&file, name.as_deref(), force, &config)?;
+        }
+        Some(Command::Install {
+            target,
+            bin,
+            run_args,
+        }) => {
+            let binary = match bin {
+                Some(path) => path,
+                None => std::env::current_exe()?,
+            };
+            let options = InstallOptions {
+                binary,
+                run_args,
+                ..target.into_options()
+            };
+            for action in service::install(&options)? {
+                println!("{}", action);
+            }
+        }
+        Some(Command::Uninstall { target, purge }) => {
+            for action in service::uninstall(&target.into_options(), purge)? {
+                println!("{}", action);
+            }
         }
+        Some(Command::SetupPermissions { user, root }) => {
+            let options = PermissionsOptions {
+                user,
+                root: root.unwrap_or_else(|| PathBuf::from("/")),
+            };
+            for action in service::setup_permissions(&options)? {
+                println!("{}", action);
+            }
+        }
+        Some(Command::Doctor { config }) => {
+            let findings = doctor::diagnose(&config, send_command("version", client.timeout));
+            println!("{}", doctor::render(&findings, client.color));
+            if findings.iter().any(|f| f.status == doctor::Status::Fail) {
+                std::process::exit(1);
+            }
+        }
+        #[cfg(feature = "daemon")]
         Some(Command::SanityCheck { fan, temp, all }) => {
             let check_all = all || (!fan && !temp);
-            run_sanity_check(check_all, fan, temp)?;
+            run_sanity_check(check_all, fan, temp, client.color)?;
         }
         None => {
-            eprintln!("Error: No command provided. Use --help for usage information.");
+            eprintln!("{} {}", tr("error-label"), tr("no-command"));
             std::process::exit(1);
         }
     }
@@ -127,214 +734,806 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn run_service(
-    config_path: PathBuf,
-    strategy: Option<String>,
-    silent: bool,
-    no_battery_sensors: bool,
-) -> Result<()> {
-    let config = Config::load(&config_path)?;
-
-    let hw = HardwareController::new(no_battery_sensors)?;
+fn unresponsive(timeout: Duration) -> Error {
+    Error::Socket(format!(
+        "Daemon unresponsive: no reply within {}s",
+        timeout.as_secs()
+    ))
+}
 
-    let controller = FanController::new(hw, config, strategy);
+/// Connects to the daemon socket, warning once if the daemon speaks another protocol version.
+fn connect(command: &str, timeout: Duration) -> Result<std::os::unix::net::UnixStream> {
+    use std::io::BufReader;
+    use std::os::unix::net::UnixStream;
+    use std::sync::mpsc;
 
-    let controller_handle: ControllerHandle = Arc::new(Mutex::new(controller));
+    use fw_fanctrl::protocol::{read_frame, write_frame};
 
-    let rt = tokio::runtime::Runtime::new().expect("Failed to create tokio runtime");
-    rt.block_on(async move {
+    // UnixStream has no connect timeout; a full accept backlog would block here
+    let connect_to = |socket_path: &'static str| {
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = tx.send(UnixStream::connect(socket_path));
+        });
+        rx.recv_timeout(timeout).map_err(|_| unresponsive(timeout))
+    };
+    let mut stream = match connect_to(protocol::COMMANDS_SOCKET_FILE_PATH)? {
+        // A restricted command socket still leaves reads to the query socket
+        Err(e)
+            if e.kind() == std::io::ErrorKind::PermissionDenied
+                && !protocol::is_state_changing(command) =>
         {
-            let ctrl = controller_handle.lock().await;
-            ctrl.enable_auto_fan()?;
+            connect_to(protocol::QUERY_SOCKET_FILE_PATH)?.map_err(|_| e)
         }
+        connected => connected,
+    }
+    .map_err(|e| Error::Socket(format!("Failed to connect: {}", e)))?;
 
-        let shutdown = Arc::new(AtomicBool::new(false));
-        let server_handle = Arc::clone(&controller_handle);
-        let shutdown_clone = Arc::clone(&shutdown);
-        let socket_task = tokio::spawn(async move {
-            if let Err(e) = start_socket_server(server_handle, shutdown_clone).await {
-                tracing::error!("Socket server error: {}", e);
-            }
-        });
+    stream
+        .set_read_timeout(Some(timeout))
+        .and_then(|_| stream.set_write_timeout(Some(timeout)))
+        .map_err(|e| Error::Socket(format!("Failed to set socket timeout: {}", e)))?;
 
-        if !silent {
-            println!(
-                "{:<15} {:<10} {:<10} {:<10}",
-                "Strategy", "Temp", "Speed", "Active"
-            );
+    // Once per invocation, so commands sent in a loop don't repeat the warning
+    static VERSION_CHECKED: AtomicBool = AtomicBool::new(false);
+    if command != "version" && !VERSION_CHECKED.swap(true, Ordering::Relaxed) {
+        write_frame(&mut stream, "version")?;
+        let reply =
+            read_frame(&mut BufReader::new(&stream))?.and_then(|r| serde_json::from_str(&r).ok());
+        if let Some(warning) = reply.as_ref().and_then(protocol::version_mismatch) {
+            eprintln!("{} {}", tr("warning-label"), warning);
         }
+    }
+
+    Ok(stream)
+}
+
+fn send_command(command: &str, timeout: Duration) -> Result<String> {
+    use std::io::BufReader;
+
+    use fw_fanctrl::protocol::{read_frame, write_frame};
 
-        let mut sigterm = signal(SignalKind::terminate())?;
-        let mut sigint = std::pin::pin!(tokio::signal::ctrl_c());
-
-        loop {
-            tokio::select! {
-                _ = &mut sigint => {
-                    tracing::info!("Received SIGINT, switching fan to auto mode before exit");
-                    break;
-                }
-                _ = sigterm.recv() => {
-                    tracing::info!("Received SIGTERM, switching fan to auto mode before exit");
-                    break;
-                }
-                _ = sleep(Duration::from_secs(1)) => {
-                    let mut ctrl = controller_handle.lock().await;
-                    match ctrl.step() {
-                        Ok(temp) => {
-                            if !silent {
-                                let strategy_name = ctrl.get_current_strategy_name();
-                                let speed = ctrl.get_current_speed();
-                                let active = ctrl.is_active();
-                                println!(
-                                    "{:<15} {:<10.1} {:<10} {:<10}",
-                                    strategy_name,
-                                    temp,
-                                    speed,
-                                    active
-                                );
-                            }
-                        }
-                        Err(e) => {
-                            tracing::error!("Error in control loop: {}", e);
-                        }
-                    }
-                }
+    let mut stream = connect(command, timeout)?;
+    let started = std::time::Instant::now();
+    write_frame(&mut stream, command)?;
+
+    let response = read_frame(&mut BufReader::new(&stream))
+        .map_err(|e| {
+            if started.elapsed() >= timeout {
+                unresponsive(timeout)
+            } else {
+                e
             }
-        }
+        })?
+        .ok_or_else(|| Error::Socket("Connection closed without a response".into()))?;
 
-        tracing::info!("Shutting down socket server...");
-        shutdown.store(true, Ordering::Relaxed);
-        let _ = socket_task.await;
-        tracing::info!("Socket server shut down");
+    Ok(response)
+}
 
-        let cleanup_result = {
-            let ctrl = controller_handle.lock().await;
-            ctrl.enable_auto_fan()
-        };
+/// Runs the daemon's fan test, printing each step as the daemon measures it.
+fn run_selftest(client: &ClientOptions) -> Result<()> {
+    use std::io::BufReader;
 
-        if let Err(e) = cleanup_result {
-            tracing::error!("Failed to restore auto fan control on shutdown: {}", e);
-            return Err(e);
+    use fw_fanctrl::protocol::{read_frame, write_frame};
+
+    let mut stream = connect("selftest", client.timeout)?;
+    // Every step holds its duty before the next frame arrives
+    stream
+        .set_read_timeout(Some(client.timeout + STEP_SETTLE))
+        .map_err(|e| Error::Socket(format!("Failed to set socket timeout: {}", e)))?;
+    write_frame(&mut stream, "selftest")?;
+
+    let natural = matches!(client.format, OutputFormat::Natural);
+    if natural {
+        println!("{:>6}  {:>6}", tr("column-speed"), tr("column-rpm"));
+    }
+    let mut reader = BufReader::new(&stream);
+    while let Some(frame) = read_frame(&mut reader)? {
+        let reply: serde_json::Value = serde_json::from_str(&frame)
+            .map_err(|e| Error::Socket(format!("Invalid reply: {}", e)))?;
+        match reply["status"].as_str() {
+            Some("progress") if natural => {
+                println!("{:>6}  {:>6}", reply["speed"], reply["rpm"]);
+            }
+            Some("progress") => println!("{}", frame),
+            Some("success") if natural => {
+                println!("{}", tr("selftest-ok"));
+                return Ok(());
+            }
+            _ => {
+                print_result(&frame, client.format, client.color);
+                return Ok(());
+            }
         }
+    }
 
-        Ok(())
-    })
+    Err(Error::Socket("Connection closed without a response".into()))
 }
 
-fn send_command(command: &str) -> Result<String> {
-    use std::io::{Read, Write};
-    use std::net::Shutdown;
-    use std::os::unix::net::UnixStream;
+fn print_result(result: &str, format: OutputFormat, color: bool) {
+    match format {
+        OutputFormat::Json => {
+            println!("{}", result);
+        }
+        OutputFormat::Natural => match serde_json::from_str::<serde_json::Value>(result) {
+            Ok(parsed) if parsed["status"] == "success" => {
+                println!("{}", output::render_natural(&parsed, color));
+            }
+            Ok(parsed) => {
+                eprintln!(
+                    "{} {}",
+                    paint(&tr("error-label"), Color::Red, color),
+                    parsed["reason"].as_str().unwrap_or("unknown error")
+                );
+            }
+            Err(_) => println!("{}", result),
+        },
+    }
+}
 
-    let socket_path = fw_fanctrl::socket::COMMANDS_SOCKET_FILE_PATH;
+/// `print all` followed by graphs of the last [`GRAPH_SECONDS`] of history.
+fn print_status_with_graphs(client: &ClientOptions) -> Result<()> {
+    let status = send_command("print all", client.timeout)?;
+    let history = send_checked(&format!("history {}", GRAPH_SECONDS), client.timeout)?;
+    print_result(&status, client.format, client.color);
+    let samples = history["history"].as_array().cloned().unwrap_or_default();
+    println!(
+        "\n{}",
+        output::render_history_graphs(&samples, GRAPH_SECONDS)
+    );
+    Ok(())
+}
 
-    let mut stream = UnixStream::connect(socket_path)
-        .map_err(|e| fw_fanctrl::error::Error::Socket(format!("Failed to connect: {}", e)))?;
+/// Redraws the status with graphs every `interval`; in JSON, prints one
+/// `print all` reply per line instead.
+fn run_watch(interval: Duration, client: &ClientOptions) -> Result<()> {
+    loop {
+        match client.format {
+            OutputFormat::Json => println!("{}", send_command("print all", client.timeout)?),
+            OutputFormat::Natural => {
+                // Clear the screen and move the cursor home before redrawing
+                print!("\x1b[H\x1b[2J");
+                print_status_with_graphs(client)?;
+            }
+        }
+        std::thread::sleep(interval);
+    }
+}
 
-    stream
-        .write_all(command.as_bytes())
-        .map_err(|e| fw_fanctrl::error::Error::Socket(format!("Failed to send: {}", e)))?;
+/// Parses durations like `90s`, `30m`, `1h` or `2d` into seconds.
+fn parse_duration(s: &str) -> Result<u64> {
+    let invalid = || Error::Command(format!("Invalid duration: {}", s));
+    let (value, unit) = s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()));
+    let value: u64 = value.parse().map_err(|_| invalid())?;
+    let multiplier = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        _ => return Err(invalid()),
+    };
+    value.checked_mul(multiplier).ok_or_else(invalid)
+}
 
-    stream
-        .shutdown(Shutdown::Write)
-        .map_err(|e| fw_fanctrl::error::Error::Socket(format!("Failed to shutdown: {}", e)))?;
+fn run_export(
+    since: &str,
+    format: ExportFormat,
+    output: Option<PathBuf>,
+    timeout: Duration,
+) -> Result<()> {
+    let seconds = parse_duration(since)?;
+    let response = send_command(&format!("history {}", seconds), timeout)?;
+    let parsed: serde_json::Value = serde_json::from_str(&response)
+        .map_err(|e| Error::Socket(format!("Invalid response: {}", e)))?;
 
-    let mut response = String::new();
-    stream
-        .read_to_string(&mut response)
-        .map_err(|e| fw_fanctrl::error::Error::Socket(format!("Failed to read: {}", e)))?;
+    if parsed["status"] != "success" {
+        return Err(Error::Command(
+            parsed["reason"]
+                .as_str()
+                .unwrap_or("unknown error")
+                .to_string(),
+        ));
+    }
 
-    Ok(response)
+    let history = parsed["history"].as_array().cloned().unwrap_or_default();
+    let rendered = match format {
+        ExportFormat::Json => {
+            serde_json::to_string_pretty(&history).map_err(|e| Error::Socket(e.to_string()))?
+        }
+        ExportFormat::Csv => {
+            let mut csv = String::from("timestamp,temperature,speed,active\n");
+            for entry in &history {
+                csv.push_str(&format!(
+                    "{},{},{},{}\n",
+                    entry["timestamp"], entry["temperature"], entry["speed"], entry["active"]
+                ));
+            }
+            csv
+        }
+    };
+
+    match output {
+        Some(path) => {
+            std::fs::write(&path, rendered)?;
+            eprintln!(
+                "{}",
+                i18n::tr_with(
+                    "export-done",
+                    &[
+                        ("samples", history.len().to_string().into()),
+                        ("path", path.display().to_string().into()),
+                    ]
+                )
+            );
+        }
+        None => print!("{}", rendered),
+    }
+    Ok(())
 }
 
-fn print_result(result: &str, format: OutputFormat) {
+#[cfg(feature = "daemon")]
+fn run_apply(
+    strategy: Option<String>,
+    config_path: &PathBuf,
+    no_battery_sensors: bool,
+    format: OutputFormat,
+) -> Result<()> {
+    let config = Config::load(config_path)?;
+    let hw = HardwareController::new(no_battery_sensors, false)?
+        .with_layout(config.ec_layout.unwrap_or_default());
+    let mut controller = FanController::new(hw, config, None);
+    if let Some(name) = strategy {
+        controller.overwrite_strategy(&name)?;
+    }
+
+    let (temperature, duty) = controller.apply_once()?;
+    let strategy = controller.get_current_strategy_name();
     match format {
-        OutputFormat::Json => {
-            println!("{}", result);
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::json!({
+                "strategy": strategy,
+                "temperature": temperature,
+                "duty": duty
+            })
+        ),
+        OutputFormat::Natural => println!(
+            "{}",
+            i18n::tr_with(
+                "apply-done",
+                &[
+                    ("duty", duty.to_string().into()),
+                    ("temperature", format!("{:.1}", temperature).into()),
+                    ("strategy", strategy.into()),
+                ]
+            )
+        ),
+    }
+    Ok(())
+}
+
+#[cfg(feature = "daemon")]
+fn run_characterize(
+    options: CharacterizeOptions,
+    no_battery_sensors: bool,
+    format: OutputFormat,
+) -> Result<()> {
+    if let Some(duty) = options.duties.iter().find(|d| **d > 100) {
+        return Err(Error::Command(format!("Invalid duty: {}", duty)));
+    }
+    if options.target_temperature >= options.max_temperature {
+        return Err(Error::Command(format!(
+            "Target {}°C must be below --max-temp {}°C",
+            options.target_temperature, options.max_temperature
+        )));
+    }
+
+    let hw = HardwareController::new(no_battery_sensors, false)?;
+    let rt = tokio::runtime::Runtime::new().expect("Failed to create tokio runtime");
+
+    eprintln!(
+        "{}",
+        i18n::tr_with(
+            "characterize-start",
+            &[
+                ("duties", options.duties.len().to_string().into()),
+                ("seconds", options.max_settle.as_secs().to_string().into()),
+            ]
+        )
+    );
+
+    let result = rt.block_on(async {
+        tokio::select! {
+            result = characterize(&hw, &options) => result.map(Some),
+            _ = tokio::signal::ctrl_c() => Ok(None),
         }
+    });
+
+    let Some(result) = result? else {
+        eprintln!("{}", tr("characterize-aborted"));
+        return hw.enable_auto_fan();
+    };
+
+    match format {
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::to_string(&result).map_err(|e| Error::Config(e.to_string()))?
+        ),
         OutputFormat::Natural => {
-            if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(result) {
-                if let Some(status) = parsed.get("status") {
-                    if status == "success" {
-                        if let Some(strategies) = parsed.get("strategies") {
-                            println!("Strategy list:");
-                            if let Some(arr) = strategies.as_array() {
-                                for s in arr {
-                                    println!("  - {}", s);
-                                }
-                            }
-                        } else if let Some(speed) = parsed.get("speed") {
-                            println!("Fan speed: {}%", speed);
-                        } else if let Some(active) = parsed.get("active") {
-                            println!("Active: {}", active);
-                        } else if let Some(strategy) = parsed.get("strategy") {
-                            println!("Current strategy: {}", strategy);
-                        }
-                    } else {
-                        eprintln!("Error: {:?}", parsed.get("reason"));
-                    }
-                }
-            } else {
-                println!("{}", result);
+            println!(
+                "{:>6}  {:>8}  {}",
+                tr("column-duty"),
+                tr("column-temperature"),
+                tr("column-settled")
+            );
+            for m in &result.measurements {
+                println!("{:>6}  {:>8.1}  {}", m.duty, m.temperature, m.settled);
             }
+            println!(
+                "\n{}",
+                i18n::tr_with(
+                    "characterize-required",
+                    &[
+                        (
+                            "temperature",
+                            format!("{:.0}", result.target_temperature).into()
+                        ),
+                        ("duty", result.required_duty.to_string().into()),
+                    ]
+                )
+            );
+            println!(
+                "{}\n{}",
+                tr("characterize-curve"),
+                serde_json::to_string_pretty(&result.speed_curve)
+                    .map_err(|e| Error::Config(e.to_string()))?
+            );
         }
     }
+    Ok(())
 }
 
-fn print_check_result<T>(name: &str, result: Result<T>, print_ok: impl FnOnce(&T)) {
+#[cfg(feature = "daemon")]
+fn run_autotune(
+    name: &str,
+    history_path: &PathBuf,
+    config_path: &PathBuf,
+    max_temp: f64,
+    apply: bool,
+) -> Result<()> {
+    let mut config = Config::load(config_path)?;
+    let strategy = config
+        .get_strategy(name)
+        .ok_or_else(|| Error::Strategy(format!("Unknown strategy: {}", name)))?;
+    let history = autotune::parse_history(&std::fs::read_to_string(history_path)?)?;
+
+    let analysis = autotune::analyze(&history, max_temp);
+    println!(
+        "{}",
+        i18n::tr_with(
+            "autotune-analysis",
+            &[
+                ("samples", analysis.samples.to_string().into()),
+                ("seconds", analysis.duration_seconds.to_string().into()),
+                (
+                    "oscillations",
+                    format!("{:.1}", analysis.oscillations_per_hour).into()
+                ),
+                (
+                    "overheat",
+                    format!("{:.1}", analysis.overheat_fraction * 100.0).into()
+                ),
+                ("limit", format!("{:.0}", max_temp).into()),
+                ("peak", format!("{:.1}", analysis.max_temperature).into()),
+            ]
+        )
+    );
+
+    let (tuned, changes) = autotune::propose(name, strategy, &analysis, max_temp);
+    if changes.is_empty() {
+        println!("{}", tr("autotune-no-changes"));
+        return Ok(());
+    }
+    for change in &changes {
+        println!("{}", change);
+    }
+
+    if apply {
+        config.strategies.insert(name.to_string(), tuned);
+        config.save(config_path)?;
+        println!(
+            "{}",
+            i18n::tr_with(
+                "autotune-applied",
+                &[("path", config_path.display().to_string().into())]
+            )
+        );
+    } else {
+        println!("{}", tr("autotune-dry-run"));
+    }
+    Ok(())
+}
+
+/// Sends a command and turns an error reply into an `Err`.
+fn send_checked(command: &str, timeout: Duration) -> Result<serde_json::Value> {
+    let response = send_command(command, timeout)?;
+    let parsed: serde_json::Value = serde_json::from_str(&response)
+        .map_err(|e| Error::Socket(format!("Invalid response: {}", e)))?;
+    if parsed["status"] != "success" {
+        return Err(Error::Command(
+            parsed["reason"]
+                .as_str()
+                .unwrap_or("unknown error")
+                .to_string(),
+        ));
+    }
+    Ok(parsed)
+}
+
+/// Compares a config file with the config the daemon is running.
+fn run_config_diff(path: Option<PathBuf>, client: &ClientOptions) -> Result<()> {
+    let reply = send_checked("print all", client.timeout)?;
+    let running: Config = serde_json::from_value(reply["configuration"].clone())
+        .map_err(|e| Error::Config(format!("Unreadable daemon config: {}", e)))?;
+    let path = match path {
+        Some(path) => path,
+        None => reply["configPath"]
+            .as_str()
+            .map(PathBuf::from)
+            .ok_or_else(|| Error::Command("The daemon did not report its config path".into()))?,
+    };
+    let on_disk = Config::load(&path)?;
+
+    let result = serde_json::json!({
+        "status": "success",
+        "path": path,
+        "daemonDiff": running.diff(&on_disk)
+    });
+    print_result(&result.to_string(), client.format, client.color);
+    Ok(())
+}
+
+/// Copies the settings changed with `tune` from the daemon into the config file.
+fn run_tune_commit(config_path: &PathBuf, timeout: Duration) -> Result<()> {
+    let mut config = Config::load(config_path)?;
+    let reply = send_checked("print list detailed", timeout)?;
+
+    let mut changes = Vec::new();
+    for live in reply["strategies"].as_array().into_iter().flatten() {
+        let Some(name) = live["name"].as_str() else {
+            continue;
+        };
+        let Some(strategy) = config.strategies.get_mut(name) else {
+            continue;
+        };
+        for field in Strategy::TUNABLE {
+            let Some(value) = live[field].as_u64().map(|v| v as u32) else {
+                continue;
+            };
+            let saved = strategy.parameter_mut(field).map(|v| *v);
+            if saved != Some(value) {
+                strategy.set_parameter(field, value)?;
+                changes.push(format!(
+                    "{}.{}: {} -> {}",
+                    name,
+                    field,
+                    saved.unwrap_or_default(),
+                    value
+                ));
+            }
+        }
+    }
+
+    if changes.is_empty() {
+        println!(
+            "{}",
+            i18n::tr_with(
+                "commit-nothing",
+                &[("path", config_path.display().to_string().into())]
+            )
+        );
+        return Ok(());
+    }
+    config.save(config_path)?;
+    for change in &changes {
+        println!("{}", change);
+    }
+    println!(
+        "{}",
+        i18n::tr_with(
+            "commit-saved",
+            &[("path", config_path.display().to_string().into())]
+        )
+    );
+    Ok(())
+}
+
+#[cfg(feature = "tui")]
+fn run_curve_edit(name: &str, config_path: &PathBuf, timeout: Duration) -> Result<()> {
+    use fw_fanctrl::curve::format_points;
+    use fw_fanctrl::tui::{self, CurveEditor, Outcome};
+
+    let mut config = Config::load(config_path)?;
+    let original = config
+        .get_strategy(name)
+        .ok_or_else(|| Error::Strategy(format!("Unknown strategy: {}", name)))?
+        .speed_curve
+        .clone();
+
+    let mut applied = false;
+    let editor = CurveEditor::new(name, original.clone());
+    let outcome = tui::run(editor, |points| {
+        send_checked(
+            &format!("curve {} {}", quote(name), format_points(points)),
+            timeout,
+        )?;
+        applied = true;
+        Ok(())
+    })?;
+
+    match outcome {
+        Outcome::Save(points) => {
+            if let Some(strategy) = config.strategies.get_mut(name) {
+                strategy.speed_curve = points;
+            }
+            config.save(config_path)?;
+            println!(
+                "{}",
+                i18n::tr_with(
+                    "curve-saved",
+                    &[
+                        ("strategy", name.into()),
+                        ("path", config_path.display().to_string().into()),
+                    ]
+                )
+            );
+        }
+        Outcome::Cancel => {
+            if applied {
+                send_checked(
+                    &format!("curve {} {}", quote(name), format_points(&original)),
+                    timeout,
+                )?;
+            }
+            println!(
+                "{}",
+                i18n::tr_with("curve-discarded", &[("strategy", name.into())])
+            );
+        }
+    }
+    Ok(())
+}
+
+fn run_check_config(path: &PathBuf, color: bool) -> Result<()> {
+    let (config, unknown) = Config::load_with_unknown_keys(path)?;
+    if !unknown.is_empty() {
+        for key in &unknown {
+            eprintln!(
+                "{} {}",
+                paint(&tr("error-label"), Color::Red, color),
+                i18n::tr_with("check-config-unknown-key", &[("key", key.as_str().into())])
+            );
+        }
+        return Err(Error::Config(format!(
+            "{} has {} unknown key(s)",
+            path.display(),
+            unknown.len()
+        )));
+    }
+    println!(
+        "{}",
+        i18n::tr_with(
+            "check-config-ok",
+            &[
+                ("path", path.display().to_string().into()),
+                ("strategies", config.strategies.len().to_string().into()),
+            ]
+        )
+    );
+    Ok(())
+}
+
+/// Writes `path` back the way the config commands do, so older key names
+/// become the current ones. Files with unknown keys are left alone, since
+/// rewriting would drop those keys.
+fn run_config_migrate(path: &PathBuf) -> Result<()> {
+    let content = std::fs::read_to_string(path)?;
+    let (config, unknown) = Config::parse(&content)?;
+    if !unknown.is_empty() {
+        return Err(Error::Config(format!(
+            "{} has unknown keys, fix or remove them first: {}",
+            path.display(),
+            unknown.join(", ")
+        )));
+    }
+    let current = serde_json::to_value(&config).map_err(|e| Error::Config(e.to_string()))?;
+    let original: serde_json::Value =
+        serde_json::from_str(&content).map_err(|e| Error::Config(e.to_string()))?;
+    if current == original {
+        println!(
+            "{}",
+            i18n::tr_with(
+                "migrate-current",
+                &[("path", path.display().to_string().into())]
+            )
+        );
+        return Ok(());
+    }
+    let rendered =
+        serde_json::to_string_pretty(&current).map_err(|e| Error::Config(e.to_string()))?;
+    std::fs::write(path, rendered + "\n")?;
+    println!(
+        "{}",
+        i18n::tr_with(
+            "migrate-done",
+            &[("path", path.display().to_string().into())]
+        )
+    );
+    Ok(())
+}
+
+fn run_strategy_export(name: &str, config_path: &PathBuf, output: Option<PathBuf>) -> Result<()> {
+    let export = Config::load(config_path)?.export_strategy(name)?;
+    let rendered =
+        serde_json::to_string_pretty(&export).map_err(|e| Error::Config(e.to_string()))? + "\n";
+    match output {
+        Some(path) => {
+            std::fs::write(&path, rendered)?;
+            println!(
+                "{}",
+                i18n::tr_with(
+                    "strategy-exported",
+                    &[
+                        ("strategy", name.into()),
+                        ("path", path.display().to_string().into()),
+                    ]
+                )
+            );
+        }
+        None => print!("{}", rendered),
+    }
+    Ok(())
+}
+
+fn run_strategy_import(
+    file: &PathBuf,
+    name: Option<&str>,
+    force: bool,
+    config_path: &PathBuf,
+) -> Result<()> {
+    let export = StrategyExport::parse(&std::fs::read_to_string(file)?, name)?;
+    let name = export.name.clone();
+    let mut config = Config::load(config_path)?;
+    config.import_strategy(export, force)?;
+    config.save(config_path)?;
+    println!(
+        "{}",
+        i18n::tr_with(
+            "strategy-imported",
+            &[
+                ("strategy", name.into()),
+                ("path", config_path.display().to_string().into()),
+            ]
+        )
+    );
+    Ok(())
+}
+
+#[cfg(feature = "daemon")]
+fn print_check_result<T>(name: &str, result: Result<T>, color: bool, print_ok: impl FnOnce(&T)) {
     match result {
         Ok(val) => print_ok(&val),
         Err(e) => {
-            println!("{}: FAILED", name);
-            eprintln!("  Error: {}", e);
+            println!(
+                "{}: {}",
+                name,
+                paint(&tr("sanity-failed"), Color::Red, color)
+            );
+            eprintln!("  {} {}", tr("error-label"), e);
         }
     }
 }
 
-fn run_sanity_check(check_all: bool, check_fan: bool, check_temp: bool) -> Result<()> {
-    let hw = HardwareController::new(false)?;
+/// Runs the fan test on `hw` directly, printing each step as it is measured.
+#[cfg(feature = "daemon")]
+fn test_fan_control(hw: &HardwareController) -> Result<Vec<selftest::FanTestStep>> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()?;
+    let target = Mutex::new(hw);
+    runtime.block_on(selftest::run_fan_test(
+        &target,
+        &selftest::test_duties(selftest::DEFAULT_STEPS),
+        STEP_SETTLE,
+        |step| println!("{:>6}  {:>6}", step.speed, step.rpm),
+    ))
+}
+
+#[cfg(feature = "daemon")]
+fn run_sanity_check(check_all: bool, check_fan: bool, check_temp: bool, color: bool) -> Result<()> {
+    let hw = HardwareController::new(false, true)?;
 
-    println!("=== Sanity Check ===\n");
+    println!("{}\n", tr("sanity-title"));
+
+    if hw.is_degraded() {
+        println!("{}", tr("sanity-degraded"));
+    }
+
+    let platform = serde_json::to_value(hw.platform_info())
+        .map_err(|e| Error::Ec(format!("Failed to describe platform: {}", e)))?;
+    println!("{}", output::render_platform(&platform));
+    if let Some(version) = hw.ec_version() {
+        println!(
+            "{}",
+            i18n::tr_with(
+                "sanity-firmware",
+                &[("status", firmware::check(&version).to_string().into())]
+            )
+        );
+    }
+    println!();
 
     // Temperature check
     if check_all || check_temp {
-        print_check_result("Temperature", hw.check_temperature(), |t| {
-            println!("Temperature: {:>5.1}°C - OK", t)
-        });
+        print_check_result(
+            &tr("sanity-temperature"),
+            hw.check_temperature(),
+            color,
+            |t| {
+                println!(
+                    "{}",
+                    i18n::tr_with(
+                        "sanity-temperature-ok",
+                        &[("celsius", format!("{:>5.1}", t).into())]
+                    )
+                )
+            },
+        );
     }
 
     // Power check
-    print_check_result("Power", hw.is_on_ac(), |on_ac| {
+    print_check_result(&tr("sanity-power"), hw.is_on_ac(), color, |on_ac| {
         if *on_ac {
-            println!("Power:       AC connected - OK")
+            println!("{}", tr("sanity-power-ac"))
         } else {
-            println!("Power:       Battery - OK")
+            println!("{}", tr("sanity-power-battery"))
         }
     });
 
     // Fan check
-    if check_all || check_fan {
-        println!("\nTesting fan control...");
-        match hw.test_fan_control(4) {
-            Ok(results) => {
-                println!("{:>6}  {:>6}", "Speed%", "RPM");
-                for (speed, rpm) in results {
-                    println!("{:>6}  {:>6}", speed, rpm);
-                }
-                println!("Fan control: OK (auto-restored)");
+    if (check_all || check_fan) && hw.is_degraded() {
+        println!("{}", tr("sanity-fan-skipped-degraded"));
+    } else if (check_all || check_fan) && hw.is_fanless() {
+        println!("{}", tr("sanity-fan-skipped-fanless"));
+    } else if (check_all || check_fan)
+        && hw
+            .ec_version()
+            .is_some_and(|v| matches!(firmware::check(&v), FirmwareStatus::Quirk(_)))
+    {
+        println!("{}", tr("sanity-fan-skipped-quirk"));
+    } else if check_all || check_fan {
+        println!("\n{}", tr("sanity-fan-testing"));
+        println!("{:>6}  {:>6}", tr("column-speed"), tr("column-rpm"));
+        match test_fan_control(&hw) {
+            Ok(_) => {
+                println!("{}", tr("sanity-fan-ok"));
             }
             Err(e) => {
-                println!("Fan control: FAILED");
-                eprintln!("  Error: {}", e);
+                println!(
+                    "{}: {}",
+                    tr("status-fan-control"),
+                    paint(&tr("sanity-failed"), Color::Red, color)
+                );
+                eprintln!("  {} {}", tr("error-label"), e);
             }
         }
     }
 
     // Always restore auto fan mode at the end
-    print_check_result("Fan mode", hw.enable_auto_fan(), |_| {
-        println!("Fan mode: Auto")
+    print_check_result(&tr("sanity-fan-mode"), hw.enable_auto_fan(), color, |_| {
+        println!("{}", tr("sanity-fan-mode-auto"))
     });
 
-    println!("\n=== Done ===");
+    println!("\n{}", tr("sanity-done"));
     Ok(())
 }
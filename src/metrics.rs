@@ -0,0 +1,165 @@
+use std::fmt::Write;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::controller::FanController;
+use crate::error::{Error, Result};
+use crate::socket::ControllerHandle;
+
+/// Escapes a Prometheus label value.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: f64) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} gauge", name);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+/// Renders the controller state in the Prometheus text exposition format.
+pub fn render(controller: &FanController) -> Result<String> {
+    let temp = controller.get_actual_temperature()?;
+    let interval = controller.get_current_strategy().moving_average_interval;
+    let mut out = String::new();
+
+    gauge(
+        &mut out,
+        "fw_fanctrl_temperature_celsius",
+        "Current temperature reading.",
+        temp,
+    );
+    gauge(
+        &mut out,
+        "fw_fanctrl_moving_average_temperature_celsius",
+        "Moving average temperature over the strategy interval.",
+        controller.get_moving_average_temperature(interval),
+    );
+    let (samples, window) = controller.moving_average_fill(interval);
+    gauge(
+        &mut out,
+        "fw_fanctrl_moving_average_samples",
+        "Samples behind the moving average; fewer than the window right after startup.",
+        samples as f64,
+    );
+    gauge(
+        &mut out,
+        "fw_fanctrl_moving_average_window",
+        "Samples it takes to fill the moving-average window.",
+        window as f64,
+    );
+    gauge(
+        &mut out,
+        "fw_fanctrl_effective_temperature_celsius",
+        "Temperature fed into the speed curve.",
+        controller.get_effective_temperature(temp, interval),
+    );
+    if let Some(battery) = controller.get_battery_temperature().ok().flatten() {
+        gauge(
+            &mut out,
+            "fw_fanctrl_battery_temperature_celsius",
+            "Battery temperature, reported even when excluded from control.",
+            battery,
+        );
+    }
+    gauge(
+        &mut out,
+        "fw_fanctrl_fan_duty_percent",
+        "Last commanded fan duty.",
+        controller.get_current_speed() as f64,
+    );
+    gauge(
+        &mut out,
+        "fw_fanctrl_active",
+        "Whether curve-based fan control is active.",
+        controller.is_active() as u8 as f64,
+    );
+    gauge(
+        &mut out,
+        "fw_fanctrl_degraded",
+        "Whether the daemon runs without EC fan control.",
+        controller.is_degraded() as u8 as f64,
+    );
+
+    let _ = writeln!(out, "# HELP fw_fanctrl_strategy_info Active strategy.");
+    let _ = writeln!(out, "# TYPE fw_fanctrl_strategy_info gauge");
+    let _ = writeln!(
+        out,
+        "fw_fanctrl_strategy_info{{strategy=\"{}\",default=\"{}\"}} 1",
+        escape_label(&controller.get_current_strategy_name()),
+        !controller.is_overwritten()
+    );
+
+    let alerts = &controller.get_config().alerts;
+    if !alerts.is_empty() {
+        let active = controller.active_alerts();
+        let _ = writeln!(
+            out,
+            "# HELP fw_fanctrl_alert_active Whether a configured alert is tripped."
+        );
+        let _ = writeln!(out, "# TYPE fw_fanctrl_alert_active gauge");
+        for alert in alerts {
+            let _ = writeln!(
+                out,
+                "fw_fanctrl_alert_active{{alert=\"{}\"}} {}",
+                escape_label(&alert.name),
+                active.contains(&alert.name) as u8
+            );
+        }
+    }
+
+    Ok(out)
+}
+
+/// Writes `contents` next to `path` and renames it into place, so the
+/// node_exporter textfile collector never sees a partially written file.
+pub fn write_textfile(path: &Path, contents: &str) -> Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| Error::Config(format!("Invalid textfile path: {}", path.display())))?;
+    let tmp_path = path.with_file_name(format!(".{}.tmp", file_name.to_string_lossy()));
+
+    std::fs::write(&tmp_path, contents)?;
+    std::fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+/// Periodically renders the metrics into a node_exporter textfile collector file.
+pub async fn run_textfile_exporter(controller: ControllerHandle, path: &Path, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        let rendered = render(&*controller.lock().await);
+        match rendered.and_then(|metrics| write_textfile(path, &metrics)) {
+            Ok(()) => tracing::trace!("Wrote metrics to {}", path.display()),
+            Err(e) => tracing::warn!("Failed to write metrics to {}: {}", path.display(), e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_label() {
+        assert_eq!(escape_label("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+
+    #[test]
+    fn test_write_textfile_replaces_atomically() {
+        let dir = std::env::temp_dir().join(format!("fw-fanctrl-textfile-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("fw_fanctrl.prom");
+
+        write_textfile(&path, "a 1\n").unwrap();
+        write_textfile(&path, "a 2\n").unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "a 2\n");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
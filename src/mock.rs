@@ -0,0 +1,218 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::error::{Error, Result};
+use crate::hardware::{
+    ChargeState, EcBackend, EC_FAN_SPEED_NOT_PRESENT, EC_MEMMAP_FAN, EC_MEMMAP_TEMP_SENSOR,
+};
+
+const EC_MEMMAP_SIZE: usize = 0x100;
+const EC_TEMP_NOT_PRESENT: u8 = 0xFF;
+const EC_TEMP_OFFSET: u8 = 73;
+
+struct MockState {
+    memory: [u8; EC_MEMMAP_SIZE],
+    duty: Option<u32>,
+    duty_writes: Vec<u32>,
+    /// Duty reported regardless of writes, to simulate a misbehaving EC.
+    stuck_duty: Option<u32>,
+    ac_present: bool,
+    /// Makes reading the AC state fail.
+    ac_unreadable: bool,
+    /// Makes switching to automatic mode fail.
+    autofanctrl_failing: bool,
+    battery_charge: Option<u32>,
+    charge_state: Option<ChargeState>,
+    charge_rate_limit: Option<f32>,
+    battery_discharge_watts: Option<f64>,
+}
+
+/// Simulated EC backed by an in-memory memmap.
+///
+/// Clones share state, so a test can keep one handle while the
+/// [`crate::hardware::HardwareController`] owns another.
+#[derive(Clone)]
+pub struct MockEc {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl Default for MockEc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockEc {
+    pub fn new() -> Self {
+        let mut memory = [0u8; EC_MEMMAP_SIZE];
+        memory[EC_MEMMAP_TEMP_SENSOR as usize..EC_MEMMAP_TEMP_SENSOR as usize + 0x10]
+            .fill(EC_TEMP_NOT_PRESENT);
+        // One fan, standing still; the other slots are empty
+        for slot in 1..4 {
+            let offset = EC_MEMMAP_FAN as usize + slot * 2;
+            memory[offset..offset + 2].copy_from_slice(&EC_FAN_SPEED_NOT_PRESENT.to_le_bytes());
+        }
+
+        Self {
+            state: Arc::new(Mutex::new(MockState {
+                memory,
+                duty: None,
+                duty_writes: Vec::new(),
+                stuck_duty: None,
+                ac_present: true,
+                ac_unreadable: false,
+                autofanctrl_failing: false,
+                battery_charge: None,
+                charge_state: None,
+                charge_rate_limit: None,
+                battery_discharge_watts: None,
+            })),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Sets the sensor temperatures in °C, marking the remaining slots not present.
+    pub fn set_temperatures(&self, celsius: &[u8]) {
+        let mut state = self.state();
+        let base = EC_MEMMAP_TEMP_SENSOR as usize;
+        state.memory[base..base + 0x10].fill(EC_TEMP_NOT_PRESENT);
+        for (i, t) in celsius.iter().take(0x10).enumerate() {
+            state.memory[base + i] = t.saturating_add(EC_TEMP_OFFSET);
+        }
+    }
+
+    /// Writes raw bytes into the memmap, e.g. to emulate a different layout.
+    pub fn write_memory(&self, offset: usize, bytes: &[u8]) {
+        self.state().memory[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+
+    pub fn set_fan_rpm(&self, fan: usize, rpm: u16) {
+        let offset = EC_MEMMAP_FAN as usize + fan * 2;
+        self.state().memory[offset..offset + 2].copy_from_slice(&rpm.to_le_bytes());
+    }
+
+    pub fn set_ac_present(&self, ac_present: bool) {
+        self.state().ac_present = ac_present;
+    }
+
+    pub fn set_ac_unreadable(&self, unreadable: bool) {
+        self.state().ac_unreadable = unreadable;
+    }
+
+    pub fn set_autofanctrl_failing(&self, failing: bool) {
+        self.state().autofanctrl_failing = failing;
+    }
+
+    pub fn set_battery_charge(&self, percent: u32) {
+        self.state().battery_charge = Some(percent);
+    }
+
+    pub fn set_charge_state(&self, state: ChargeState) {
+        self.state().charge_state = Some(state);
+    }
+
+    pub fn set_battery_discharge_watts(&self, watts: f64) {
+        self.state().battery_discharge_watts = Some(watts);
+    }
+
+    /// The last commanded duty, or `None` while the EC is in automatic mode.
+    pub fn duty(&self) -> Option<u32> {
+        self.state().duty
+    }
+
+    /// Simulates another process writing `percent` to the EC behind the controller's back.
+    pub fn override_duty(&self, percent: u32) {
+        self.state().duty = Some(percent);
+    }
+
+    /// Makes the EC report `duty` (values above 100 are invalid) no matter
+    /// what is written; `None` restores normal readback.
+    pub fn set_stuck_duty(&self, duty: Option<u32>) {
+        self.state().stuck_duty = duty;
+    }
+
+    /// Every duty written since creation, in order.
+    pub fn duty_writes(&self) -> Vec<u32> {
+        self.state().duty_writes.clone()
+    }
+
+    /// The last charge-rate limit set, or `None` if none was.
+    pub fn charge_rate_limit(&self) -> Option<f32> {
+        self.state().charge_rate_limit
+    }
+}
+
+impl EcBackend for MockEc {
+    fn read_memory(&self, offset: u16, length: u16) -> Option<Vec<u8>> {
+        let start = offset as usize;
+        let end = start + length as usize;
+        self.state().memory.get(start..end).map(|m| m.to_vec())
+    }
+
+    fn read_memory_into(&self, offset: u16, length: u16, buf: &mut Vec<u8>) -> bool {
+        let start = offset as usize;
+        let end = start + length as usize;
+        let state = self.state();
+        let Some(memory) = state.memory.get(start..end) else {
+            return false;
+        };
+        buf.clear();
+        buf.extend_from_slice(memory);
+        true
+    }
+
+    fn fan_set_duty(&self, percent: u32) -> Result<()> {
+        if percent > 100 {
+            return Err(Error::Ec(format!("Invalid duty: {}", percent)));
+        }
+        let mut state = self.state();
+        state.duty = Some(percent);
+        state.duty_writes.push(percent);
+        Ok(())
+    }
+
+    fn fan_duty(&self) -> Option<u32> {
+        let state = self.state();
+        state.stuck_duty.or(state.duty)
+    }
+
+    fn autofanctrl(&self) -> Result<()> {
+        let mut state = self.state();
+        if state.autofanctrl_failing {
+            return Err(Error::Ec("EC rejected auto fan control".into()));
+        }
+        state.duty = None;
+        Ok(())
+    }
+
+    fn set_charge_rate_limit(&self, rate: f32) -> Result<()> {
+        self.state().charge_rate_limit = Some(rate);
+        Ok(())
+    }
+
+    fn ac_present(&self) -> Result<bool> {
+        let state = self.state();
+        if state.ac_unreadable {
+            return Err(Error::Ec("Failed to read power info from EC".into()));
+        }
+        Ok(state.ac_present)
+    }
+
+    fn battery_charge(&self) -> Option<u32> {
+        self.state().battery_charge
+    }
+
+    fn charge_state(&self) -> Option<ChargeState> {
+        self.state().charge_state
+    }
+
+    fn battery_discharge_watts(&self) -> Option<f64> {
+        self.state().battery_discharge_watts
+    }
+
+    fn driver(&self) -> String {
+        "mock".into()
+    }
+}
@@ -0,0 +1,109 @@
+use opentelemetry::metrics::Gauge;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::metrics::SdkMeterProvider;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+use crate::error::{Error, Result};
+
+const SERVICE_NAME: &str = "fw-fanctrl";
+
+/// OTLP trace and metric pipelines; flushed by [`Otel::shutdown`].
+pub struct Otel {
+    tracer_provider: SdkTracerProvider,
+    meter_provider: SdkMeterProvider,
+    temperature: Gauge<f64>,
+    duty: Gauge<u64>,
+}
+
+impl Otel {
+    /// Records one control-loop sample.
+    pub fn record(&self, temperature: f64, duty: u32, strategy: &str) {
+        let attributes = [KeyValue::new("strategy", strategy.to_string())];
+        self.temperature.record(temperature, &attributes);
+        self.duty.record(duty as u64, &attributes);
+    }
+
+    pub fn shutdown(self) {
+        if let Err(e) = self.tracer_provider.shutdown() {
+            tracing::warn!("Failed to flush OTLP traces: {}", e);
+        }
+        if let Err(e) = self.meter_provider.shutdown() {
+            tracing::warn!("Failed to flush OTLP metrics: {}", e);
+        }
+    }
+}
+
+/// Installs the global tracing subscriber, exporting spans and metrics over
+/// OTLP/gRPC to `endpoint` when one is given.
+///
+/// Must run inside a tokio runtime, which the exporters use for their connections.
+pub fn init(endpoint: Option<&str>) -> Result<Option<Otel>> {
+    let fmt_layer = tracing_subscriber::fmt::layer().with_filter(EnvFilter::from_default_env());
+
+    let Some(endpoint) = endpoint else {
+        tracing_subscriber::registry().with(fmt_layer).init();
+        return Ok(None);
+    };
+
+    let otlp_error = |e: opentelemetry_otlp::ExporterBuildError| {
+        Error::Config(format!("Failed to set up OTLP exporter: {}", e))
+    };
+    let resource = Resource::builder().with_service_name(SERVICE_NAME).build();
+
+    let span_exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()
+        .map_err(otlp_error)?;
+    let tracer_provider = SdkTracerProvider::builder()
+        .with_batch_exporter(span_exporter)
+        .with_resource(resource.clone())
+        .build();
+
+    let metric_exporter = opentelemetry_otlp::MetricExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()
+        .map_err(otlp_error)?;
+    let meter_provider = SdkMeterProvider::builder()
+        .with_periodic_exporter(metric_exporter)
+        .with_resource(resource)
+        .build();
+
+    // Spans are exported regardless of RUST_LOG, which only governs console output
+    let otel_layer = tracing_opentelemetry::layer()
+        .with_tracer(tracer_provider.tracer(SERVICE_NAME))
+        .with_filter(LevelFilter::INFO);
+    tracing_subscriber::registry()
+        .with(fmt_layer)
+        .with(otel_layer)
+        .init();
+
+    let meter = opentelemetry::metrics::MeterProvider::meter(&meter_provider, SERVICE_NAME);
+    let temperature = meter
+        .f64_gauge("fw_fanctrl.temperature")
+        .with_unit("Cel")
+        .with_description("Current temperature reading")
+        .build();
+    let duty = meter
+        .u64_gauge("fw_fanctrl.fan.duty")
+        .with_unit("%")
+        .with_description("Fan duty set by the controller")
+        .build();
+
+    tracing::info!("Exporting traces and metrics over OTLP to {}", endpoint);
+
+    Ok(Some(Otel {
+        tracer_provider,
+        meter_provider,
+        temperature,
+        duty,
+    }))
+}
@@ -0,0 +1,1310 @@
+use serde_json::Value;
+
+use crate::i18n::{tr, tr_with};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    Green,
+    Yellow,
+    Red,
+}
+
+/// Wraps `text` in ANSI color codes when `enabled`.
+pub fn paint(text: &str, color: Color, enabled: bool) -> String {
+    if !enabled {
+        return text.to_string();
+    }
+    let code = match color {
+        Color::Green => "32",
+        Color::Yellow => "33",
+        Color::Red => "31",
+    };
+    format!("\x1b[{}m{}\x1b[0m", code, text)
+}
+
+/// Whether `auto` color should be used: stdout is a terminal and `NO_COLOR` is unset.
+pub fn auto_color() -> bool {
+    use std::io::IsTerminal;
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    std::io::stdout().is_terminal() && !no_color
+}
+
+/// Where `temp` falls on a speed curve: green while the fan stays at its
+/// floor, red once it reaches full speed, yellow in between.
+pub fn temperature_zone(curve: &[Value], temp: f64) -> Color {
+    let points: Vec<(f64, f64)> = curve
+        .iter()
+        .filter_map(|p| Some((p["temp"].as_f64()?, p["speed"].as_f64()?)))
+        .collect();
+    let Some(&(_, floor)) = points.first() else {
+        return Color::Green;
+    };
+
+    let quiet_limit = points
+        .iter()
+        .take_while(|(_, speed)| *speed <= floor)
+        .last()
+        .map_or(0.0, |(t, _)| *t);
+    let full_speed = points
+        .iter()
+        .find(|(_, speed)| *speed >= 100.0)
+        .or(points.last())
+        .map_or(f64::MAX, |(t, _)| *t);
+
+    if temp <= quiet_limit {
+        Color::Green
+    } else if temp >= full_speed {
+        Color::Red
+    } else {
+        Color::Yellow
+    }
+}
+
+/// Renders a successful socket reply for humans.
+///
+/// Known reply shapes get a dedicated layout; anything else falls back to
+/// pretty-printed JSON so no field is silently dropped.
+pub fn render_natural(reply: &Value, color: bool) -> String {
+    if reply.get("configuration").is_some() {
+        render_status(reply, color)
+    } else if let Some(platform) = reply.get("platform").filter(|p| p.is_object()) {
+        render_platform(platform)
+    } else if let Some(sensors) = reply.get("sensors").and_then(Value::as_array) {
+        render_sensors(sensors)
+    } else if let Some(errors) = single(reply, "errors").and_then(Value::as_array) {
+        render_errors(errors)
+    } else if let Some(diff) = reply.get("diff").filter(|d| d.is_object()) {
+        render_reload(plain(&reply["path"]).as_str(), diff)
+    } else if let Some(diff) = reply.get("daemonDiff").filter(|d| d.is_object()) {
+        render_daemon_diff(plain(&reply["path"]).as_str(), diff)
+    } else if let Some(stats) = single(reply, "stats").filter(|s| s.is_object()) {
+        render_thermal_stats(stats)
+    } else if let Some(jobs) = single(reply, "jobs").and_then(Value::as_array) {
+        render_jobs(jobs)
+    } else if let Some(job) = single(reply, "job") {
+        render_job(job)
+    } else if let Some(id) = single(reply, "jobId") {
+        tr_with("reply-started-job", &[("id", plain(id).into())])
+    } else if let Some(profiles) = reply.get("profiles").and_then(Value::as_array) {
+        render_profiles(reply, profiles)
+    } else if let Some(strategies) = reply.get("strategies").and_then(Value::as_array) {
+        render_strategy_list(strategies, reply.get("current").and_then(Value::as_str))
+    } else if let Some(config) = reply.get("config").filter(|c| c.is_object()) {
+        format!(
+            "# {}\n{}",
+            plain(&reply["path"]),
+            serde_json::to_string_pretty(config).unwrap_or_default()
+        )
+    } else if let Some(curve) = reply.get("speedCurve").and_then(Value::as_array) {
+        render_resolved_curve(reply, curve)
+    } else if let Some(definition) = reply.get("definition") {
+        render_strategy(plain(&reply["name"]).as_str(), definition)
+    } else if let Some(version) = reply.get("protocolVersion").and(reply.get("version")) {
+        format!(
+            "{}\n{}",
+            tr_with(
+                "reply-client-version",
+                &[("version", env!("CARGO_PKG_VERSION").into())]
+            ),
+            tr_with(
+                "reply-daemon-version",
+                &[
+                    ("version", plain(version).into()),
+                    ("protocol", plain(&reply["protocolVersion"]).into())
+                ]
+            )
+        )
+    } else if let Some(speed) = single(reply, "speed") {
+        tr_with("reply-fan-speed", &[("speed", plain(speed).into())])
+    } else if let Some(rpm) = single(reply, "rpm").and_then(Value::as_array) {
+        tr_with("reply-fan-rpm", &[("rpm", rpm_list(rpm).into())])
+    } else if let Some(bias) = single(reply, "bias").and_then(Value::as_i64) {
+        tr_with(
+            "reply-speed-bias",
+            &[("bias", format!("{:+}", bias).into())],
+        )
+    } else if let Some(excluded) = single(reply, "batteryExcluded").and_then(Value::as_bool) {
+        tr(if excluded {
+            "reply-battery-excluded"
+        } else {
+            "reply-battery-included"
+        })
+    } else if let Some(active) = single(reply, "active").and_then(Value::as_bool) {
+        tr_with(
+            "reply-active",
+            &[("active", active_label(active, color).into())],
+        )
+    } else if let Some(strategy) = reply.get("strategy").filter(|_| fields(reply) <= 4) {
+        let mut strategy = plain(strategy);
+        if reply.get("default").and_then(Value::as_bool) == Some(true) {
+            strategy = format!("{} {}", strategy, tr("status-default"));
+        }
+        let mut out = tr_with("reply-current-strategy", &[("strategy", strategy.into())]);
+        if let Some(pending) = reply.get("pending") {
+            out.push('\n');
+            out.push_str(&tr_with(
+                "reply-pending-switch",
+                &[
+                    ("strategy", plain(&pending["strategy"]).into()),
+                    ("seconds", plain(&pending["inSeconds"]).into()),
+                ],
+            ));
+        }
+        if let Some(reason) = reply.get("lastChangeReason").and_then(Value::as_str) {
+            out.push('\n');
+            out.push_str(&tr_with(
+                "reply-last-change",
+                &[("reason", change_reason(reason).into())],
+            ));
+        }
+        out
+    } else if fields(reply) == 0 {
+        tr("reply-ok")
+    } else {
+        let mut rest = reply.clone();
+        if let Some(map) = rest.as_object_mut() {
+            map.remove("status");
+        }
+        serde_json::to_string_pretty(&rest).unwrap_or_default()
+    }
+}
+
+/// Spells out a `lastChangeReason` the way the daemon logs it.
+fn change_reason(reason: &str) -> String {
+    match reason {
+        "powerSource" => tr("reason-power-source"),
+        "batteryThreshold" => tr("reason-battery-threshold"),
+        "chargeState" => tr("reason-charge-state"),
+        "quietHours" => tr("reason-quiet-hours"),
+        other => other.to_string(),
+    }
+}
+
+/// Spells out a `chargeState` the way the daemon logs it.
+fn charge_state_label(state: &str) -> &str {
+    match state {
+        "chargeLimited" => "charge-limited",
+        other => other,
+    }
+}
+
+/// Number of fields besides `status`.
+fn fields(reply: &Value) -> usize {
+    reply
+        .as_object()
+        .map_or(0, |m| m.keys().filter(|k| *k != "status").count())
+}
+
+fn single<'a>(reply: &'a Value, key: &str) -> Option<&'a Value> {
+    reply.get(key).filter(|_| fields(reply) == 1)
+}
+
+/// A value without JSON string quotes.
+fn plain(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn rpm_list(rpm: &[Value]) -> String {
+    if rpm.is_empty() {
+        return "-".to_string();
+    }
+    rpm.iter().map(plain).collect::<Vec<_>>().join(", ")
+}
+
+fn active_label(active: bool, color: bool) -> String {
+    if active {
+        tr("status-active-yes")
+    } else {
+        paint(&tr("status-active-no"), Color::Yellow, color)
+    }
+}
+
+/// Spells out a `mode` the way the daemon logs it, with its reason.
+fn mode_label(mode: &str, reason: Option<&str>, color: bool) -> String {
+    let label = match mode {
+        "ecAuto" => "EC auto",
+        other => other,
+    };
+    let label = match reason {
+        Some(reason) => format!("{} ({})", label, reason),
+        None => label.to_string(),
+    };
+    if mode == "held" {
+        paint(&label, Color::Red, color)
+    } else {
+        label
+    }
+}
+
+/// Temperatures travel as strings for compatibility with the Python daemon.
+fn temperature(value: Option<&Value>) -> Option<f64> {
+    match value {
+        Some(Value::String(s)) => s.parse::<f64>().ok(),
+        Some(v) => v.as_f64(),
+        None => None,
+    }
+}
+
+fn celsius(value: Option<&Value>, curve: &[Value], color: bool) -> String {
+    match temperature(value) {
+        Some(t) => paint(&format!("{:.1}°C", t), temperature_zone(curve, t), color),
+        None => "-".to_string(),
+    }
+}
+
+/// Label `id` indented under the row before it.
+fn indented(id: &str) -> String {
+    format!("  {}", tr(id))
+}
+
+fn row(out: &mut Vec<String>, label: &str, value: impl std::fmt::Display) {
+    out.push(format!("{:<24}{}", format!("{}:", label), value));
+}
+
+/// `3d 4h`, `2h 5m` or `42s`; precise enough for an uptime.
+fn format_uptime(seconds: u64) -> String {
+    let (days, hours, minutes) = (seconds / 86400, seconds / 3600 % 24, seconds / 60 % 60);
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m {}s", minutes, seconds % 60)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+fn render_status(reply: &Value, color: bool) -> String {
+    let mut out = Vec::new();
+    let config = &reply["configuration"];
+    let strategy_name = plain(&reply["strategy"]);
+    let curve = config["strategies"][&strategy_name]["speedCurve"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+
+    let strategy = if reply["default"].as_bool() == Some(true) {
+        format!("{} {}", strategy_name, tr("status-default"))
+    } else {
+        strategy_name.clone()
+    };
+    row(&mut out, &tr("status-strategy"), strategy);
+    if reply.get("lastStrategyChange").is_some() {
+        row(
+            &mut out,
+            &indented("status-since"),
+            local_time(&reply["lastStrategyChange"]),
+        );
+    }
+    if let Some(profile) = reply["profile"].as_str() {
+        row(&mut out, &tr("status-profile"), profile);
+    }
+    row(
+        &mut out,
+        &tr("status-active"),
+        active_label(reply["active"].as_bool().unwrap_or(false), color),
+    );
+    if let Some(mode) = reply["mode"].as_str() {
+        row(
+            &mut out,
+            &tr("status-mode"),
+            mode_label(mode, reply["modeReason"].as_str(), color),
+        );
+    }
+    if reply["capabilities"]["fanless"].as_bool() == Some(true) {
+        row(&mut out, &tr("status-fan"), tr("status-fanless"));
+    } else {
+        row(
+            &mut out,
+            &tr("status-fan-speed"),
+            format!("{}%", plain(&reply["speed"])),
+        );
+        if let Some(rpm) = reply["fanRpm"].as_array() {
+            row(&mut out, &tr("status-fan-rpm"), rpm_list(rpm));
+        }
+    }
+    if let Some(bias) = reply["bias"].as_i64().filter(|b| *b != 0) {
+        row(&mut out, &tr("status-speed-bias"), format!("{:+}%", bias));
+    }
+    row(
+        &mut out,
+        &tr("status-temperature"),
+        celsius(reply.get("temperature"), curve, color),
+    );
+    let mut moving_average = celsius(reply.get("movingAverageTemperature"), curve, color);
+    if let (Some(samples), Some(window)) = (
+        reply["movingAverageSamples"].as_u64(),
+        reply["movingAverageWindow"].as_u64(),
+    ) {
+        let fill = tr_with(
+            "status-window-fill",
+            &[("samples", samples.into()), ("window", window.into())],
+        );
+        moving_average = format!("{} ({})", moving_average, fill);
+    }
+    row(&mut out, &indented("status-moving-average"), moving_average);
+    row(
+        &mut out,
+        &indented("status-effective"),
+        celsius(reply.get("effectiveTemperature"), curve, color),
+    );
+    if let Some(slope) = reply["trend"]["slopePerMinute"].as_f64() {
+        let mut trend = format!("{:+.1}°C/min", slope);
+        let target = &reply["trend"]["target"];
+        if let (Some(temperature), Some(eta)) = (
+            target["temperature"].as_u64(),
+            target["etaSeconds"].as_u64(),
+        ) {
+            let id = match target["kind"].as_str() {
+                Some("safetyTemp") => "status-trend-safety",
+                _ => "status-trend-breakpoint",
+            };
+            let eta = format_uptime(eta);
+            trend = format!(
+                "{}, {}",
+                trend,
+                tr_with(
+                    id,
+                    &[("temperature", temperature.into()), ("eta", eta.into())]
+                )
+            );
+        }
+        row(&mut out, &indented("status-trend"), trend);
+    }
+    if let Some(aggregation) = reply["sensorAggregation"].as_str() {
+        row(
+            &mut out,
+            &indented("status-sensors"),
+            tr_with(
+                "status-sensors-value",
+                &[("aggregation", aggregation.into())],
+            ),
+        );
+    }
+    if let Some(battery) = reply["batteryTemperature"].as_f64() {
+        row(
+            &mut out,
+            &indented("status-battery"),
+            format!("{:.1}°C", battery),
+        );
+    }
+    if let Some(quiet) = reply.get("quietHours").filter(|q| !q.is_null()) {
+        row(
+            &mut out,
+            &tr("status-quiet-hours"),
+            tr_with(
+                "status-quiet-hours-value",
+                &[
+                    ("start", plain(&quiet["start"]).into()),
+                    ("end", plain(&quiet["end"]).into()),
+                    ("speed", plain(&quiet["maxSpeed"]).into()),
+                ],
+            ),
+        );
+    }
+    let alerts: Vec<&str> = reply["alerts"]
+        .as_array()
+        .map(|alerts| alerts.iter().filter_map(|a| a.as_str()).collect())
+        .unwrap_or_default();
+    if !alerts.is_empty() {
+        row(
+            &mut out,
+            &tr("status-alerts"),
+            paint(&alerts.join(", "), Color::Red, color),
+        );
+    }
+    if reply["degraded"].as_bool() == Some(true) {
+        row(
+            &mut out,
+            &tr("status-degraded"),
+            paint(&reply["capabilities"].to_string(), Color::Yellow, color),
+        );
+    }
+    if reply["monitorOnly"].as_bool() == Some(true) {
+        row(
+            &mut out,
+            &tr("status-fan-control"),
+            paint(&tr("status-monitor-only"), Color::Yellow, color),
+        );
+    }
+    if let Some(reported) = reply["dutyDrift"].as_u64() {
+        row(
+            &mut out,
+            &tr("status-duty-drift"),
+            paint(
+                &tr_with("status-duty-drift-value", &[("duty", reported.into())]),
+                Color::Yellow,
+                color,
+            ),
+        );
+    }
+    if let Some(ac) = reply["acConnected"].as_bool() {
+        let mut source = tr(if ac {
+            "status-power-ac"
+        } else {
+            "status-power-battery"
+        });
+        if let Some(state) = reply["chargeState"].as_str().filter(|_| ac) {
+            source = format!("{}, {}", source, charge_state_label(state));
+        }
+        let mut power = match reply["batteryPercent"].as_u64() {
+            Some(charge) => tr_with(
+                "status-power-battery-at",
+                &[("source", source.into()), ("percent", charge.into())],
+            ),
+            None => source,
+        };
+        if let Some(watts) = reply["batteryDischargeWatts"].as_f64().filter(|w| *w > 0.0) {
+            power = format!("{}, {:.1} W", power, watts);
+        }
+        row(&mut out, &tr("status-power"), power);
+    }
+    if let Some(platform) = reply["platform"].as_str() {
+        row(&mut out, &tr("status-platform"), platform);
+    }
+    if let Some(uptime) = reply["uptimeSeconds"].as_u64() {
+        row(&mut out, &tr("status-uptime"), format_uptime(uptime));
+    }
+
+    out.push(String::new());
+    out.push(tr("status-configuration"));
+    row(
+        &mut out,
+        &indented("status-default-strategy"),
+        plain(&config["defaultStrategy"]),
+    );
+    let discharging = plain(&config["strategyOnDischarging"]);
+    row(
+        &mut out,
+        &indented("status-on-battery"),
+        if discharging.is_empty() {
+            tr("status-default")
+        } else {
+            discharging
+        },
+    );
+    if let Some(strategies) = config["strategies"].as_object() {
+        let mut names: Vec<&String> = strategies.keys().collect();
+        names.sort();
+        row(
+            &mut out,
+            &indented("status-strategies"),
+            names
+                .iter()
+                .map(|s| s.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+        );
+    }
+
+    out.join("\n")
+}
+
+const SPARK_LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+/// Most columns a history graph takes; longer histories are bucketed.
+const GRAPH_WIDTH: usize = 50;
+/// Smallest temperature range a graph spans, so sensor noise stays flat.
+const GRAPH_MIN_TEMP_SPAN: f64 = 5.0;
+
+/// One block character per value, scaled from `low` (lowest block) to `high`.
+pub fn sparkline(values: &[f64], low: f64, high: f64) -> String {
+    let span = (high - low).max(f64::EPSILON);
+    let top = (SPARK_LEVELS.len() - 1) as f64;
+    values
+        .iter()
+        .map(|v| SPARK_LEVELS[((v - low) / span * top).round().clamp(0.0, top) as usize])
+        .collect()
+}
+
+/// Shrinks `values` to at most `width` by keeping the highest value of each
+/// bucket, so short spikes stay visible.
+fn downsample(values: &[f64], width: usize) -> Vec<f64> {
+    if values.len() <= width {
+        return values.to_vec();
+    }
+    (0..width)
+        .map(|i| {
+            let start = i * values.len() / width;
+            let end = (i + 1) * values.len() / width;
+            values[start..end.max(start + 1)]
+                .iter()
+                .copied()
+                .fold(f64::MIN, f64::max)
+        })
+        .collect()
+}
+
+fn min_max(values: &[f64]) -> (f64, f64) {
+    values
+        .iter()
+        .fold((f64::MAX, f64::MIN), |(lo, hi), &v| (lo.min(v), hi.max(v)))
+}
+
+/// Temperature and duty sparklines for the samples of a `history` reply,
+/// covering the last `seconds`.
+pub fn render_history_graphs(history: &[Value], seconds: u64) -> String {
+    let temps: Vec<f64> = history
+        .iter()
+        .filter_map(|e| e["temperature"].as_f64())
+        .collect();
+    let duties: Vec<f64> = history.iter().filter_map(|e| e["speed"].as_f64()).collect();
+    let mut out = vec![format!("History (last {}s):", seconds)];
+    if temps.is_empty() {
+        out.push("  no samples yet".to_string());
+        return out.join("\n");
+    }
+
+    let (coolest, hottest) = min_max(&temps);
+    let low = coolest.floor();
+    let high = hottest.ceil().max(low + GRAPH_MIN_TEMP_SPAN);
+    out.push(format!(
+        "  Temp  {}  {:.1}-{:.1}°C",
+        sparkline(&downsample(&temps, GRAPH_WIDTH), low, high),
+        coolest,
+        hottest
+    ));
+    let (slowest, fastest) = min_max(&duties);
+    out.push(format!(
+        "  Duty  {}  {}-{}%",
+        sparkline(&downsample(&duties, GRAPH_WIDTH), 0.0, 100.0),
+        slowest,
+        fastest
+    ));
+    out.join("\n")
+}
+
+/// Hardware details from `print platform`, also shown by `sanity-check`.
+pub fn render_platform(platform: &Value) -> String {
+    let or_unknown = |value: &Value| {
+        if value.is_null() {
+            "unknown".to_string()
+        } else {
+            plain(value)
+        }
+    };
+    let mut out = Vec::new();
+    row(&mut out, "Platform", plain(&platform["platform"]));
+    row(
+        &mut out,
+        "EC driver",
+        match &platform["ecDriver"] {
+            Value::Null => "none (degraded mode)".to_string(),
+            driver => plain(driver),
+        },
+    );
+    row(&mut out, "EC version", or_unknown(&platform["ecVersion"]));
+    row(&mut out, "Fans", plain(&platform["fanCount"]));
+    row(
+        &mut out,
+        "Battery sensor",
+        match platform["batterySensorIndex"].as_u64() {
+            Some(i) if platform["batteryExcluded"].as_bool() == Some(true) => {
+                format!("ec{} (excluded)", i)
+            }
+            Some(i) => format!("ec{}", i),
+            None => "unknown".to_string(),
+        },
+    );
+    let layout = &platform["ecLayout"];
+    row(
+        &mut out,
+        "EC layout",
+        format!(
+            "temperatures {:#04x} x{}, fans {:#04x}",
+            layout["temperatureOffset"].as_u64().unwrap_or_default(),
+            layout["temperatureSensorCount"]
+                .as_u64()
+                .unwrap_or_default(),
+            layout["fanOffset"].as_u64().unwrap_or_default()
+        ),
+    );
+    if let Some(sensors) = platform["sensors"].as_array() {
+        out.push(render_sensors(sensors));
+    }
+    out.join("\n")
+}
+
+fn render_sensors(sensors: &[Value]) -> String {
+    let mut out = vec!["Sensors:".to_string()];
+    for sensor in sensors {
+        let mut label = plain(&sensor["label"]);
+        if let Some(name) = sensor["name"].as_str() {
+            label = format!("{} {}", label, name);
+        }
+        let marks: Vec<&str> = ["battery", "excluded"]
+            .into_iter()
+            .filter(|key| sensor[*key].as_bool() == Some(true))
+            .collect();
+        let marks = if marks.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", marks.join(", "))
+        };
+        out.push(format!(
+            "  {:<22}{:.1}°C{}",
+            label,
+            sensor["celsius"].as_f64().unwrap_or_default(),
+            marks
+        ));
+    }
+    out.join("\n")
+}
+
+/// Unix seconds as a local date and time.
+fn local_time(timestamp: &Value) -> String {
+    timestamp
+        .as_i64()
+        .and_then(|t| chrono::DateTime::from_timestamp(t, 0))
+        .map(|t| {
+            t.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        })
+        .unwrap_or_else(|| "-".to_string())
+}
+
+fn job_line(job: &Value) -> String {
+    format!(
+        "  #{:<4}{:<12}{:<11}started {}",
+        plain(&job["id"]),
+        plain(&job["kind"]),
+        plain(&job["state"]),
+        local_time(&job["startedAt"])
+    )
+}
+
+fn render_jobs(jobs: &[Value]) -> String {
+    if jobs.is_empty() {
+        return "No jobs".to_string();
+    }
+    let mut out = vec!["Jobs (oldest first):".to_string()];
+    out.extend(jobs.iter().map(job_line));
+    out.join("\n")
+}
+
+fn render_job(job: &Value) -> String {
+    let mut out = vec![job_line(job)];
+    for progress in job["progress"].as_array().into_iter().flatten() {
+        out.push(format!("    {}", progress));
+    }
+    if let Some(error) = job.get("error") {
+        out.push(format!("  Error: {}", plain(error)));
+    } else if let Some(result) = job.get("result") {
+        out.push(format!("  Result: {}", result));
+    }
+    out.join("\n")
+}
+
+fn render_reload(path: &str, diff: &Value) -> String {
+    if !has_changes(diff) {
+        return format!("Reloaded {} (no changes)", path);
+    }
+    let mut out = vec![format!("Reloaded {}:", path)];
+    diff_rows(&mut out, diff);
+    out.join("\n")
+}
+
+/// `config diff`: what a reload of `path` would change in the running daemon.
+fn render_daemon_diff(path: &str, diff: &Value) -> String {
+    if !has_changes(diff) {
+        return format!("{} matches the running daemon", path);
+    }
+    let mut out = vec![format!("{} differs from the running daemon:", path)];
+    diff_rows(&mut out, diff);
+    out.join("\n")
+}
+
+fn has_changes(diff: &Value) -> bool {
+    diff["changes"].as_array().is_some_and(|c| !c.is_empty())
+}
+
+/// The rows of a [`crate::config::ConfigDiff`], from the daemon's config to the new one.
+fn diff_rows(out: &mut Vec<String>, diff: &Value) {
+    let changes = diff["changes"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    if let Some(default) = diff.get("defaultStrategy") {
+        row(
+            out,
+            "  Default strategy",
+            format!("{} -> {}", plain(&default["from"]), plain(&default["to"])),
+        );
+    }
+    for (key, label) in [
+        ("strategiesAdded", "  Added strategies"),
+        ("strategiesRemoved", "  Removed strategies"),
+        ("strategiesModified", "  Modified strategies"),
+    ] {
+        let names: Vec<String> = diff[key]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(plain)
+            .collect();
+        if !names.is_empty() {
+            row(out, label, names.join(", "));
+        }
+    }
+    out.extend(changes.iter().map(|change| format!("  {}", plain(change))));
+}
+
+fn render_profiles(reply: &Value, profiles: &[Value]) -> String {
+    let profile = match reply["profile"].as_str() {
+        Some(name) if reply["pinned"].as_bool() == Some(true) => format!("{} (pinned)", name),
+        Some(name) => name.to_string(),
+        None => "none".to_string(),
+    };
+    let mut out = vec![format!("Profile: {}", profile)];
+    if !profiles.is_empty() {
+        let names: Vec<String> = profiles.iter().map(plain).collect();
+        out.push(format!("Available: {}", names.join(", ")));
+    }
+    out.push(format!("Current strategy: {}", plain(&reply["strategy"])));
+    out.join("\n")
+}
+
+fn render_thermal_stats(stats: &Value) -> String {
+    let mut out = vec![format!(
+        "Thermal stats since {}:",
+        local_time(&stats["since"])
+    )];
+    row(
+        &mut out,
+        "  Overheat events",
+        plain(&stats["overheatEvents"]),
+    );
+    row(
+        &mut out,
+        "  Failsafe activations",
+        plain(&stats["failsafeActivations"]),
+    );
+    if let Some(test) = stats.get("lastSelfTest") {
+        let outcome = if test["passed"].as_bool() == Some(true) {
+            "passed"
+        } else {
+            "FAILED"
+        };
+        row(
+            &mut out,
+            "  Last self-test",
+            format!(
+                "{} at {} ({} RPM at {}%)",
+                outcome,
+                local_time(&test["at"]),
+                plain(&test["rpm"]),
+                plain(&test["duty"])
+            ),
+        );
+        row(
+            &mut out,
+            "  Self-test failures",
+            plain(&stats["selfTestFailures"]),
+        );
+    }
+    for (band, seconds) in stats["secondsAbove"].as_object().into_iter().flatten() {
+        row(
+            &mut out,
+            &format!("  Above {}°C", band),
+            format!("{} min", seconds.as_u64().unwrap_or_default() / 60),
+        );
+    }
+    out.join("\n")
+}
+
+fn render_errors(errors: &[Value]) -> String {
+    if errors.is_empty() {
+        return "No errors recorded".to_string();
+    }
+    let mut out = vec!["Recent errors (oldest first):".to_string()];
+    for error in errors {
+        let time = local_time(&error["timestamp"]);
+        let context = match error["context"].as_str() {
+            Some("ecRead") => "EC read",
+            Some("ecWrite") => "EC write",
+            Some("configReload") => "config reload",
+            Some("socket") => "socket",
+            _ => "other",
+        };
+        out.push(format!(
+            "  {}  {:<14}{}",
+            time,
+            context,
+            plain(&error["message"])
+        ));
+    }
+    out.join("\n")
+}
+
+fn render_strategy_list(strategies: &[Value], current: Option<&str>) -> String {
+    // `print list --detailed` sends full definitions instead of bare names
+    let mut entries: Vec<(String, Option<&str>)> = strategies
+        .iter()
+        .map(|s| match s.get("name") {
+            Some(name) => (plain(name), s["description"].as_str()),
+            None => (plain(s), None),
+        })
+        .collect();
+    entries.sort();
+
+    let mut out = vec!["Strategy list:".to_string()];
+    for (name, description) in entries {
+        let marker = if Some(name.as_str()) == current {
+            "*"
+        } else {
+            " "
+        };
+        match description {
+            Some(description) => out.push(format!("  {} {} - {}", marker, name, description)),
+            None => out.push(format!("  {} {}", marker, name)),
+        }
+    }
+    out.join("\n")
+}
+
+/// `print curve`: the curve the fan follows, with what went into it.
+fn render_resolved_curve(reply: &Value, curve: &[Value]) -> String {
+    let mut out = Vec::new();
+    row(&mut out, &tr("status-strategy"), plain(&reply["name"]));
+    let composed: Vec<String> = reply["compose"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(plain)
+        .collect();
+    if !composed.is_empty() {
+        row(&mut out, "Composes", composed.join(", "));
+    }
+    let sensor_curves: Vec<String> = reply["sensorCurves"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|c| plain(&c["name"]))
+        .collect();
+    if !sensor_curves.is_empty() {
+        row(&mut out, "Sensor curves", sensor_curves.join(", "));
+    }
+    out.push("Resolved curve:".to_string());
+    out.extend(curve_rows(curve));
+    out.join("\n")
+}
+
+fn curve_rows(curve: &[Value]) -> impl Iterator<Item = String> + '_ {
+    curve.iter().map(|point| {
+        format!(
+            "  {:>3}°C  {:>3}%",
+            point["temp"].as_u64().unwrap_or(0),
+            point["speed"].as_u64().unwrap_or(0)
+        )
+    })
+}
+
+fn render_strategy(name: &str, definition: &Value) -> String {
+    let mut out = Vec::new();
+    row(&mut out, &tr("status-strategy"), name);
+    if let Some(description) = definition["description"].as_str() {
+        row(&mut out, "Description", description);
+    }
+    row(
+        &mut out,
+        "Update interval",
+        format!("{}s", definition["updateIntervalSeconds"]),
+    );
+    row(
+        &mut out,
+        "Moving average",
+        format!("{}s", definition["movingAverageInterval"]),
+    );
+    if let Some(hysteresis) = definition["hysteresis"].as_u64().filter(|h| *h > 0) {
+        row(&mut out, "Hysteresis", format!("{}°C", hysteresis));
+    }
+    if let Some(decay) = definition["speedDecaySeconds"].as_u64().filter(|d| *d > 0) {
+        row(&mut out, "Speed decay", format!("{}s per %", decay));
+    }
+
+    out.push("Speed curve:".to_string());
+    if let Some(curve) = definition["speedCurve"].as_array() {
+        out.extend(curve_rows(curve));
+    }
+    out.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_strategy_list_marks_current() {
+        let reply = json!({
+            "status": "success",
+            "strategies": ["lazy", "agile"],
+            "current": "lazy"
+        });
+        assert_eq!(
+            render_natural(&reply, false),
+            "Strategy list:\n    agile\n  * lazy"
+        );
+    }
+
+    #[test]
+    fn test_detailed_strategy_list_and_definition() {
+        let lazy = json!({
+            "name": "lazy",
+            "description": "Quiet until warm",
+            "updateIntervalSeconds": 5,
+            "movingAverageInterval": 30,
+            "hysteresis": 0,
+            "speedCurve": [{"temp": 0, "speed": 15}, {"temp": 85, "speed": 100}]
+        });
+        let reply = json!({
+            "status": "success",
+            "strategies": [lazy, {"name": "agile"}],
+            "current": "lazy"
+        });
+        assert_eq!(
+            render_natural(&reply, false),
+            "Strategy list:\n    agile\n  * lazy - Quiet until warm"
+        );
+
+        let reply = json!({"status": "success", "name": "lazy", "definition": lazy});
+        let rendered = render_natural(&reply, false);
+        assert!(rendered.contains("Description:            Quiet until warm"));
+        assert!(rendered.contains("Moving average:         30s"));
+        assert!(!rendered.contains("Hysteresis"));
+        assert!(rendered.ends_with("Speed curve:\n    0°C   15%\n   85°C  100%"));
+    }
+
+    #[test]
+    fn test_status_table_includes_temperatures_and_configuration() {
+        let reply = json!({
+            "status": "success",
+            "strategy": "lazy",
+            "default": true,
+            "speed": "30",
+            "temperature": "52",
+            "movingAverageTemperature": "50.26",
+            "movingAverageSamples": 12,
+            "movingAverageWindow": 30,
+            "effectiveTemperature": "50.83",
+            "trend": {
+                "slopePerMinute": 1.5,
+                "target": {"kind": "breakpoint", "temperature": 60, "etaSeconds": 380}
+            },
+            "active": true,
+            "mode": "managed",
+            "degraded": false,
+            "quietHours": null,
+            "bias": 0,
+            "acConnected": false,
+            "batteryPercent": 54,
+            "chargeState": "discharging",
+            "platform": "Framework13Amd7080",
+            "uptimeSeconds": 7_500,
+            "lastStrategyChange": 1_700_000_000,
+            "configuration": {
+                "defaultStrategy": "lazy",
+                "strategyOnDischarging": "",
+                "strategies": {"lazy": {}, "agile": {}}
+            }
+        });
+        let rendered = render_natural(&reply, false);
+        assert!(rendered.contains("Strategy:               lazy (default)"));
+        assert!(rendered.contains("  Since:                2023-11-"));
+        assert!(rendered.contains("Power:                  battery, battery at 54%"));
+        assert!(rendered.contains("Platform:               Framework13Amd7080"));
+        assert!(rendered.contains("Mode:                   managed"));
+        assert!(rendered.contains("Uptime:                 2h 5m"));
+        assert!(rendered.contains("Temperature:            52.0°C"));
+        assert!(rendered.contains("  Moving average:       50.3°C (12/30 samples)"));
+        assert!(rendered.contains("  Trend:                +1.5°C/min, curve point 60°C in 6m 20s"));
+        assert!(rendered.contains("  Strategies:           agile, lazy"));
+        assert!(!rendered.contains("Speed bias"));
+
+        let mut reply = reply;
+        reply["acConnected"] = json!(true);
+        reply["chargeState"] = json!("chargeLimited");
+        let rendered = render_natural(&reply, false);
+        assert!(rendered.contains("Power:                  AC, charge-limited, battery at 54%"));
+    }
+
+    #[test]
+    fn test_history_graphs_scale_and_bucket_samples() {
+        assert_eq!(sparkline(&[0.0, 50.0, 100.0, 120.0], 0.0, 100.0), "▁▅██");
+
+        let history: Vec<Value> = (0..100)
+            .map(|i| json!({"temperature": 50.0 + (i / 10) as f64, "speed": i, "active": true}))
+            .collect();
+        let rendered = render_history_graphs(&history, 100);
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(lines[0], "History (last 100s):");
+        assert!(lines[1].ends_with("  50.0-59.0°C"));
+        assert!(lines[1].starts_with("  Temp  ▁"));
+        assert!(lines[2].ends_with("█  0-99%"));
+        assert_eq!(
+            lines[2].chars().count(),
+            "  Duty  ".len() + GRAPH_WIDTH + "  0-99%".len()
+        );
+
+        let empty = render_history_graphs(&[], 60);
+        assert!(empty.ends_with("no samples yet"));
+    }
+
+    #[test]
+    fn test_sensors_mark_battery() {
+        let reply = json!({
+            "status": "success",
+            "sensors": [
+                {"label": "ec0", "celsius": 45.0, "battery": false},
+                {"label": "ec3", "celsius": 38.0, "battery": true},
+                {"label": "ec4", "name": "peci", "celsius": 90.0, "battery": false, "excluded": true}
+            ],
+            "batteryTemperature": 38.0
+        });
+        assert_eq!(
+            render_natural(&reply, false),
+            "Sensors:\n  ec0                   45.0°C\n  ec3                   38.0°C (battery)\n  \
+             ec4 peci              90.0°C (excluded)"
+        );
+    }
+
+    #[test]
+    fn test_platform_details() {
+        let reply = json!({
+            "status": "success",
+            "platform": {
+                "platform": "Some(IntelGen13)",
+                "ecDriver": "Portio",
+                "ecVersion": null,
+                "fanCount": 1,
+                "batterySensorIndex": 3,
+                "batteryExcluded": true,
+                "sensors": [],
+                "ecLayout": {"temperatureOffset": 0, "temperatureSensorCount": 15, "fanOffset": 16},
+                "capabilities": {}
+            }
+        });
+        let rendered = render_natural(&reply, false);
+        assert!(rendered.contains("EC driver:              Portio"));
+        assert!(rendered.contains("EC version:             unknown"));
+        assert!(rendered.contains("Battery sensor:         ec3 (excluded)"));
+        assert!(rendered.contains("EC layout:              temperatures 0x00 x15, fans 0x10"));
+    }
+
+    #[test]
+    fn test_unknown_reply_falls_back_to_json() {
+        let reply = json!({"status": "success", "foo": 1, "bar": [2]});
+        let rendered = render_natural(&reply, false);
+        assert!(rendered.contains("\"foo\": 1"));
+        assert!(!rendered.contains("status"));
+    }
+
+    #[test]
+    fn test_single_field_replies() {
+        assert_eq!(
+            render_natural(&json!({"status": "success", "speed": "40"}), false),
+            "Fan speed: 40%"
+        );
+        assert_eq!(
+            render_natural(&json!({"status": "success", "bias": -5}), false),
+            "Speed bias: -5%"
+        );
+        assert_eq!(
+            render_natural(
+                &json!({"status": "success", "batteryExcluded": false}),
+                false
+            ),
+            "Battery sensor included in the control temperature"
+        );
+        assert_eq!(
+            render_natural(&json!({"status": "success", "strategy": "lazy"}), false),
+            "Current strategy: lazy"
+        );
+        assert_eq!(
+            render_natural(&json!({"status": "success", "rpm": [2400, 2350]}), false),
+            "Fan RPM: 2400, 2350"
+        );
+        assert_eq!(render_natural(&json!({"status": "success"}), false), "OK");
+    }
+
+    #[test]
+    fn test_errors_list() {
+        let empty = json!({"status": "success", "errors": []});
+        assert_eq!(render_natural(&empty, false), "No errors recorded");
+
+        let reply = json!({
+            "status": "success",
+            "errors": [{
+                "timestamp": 1_700_000_000,
+                "context": "configReload",
+                "message": "Config error: Invalid JSON"
+            }]
+        });
+        let rendered = render_natural(&reply, false);
+        assert!(rendered.starts_with("Recent errors (oldest first):\n  2023-11-"));
+        assert!(rendered.ends_with("  config reload Config error: Invalid JSON"));
+    }
+
+    #[test]
+    fn test_reload_lists_changes() {
+        let reply = json!({
+            "status": "success",
+            "path": "/etc/fw-fanctrl/config.json",
+            "diff": {
+                "strategiesAdded": [],
+                "strategiesRemoved": [],
+                "strategiesModified": ["lazy"],
+                "defaultStrategy": {"from": "lazy", "to": "quiet"},
+                "changes": [
+                    "defaultStrategy: \"lazy\" -> \"quiet\"",
+                    "strategies.lazy.hysteresis: 0 -> 2"
+                ]
+            }
+        });
+        assert_eq!(
+            render_natural(&reply, false),
+            [
+                "Reloaded /etc/fw-fanctrl/config.json:",
+                "  Default strategy:     lazy -> quiet",
+                "  Modified strategies:  lazy",
+                "  defaultStrategy: \"lazy\" -> \"quiet\"",
+                "  strategies.lazy.hysteresis: 0 -> 2",
+            ]
+            .join("\n")
+        );
+        let unchanged = json!({
+            "status": "success",
+            "path": "/tmp/a.json",
+            "diff": {"changes": []}
+        });
+        assert_eq!(
+            render_natural(&unchanged, false),
+            "Reloaded /tmp/a.json (no changes)"
+        );
+    }
+
+    #[test]
+    fn test_config_diff_against_daemon() {
+        let reply = json!({
+            "status": "success",
+            "path": "/etc/fw-fanctrl/config.json",
+            "daemonDiff": {
+                "strategiesAdded": ["quiet"],
+                "changes": ["strategies.quiet: added"]
+            }
+        });
+        assert_eq!(
+            render_natural(&reply, false),
+            "/etc/fw-fanctrl/config.json differs from the running daemon:\n  \
+             Added strategies:     quiet\n  \
+             strategies.quiet: added"
+        );
+        let unchanged =
+            json!({"status": "success", "path": "/tmp/a.json", "daemonDiff": {"changes": []}});
+        assert_eq!(
+            render_natural(&unchanged, false),
+            "/tmp/a.json matches the running daemon"
+        );
+    }
+
+    #[test]
+    fn test_profiles() {
+        let reply = json!({
+            "status": "success",
+            "profile": "docked",
+            "pinned": true,
+            "profiles": ["docked", "mobile"],
+            "strategy": "deaf"
+        });
+        assert_eq!(
+            render_natural(&reply, false),
+            "Profile: docked (pinned)\nAvailable: docked, mobile\nCurrent strategy: deaf"
+        );
+    }
+
+    #[test]
+    fn test_thermal_stats() {
+        let reply = json!({
+            "status": "success",
+            "stats": {
+                "since": 1_700_000_000,
+                "overheatEvents": 3,
+                "failsafeActivations": 1,
+                "secondsAbove": {"70": 7250, "90": 59},
+                "lastSelfTest": {"at": 1_700_000_000, "duty": 30, "rpm": 0, "passed": false},
+                "selfTestFailures": 2
+            }
+        });
+        let rendered = render_natural(&reply, false);
+        assert!(rendered.starts_with("Thermal stats since 2023-11-"));
+        assert!(rendered.contains("\n  Overheat events:      3\n"));
+        assert!(rendered.contains("  Last self-test:       FAILED at 2023-11-"));
+        assert!(rendered.contains("(0 RPM at 30%)\n  Self-test failures:   2\n"));
+        assert!(
+            rendered.ends_with("  Above 70°C:           120 min\n  Above 90°C:           0 min")
+        );
+    }
+
+    #[test]
+    fn test_job_status() {
+        let reply = json!({
+            "status": "success",
+            "job": {
+                "id": 3,
+                "kind": "fan-test",
+                "state": "failed",
+                "startedAt": 1_700_000_000,
+                "progress": [{"speed": 25, "rpm": 1800}],
+                "error": "EC error: busy"
+            }
+        });
+        let rendered = render_natural(&reply, false);
+        assert!(rendered.starts_with("  #3   fan-test    failed     started 2023-11-"));
+        assert!(rendered.ends_with("\n    {\"rpm\":1800,\"speed\":25}\n  Error: EC error: busy"));
+        assert_eq!(
+            render_natural(&json!({"status": "success", "jobs": []}), false),
+            "No jobs"
+        );
+    }
+
+    #[test]
+    fn test_current_strategy_shows_pending_switch() {
+        let reply = json!({
+            "status": "success",
+            "strategy": "performance",
+            "default": true,
+            "pending": {"strategy": "lazy", "inSeconds": 25},
+            "lastChangeReason": "quietHours"
+        });
+        assert_eq!(
+            render_natural(&reply, false),
+            "Current strategy: performance (default)\n\
+             Switching to lazy in 25s (power source changed)\n\
+             Last change: quiet hours"
+        );
+    }
+
+    #[test]
+    fn test_temperature_zones_follow_curve() {
+        let curve = json!([
+            {"temp": 0, "speed": 0},
+            {"temp": 50, "speed": 0},
+            {"temp": 70, "speed": 60},
+            {"temp": 85, "speed": 100}
+        ]);
+        let curve = curve.as_array().unwrap();
+        assert_eq!(temperature_zone(curve, 45.0), Color::Green);
+        assert_eq!(temperature_zone(curve, 60.0), Color::Yellow);
+        assert_eq!(temperature_zone(curve, 90.0), Color::Red);
+    }
+
+    #[test]
+    fn test_paint_only_when_enabled() {
+        assert_eq!(paint("hot", Color::Red, false), "hot");
+        assert_eq!(paint("hot", Color::Red, true), "\x1b[31mhot\x1b[0m");
+    }
+}
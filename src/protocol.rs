@@ -0,0 +1,392 @@
+use std::io::{BufRead, Read, Write};
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::command;
+use crate::config::Strategy;
+use crate::error::{Error, Result};
+
+pub const SOCKET_FOLDER_PATH: &str = "/run/fw-fanctrl";
+pub const COMMANDS_SOCKET_FILE_PATH: &str = "/run/fw-fanctrl/.fw-fanctrl.commands.sock";
+/// Read-only socket served with `run --query-socket`, for status bars and the like.
+pub const QUERY_SOCKET_FILE_PATH: &str = "/run/fw-fanctrl/.fw-fanctrl.query.sock";
+
+/// Upper bound on a single newline-delimited frame, in either direction.
+pub const MAX_FRAME_SIZE: usize = 1024 * 1024;
+/// Upper bound on a command frame sent to the daemon.
+pub const MAX_REQUEST_SIZE: usize = 64 * 1024;
+/// Bumped whenever a command or reply changes incompatibly.
+pub const PROTOCOL_VERSION: u32 = 1;
+/// How long `selftest` holds each duty before the fan speed is read.
+pub const STEP_SETTLE: Duration = Duration::from_secs(2);
+
+/// A positional argument of a socket command, as listed by `capabilities`.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ArgumentSpec {
+    pub name: &'static str,
+    /// `string`, `integer` or `enum`.
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub required: bool,
+    /// Accepted values of an `enum` argument.
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub values: &'static [&'static str],
+}
+
+/// A socket command, as listed by `capabilities`.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub arguments: &'static [ArgumentSpec],
+}
+
+const fn arg(name: &'static str, kind: &'static str, required: bool) -> ArgumentSpec {
+    ArgumentSpec {
+        name,
+        kind,
+        required,
+        values: &[],
+    }
+}
+
+const fn choice(
+    name: &'static str,
+    required: bool,
+    values: &'static [&'static str],
+) -> ArgumentSpec {
+    ArgumentSpec {
+        name,
+        kind: "enum",
+        required,
+        values,
+    }
+}
+
+/// Selections `print` accepts.
+pub const PRINT_SELECTIONS: &[&str] = &[
+    "all", "active", "current", "list", "speed", "platform", "sensors", "rpm", "errors", "stats",
+    "strategy", "config", "curve",
+];
+
+/// Every command the daemon accepts, so clients can adapt to older and newer daemons.
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        name: "use",
+        description: "Switch to a strategy until reset",
+        arguments: &[arg("strategy", "string", true)],
+    },
+    CommandSpec {
+        name: "toggle",
+        description: "Switch between two strategies",
+        arguments: &[
+            arg("strategy", "string", true),
+            arg("other", "string", true),
+        ],
+    },
+    CommandSpec {
+        name: "cycle",
+        description: "Advance to the next strategy in strategyCycle",
+        arguments: &[],
+    },
+    CommandSpec {
+        name: "reset",
+        description: "Return to the default strategy and clear the speed bias",
+        arguments: &[],
+    },
+    CommandSpec {
+        name: "reload",
+        description: "Reload the config file, or switch to another one",
+        arguments: &[arg("path", "string", false)],
+    },
+    CommandSpec {
+        name: "curve",
+        description: "Replace a strategy's speed curve until reload",
+        arguments: &[
+            arg("strategy", "string", true),
+            arg("points", "string", true),
+        ],
+    },
+    CommandSpec {
+        name: "tune",
+        description: "Change a numeric strategy setting until reload",
+        arguments: &[
+            arg("strategy", "string", true),
+            choice("field", true, Strategy::TUNABLE),
+            arg("value", "integer", true),
+        ],
+    },
+    CommandSpec {
+        name: "adjust",
+        description: "Add a percentage bias to the curve output until reset",
+        arguments: &[arg("bias", "integer", true)],
+    },
+    CommandSpec {
+        name: "battery-sensor",
+        description: "Leave the battery sensor out of the control temperature or take it back in",
+        arguments: &[choice("action", true, &["exclude", "include"])],
+    },
+    CommandSpec {
+        name: "clear-history",
+        description: "Forget the readings behind the moving average, as after startup",
+        arguments: &[],
+    },
+    CommandSpec {
+        name: "pause",
+        description: "Hand the fan back to the EC",
+        arguments: &[],
+    },
+    CommandSpec {
+        name: "resume",
+        description: "Take over fan control again",
+        arguments: &[],
+    },
+    CommandSpec {
+        name: "print",
+        description: "Report status; `list detailed` includes full definitions, `curve` a strategy's resolved curve",
+        arguments: &[
+            choice("selection", false, PRINT_SELECTIONS),
+            arg("argument", "string", false),
+        ],
+    },
+    CommandSpec {
+        name: "history",
+        description: "Samples recorded in the last seconds (default: all)",
+        arguments: &[arg("seconds", "integer", false)],
+    },
+    CommandSpec {
+        name: "selftest",
+        description: "Step the fan through its duties, streaming progress frames",
+        arguments: &[],
+    },
+    CommandSpec {
+        name: "jobs",
+        description: "List, inspect, cancel or start long-running jobs",
+        arguments: &[
+            choice("action", false, &["list", "status", "cancel", "start"]),
+            arg("id or kind", "string", false),
+        ],
+    },
+    CommandSpec {
+        name: "profile",
+        description: "Show profiles, switch to one until `profile auto`, or follow triggers again",
+        arguments: &[
+            choice("action", false, &["list", "use", "auto"]),
+            arg("profile", "string", false),
+        ],
+    },
+    CommandSpec {
+        name: "version",
+        description: "Daemon and protocol version",
+        arguments: &[],
+    },
+    CommandSpec {
+        name: "capabilities",
+        description: "This list",
+        arguments: &[],
+    },
+    CommandSpec {
+        name: "shutdown",
+        description: "Exit as on SIGTERM (root only)",
+        arguments: &[],
+    },
+    CommandSpec {
+        name: "restart-auto",
+        description: "Re-enable EC auto fan control and exit (root only)",
+        arguments: &[],
+    },
+];
+
+/// Numeric `major.minor.patch` parts, ignoring any pre-release suffix.
+fn semver_parts(version: &str) -> Vec<u64> {
+    version
+        .split(['-', '+'])
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
+
+/// Compares a `version` reply with this build, describing the mismatch if any.
+///
+/// Mismatches are common right after a package upgrade, before the service
+/// has been restarted.
+pub fn version_mismatch(reply: &serde_json::Value) -> Option<String> {
+    let client = env!("CARGO_PKG_VERSION");
+    if reply["status"] != "success" {
+        return Some(format!(
+            "The running daemon predates fw-fanctrl {}; restart the service to finish the upgrade",
+            client
+        ));
+    }
+
+    let daemon = reply["version"].as_str().unwrap_or("unknown");
+    let protocol = reply["protocolVersion"].as_u64();
+    if daemon == client && protocol == Some(PROTOCOL_VERSION as u64) {
+        return None;
+    }
+
+    let relation = if semver_parts(client) > semver_parts(daemon) {
+        "newer"
+    } else {
+        "older"
+    };
+    let mut message = format!(
+        "fw-fanctrl {} is {} than the running daemon ({})",
+        client, relation, daemon
+    );
+    if protocol != Some(PROTOCOL_VERSION as u64) {
+        message.push_str(&format!(
+            ", which speaks protocol {} instead of {}; commands may fail",
+            protocol.map_or("unknown".to_string(), |p| p.to_string()),
+            PROTOCOL_VERSION
+        ));
+    }
+    if relation == "newer" {
+        message.push_str("; restart the service to finish the upgrade");
+    }
+    Some(message)
+}
+
+/// Whether `command` changes the daemon's state and so belongs in the audit log.
+/// Decided on the parsed command, so quoting can't hide its name; a command
+/// that doesn't parse changes nothing.
+pub fn is_state_changing(command: &str) -> bool {
+    command::parse(command).is_ok_and(|parsed| parsed.is_state_changing())
+}
+
+/// Checks a frame read up to `\n` or EOF against `limit` and strips the newline.
+pub(crate) fn finish_frame(
+    mut buffer: Vec<u8>,
+    bytes_read: usize,
+    limit: usize,
+) -> Result<Option<Vec<u8>>> {
+    if bytes_read == 0 {
+        return Ok(None);
+    }
+
+    if buffer.len() > limit {
+        return Err(Error::Socket(format!("Frame exceeds {} bytes", limit)));
+    }
+
+    if buffer.last() == Some(&b'\n') {
+        buffer.pop();
+    }
+
+    Ok(Some(buffer))
+}
+
+pub(crate) fn decode_frame(frame: Vec<u8>) -> Result<String> {
+    String::from_utf8(frame).map_err(|_| Error::Socket("Frame is not valid UTF-8".into()))
+}
+
+/// Reads one newline-delimited frame, looping over partial reads.
+///
+/// A frame ends at `\n` or at EOF; `Ok(None)` means the peer closed the
+/// connection without sending anything.
+pub fn read_frame<R: BufRead>(reader: &mut R) -> Result<Option<String>> {
+    let mut buffer = Vec::new();
+    let bytes_read = reader
+        .take(MAX_FRAME_SIZE as u64 + 1)
+        .read_until(b'\n', &mut buffer)
+        .map_err(|e| Error::Socket(format!("Failed to read from socket: {}", e)))?;
+
+    finish_frame(buffer, bytes_read, MAX_FRAME_SIZE)?
+        .map(decode_frame)
+        .transpose()
+}
+
+/// Writes `payload` as a single newline-terminated frame.
+pub fn write_frame<W: Write>(writer: &mut W, payload: &str) -> Result<()> {
+    writer
+        .write_all(payload.as_bytes())
+        .and_then(|_| writer.write_all(b"\n"))
+        .and_then(|_| writer.flush())
+        .map_err(|e| Error::Socket(format!("Failed to write to socket: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufReader, Cursor};
+
+    #[test]
+    fn test_state_changing_commands() {
+        assert!(is_state_changing("use lazy"));
+        assert!(is_state_changing("jobs cancel 3"));
+        assert!(!is_state_changing("jobs list"));
+        assert!(is_state_changing("profile use docked"));
+        assert!(!is_state_changing("profile"));
+        assert!(!is_state_changing("print all"));
+        assert!(!is_state_changing(""));
+    }
+
+    #[test]
+    fn test_quoted_command_names_are_state_changing() {
+        assert!(is_state_changing("\"pause\""));
+        assert!(is_state_changing("'reload' /tmp/x.json"));
+        assert!(is_state_changing("'profile' \"use\" docked"));
+        assert!(!is_state_changing("'print' all"));
+    }
+
+    #[test]
+    fn test_version_mismatch() {
+        let reply = |version: &str, protocol: u32| {
+            serde_json::json!({
+                "status": "success",
+                "version": version,
+                "protocolVersion": protocol
+            })
+        };
+
+        let newer = version_mismatch(&reply("0.0.1", PROTOCOL_VERSION)).unwrap();
+        assert!(newer.contains("is newer than the running daemon (0.0.1)"));
+        assert!(newer.contains("restart the service"));
+
+        let older = version_mismatch(&reply("99.0.0", PROTOCOL_VERSION + 1)).unwrap();
+        assert!(older.contains("is older than the running daemon (99.0.0)"));
+        assert!(older.contains(&format!("protocol {}", PROTOCOL_VERSION + 1)));
+
+        let legacy = serde_json::json!({"status": "error", "reason": "Unknown command: version"});
+        assert!(version_mismatch(&legacy).unwrap().contains("predates"));
+    }
+
+    #[test]
+    fn test_read_frame_splits_on_newline() {
+        let mut reader = Cursor::new(b"print all\nreset\n".to_vec());
+        assert_eq!(
+            read_frame(&mut reader).unwrap().as_deref(),
+            Some("print all")
+        );
+        assert_eq!(read_frame(&mut reader).unwrap().as_deref(), Some("reset"));
+        assert_eq!(read_frame(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn test_read_frame_accepts_eof_terminated_frame() {
+        let mut reader = Cursor::new(b"pause".to_vec());
+        assert_eq!(read_frame(&mut reader).unwrap().as_deref(), Some("pause"));
+    }
+
+    #[test]
+    fn test_read_frame_larger_than_single_read() {
+        let payload = "x".repeat(64 * 1024);
+        let mut reader = BufReader::with_capacity(16, Cursor::new(format!("{}\n", payload)));
+        assert_eq!(read_frame(&mut reader).unwrap(), Some(payload));
+    }
+
+    #[test]
+    fn test_read_frame_rejects_oversized_frame() {
+        let mut reader = Cursor::new(vec![b'x'; MAX_FRAME_SIZE + 10]);
+        assert!(read_frame(&mut reader).is_err());
+    }
+
+    #[test]
+    fn test_write_frame_appends_newline() {
+        let mut out = Vec::new();
+        write_frame(&mut out, "{\"status\": \"success\"}").unwrap();
+        assert_eq!(out, b"{\"status\": \"success\"}\n");
+    }
+}
@@ -0,0 +1,446 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File};
+use std::io::{LineWriter, Write};
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::hardware::{ChargeState, EcBackend, PlatformInfo};
+
+/// One line of a recording: an EC call and its result.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EcRecord {
+    /// Milliseconds since recording started.
+    pub ms: u64,
+    #[serde(flatten)]
+    pub event: EcEvent,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "camelCase")]
+pub enum EcEvent {
+    /// First line: the machine the session was recorded on.
+    #[serde(rename_all = "camelCase")]
+    Session {
+        platform: String,
+        battery_sensor_index: Option<usize>,
+        driver: String,
+        version: Option<String>,
+    },
+    ReadMemory {
+        offset: u16,
+        length: u16,
+        data: Option<Vec<u8>>,
+    },
+    FanSetDuty {
+        percent: u32,
+        error: Option<String>,
+    },
+    FanDuty {
+        percent: Option<u32>,
+    },
+    Autofanctrl {
+        error: Option<String>,
+    },
+    ChargeRateLimit {
+        rate: f32,
+        error: Option<String>,
+    },
+    AcPresent {
+        /// `None` when reading it failed.
+        present: Option<bool>,
+    },
+    BatteryCharge {
+        percent: Option<u32>,
+    },
+    ChargeState {
+        state: Option<ChargeState>,
+    },
+    BatteryDischarge {
+        watts: Option<f64>,
+    },
+}
+
+struct Recorder {
+    file: LineWriter<File>,
+    /// Set after the first failed write, so a full disk is only reported once.
+    failed: bool,
+}
+
+/// Passes every call through to another backend and appends it, with its result,
+/// to a JSON Lines file that [`ReplayEc`] can play back.
+pub struct RecordingEc {
+    inner: Box<dyn EcBackend>,
+    recorder: Mutex<Recorder>,
+    started: Instant,
+}
+
+impl RecordingEc {
+    /// Starts a recording at `path`, replacing an older one, with `info` as its
+    /// session header.
+    pub fn create(path: &Path, inner: Box<dyn EcBackend>, info: &PlatformInfo) -> Result<Self> {
+        let recording = Self {
+            recorder: Mutex::new(Recorder {
+                file: LineWriter::new(File::create(path)?),
+                failed: false,
+            }),
+            started: Instant::now(),
+            inner,
+        };
+        recording.record(EcEvent::Session {
+            platform: info.platform.clone(),
+            battery_sensor_index: info.battery_sensor_index,
+            driver: info.ec_driver.clone().unwrap_or_default(),
+            version: info.ec_version.clone(),
+        });
+        Ok(recording)
+    }
+
+    fn record(&self, event: EcEvent) {
+        let record = EcRecord {
+            ms: self.started.elapsed().as_millis() as u64,
+            event,
+        };
+        let mut recorder = self.recorder.lock().unwrap_or_else(|e| e.into_inner());
+        let line = serde_json::to_string(&record).unwrap_or_default();
+        if let Err(e) = writeln!(recorder.file, "{}", line) {
+            if !recorder.failed {
+                tracing::warn!("Failed to record EC call: {}", e);
+            }
+            recorder.failed = true;
+        }
+    }
+}
+
+fn error_text<T>(result: &Result<T>) -> Option<String> {
+    result.as_ref().err().map(|e| e.to_string())
+}
+
+impl EcBackend for RecordingEc {
+    fn read_memory(&self, offset: u16, length: u16) -> Option<Vec<u8>> {
+        let data = self.inner.read_memory(offset, length);
+        self.record(EcEvent::ReadMemory {
+            offset,
+            length,
+            data: data.clone(),
+        });
+        data
+    }
+
+    fn fan_set_duty(&self, percent: u32) -> Result<()> {
+        let result = self.inner.fan_set_duty(percent);
+        self.record(EcEvent::FanSetDuty {
+            percent,
+            error: error_text(&result),
+        });
+        result
+    }
+
+    fn fan_duty(&self) -> Option<u32> {
+        let percent = self.inner.fan_duty();
+        self.record(EcEvent::FanDuty { percent });
+        percent
+    }
+
+    fn autofanctrl(&self) -> Result<()> {
+        let result = self.inner.autofanctrl();
+        self.record(EcEvent::Autofanctrl {
+            error: error_text(&result),
+        });
+        result
+    }
+
+    fn set_charge_rate_limit(&self, rate: f32) -> Result<()> {
+        let result = self.inner.set_charge_rate_limit(rate);
+        self.record(EcEvent::ChargeRateLimit {
+            rate,
+            error: error_text(&result),
+        });
+        result
+    }
+
+    fn ac_present(&self) -> Result<bool> {
+        let result = self.inner.ac_present();
+        self.record(EcEvent::AcPresent {
+            present: result.as_ref().ok().copied(),
+        });
+        result
+    }
+
+    fn battery_charge(&self) -> Option<u32> {
+        let percent = self.inner.battery_charge();
+        self.record(EcEvent::BatteryCharge { percent });
+        percent
+    }
+
+    fn charge_state(&self) -> Option<ChargeState> {
+        let state = self.inner.charge_state();
+        self.record(EcEvent::ChargeState { state });
+        state
+    }
+
+    fn battery_discharge_watts(&self) -> Option<f64> {
+        let watts = self.inner.battery_discharge_watts();
+        self.record(EcEvent::BatteryDischarge { watts });
+        watts
+    }
+
+    fn driver(&self) -> String {
+        self.inner.driver()
+    }
+
+    fn version(&self) -> Option<String> {
+        self.inner.version()
+    }
+}
+
+/// Recorded results of one kind of call, handed out in order. The last one
+/// repeats once the others are used up, so the session ends in a steady state.
+struct Track<T> {
+    results: VecDeque<T>,
+}
+
+impl<T: Clone> Track<T> {
+    fn next(&mut self) -> Option<T> {
+        if self.results.len() > 1 {
+            self.results.pop_front()
+        } else {
+            self.results.front().cloned()
+        }
+    }
+}
+
+impl<T> Default for Track<T> {
+    fn default() -> Self {
+        Self {
+            results: VecDeque::new(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct ReplayState {
+    reads: HashMap<(u16, u16), Track<Option<Vec<u8>>>>,
+    duty_writes: Track<(u32, Option<String>)>,
+    /// Duty the daemon wrote where it differs from the recorded write; duty
+    /// reads report it, so readback agrees with the new write.
+    duty_override: Option<u32>,
+    fan_duty: Track<Option<u32>>,
+    autofanctrl: Track<Option<String>>,
+    charge_rate_limit: Track<Option<String>>,
+    ac_present: Track<Option<bool>>,
+    battery_charge: Track<Option<u32>>,
+    charge_state: Track<Option<ChargeState>>,
+    battery_discharge: Track<Option<f64>>,
+}
+
+/// The machine a recording was made on, from its session header.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RecordedSession {
+    pub platform: String,
+    pub battery_sensor_index: Option<usize>,
+}
+
+/// Plays a [`RecordingEc`] session back instead of talking to an EC.
+///
+/// Each call gets the next recorded result of the same kind, reads also by
+/// offset and length, so the daemon sees the same sequence of readings as the
+/// recorded one regardless of timing. Writes return the recorded outcome; only a
+/// duty other than the recorded one changes what is read back.
+pub struct ReplayEc {
+    state: Mutex<ReplayState>,
+    driver: String,
+    version: Option<String>,
+}
+
+impl ReplayEc {
+    pub fn load(path: &Path) -> Result<(Self, RecordedSession)> {
+        let content = fs::read_to_string(path)?;
+        let mut state = ReplayState::default();
+        let mut session = RecordedSession::default();
+        let mut driver = String::new();
+        let mut version = None;
+
+        for (number, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: EcRecord = serde_json::from_str(line).map_err(|e| {
+                Error::Config(format!("{} line {}: {}", path.display(), number + 1, e))
+            })?;
+            match record.event {
+                EcEvent::Session {
+                    platform,
+                    battery_sensor_index,
+                    driver: recorded_driver,
+                    version: recorded_version,
+                } => {
+                    session = RecordedSession {
+                        platform,
+                        battery_sensor_index,
+                    };
+                    driver = recorded_driver;
+                    version = recorded_version;
+                }
+                EcEvent::ReadMemory {
+                    offset,
+                    length,
+                    data,
+                } => state
+                    .reads
+                    .entry((offset, length))
+                    .or_default()
+                    .results
+                    .push_back(data),
+                EcEvent::FanSetDuty { percent, error } => {
+                    state.duty_writes.results.push_back((percent, error))
+                }
+                EcEvent::FanDuty { percent } => state.fan_duty.results.push_back(percent),
+                EcEvent::Autofanctrl { error } => state.autofanctrl.results.push_back(error),
+                EcEvent::ChargeRateLimit { error, .. } => {
+                    state.charge_rate_limit.results.push_back(error)
+                }
+                EcEvent::AcPresent { present } => state.ac_present.results.push_back(present),
+                EcEvent::BatteryCharge { percent } => {
+                    state.battery_charge.results.push_back(percent)
+                }
+                EcEvent::ChargeState { state: recorded } => {
+                    state.charge_state.results.push_back(recorded)
+                }
+                EcEvent::BatteryDischarge { watts } => {
+                    state.battery_discharge.results.push_back(watts)
+                }
+            }
+        }
+
+        let replay = Self {
+            state: Mutex::new(state),
+            driver: format!("replay of {}", driver),
+            version,
+        };
+        Ok((replay, session))
+    }
+
+    fn state(&self) -> MutexGuard<'_, ReplayState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn recorded_outcome(error: Option<Option<String>>) -> Result<()> {
+    match error.flatten() {
+        Some(error) => Err(Error::Ec(error)),
+        None => Ok(()),
+    }
+}
+
+impl EcBackend for ReplayEc {
+    fn read_memory(&self, offset: u16, length: u16) -> Option<Vec<u8>> {
+        self.state()
+            .reads
+            .get_mut(&(offset, length))
+            .and_then(Track::next)
+            .flatten()
+    }
+
+    fn fan_set_duty(&self, percent: u32) -> Result<()> {
+        let mut state = self.state();
+        let (recorded, error) = state.duty_writes.next().unwrap_or((percent, None));
+        if let Some(error) = error {
+            return Err(Error::Ec(error));
+        }
+        state.duty_override = (recorded != percent).then_some(percent);
+        Ok(())
+    }
+
+    fn fan_duty(&self) -> Option<u32> {
+        let mut state = self.state();
+        let recorded = state.fan_duty.next().flatten();
+        state.duty_override.or(recorded)
+    }
+
+    fn autofanctrl(&self) -> Result<()> {
+        let mut state = self.state();
+        state.duty_override = None;
+        recorded_outcome(state.autofanctrl.next())
+    }
+
+    fn set_charge_rate_limit(&self, _rate: f32) -> Result<()> {
+        recorded_outcome(self.state().charge_rate_limit.next())
+    }
+
+    fn ac_present(&self) -> Result<bool> {
+        self.state()
+            .ac_present
+            .next()
+            .flatten()
+            .ok_or_else(|| Error::Ec("AC state not readable in the recording".into()))
+    }
+
+    fn battery_charge(&self) -> Option<u32> {
+        self.state().battery_charge.next().flatten()
+    }
+
+    fn charge_state(&self) -> Option<ChargeState> {
+        self.state().charge_state.next().flatten()
+    }
+
+    fn battery_discharge_watts(&self) -> Option<f64> {
+        self.state().battery_discharge.next().flatten()
+    }
+
+    fn driver(&self) -> String {
+        self.driver.clone()
+    }
+
+    fn version(&self) -> Option<String> {
+        self.version.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SensorAggregation;
+    use crate::hardware::HardwareController;
+    use crate::mock::MockEc;
+
+    #[test]
+    fn test_replay_reproduces_recorded_readings() {
+        let path = std::env::temp_dir().join(format!("fw-fanctrl-ec-{}.jsonl", std::process::id()));
+        let ec = MockEc::new();
+        let live = HardwareController::with_backend(Box::new(ec.clone()), Some(1), "mock");
+        let info = live.platform_info();
+        let recording = RecordingEc::create(&path, Box::new(ec.clone()), &info).unwrap();
+        let hw = HardwareController::with_backend(Box::new(recording), Some(1), "mock");
+
+        let mut recorded = Vec::new();
+        for temps in [[40, 30], [55, 31], [70, 33]] {
+            ec.set_temperatures(&temps);
+            recorded.push(
+                hw.get_aggregated_temperature(SensorAggregation::default())
+                    .unwrap(),
+            );
+            hw.set_fan_speed(50).unwrap();
+        }
+        drop(hw);
+
+        let (replay, session) = ReplayEc::load(&path).unwrap();
+        assert_eq!(session.platform, "mock");
+        assert_eq!(session.battery_sensor_index, Some(1));
+        let hw = HardwareController::with_backend(Box::new(replay), Some(1), session.platform);
+        let replayed: Vec<f64> = (0..4)
+            .map(|_| {
+                hw.get_aggregated_temperature(SensorAggregation::default())
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(replayed[..3], recorded[..]);
+        assert_eq!(replayed[3], recorded[2]);
+        hw.set_fan_speed(80).unwrap();
+        assert_eq!(hw.get_fan_duty().unwrap(), Some(80));
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use landlock::{
+    path_beneath_rules, Access, AccessFs, Ruleset, RulesetAttr, RulesetCreatedAttr, RulesetStatus,
+    ABI,
+};
+use seccompiler::{BpfProgram, SeccompAction, SeccompFilter, TargetArch};
+
+use crate::error::{Error, Result};
+use crate::protocol::SOCKET_FOLDER_PATH;
+
+/// Newest Landlock ABI the rules are written for; older kernels enforce what they support.
+const LANDLOCK_ABI: ABI = ABI::V5;
+
+/// Read-only locations: sensors, power supplies and USB devices in `/sys`, other
+/// processes in `/proc`, power-profiles-daemon's state for profile triggers, and what
+/// libc needs for time zones and name resolution.
+const READ_PATHS: &[&str] = &[
+    "/proc",
+    "/sys",
+    "/etc",
+    "/usr",
+    "/lib",
+    "/lib64",
+    "/dev",
+    "/var/lib/power-profiles-daemon",
+];
+
+/// Writable locations: the socket folder with the EC lock, and the EC device itself.
+const WRITE_PATHS: &[&str] = &[SOCKET_FOLDER_PATH, "/dev/cros_ec", "/dev/null"];
+
+/// Syscalls the daemon never makes once running. They fail with `EPERM` instead of
+/// giving an attacker who gets code execution through the socket a way out; this
+/// is a denylist, so every syscall not named here stays allowed.
+const DENIED_SYSCALLS: &[libc::c_long] = &[
+    libc::SYS_execve,
+    libc::SYS_execveat,
+    libc::SYS_ptrace,
+    libc::SYS_process_vm_readv,
+    libc::SYS_process_vm_writev,
+    libc::SYS_mount,
+    libc::SYS_umount2,
+    libc::SYS_pivot_root,
+    libc::SYS_chroot,
+    libc::SYS_unshare,
+    libc::SYS_setns,
+    libc::SYS_init_module,
+    libc::SYS_finit_module,
+    libc::SYS_delete_module,
+    libc::SYS_kexec_load,
+    libc::SYS_kexec_file_load,
+    libc::SYS_bpf,
+    libc::SYS_perf_event_open,
+    libc::SYS_keyctl,
+    libc::SYS_add_key,
+    libc::SYS_request_key,
+    libc::SYS_reboot,
+    libc::SYS_swapon,
+    libc::SYS_swapoff,
+    libc::SYS_setuid,
+    libc::SYS_setgid,
+    libc::SYS_setreuid,
+    libc::SYS_setregid,
+    libc::SYS_setresuid,
+    libc::SYS_setresgid,
+    libc::SYS_setgroups,
+    libc::SYS_personality,
+    libc::SYS_userfaultfd,
+    libc::SYS_open_by_handle_at,
+];
+
+/// Filesystem and syscall restrictions for the running daemon.
+///
+/// Landlock only restricts the calling thread and threads it starts later, so
+/// [`Sandbox::restrict_thread`] has to run on every runtime thread as it starts,
+/// and [`Sandbox::apply`] on the main thread once initialization is done.
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    read: Vec<PathBuf>,
+    write: Vec<PathBuf>,
+    exec: bool,
+}
+
+impl Sandbox {
+    /// The default rules, plus the folder of `config_path` for `reload`.
+    pub fn new(config_path: &Path) -> Self {
+        let mut read: Vec<PathBuf> = READ_PATHS.iter().map(PathBuf::from).collect();
+        if let Some(folder) = config_path.parent() {
+            read.push(folder.to_path_buf());
+        }
+        Self {
+            read,
+            write: WRITE_PATHS.iter().map(PathBuf::from).collect(),
+            exec: false,
+        }
+    }
+
+    /// Also allows writing below `path`, e.g. the folder of the metrics textfile.
+    pub fn allow_write(mut self, path: &Path) -> Self {
+        self.write.push(path.to_path_buf());
+        self
+    }
+
+    /// Also allows starting programs, for `sensorSources`, alert commands and the
+    /// self-test `onFailure` hook. They inherit the filesystem rules, so they can
+    /// only write where the daemon can.
+    pub fn allow_exec(mut self) -> Self {
+        self.exec = true;
+        self
+    }
+
+    /// Restricts filesystem access of the calling thread with Landlock.
+    ///
+    /// Paths that don't exist are left out, and kernels without Landlock leave the
+    /// thread unrestricted; the returned status tells how much is enforced.
+    pub fn restrict_thread(&self) -> Result<RulesetStatus> {
+        let status = Ruleset::default()
+            .handle_access(AccessFs::from_all(LANDLOCK_ABI))
+            .and_then(|ruleset| ruleset.create())
+            .and_then(|ruleset| {
+                ruleset.add_rules(path_beneath_rules(
+                    existing(&self.read),
+                    AccessFs::from_read(LANDLOCK_ABI),
+                ))
+            })
+            .and_then(|ruleset| {
+                ruleset.add_rules(path_beneath_rules(
+                    existing(&self.write),
+                    AccessFs::from_all(LANDLOCK_ABI),
+                ))
+            })
+            .and_then(|ruleset| ruleset.restrict_self())
+            .map_err(|e| Error::Sandbox(format!("Landlock: {}", e)))?;
+        Ok(status.ruleset)
+    }
+
+    /// Restricts the calling thread with Landlock and installs the syscall filter
+    /// on all threads of the process.
+    pub fn apply(&self) -> Result<()> {
+        match self.restrict_thread()? {
+            RulesetStatus::FullyEnforced => tracing::info!("Landlock filesystem rules enforced"),
+            RulesetStatus::PartiallyEnforced => {
+                tracing::info!("Landlock filesystem rules partially enforced by this kernel")
+            }
+            RulesetStatus::NotEnforced => {
+                tracing::warn!("Landlock is not supported by this kernel; filesystem unrestricted")
+            }
+        }
+
+        let Ok(arch) = TargetArch::try_from(std::env::consts::ARCH) else {
+            tracing::warn!(
+                "No syscall filter for {}; syscalls unrestricted",
+                std::env::consts::ARCH
+            );
+            return Ok(());
+        };
+        let filter = syscall_filter(arch, self.exec)?;
+        seccompiler::apply_filter_all_threads(&filter)
+            .map_err(|e| Error::Sandbox(format!("seccomp: {}", e)))?;
+        tracing::info!("Syscall filter installed");
+        Ok(())
+    }
+}
+
+fn existing(paths: &[PathBuf]) -> impl Iterator<Item = &PathBuf> {
+    paths.iter().filter(|path| path.exists())
+}
+
+fn syscall_filter(arch: TargetArch, exec: bool) -> Result<BpfProgram> {
+    let rules: BTreeMap<i64, Vec<_>> = DENIED_SYSCALLS
+        .iter()
+        .filter(|&&syscall| {
+            !(exec && (syscall == libc::SYS_execve || syscall == libc::SYS_execveat))
+        })
+        .map(|&syscall| (syscall, Vec::new()))
+        .collect();
+    SeccompFilter::new(
+        rules,
+        SeccompAction::Allow,
+        SeccompAction::Errno(libc::EPERM as u32),
+        arch,
+    )
+    .and_then(BpfProgram::try_from)
+    .map_err(|e| Error::Sandbox(format!("seccomp: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use tokio::sync::Mutex;
+
+    use super::*;
+    use crate::config::Config;
+    use crate::controller::FanController;
+    use crate::hardware::HardwareController;
+    use crate::mock::MockEc;
+    use crate::socket::process_command;
+
+    #[test]
+    fn test_config_folder_is_readable() {
+        let sandbox = Sandbox::new(Path::new("/opt/fan/config.json"))
+            .allow_write(Path::new("/var/lib/node_exporter"));
+        assert!(sandbox.read.contains(&PathBuf::from("/opt/fan")));
+        assert!(sandbox
+            .write
+            .contains(&PathBuf::from("/var/lib/node_exporter")));
+    }
+
+    #[test]
+    fn test_syscall_filter_compiles() {
+        let filter = syscall_filter(TargetArch::x86_64, false).unwrap();
+        assert!(!filter.is_empty());
+        let exec = syscall_filter(TargetArch::x86_64, true).unwrap();
+        assert!(exec.len() < filter.len());
+    }
+
+    #[test]
+    fn test_control_loop_runs_under_the_filter() {
+        let arch = TargetArch::try_from(std::env::consts::ARCH).unwrap();
+        let filter = syscall_filter(arch, false).unwrap();
+        let stats_path =
+            std::env::temp_dir().join(format!("fw-fanctrl-sandbox-{}.json", std::process::id()));
+
+        // Filters the spawned thread and the runtime threads it starts, not the test harness
+        std::thread::spawn(move || {
+            seccompiler::apply_filter(&filter).unwrap();
+
+            let config: Config = serde_json::from_str(
+                r#"{"defaultStrategy": "lazy", "strategies": {"lazy": {
+                    "updateIntervalSeconds": 1, "movingAverageInterval": 1,
+                    "speedCurve": [{"temp": 0, "speed": 0}, {"temp": 100, "speed": 100}]}}}"#,
+            )
+            .unwrap();
+            let ec = MockEc::new();
+            ec.set_temperatures(&[55]);
+            ec.set_fan_rpm(0, 2400);
+            let hw = HardwareController::with_backend(Box::new(ec.clone()), None, "mock");
+            let controller = Arc::new(Mutex::new(FanController::new(hw, config, None)));
+
+            let runtime = tokio::runtime::Runtime::new().unwrap();
+            runtime.block_on(async {
+                let mut tick = tokio::time::interval(Duration::from_millis(10));
+                for _ in 0..3 {
+                    tick.tick().await;
+                    crate::conflicts::find_conflicting_processes();
+                    let mut ctrl = controller.lock().await;
+                    ctrl.step().unwrap();
+                    ctrl.get_fan_rpm().unwrap();
+                    ctrl.thermal_stats().save(&stats_path).unwrap();
+                }
+                let reply = tokio::spawn(process_command("print current", Arc::clone(&controller)))
+                    .await
+                    .unwrap()
+                    .unwrap();
+                assert!(reply.contains("lazy"));
+                controller.lock().await.enable_auto_fan().unwrap();
+            });
+            assert_eq!(ec.duty_writes().first(), Some(&55));
+            std::fs::remove_file(&stats_path).unwrap();
+
+            let denied = std::process::Command::new("true").status().unwrap_err();
+            assert_eq!(denied.raw_os_error(), Some(libc::EPERM));
+        })
+        .join()
+        .unwrap();
+    }
+}
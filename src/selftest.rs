@@ -0,0 +1,203 @@
+use std::future::Future;
+use std::process::Command;
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::sync::Mutex;
+
+use crate::config::ScheduledSelfTest;
+use crate::controller::unix_now;
+use crate::error::Result;
+use crate::hardware::HardwareController;
+use crate::jobs::JobContext;
+use crate::protocol::STEP_SETTLE;
+use crate::socket::ControllerHandle;
+use crate::stats::SelfTestResult;
+
+/// Duties the default test steps through: 25, 50, 75 and 100%.
+pub const DEFAULT_STEPS: u32 = 4;
+/// Kind of the job [`fan_test_job`] runs as.
+pub const FAN_TEST_JOB: &str = "fan-test";
+/// Kind of the job [`scheduled_self_test_job`] runs as.
+pub const SCHEDULED_SELF_TEST_JOB: &str = "scheduled-self-test";
+
+/// Fan speed measured at one duty of the fan test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct FanTestStep {
+    pub speed: u32,
+    pub rpm: u16,
+}
+
+/// Duties for `steps` evenly spaced steps up to full speed.
+pub fn test_duties(steps: u32) -> Vec<u32> {
+    let speed_step = 100 / steps.max(1);
+    (1..=steps).map(|i| (speed_step * i).min(100)).collect()
+}
+
+/// Steps the fan through `duties` and passes each measurement to `on_step` as
+/// soon as it is taken.
+///
+/// The lock is only held while talking to the EC, never across the settle time.
+/// Dropping the future cancels the test; the caller restores fan control either way.
+pub async fn run_fan_test<T: AsRef<HardwareController>>(
+    target: &Mutex<T>,
+    duties: &[u32],
+    settle: Duration,
+    mut on_step: impl FnMut(FanTestStep),
+) -> Result<Vec<FanTestStep>> {
+    let mut results = Vec::new();
+
+    for &speed in duties {
+        target.lock().await.as_ref().set_fan_speed(speed)?;
+        tokio::time::sleep(settle).await;
+        let rpm = target.lock().await.as_ref().get_fan_rpm().unwrap_or(0);
+
+        let step = FanTestStep { speed, rpm };
+        on_step(step);
+        results.push(step);
+    }
+
+    Ok(results)
+}
+
+/// Runs `test` with the controller paused. Fan control is resumed afterwards,
+/// also when cancelled, unless it was paused before.
+async fn with_control_paused<T>(
+    controller: &ControllerHandle,
+    ctx: &JobContext,
+    test: impl Future<Output = Result<Vec<T>>>,
+) -> Result<Vec<T>> {
+    let was_active = {
+        let mut controller = controller.lock().await;
+        let active = controller.is_active();
+        controller.pause()?;
+        active
+    };
+
+    let result = tokio::select! {
+        result = test => result,
+        _ = ctx.cancelled() => Ok(Vec::new()),
+    };
+
+    let mut controller = controller.lock().await;
+    controller.pause()?;
+    if was_active {
+        controller.resume()?;
+    }
+    result
+}
+
+/// The `fanTest` job: runs the fan test with the controller paused, reporting each
+/// step.
+pub async fn fan_test_job(
+    controller: ControllerHandle,
+    ctx: JobContext,
+) -> Result<serde_json::Value> {
+    let duties = test_duties(DEFAULT_STEPS);
+    let test = run_fan_test(&controller, &duties, STEP_SETTLE, |step| ctx.report(step));
+    let result = with_control_paused(&controller, &ctx, test).await?;
+    Ok(serde_json::json!(result))
+}
+
+/// The `scheduledSelfTest` job: holds the configured duty for one settle time and
+/// passes if the fan reaches `minRpm`. The result goes into the thermal stats;
+/// a failure is logged and runs `onFailure`.
+pub async fn scheduled_self_test_job(
+    controller: ControllerHandle,
+    schedule: ScheduledSelfTest,
+    ctx: JobContext,
+) -> Result<serde_json::Value> {
+    let duties = [schedule.duty];
+    let test = run_fan_test(&controller, &duties, STEP_SETTLE, |step| ctx.report(step));
+    let Some(step) = with_control_paused(&controller, &ctx, test).await?.pop() else {
+        return Ok(serde_json::Value::Null);
+    };
+
+    let result = SelfTestResult {
+        at: unix_now(),
+        duty: step.speed,
+        rpm: step.rpm,
+        passed: step.rpm >= schedule.min_rpm,
+    };
+    controller.lock().await.record_self_test(result);
+    if result.passed {
+        tracing::info!(
+            "Scheduled self-test passed: {} RPM at {}%",
+            step.rpm,
+            step.speed
+        );
+    } else {
+        tracing::warn!(
+            "Scheduled self-test failed: {} RPM at {}%, expected at least {}",
+            step.rpm,
+            step.speed,
+            schedule.min_rpm
+        );
+        run_failure_hook(&schedule.on_failure, &result).await;
+    }
+    Ok(serde_json::json!(result))
+}
+
+/// Runs the `onFailure` command off the runtime threads, passing the measurement
+/// in `FW_FANCTRL_DUTY` and `FW_FANCTRL_RPM`.
+async fn run_failure_hook(command: &[String], result: &SelfTestResult) {
+    let Some((program, args)) = command.split_first() else {
+        return;
+    };
+    let mut hook = Command::new(program);
+    hook.args(args)
+        .env("FW_FANCTRL_DUTY", result.duty.to_string())
+        .env("FW_FANCTRL_RPM", result.rpm.to_string());
+    match tokio::task::spawn_blocking(move || hook.status()).await {
+        Ok(Ok(status)) if status.success() => {}
+        Ok(Ok(status)) => {
+            tracing::warn!("Self-test failure hook {} exited with {}", program, status)
+        }
+        Ok(Err(e)) => tracing::warn!("Failed to run self-test failure hook {}: {}", program, e),
+        Err(e) => tracing::warn!("Self-test failure hook {} panicked: {}", program, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockEc;
+
+    #[test]
+    fn test_duties_reach_full_speed() {
+        assert_eq!(test_duties(4), vec![25, 50, 75, 100]);
+        assert_eq!(test_duties(3), vec![33, 66, 99]);
+        assert_eq!(test_duties(0), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn test_fan_test_reports_each_step() {
+        let ec = MockEc::new();
+        ec.set_temperatures(&[50]);
+        ec.set_fan_rpm(0, 1800);
+        let hw = HardwareController::with_backend(Box::new(ec.clone()), None, "mock");
+        let target = Mutex::new(hw);
+        let mut reported = Vec::new();
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let results = runtime
+            .block_on(run_fan_test(
+                &target,
+                &test_duties(2),
+                Duration::ZERO,
+                |step| reported.push(step),
+            ))
+            .unwrap();
+
+        assert_eq!(results, reported);
+        assert_eq!(
+            reported[0],
+            FanTestStep {
+                speed: 50,
+                rpm: 1800
+            }
+        );
+        assert_eq!(reported[1].speed, 100);
+        assert_eq!(ec.duty(), Some(100));
+    }
+}
@@ -0,0 +1,491 @@
+use std::fs;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::config::DEFAULT_CONFIG_PATH;
+use crate::error::{Error, Result};
+use crate::protocol::SOCKET_FOLDER_PATH;
+
+const SERVICE_NAME: &str = "fw-fanctrl";
+const DEFAULT_CONFIG: &str = include_str!("../config.json");
+
+const SYSTEMD_UNIT_PATH: &str = "/etc/systemd/system/fw-fanctrl.service";
+const TMPFILES_PATH: &str = "/etc/tmpfiles.d/fw-fanctrl.conf";
+const OPENRC_SCRIPT_PATH: &str = "/etc/init.d/fw-fanctrl";
+const RUNIT_SERVICE_DIR: &str = "/etc/sv/fw-fanctrl";
+const RUNIT_RUN_PATH: &str = "/etc/sv/fw-fanctrl/run";
+const RUNIT_ENABLED_LINK: &str = "/var/service/fw-fanctrl";
+const UDEV_RULE_PATH: &str = "/etc/udev/rules.d/60-fw-fanctrl.rules";
+const GROUP_FILE_PATH: &str = "/etc/group";
+
+/// Group given read-write access to the EC device by `setup-permissions`.
+pub const EC_GROUP: &str = "fw-fanctrl";
+/// Character device of the kernel `cros_ec` driver. Without it framework_lib
+/// falls back to port I/O, which needs root no matter the permissions.
+pub const EC_DEVICE_PATH: &str = "/dev/cros_ec";
+
+/// Service manager the daemon is installed for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum InitSystem {
+    Systemd,
+    Openrc,
+    Runit,
+}
+
+pub struct InstallOptions {
+    pub init: InitSystem,
+    /// Binary the service runs.
+    pub binary: PathBuf,
+    pub config_path: PathBuf,
+    /// Extra arguments appended to `run`, e.g. `--no-battery-sensors`.
+    pub run_args: Vec<String>,
+    /// Directory the files are written under; anything but `/` only stages them.
+    pub root: PathBuf,
+    /// Enable and start (or stop and disable) the service with the init system.
+    pub enable: bool,
+}
+
+impl Default for InstallOptions {
+    fn default() -> Self {
+        Self {
+            init: InitSystem::Systemd,
+            binary: PathBuf::from("/usr/local/bin/fw-fanctrl"),
+            config_path: PathBuf::from(DEFAULT_CONFIG_PATH),
+            run_args: Vec::new(),
+            root: PathBuf::from("/"),
+            enable: true,
+        }
+    }
+}
+
+impl InstallOptions {
+    fn target(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.root
+            .join(path.as_ref().strip_prefix("/").unwrap_or(path.as_ref()))
+    }
+
+    fn exec_line(&self) -> String {
+        let mut parts = vec![
+            self.binary.display().to_string(),
+            "run".to_string(),
+            "--config".to_string(),
+            self.config_path.display().to_string(),
+            "--silent".to_string(),
+        ];
+        parts.extend(self.run_args.iter().cloned());
+        parts.join(" ")
+    }
+
+    fn service_files(&self) -> Vec<(&'static str, String, u32)> {
+        match self.init {
+            InitSystem::Systemd => vec![
+                (SYSTEMD_UNIT_PATH, systemd_unit(&self.exec_line()), 0o644),
+                (
+                    TMPFILES_PATH,
+                    format!("d {} 0755 root root -\n", SOCKET_FOLDER_PATH),
+                    0o644,
+                ),
+            ],
+            InitSystem::Openrc => vec![(
+                OPENRC_SCRIPT_PATH,
+                openrc_script(&self.binary, &self.exec_line()),
+                0o755,
+            )],
+            InitSystem::Runit => vec![(RUNIT_RUN_PATH, runit_script(&self.exec_line()), 0o755)],
+        }
+    }
+}
+
+fn systemd_unit(exec: &str) -> String {
+    format!(
+        "[Unit]\n\
+         Description=Framework Fan Controller (Rust)\n\
+         After=multi-user.target\n\
+         \n\
+         [Service]\n\
+         Type=simple\n\
+         Restart=on-failure\n\
+         RestartSec=5\n\
+         ExecStart={}\n\
+         \n\
+         [Install]\n\
+         WantedBy=multi-user.target\n",
+        exec
+    )
+}
+
+fn openrc_script(binary: &Path, exec: &str) -> String {
+    let args = exec
+        .strip_prefix(&binary.display().to_string())
+        .unwrap_or(exec)
+        .trim_start();
+    format!(
+        "#!/sbin/openrc-run\n\
+         \n\
+         description=\"Framework Fan Controller (Rust)\"\n\
+         command=\"{}\"\n\
+         command_args=\"{}\"\n\
+         command_background=true\n\
+         pidfile=\"/run/${{RC_SVCNAME}}.pid\"\n\
+         \n\
+         depend() {{\n\
+         \tafter modules\n\
+         }}\n\
+         \n\
+         start_pre() {{\n\
+         \tcheckpath --directory --mode 0755 {}\n\
+         }}\n",
+        binary.display(),
+        args,
+        SOCKET_FOLDER_PATH
+    )
+}
+
+fn runit_script(exec: &str) -> String {
+    format!(
+        "#!/bin/sh\n\
+         mkdir -p {}\n\
+         exec {} 2>&1\n",
+        SOCKET_FOLDER_PATH, exec
+    )
+}
+
+fn run(program: &str, args: &[&str]) -> Result<()> {
+    let status = Command::new(program)
+        .args(args)
+        .status()
+        .map_err(|e| Error::Command(format!("Failed to run {}: {}", program, e)))?;
+    if !status.success() {
+        return Err(Error::Command(format!(
+            "{} {} failed: {}",
+            program,
+            args.join(" "),
+            status
+        )));
+    }
+    Ok(())
+}
+
+pub struct PermissionsOptions {
+    /// User added to [`EC_GROUP`].
+    pub user: Option<String>,
+    /// Directory the udev rule is written under; anything but `/` only stages it.
+    pub root: PathBuf,
+}
+
+fn udev_rule() -> String {
+    format!(
+        "# Written by fw-fanctrl setup-permissions: EC access without root\n\
+         KERNEL==\"cros_ec\", GROUP=\"{}\", MODE=\"0660\"\n",
+        EC_GROUP
+    )
+}
+
+/// Looks `name` up in the contents of `/etc/group`.
+fn group_id(groups: &str, name: &str) -> Option<u32> {
+    groups.lines().find_map(|line| {
+        let mut fields = line.split(':');
+        if fields.next()? != name {
+            return None;
+        }
+        fields.nth(1)?.parse().ok()
+    })
+}
+
+/// Checks that members of group `gid` can read and write the EC device at `path`.
+fn verify_device_access(path: &Path, gid: u32) -> Result<()> {
+    let metadata = fs::metadata(path)?;
+    if metadata.gid() != gid {
+        return Err(Error::Ec(format!(
+            "{} belongs to group {}, not {} ({}); check that no other udev rule overrides it",
+            path.display(),
+            metadata.gid(),
+            EC_GROUP,
+            gid
+        )));
+    }
+    if metadata.mode() & 0o060 != 0o060 {
+        return Err(Error::Ec(format!(
+            "{} has mode {:o}, the group cannot read and write it",
+            path.display(),
+            metadata.mode() & 0o777
+        )));
+    }
+    Ok(())
+}
+
+/// Installs the udev rule giving [`EC_GROUP`] access to the EC device, creates the
+/// group, optionally adds a user to it, and verifies the device permissions.
+///
+/// With a staging root only the rule is written. Returns a line per action taken.
+pub fn setup_permissions(options: &PermissionsOptions) -> Result<Vec<String>> {
+    let mut actions = Vec::new();
+
+    let rule = options.root.join(UDEV_RULE_PATH.trim_start_matches('/'));
+    if let Some(dir) = rule.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&rule, udev_rule())?;
+    fs::set_permissions(&rule, fs::Permissions::from_mode(0o644))?;
+    actions.push(format!("Wrote {}", rule.display()));
+
+    if options.root != Path::new("/") {
+        return Ok(actions);
+    }
+
+    if group_id(&fs::read_to_string(GROUP_FILE_PATH)?, EC_GROUP).is_none() {
+        run("groupadd", &["--system", EC_GROUP])?;
+        actions.push(format!("Created group {}", EC_GROUP));
+    }
+    let gid = group_id(&fs::read_to_string(GROUP_FILE_PATH)?, EC_GROUP)
+        .ok_or_else(|| Error::Command(format!("groupadd did not create {}", EC_GROUP)))?;
+    if let Some(user) = &options.user {
+        run("usermod", &["--append", "--groups", EC_GROUP, user])?;
+        actions.push(format!(
+            "Added {} to {} (takes effect on next login)",
+            user, EC_GROUP
+        ));
+    }
+
+    let device = Path::new(EC_DEVICE_PATH);
+    if !device.exists() {
+        actions.push(format!(
+            "{} not found: this kernel has no cros_ec driver for the EC, \
+             so EC access goes through port I/O and still needs root",
+            EC_DEVICE_PATH
+        ));
+        return Ok(actions);
+    }
+    run("udevadm", &["control", "--reload-rules"])?;
+    run("udevadm", &["trigger", "--name-match", EC_DEVICE_PATH])?;
+    run("udevadm", &["settle"])?;
+    verify_device_access(device, gid)?;
+    actions.push(format!(
+        "Verified {} is read-write for group {}",
+        EC_DEVICE_PATH, EC_GROUP
+    ));
+
+    Ok(actions)
+}
+
+/// Writes the service files and default config, then enables the service.
+///
+/// An existing config file is left untouched. Returns a line per action taken.
+pub fn install(options: &InstallOptions) -> Result<Vec<String>> {
+    let mut actions = Vec::new();
+
+    let config = options.target(&options.config_path);
+    if config.exists() {
+        actions.push(format!("Kept existing {}", config.display()));
+    } else {
+        if let Some(dir) = config.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&config, DEFAULT_CONFIG)?;
+        actions.push(format!("Wrote default config to {}", config.display()));
+    }
+
+    for (path, content, mode) in options.service_files() {
+        let target = options.target(path);
+        if let Some(dir) = target.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&target, content)?;
+        fs::set_permissions(&target, fs::Permissions::from_mode(mode))?;
+        actions.push(format!("Wrote {}", target.display()));
+    }
+
+    if options.enable {
+        match options.init {
+            InitSystem::Systemd => {
+                run("systemd-tmpfiles", &["--create", TMPFILES_PATH])?;
+                run("systemctl", &["daemon-reload"])?;
+                run("systemctl", &["enable", "--now", SERVICE_NAME])?;
+            }
+            InitSystem::Openrc => {
+                run("rc-update", &["add", SERVICE_NAME, "default"])?;
+                run("rc-service", &[SERVICE_NAME, "start"])?;
+            }
+            InitSystem::Runit => {
+                let link = options.target(RUNIT_ENABLED_LINK);
+                if fs::symlink_metadata(&link).is_err() {
+                    std::os::unix::fs::symlink(RUNIT_SERVICE_DIR, &link)?;
+                }
+            }
+        }
+        actions.push(format!("Enabled and started {}", SERVICE_NAME));
+    }
+
+    Ok(actions)
+}
+
+/// Stops and disables the service and removes its files; the config is only
+/// removed with `purge`, along with its directory when that is fw-fanctrl's own.
+pub fn uninstall(options: &InstallOptions, purge: bool) -> Result<Vec<String>> {
+    let mut actions = Vec::new();
+
+    if options.enable {
+        // The service may already be stopped or disabled
+        let _ = match options.init {
+            InitSystem::Systemd => run("systemctl", &["disable", "--now", SERVICE_NAME]),
+            InitSystem::Openrc => {
+                let _ = run("rc-service", &[SERVICE_NAME, "stop"]);
+                run("rc-update", &["del", SERVICE_NAME, "default"])
+            }
+            InitSystem::Runit => {
+                fs::remove_file(options.target(RUNIT_ENABLED_LINK)).map_err(Error::from)
+            }
+        };
+        actions.push(format!("Stopped and disabled {}", SERVICE_NAME));
+    }
+
+    for (path, _, _) in options.service_files() {
+        let target = options.target(path);
+        if target.exists() {
+            fs::remove_file(&target)?;
+            actions.push(format!("Removed {}", target.display()));
+        }
+    }
+    if options.init == InitSystem::Runit {
+        let _ = fs::remove_dir(options.target(RUNIT_SERVICE_DIR));
+    }
+    if options.enable && options.init == InitSystem::Systemd {
+        run("systemctl", &["daemon-reload"])?;
+    }
+
+    if purge {
+        let config = options.target(&options.config_path);
+        // The config, and the temporary file an interrupted save leaves next to it
+        for path in [config.with_extension("json.tmp"), config.clone()] {
+            if path.is_file() {
+                fs::remove_file(&path)?;
+                actions.push(format!("Removed {}", path.display()));
+            }
+        }
+        // A directory of the service's own goes too, unless the user keeps more in it
+        if let Some(dir) = config
+            .parent()
+            .filter(|dir| dir.file_name() == Some(SERVICE_NAME.as_ref()))
+        {
+            if fs::remove_dir(dir).is_ok() {
+                actions.push(format!("Removed {}", dir.display()));
+            }
+        }
+    }
+
+    Ok(actions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn staged(init: InitSystem) -> InstallOptions {
+        InstallOptions {
+            init,
+            root: std::env::temp_dir().join(format!(
+                "fw-fanctrl-install-{:?}-{}",
+                init,
+                std::process::id()
+            )),
+            run_args: vec!["--no-battery-sensors".into()],
+            enable: false,
+            ..InstallOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_systemd_install_and_uninstall() {
+        let options = staged(InitSystem::Systemd);
+        install(&options).unwrap();
+
+        let unit = fs::read_to_string(options.target(SYSTEMD_UNIT_PATH)).unwrap();
+        assert!(unit.contains(
+            "ExecStart=/usr/local/bin/fw-fanctrl run --config /etc/fw-fanctrl/config.json \
+             --silent --no-battery-sensors"
+        ));
+        let tmpfiles = fs::read_to_string(options.target(TMPFILES_PATH)).unwrap();
+        assert_eq!(tmpfiles, "d /run/fw-fanctrl 0755 root root -\n");
+        let config = options.target(DEFAULT_CONFIG_PATH);
+        assert!(crate::config::Config::load(&config).is_ok());
+
+        uninstall(&options, false).unwrap();
+        assert!(!options.target(SYSTEMD_UNIT_PATH).exists());
+        assert!(config.exists());
+
+        uninstall(&options, true).unwrap();
+        assert!(!config.exists());
+        assert!(!config.parent().unwrap().exists());
+        fs::remove_dir_all(&options.root).unwrap();
+
+        // Files the user keeps next to the config survive, and so does the directory
+        install(&options).unwrap();
+        let token = options.target("/etc/fw-fanctrl/http-token");
+        fs::write(&token, "secret\n").unwrap();
+        uninstall(&options, true).unwrap();
+        assert!(!config.exists());
+        assert!(token.exists());
+        fs::remove_dir_all(&options.root).unwrap();
+    }
+
+    #[test]
+    fn test_purge_leaves_a_shared_config_directory() {
+        let options = InstallOptions {
+            config_path: PathBuf::from("/etc/fw-fanctrl.json"),
+            ..staged(InitSystem::Runit)
+        };
+        install(&options).unwrap();
+        let other = options.target("/etc/hostname");
+        fs::write(&other, "framework\n").unwrap();
+
+        uninstall(&options, true).unwrap();
+        assert!(!options.target("/etc/fw-fanctrl.json").exists());
+        assert!(other.exists());
+        fs::remove_dir_all(&options.root).unwrap();
+    }
+
+    #[test]
+    fn test_group_id_lookup() {
+        let groups = "root:x:0:\nvideo:x:44:alice\nfw-fanctrl:x:978:alice,bob\n";
+        assert_eq!(group_id(groups, "fw-fanctrl"), Some(978));
+        assert_eq!(group_id(groups, "fw"), None);
+        assert_eq!(group_id("broken\n", "broken"), None);
+    }
+
+    #[test]
+    fn test_verify_device_access() {
+        let path = std::env::temp_dir().join(format!("fw-fanctrl-ec-{}", std::process::id()));
+        fs::write(&path, "").unwrap();
+        let gid = fs::metadata(&path).unwrap().gid();
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o660)).unwrap();
+        assert!(verify_device_access(&path, gid).is_ok());
+        assert!(verify_device_access(&path, gid + 1).is_err());
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        assert!(verify_device_access(&path, gid).is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_install_keeps_existing_config() {
+        let options = staged(InitSystem::Openrc);
+        let config = options.target(DEFAULT_CONFIG_PATH);
+        fs::create_dir_all(config.parent().unwrap()).unwrap();
+        fs::write(&config, "{}").unwrap();
+
+        install(&options).unwrap();
+        assert_eq!(fs::read_to_string(&config).unwrap(), "{}");
+        let script = options.target(OPENRC_SCRIPT_PATH);
+        assert!(fs::read_to_string(&script)
+            .unwrap()
+            .contains("command_args=\"run --config"));
+        assert_eq!(
+            fs::metadata(&script).unwrap().permissions().mode() & 0o777,
+            0o755
+        );
+
+        fs::remove_dir_all(&options.root).unwrap();
+    }
+}
@@ -1,203 +1,624 @@
-use std::io::{Read, Write};
+use std::collections::HashMap;
 use std::os::unix::fs::PermissionsExt;
-use std::os::unix::net::UnixListener;
-use std::path::PathBuf;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use tokio::sync::Mutex;
-use tokio::task::JoinHandle;
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader,
+};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::{watch, Mutex};
 
-use crate::controller::FanController;
+use crate::audit::{AuditLog, Peer};
+use crate::command::{self, Command, JobsAction, ProfileAction, Selection};
+use crate::controller::{ErrorContext, FanController, ShutdownRequest};
 use crate::error::{Error, Result};
+use crate::jobs::JobState;
+use crate::protocol::{
+    decode_frame, finish_frame, COMMANDS, COMMANDS_SOCKET_FILE_PATH, MAX_REQUEST_SIZE,
+    PRINT_SELECTIONS, PROTOCOL_VERSION, QUERY_SOCKET_FILE_PATH, SOCKET_FOLDER_PATH,
+};
+use crate::selftest;
 
-pub const SOCKET_FOLDER_PATH: &str = "/run/fw-fanctrl";
-pub const COMMANDS_SOCKET_FILE_PATH: &str = "/run/fw-fanctrl/.fw-fanctrl.commands.sock";
+/// A client that stops reading its replies is dropped after this long.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+/// A connection without a complete command for this long is closed.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+/// Commands a user may send per second, across all of their connections.
+const MAX_COMMANDS_PER_SECOND: u32 = 20;
+/// Commands only root or the daemon's own user may send; the socket itself is world-writable.
+const PRIVILEGED_COMMANDS: &[&str] = &["shutdown", "restart-auto"];
 
 pub type ControllerHandle = Arc<Mutex<FanController>>;
 
+/// Which commands a socket serves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SocketAccess {
+    Control,
+    /// Only commands that leave the daemon's state alone, see
+    /// [`Command::is_state_changing`].
+    Query,
+}
+
+/// Binds `path` with permissions `mode`, replacing a stale socket.
+fn bind_socket(path: &Path, mode: u32) -> Result<UnixListener> {
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)
+        .map_err(|e| Error::Socket(format!("Failed to bind socket: {}", e)))?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+        .map_err(|e| Error::Socket(format!("Failed to set socket permissions: {}", e)))?;
+    tracing::info!("Socket server listening on {}", path.display());
+    Ok(listener)
+}
+
+/// Accepts on `listener`, or never without one.
+async fn accept_on(
+    listener: &Option<UnixListener>,
+) -> std::io::Result<(UnixStream, tokio::net::unix::SocketAddr)> {
+    match listener {
+        Some(listener) => listener.accept().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Serves the command socket until `shutdown` turns true, recording state-changing
+/// commands in `audit`.
+///
+/// With `query_socket`, read-only commands are also served on [`QUERY_SOCKET_FILE_PATH`],
+/// open to everyone, while the command socket is restricted to the daemon's user and group.
+///
+/// Accepts and connections are async, so an idle daemon doesn't wake up for the socket.
 pub async fn start_socket_server(
     controller: ControllerHandle,
-    shutdown: Arc<AtomicBool>,
+    audit: Arc<AuditLog>,
+    query_socket: bool,
+    mut shutdown: watch::Receiver<bool>,
 ) -> Result<()> {
     let socket_path = PathBuf::from(COMMANDS_SOCKET_FILE_PATH);
+    let query_path = PathBuf::from(QUERY_SOCKET_FILE_PATH);
     let folder_path = PathBuf::from(SOCKET_FOLDER_PATH);
 
-    if socket_path.exists() {
-        std::fs::remove_file(&socket_path)?;
-    }
-
     if !folder_path.exists() {
         std::fs::create_dir_all(&folder_path)?;
     }
 
-    let listener = UnixListener::bind(&socket_path)
-        .map_err(|e| Error::Socket(format!("Failed to bind socket: {}", e)))?;
-
-    listener
-        .set_nonblocking(true)
-        .map_err(|e| Error::Socket(format!("Failed to set nonblocking: {}", e)))?;
-
-    std::fs::set_permissions(&socket_path, std::fs::Permissions::from_mode(0o777))
-        .map_err(|e| Error::Socket(format!("Failed to set socket permissions: {}", e)))?;
+    let listener = bind_socket(&socket_path, if query_socket { 0o770 } else { 0o777 })?;
+    let query_listener = query_socket
+        .then(|| bind_socket(&query_path, 0o777))
+        .transpose()?;
+    let limiter = Arc::new(RateLimiter::new(MAX_COMMANDS_PER_SECOND));
 
-    tracing::info!("Socket server listening on {}", COMMANDS_SOCKET_FILE_PATH);
-
-    let shutdown_check = Arc::clone(&shutdown);
-    let accept_task: JoinHandle<Result<()>> = tokio::task::spawn_blocking(move || loop {
-        if shutdown_check.load(Ordering::Relaxed) {
-            tracing::info!("Socket server received shutdown signal");
-            break Ok(());
-        }
-
-        match listener.accept() {
-            Ok((mut stream, _addr)) => {
+    loop {
+        let (accepted, access) = tokio::select! {
+            _ = shutdown.wait_for(|stop| *stop) => {
+                tracing::info!("Socket server received shutdown signal");
+                break;
+            }
+            accepted = listener.accept() => (accepted, SocketAccess::Control),
+            accepted = accept_on(&query_listener) => (accepted, SocketAccess::Query),
+        };
+        match accepted {
+            Ok((stream, _addr)) => {
                 let controller = Arc::clone(&controller);
+                let audit = Arc::clone(&audit);
+                let limiter = Arc::clone(&limiter);
                 tokio::spawn(async move {
-                    if let Err(e) = handle_connection(&mut stream, controller).await {
+                    let served = handle_connection(
+                        stream,
+                        Arc::clone(&controller),
+                        access,
+                        &audit,
+                        &limiter,
+                    );
+                    if let Err(e) = served.await {
                         tracing::error!("Error handling connection: {}", e);
+                        controller
+                            .lock()
+                            .await
+                            .record_error(ErrorContext::Socket, &e);
                     }
                 });
             }
-            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
-                std::thread::sleep(Duration::from_millis(100));
-            }
-            Err(e) => {
-                tracing::error!("Accept error: {}", e);
-            }
+            Err(e) => tracing::error!("Accept error: {}", e),
         }
-    });
-
-    let _ = accept_task
-        .await
-        .map_err(|e| Error::Socket(format!("Socket accept task failed: {}", e)))?;
+    }
 
     tracing::info!("Socket server shutting down");
 
-    if socket_path.exists() {
-        let _ = std::fs::remove_file(&socket_path);
+    for path in [&socket_path, &query_path] {
+        if path.exists() {
+            let _ = std::fs::remove_file(path);
+        }
     }
 
     Ok(())
 }
 
-async fn handle_connection(
-    stream: &mut std::os::unix::net::UnixStream,
-    controller: ControllerHandle,
-) -> Result<()> {
-    let mut buffer = [0u8; 4096];
-    let bytes_read = stream
-        .read(&mut buffer)
+/// Async counterpart of [`read_frame`], used by the daemon; frames longer than
+/// `limit` are rejected and left undecoded.
+async fn read_frame_async<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    limit: usize,
+) -> Result<Option<Vec<u8>>> {
+    let mut buffer = Vec::new();
+    let bytes_read = (&mut *reader)
+        .take(limit as u64 + 1)
+        .read_until(b'\n', &mut buffer)
+        .await
         .map_err(|e| Error::Socket(format!("Failed to read from socket: {}", e)))?;
+    finish_frame(buffer, bytes_read, limit)
+}
+
+/// Async counterpart of [`write_frame`]; gives up after [`WRITE_TIMEOUT`].
+async fn write_frame_async<W: AsyncWrite + Unpin>(writer: &mut W, payload: &str) -> Result<()> {
+    let write = async {
+        writer.write_all(payload.as_bytes()).await?;
+        writer.write_all(b"\n").await?;
+        writer.flush().await
+    };
+    tokio::time::timeout(WRITE_TIMEOUT, write)
+        .await
+        .map_err(|_| Error::Socket("Timed out writing to socket".into()))?
+        .map_err(|e| Error::Socket(format!("Failed to write to socket: {}", e)))
+}
 
-    if bytes_read == 0 {
-        return Ok(());
+/// Rejects privileged commands from anyone but root and the daemon's own user.
+/// `reload <path>` is privileged too: the file it loads may name commands
+/// (`sensorSources`, `alerts`, `scheduledSelfTest`) the daemon runs as itself.
+fn authorize(command: &Command, peer_uid: Option<u32>) -> Result<()> {
+    let what = match command {
+        Command::Reload { path: Some(_) } => "reload with a path",
+        _ if PRIVILEGED_COMMANDS.contains(&command.name()) => command.name(),
+        _ => return Ok(()),
+    };
+    let daemon_uid = unsafe { libc::geteuid() };
+    match peer_uid {
+        Some(uid) if uid == 0 || uid == daemon_uid => Ok(()),
+        _ => Err(Error::Command(format!(
+            "Permission denied: {} requires root",
+            what
+        ))),
+    }
+}
+
+/// Counts commands per peer UID in one-second windows.
+struct RateLimiter {
+    limit: u32,
+    windows: std::sync::Mutex<HashMap<Option<u32>, (Instant, u32)>>,
+}
+
+impl RateLimiter {
+    fn new(limit: u32) -> Self {
+        Self {
+            limit,
+            windows: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Counts a command from `uid`; `false` once the current second's limit is used up.
+    fn allow(&self, uid: Option<u32>) -> bool {
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        let (start, count) = windows.entry(uid).or_insert((now, 0));
+        if now.duration_since(*start) >= Duration::from_secs(1) {
+            *start = now;
+            *count = 0;
+        }
+        *count += 1;
+        *count <= self.limit
     }
+}
 
-    let command = String::from_utf8_lossy(&buffer[..bytes_read]);
-    let command = command.trim();
+/// Serves newline-delimited commands until the client closes the connection
+/// or stays silent for [`READ_TIMEOUT`], replying to each one in order.
+async fn handle_connection(
+    stream: UnixStream,
+    controller: ControllerHandle,
+    access: SocketAccess,
+    audit: &AuditLog,
+    limiter: &RateLimiter,
+) -> Result<()> {
+    let peer = Peer::of(&stream);
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
 
-    tracing::debug!("Received command: {}", command);
+    loop {
+        let read = read_frame_async(&mut reader, MAX_REQUEST_SIZE);
+        let frame = match tokio::time::timeout(READ_TIMEOUT, read).await {
+            Ok(Ok(Some(frame))) => frame,
+            Ok(Ok(None)) => break,
+            Ok(Err(e)) => {
+                // The rest of an oversized frame can't be told apart from the next one
+                let _ = write_frame_async(&mut writer, &error_response(&e)).await;
+                return Err(e);
+            }
+            Err(_) => {
+                tracing::debug!("Closing connection idle for {}s", READ_TIMEOUT.as_secs());
+                break;
+            }
+        };
+        let command = match decode_frame(frame) {
+            Ok(command) => command,
+            Err(e) => {
+                write_frame_async(&mut writer, &error_response(&e)).await?;
+                continue;
+            }
+        };
+        let command = command.trim();
+        if command.is_empty() {
+            continue;
+        }
+        if !limiter.allow(peer.uid) {
+            let e = Error::Socket(format!(
+                "Rate limit exceeded, at most {} commands per second",
+                MAX_COMMANDS_PER_SECOND
+            ));
+            write_frame_async(&mut writer, &error_response(&e)).await?;
+            continue;
+        }
 
-    let response = process_command(command, controller).await?;
+        tracing::debug!("Received command: {}", command);
 
-    stream
-        .write_all(response.as_bytes())
-        .map_err(|e| Error::Socket(format!("Failed to write to socket: {}", e)))?;
+        let parsed = command::parse(command);
+        let state_changing = parsed.as_ref().is_ok_and(Command::is_state_changing);
+        let result = match parsed {
+            Ok(parsed) if access == SocketAccess::Query && state_changing => Err(Error::Command(
+                format!("{} is not available on the query socket", parsed.name()),
+            )),
+            Ok(Command::Selftest) => stream_selftest(&mut writer, &controller).await,
+            Ok(parsed) => match authorize(&parsed, peer.uid) {
+                Ok(()) => execute(parsed, Arc::clone(&controller)).await,
+                Err(e) => Err(e),
+            },
+            Err(e) => Err(e),
+        };
+        let outcome = match &result {
+            Ok(_) => "success".to_string(),
+            Err(e) => e.to_string(),
+        };
+        audit.record(&peer, command, &outcome);
+        let response = result.unwrap_or_else(|e| error_response(&e));
+
+        write_frame_async(&mut writer, &response).await?;
+    }
 
     Ok(())
 }
 
-pub async fn process_command(command: &str, controller: ControllerHandle) -> Result<String> {
-    // Filter out arguments starting with -- (e.g., --output-format=JSON)
-    let parts: Vec<&str> = command
-        .split_whitespace()
-        .filter(|s| !s.starts_with("--"))
-        .collect();
+/// Starts a fan test job and streams its steps as `progress` frames; returns the
+/// final reply. A client that disconnects cancels the job.
+async fn stream_selftest<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    controller: &ControllerHandle,
+) -> Result<String> {
+    let jobs = controller.lock().await.jobs().clone();
+    let handle = Arc::clone(controller);
+    let id = jobs.spawn(selftest::FAN_TEST_JOB, |ctx| {
+        selftest::fan_test_job(handle, ctx)
+    })?;
+    let mut info = jobs.subscribe(id)?;
+    let mut sent = 0;
 
-    if parts.is_empty() {
-        return Err(Error::Command("Empty command".into()));
+    loop {
+        let (progress, ended) = {
+            let info = info.borrow_and_update();
+            let ended = (info.state != JobState::Running).then(|| info.clone());
+            (info.progress[sent..].to_vec(), ended)
+        };
+        for mut frame in progress {
+            if let Some(map) = frame.as_object_mut() {
+                map.insert("status".into(), "progress".into());
+            }
+            if let Err(e) = write_frame_async(writer, &frame.to_string()).await {
+                jobs.cancel(id)?;
+                return Err(e);
+            }
+            sent += 1;
+        }
+        if let Some(ended) = ended {
+            return match ended.state {
+                JobState::Failed => Err(Error::Command(ended.error.unwrap_or_default())),
+                JobState::Cancelled => Err(Error::Command("Fan test was cancelled".into())),
+                _ => Ok(serde_json::json!({
+                    "status": "success",
+                    "fanTest": ended.result
+                })
+                .to_string()),
+            };
+        }
+        info.changed()
+            .await
+            .map_err(|_| Error::Command(format!("Job {} is gone", id)))?;
     }
+}
+
+/// `jobs list|status <id>|cancel <id>|start <kind>`.
+fn jobs_command(
+    action: JobsAction,
+    controller: &FanController,
+    handle: ControllerHandle,
+) -> Result<String> {
+    let jobs = controller.jobs();
+    let reply = match action {
+        JobsAction::List => serde_json::json!({"status": "success", "jobs": jobs.list()}),
+        JobsAction::Status(id) => serde_json::json!({"status": "success", "job": jobs.status(id)?}),
+        JobsAction::Cancel(id) => {
+            jobs.cancel(id)?;
+            serde_json::json!({"status": "success"})
+        }
+        JobsAction::Start(kind) => {
+            let id = match kind.as_str() {
+                selftest::FAN_TEST_JOB => jobs.spawn(selftest::FAN_TEST_JOB, |ctx| {
+                    selftest::fan_test_job(handle, ctx)
+                })?,
+                _ => return Err(Error::Command(format!("Unknown job kind: {}", kind))),
+            };
+            serde_json::json!({"status": "success", "jobId": id})
+        }
+    };
+    Ok(reply.to_string())
+}
 
+fn error_response(error: &Error) -> String {
+    serde_json::json!({
+        "status": "error",
+        "reason": error.to_string()
+    })
+    .to_string()
+}
+
+#[tracing::instrument(skip(controller))]
+pub async fn process_command(command: &str, controller: ControllerHandle) -> Result<String> {
+    execute(command::parse(command)?, controller).await
+}
+
+/// Runs a parsed command; `selftest` streams and is handled per connection.
+async fn execute(command: Command, controller: ControllerHandle) -> Result<String> {
+    let handle = Arc::clone(&controller);
     let mut controller = controller.lock().await;
 
-    match parts[0] {
-        "use" => {
-            if parts.len() < 2 {
-                return Err(Error::Command("Usage: use <strategy>".into()));
-            }
-            let strategy = parts[1];
-            controller.overwrite_strategy(strategy)?;
-            Ok(format!(
-                "{{\"status\": \"success\", \"strategy\": \"{}\"}}",
-                controller.get_current_strategy_name()
-            ))
+    match command {
+        Command::Use { strategy } => {
+            controller.overwrite_strategy(&strategy)?;
+            Ok(serde_json::json!({
+                "status": "success",
+                "strategy": controller.get_current_strategy_name()
+            })
+            .to_string())
+        }
+        Command::Toggle { strategy, other } => {
+            controller.toggle_strategy(&strategy, &other)?;
+            Ok(serde_json::json!({
+                "status": "success",
+                "strategy": controller.get_current_strategy_name()
+            })
+            .to_string())
         }
-        "reset" => {
+        Command::Cycle => {
+            controller.cycle_strategy()?;
+            Ok(serde_json::json!({
+                "status": "success",
+                "strategy": controller.get_current_strategy_name()
+            })
+            .to_string())
+        }
+        Command::Reset => {
             controller.clear_overwritten_strategy();
-            Ok(format!(
-                "{{\"status\": \"success\", \"strategy\": \"{}\"}}",
-                controller.get_current_strategy_name()
-            ))
+            Ok(serde_json::json!({
+                "status": "success",
+                "strategy": controller.get_current_strategy_name()
+            })
+            .to_string())
+        }
+        Command::Reload { path } => {
+            let diff = controller.reload_from(path)?;
+            Ok(serde_json::json!({
+                "status": "success",
+                "path": controller.config_path(),
+                "diff": diff
+            })
+            .to_string())
+        }
+        Command::Curve { strategy, points } => {
+            controller.set_strategy_curve(&strategy, crate::curve::parse_points(&points)?)?;
+            Ok("{\"status\": \"success\"}".into())
+        }
+        Command::Tune {
+            strategy,
+            field,
+            value,
+        } => {
+            let definition = controller.tune_strategy(&strategy, &field, value)?;
+            Ok(serde_json::json!({
+                "status": "success",
+                "name": strategy,
+                "definition": definition
+            })
+            .to_string())
         }
-        "reload" => {
-            let config =
-                crate::config::Config::load(&PathBuf::from("/etc/fw-fanctrl/config.json"))?;
-            controller.reload_config(config);
+        Command::Adjust { bias } => {
+            controller.set_speed_bias(bias);
+            Ok(serde_json::json!({
+                "status": "success",
+                "bias": controller.get_speed_bias()
+            })
+            .to_string())
+        }
+        Command::BatterySensor { exclude } => {
+            controller.set_battery_excluded(exclude)?;
+            Ok(serde_json::json!({
+                "status": "success",
+                "batteryExcluded": controller.is_battery_excluded()
+            })
+            .to_string())
+        }
+        Command::ClearHistory => {
+            controller.clear_history();
             Ok("{\"status\": \"success\"}".into())
         }
-        "pause" => {
+        Command::Pause => {
             controller.pause()?;
             Ok("{\"status\": \"success\"}".into())
         }
-        "resume" => {
+        Command::Resume => {
             controller.resume()?;
             Ok("{\"status\": \"success\"}".into())
         }
-        "print" => {
-            let selection = parts.get(1).copied().unwrap_or("all");
-            print_selection(selection, &mut controller).await
+        Command::Print(selection) => print_selection(selection, &mut controller).await,
+        Command::Shutdown => {
+            controller.request_shutdown(ShutdownRequest::Stop)?;
+            Ok("{\"status\": \"success\"}".into())
         }
-        _ => Err(Error::Command(format!("Unknown command: {}", parts[0]))),
-    }
-}
-
-async fn print_selection(selection: &str, controller: &mut FanController) -> Result<String> {
-    match selection {
-        "all" => {
-            let temp = controller.get_actual_temperature()?;
-            let strategy = controller.get_current_strategy();
-            let moving_avg =
-                controller.get_moving_average_temperature(strategy.moving_average_interval);
-            let effective =
-                controller.get_effective_temperature(temp, strategy.moving_average_interval);
-
-            let response = serde_json::json!({
+        Command::RestartAuto => {
+            controller.request_shutdown(ShutdownRequest::RestartAuto)?;
+            Ok("{\"status\": \"success\"}".into())
+        }
+        Command::Version => Ok(serde_json::json!({
+            "status": "success",
+            "version": env!("CARGO_PKG_VERSION"),
+            "protocolVersion": PROTOCOL_VERSION
+        })
+        .to_string()),
+        Command::Jobs(action) => jobs_command(action, &controller, handle),
+        Command::Profile(action) => {
+            match action {
+                ProfileAction::List => {}
+                ProfileAction::Use(name) => controller.use_profile(Some(&name))?,
+                ProfileAction::Auto => controller.use_profile(None)?,
+            }
+            Ok(serde_json::json!({
                 "status": "success",
-                "strategy": controller.get_current_strategy_name(),
-                "default": !controller.is_overwritten(),
-                "speed": controller.get_current_speed().to_string(),
-                "temperature": temp.to_string(),
-                "movingAverageTemperature": moving_avg.to_string(),
-                "effectiveTemperature": effective.to_string(),
-                "active": controller.is_active(),
-                "configuration": controller.get_config()
-            });
-            Ok(serde_json::to_string(&response).map_err(|e| Error::Config(e.to_string()))?)
+                "profile": controller.profile(),
+                "pinned": controller.is_profile_pinned(),
+                "profiles": controller.profile_names(),
+                "strategy": controller.get_current_strategy_name()
+            })
+            .to_string())
         }
-        "active" => Ok(serde_json::json!({
+        Command::Capabilities => Ok(serde_json::json!({
             "status": "success",
-            "active": controller.is_active()
+            "protocolVersion": PROTOCOL_VERSION,
+            "commands": COMMANDS,
+            "privileged": PRIVILEGED_COMMANDS,
+            "printSelections": PRINT_SELECTIONS
         })
         .to_string()),
-        "current" => Ok(serde_json::json!({
+        Command::History { seconds } => {
+            let seconds = seconds.unwrap_or(u64::MAX);
+            let since = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0)
+                .saturating_sub(seconds);
+            Ok(serde_json::json!({
+                "status": "success",
+                "history": controller.history_since(since)
+            })
+            .to_string())
+        }
+        Command::Selftest => Err(Error::Command(
+            "selftest is only available on a connection".into(),
+        )),
+    }
+}
+
+/// The `print all` snapshot, shared with the other control interfaces.
+pub fn status_json(controller: &FanController) -> Result<serde_json::Value> {
+    let temp = controller.get_actual_temperature()?;
+    let strategy = controller.get_current_strategy();
+    let moving_avg = controller.get_moving_average_temperature(strategy.moving_average_interval);
+    let effective = controller.get_effective_temperature(temp, strategy.moving_average_interval);
+    let (samples, window) = controller.moving_average_fill(strategy.moving_average_interval);
+    let (mode, mode_reason) = controller.fan_mode();
+
+    Ok(serde_json::json!({
+        "status": "success",
+        "strategy": controller.get_current_strategy_name(),
+        "default": !controller.is_overwritten(),
+        "profile": controller.profile(),
+        "speed": controller.get_current_speed().to_string(),
+        "temperature": temp.to_string(),
+        "movingAverageTemperature": moving_avg.to_string(),
+        "movingAverageSamples": samples,
+        "movingAverageWindow": window,
+        "effectiveTemperature": effective.to_string(),
+        "trend": controller.temperature_trend(),
+        "alerts": controller.active_alerts(),
+        "sensorAggregation": strategy.sensor_aggregation,
+        "active": controller.is_active(),
+        "mode": mode,
+        "modeReason": mode_reason,
+        "degraded": controller.is_degraded(),
+        "monitorOnly": controller.is_monitor_only(),
+        "dutyDrift": controller.duty_drift(),
+        "capabilities": controller.capabilities(),
+        "gpuBoostFloor": controller.gpu_boost_floor(),
+        "tripPoint": controller.near_trip(),
+        "tripPointFloor": controller.trip_point_floor(),
+        "quietHours": controller.active_quiet_hours(),
+        "bias": controller.get_speed_bias(),
+        "fanRpm": controller.get_fan_rpms().unwrap_or_default(),
+        "batteryTemperature": controller.get_battery_temperature().ok().flatten(),
+        "acConnected": controller.is_on_ac(),
+        "batteryPercent": controller.battery_charge(),
+        "batteryDischargeWatts": controller.battery_discharge_watts(),
+        "chargeState": controller.charge_state(),
+        "platform": controller.platform_name(),
+        "uptimeSeconds": controller.uptime().as_secs(),
+        "lastStrategyChange": controller.last_strategy_change(),
+        "configPath": controller.config_path(),
+        "configuration": controller.get_config()
+    }))
+}
+
+async fn print_selection(selection: Selection, controller: &mut FanController) -> Result<String> {
+    match selection {
+        Selection::All => Ok(status_json(controller)?.to_string()),
+        Selection::Active => Ok(serde_json::json!({
             "status": "success",
-            "strategy": controller.get_current_strategy_name(),
-            "default": !controller.is_overwritten()
+            "active": controller.is_active()
         })
         .to_string()),
-        "list" => {
+        Selection::Current => {
+            let mut reply = serde_json::json!({
+                "status": "success",
+                "strategy": controller.get_current_strategy_name(),
+                "default": !controller.is_overwritten()
+            });
+            if let Some((strategy, seconds)) = controller.pending_strategy_switch() {
+                reply["pending"] = serde_json::json!({
+                    "strategy": strategy,
+                    "inSeconds": seconds
+                });
+            }
+            if let Some(reason) = controller.last_strategy_change_reason() {
+                reply["lastChangeReason"] = serde_json::json!(reason);
+            }
+            Ok(reply.to_string())
+        }
+        Selection::List { detailed: true } => {
+            let config = controller.get_config();
+            let mut names = config.strategy_names();
+            names.sort();
+            let strategies: Vec<serde_json::Value> = names
+                .into_iter()
+                .map(|name| {
+                    let mut definition = serde_json::json!(config.strategies[name]);
+                    definition["name"] = serde_json::json!(name);
+                    definition
+                })
+                .collect();
+            Ok(serde_json::json!({
+                "status": "success",
+                "strategies": strategies,
+                "current": controller.get_current_strategy_name()
+            })
+            .to_string())
+        }
+        Selection::List { detailed: false } => {
             let strategies: Vec<String> = controller
                 .get_config()
                 .strategy_names()
@@ -206,18 +627,395 @@ async fn print_selection(selection: &str, controller: &mut FanController) -> Res
                 .collect();
             Ok(serde_json::json!({
                 "status": "success",
-                "strategies": strategies
+                "strategies": strategies,
+                "current": controller.get_current_strategy_name()
             })
             .to_string())
         }
-        "speed" => Ok(serde_json::json!({
+        Selection::Speed => Ok(serde_json::json!({
             "status": "success",
             "speed": controller.get_current_speed().to_string()
         })
         .to_string()),
-        _ => Err(Error::Command(format!(
-            "Unknown print selection: {}",
-            selection
-        ))),
+        Selection::Platform => Ok(serde_json::json!({
+            "status": "success",
+            "platform": controller.platform_info()
+        })
+        .to_string()),
+        Selection::Sensors => Ok(serde_json::json!({
+            "status": "success",
+            "sensors": controller.get_sensor_temperatures()?,
+            "batteryTemperature": controller.get_battery_temperature()?
+        })
+        .to_string()),
+        Selection::Rpm => Ok(serde_json::json!({
+            "status": "success",
+            "rpm": controller.get_fan_rpms()?
+        })
+        .to_string()),
+        Selection::Errors => Ok(serde_json::json!({
+            "status": "success",
+            "errors": controller.recent_errors()
+        })
+        .to_string()),
+        Selection::Stats => Ok(serde_json::json!({
+            "status": "success",
+            "stats": controller.thermal_stats()
+        })
+        .to_string()),
+        Selection::Config => Ok(serde_json::json!({
+            "status": "success",
+            "path": controller.config_path(),
+            "config": controller.get_config()
+        })
+        .to_string()),
+        Selection::Curve(name) => {
+            let name = name.unwrap_or_else(|| controller.get_current_strategy_name());
+            let config = controller.get_config();
+            let curve = config
+                .resolved_curve(&name)
+                .ok_or_else(|| Error::Strategy(format!("Unknown strategy: {}", name)))?;
+            Ok(serde_json::json!({
+                "status": "success",
+                "name": name,
+                "speedCurve": curve,
+                "compose": config.strategies[&name].compose,
+                "sensorCurves": config.strategies[&name].sensor_curves
+            })
+            .to_string())
+        }
+        Selection::Strategy(name) => {
+            let name = name.unwrap_or_else(|| controller.get_current_strategy_name());
+            let definition = controller
+                .get_config()
+                .get_strategy(&name)
+                .ok_or_else(|| Error::Strategy(format!("Unknown strategy: {}", name)))?;
+            Ok(serde_json::json!({
+                "status": "success",
+                "name": name,
+                "definition": definition
+            })
+            .to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufReader, Write};
+    use std::os::unix::net::UnixStream;
+
+    use crate::config::Config;
+    use crate::hardware::HardwareController;
+    use crate::mock::MockEc;
+    use crate::protocol::{read_frame, version_mismatch, write_frame};
+
+    fn mock_controller() -> ControllerHandle {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "defaultStrategy": "lazy",
+                "strategyOnDischarging": "",
+                "strategies": {
+                    "lazy": {
+                        "description": "Quiet until warm",
+                        "updateIntervalSeconds": 5,
+                        "movingAverageInterval": 30,
+                        "speedCurve": [{"temp": 0, "speed": 15}, {"temp": 85, "speed": 100}]
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        let ec = MockEc::new();
+        ec.set_temperatures(&[45]);
+        ec.set_fan_rpm(0, 2400);
+        for slot in 1..4 {
+            ec.set_fan_rpm(slot, 0xFFFF);
+        }
+        let hw = HardwareController::with_backend(Box::new(ec), None, "mock");
+        Arc::new(Mutex::new(FanController::new(hw, config, None)))
+    }
+
+    /// Serves one end of a socket pair on `runtime` and returns the other.
+    fn serve(
+        runtime: &tokio::runtime::Runtime,
+        controller: ControllerHandle,
+    ) -> (UnixStream, tokio::task::JoinHandle<Result<()>>) {
+        serve_as(runtime, controller, SocketAccess::Control)
+    }
+
+    fn serve_as(
+        runtime: &tokio::runtime::Runtime,
+        controller: ControllerHandle,
+        access: SocketAccess,
+    ) -> (UnixStream, tokio::task::JoinHandle<Result<()>>) {
+        let (client, server) = UnixStream::pair().unwrap();
+        server.set_nonblocking(true).unwrap();
+        let task = runtime.spawn(async move {
+            let stream = tokio::net::UnixStream::from_std(server)?;
+            let limiter = RateLimiter::new(MAX_COMMANDS_PER_SECOND);
+            handle_connection(stream, controller, access, &AuditLog::default(), &limiter).await
+        });
+        (client, task)
+    }
+
+    #[test]
+    fn test_connection_serves_multiple_commands() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let (mut client, server_task) = serve(&runtime, mock_controller());
+
+        write_frame(&mut client, "print current").unwrap();
+        write_frame(&mut client, "use missing").unwrap();
+        write_frame(&mut client, "print active").unwrap();
+        write_frame(&mut client, "print strategy lazy").unwrap();
+        write_frame(&mut client, "print list detailed").unwrap();
+        write_frame(&mut client, "print rpm").unwrap();
+        write_frame(&mut client, "version").unwrap();
+        write_frame(&mut client, "print errors").unwrap();
+        client.shutdown(std::net::Shutdown::Write).unwrap();
+
+        let mut reader = BufReader::new(&client);
+        let replies: Vec<serde_json::Value> =
+            std::iter::from_fn(|| read_frame(&mut reader).unwrap())
+                .map(|r| serde_json::from_str(&r).unwrap())
+                .collect();
+
+        assert_eq!(replies.len(), 8);
+        assert_eq!(replies[0]["strategy"], "lazy");
+        assert_eq!(replies[1]["status"], "error");
+        assert_eq!(replies[2]["active"], true);
+        assert_eq!(replies[3]["definition"]["description"], "Quiet until warm");
+        assert_eq!(replies[4]["strategies"][0]["name"], "lazy");
+        assert_eq!(replies[4]["strategies"][0]["movingAverageInterval"], 30);
+        assert_eq!(replies[5]["rpm"], serde_json::json!([2400]));
+        assert_eq!(version_mismatch(&replies[6]), None);
+        assert_eq!(replies[7]["errors"], serde_json::json!([]));
+        runtime.block_on(server_task).unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_strategy_replies_escape_the_name() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "defaultStrategy": "lazy",
+                "strategies": {
+                    "lazy": {"speedCurve": [{"temp": 0, "speed": 15}]},
+                    "say \"hi\" \\o/": {"speedCurve": [{"temp": 0, "speed": 30}]}
+                }
+            }"#,
+        )
+        .unwrap();
+        let hw = HardwareController::with_backend(Box::new(MockEc::new()), None, "mock");
+        let controller = Arc::new(Mutex::new(FanController::new(hw, config, None)));
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+
+        let name = r#"say "hi" \o/"#.to_string();
+        for command in [
+            Command::Use {
+                strategy: name.clone(),
+            },
+            Command::Cycle,
+            Command::Toggle {
+                strategy: name.clone(),
+                other: "lazy".into(),
+            },
+            Command::Reset,
+        ] {
+            let reply = runtime
+                .block_on(execute(command, Arc::clone(&controller)))
+                .unwrap();
+            let reply: serde_json::Value = serde_json::from_str(&reply).unwrap();
+            assert_eq!(reply["status"], "success");
+        }
+        let reply = runtime
+            .block_on(execute(
+                Command::Use {
+                    strategy: name.clone(),
+                },
+                controller,
+            ))
+            .unwrap();
+        let reply: serde_json::Value = serde_json::from_str(&reply).unwrap();
+        assert_eq!(reply["strategy"], name);
+    }
+
+    #[test]
+    fn test_jobs_commands() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let controller = mock_controller();
+        let command = |command: &str| -> serde_json::Value {
+            let reply = runtime
+                .block_on(process_command(command, Arc::clone(&controller)))
+                .unwrap_or_else(|e| error_response(&e));
+            serde_json::from_str(&reply).unwrap()
+        };
+
+        assert_eq!(command("jobs list")["jobs"], serde_json::json!([]));
+        assert_eq!(command("jobs start calibration")["status"], "error");
+        let id = command("jobs start fan-test")["jobId"].as_u64().unwrap();
+        assert_eq!(command("jobs start fan-test")["status"], "error");
+        assert_eq!(
+            command(&format!("jobs status {}", id))["job"]["kind"],
+            "fan-test"
+        );
+        assert_eq!(command(&format!("jobs cancel {}", id))["status"], "success");
+        assert_eq!(command("jobs status 99")["status"], "error");
+    }
+
+    #[test]
+    fn test_capabilities_match_the_command_set() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let controller = mock_controller();
+        let command = |command: &str| -> serde_json::Value {
+            let reply = runtime
+                .block_on(process_command(command, Arc::clone(&controller)))
+                .unwrap_or_else(|e| error_response(&e));
+            serde_json::from_str(&reply).unwrap()
+        };
+
+        let capabilities = command("capabilities");
+        assert_eq!(capabilities["protocolVersion"], PROTOCOL_VERSION);
+        assert_eq!(
+            capabilities["commands"].as_array().unwrap().len(),
+            COMMANDS.len()
+        );
+
+        // `selftest` streams and is handled per connection
+        for spec in COMMANDS.iter().filter(|spec| spec.name != "selftest") {
+            let reply = command(spec.name);
+            assert!(
+                !reply["reason"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .contains("Unknown command"),
+                "{} is listed but not handled",
+                spec.name
+            );
+        }
+        for selection in PRINT_SELECTIONS {
+            let reply = command(&format!("print {}", selection));
+            assert_eq!(reply["status"], "success", "print {}", selection);
+        }
+    }
+
+    #[test]
+    fn test_restart_auto_requests_shutdown() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let controller = mock_controller();
+        let (mut client, server_task) = serve(&runtime, Arc::clone(&controller));
+
+        write_frame(&mut client, "restart-auto").unwrap();
+        client.shutdown(std::net::Shutdown::Write).unwrap();
+        let reply = read_frame(&mut BufReader::new(&client)).unwrap().unwrap();
+        runtime.block_on(server_task).unwrap().unwrap();
+
+        assert!(reply.contains("success"));
+        let mut controller = controller.blocking_lock();
+        assert!(!controller.is_active());
+        assert_eq!(
+            controller.take_shutdown_request(),
+            Some(ShutdownRequest::RestartAuto)
+        );
+    }
+
+    #[test]
+    fn test_privileged_commands_need_root_or_daemon_user() {
+        let own = unsafe { libc::geteuid() };
+        let parse = |command: &str| command::parse(command).unwrap();
+        assert!(authorize(&parse("shutdown"), Some(own)).is_ok());
+        assert!(authorize(&parse("shutdown"), Some(0)).is_ok());
+        assert!(authorize(&parse("print"), None).is_ok());
+        assert!(authorize(&parse("restart-auto"), Some(own + 1)).is_err());
+        assert!(authorize(&parse("shutdown"), None).is_err());
+    }
+
+    #[test]
+    fn test_reload_with_a_path_needs_root_or_daemon_user() {
+        let own = unsafe { libc::geteuid() };
+        let reload_path = command::parse("reload /tmp/config.json").unwrap();
+        assert!(authorize(&reload_path, Some(own)).is_ok());
+        assert!(authorize(&reload_path, Some(0)).is_ok());
+        let denied = authorize(&reload_path, Some(own + 1)).unwrap_err();
+        assert_eq!(
+            denied.to_string(),
+            "Invalid command: Permission denied: reload with a path requires root"
+        );
+        let reload = command::parse("reload").unwrap();
+        assert!(authorize(&reload, Some(own + 1)).is_ok());
+    }
+
+    #[test]
+    fn test_query_socket_only_serves_reads() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let controller = mock_controller();
+        let (mut client, server_task) =
+            serve_as(&runtime, Arc::clone(&controller), SocketAccess::Query);
+
+        write_frame(&mut client, "use agile").unwrap();
+        write_frame(&mut client, "jobs start fan-test").unwrap();
+        write_frame(&mut client, "print current").unwrap();
+        write_frame(&mut client, "\"pause\"").unwrap();
+        write_frame(&mut client, "'reload' /tmp/x.json").unwrap();
+        client.shutdown(std::net::Shutdown::Write).unwrap();
+
+        let mut reader = BufReader::new(&client);
+        let replies: Vec<serde_json::Value> =
+            std::iter::from_fn(|| read_frame(&mut reader).unwrap())
+                .map(|r| serde_json::from_str(&r).unwrap())
+                .collect();
+        runtime.block_on(server_task).unwrap().unwrap();
+
+        assert_eq!(
+            replies[0]["reason"],
+            "Invalid command: use is not available on the query socket"
+        );
+        assert_eq!(replies[1]["status"], "error");
+        assert_eq!(replies[2]["strategy"], "lazy");
+        assert_eq!(
+            replies[3]["reason"],
+            "Invalid command: pause is not available on the query socket"
+        );
+        assert_eq!(
+            replies[4]["reason"],
+            "Invalid command: reload is not available on the query socket"
+        );
+        assert!(controller.blocking_lock().is_active());
+    }
+
+    #[test]
+    fn test_malformed_requests_get_error_replies() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let (mut client, server_task) = serve(&runtime, mock_controller());
+
+        client.write_all(b"use \xff\xfe\n").unwrap();
+        write_frame(&mut client, "version").unwrap();
+        client.write_all(&vec![b'x'; MAX_REQUEST_SIZE + 1]).unwrap();
+
+        let mut reader = BufReader::new(&client);
+        let replies: Vec<serde_json::Value> =
+            std::iter::from_fn(|| read_frame(&mut reader).unwrap())
+                .map(|r| serde_json::from_str(&r).unwrap())
+                .collect();
+        assert_eq!(replies.len(), 3);
+        assert_eq!(
+            replies[0]["reason"],
+            "Socket error: Frame is not valid UTF-8"
+        );
+        assert_eq!(replies[1]["status"], "success");
+        assert_eq!(
+            replies[2]["reason"],
+            format!("Socket error: Frame exceeds {} bytes", MAX_REQUEST_SIZE)
+        );
+        assert!(runtime.block_on(server_task).unwrap().is_err());
+    }
+
+    #[test]
+    fn test_rate_limit_is_per_user() {
+        let limiter = RateLimiter::new(2);
+        assert!(limiter.allow(Some(1000)));
+        assert!(limiter.allow(Some(1000)));
+        assert!(!limiter.allow(Some(1000)));
+        assert!(limiter.allow(Some(0)));
     }
 }
@@ -0,0 +1,163 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::controller::unix_now;
+use crate::error::Result;
+
+/// Where the daemon keeps its thermal counters across restarts.
+pub const STATS_PATH: &str = "/var/lib/fw-fanctrl/stats.json";
+
+/// Outcome of one scheduled self-test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelfTestResult {
+    /// Seconds since the Unix epoch.
+    pub at: u64,
+    pub duty: u32,
+    pub rpm: u16,
+    pub passed: bool,
+}
+
+/// Long-term thermal counters, for judging whether a repaste or curve change
+/// helped over weeks.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThermalStats {
+    /// Seconds since the Unix epoch when counting started.
+    #[serde(default)]
+    pub since: u64,
+    /// Times the temperature rose above the strategy's `safetyTemp`.
+    #[serde(default)]
+    pub overheat_events: u64,
+    /// Times `safetyTemp` forced a speed change the change budget would have held.
+    #[serde(default)]
+    pub failsafe_activations: u64,
+    /// Seconds spent above each `temperatureBands` entry, by band.
+    #[serde(default)]
+    pub seconds_above: BTreeMap<u32, u64>,
+    /// Outcome of the most recent `scheduledSelfTest`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_self_test: Option<SelfTestResult>,
+    #[serde(default)]
+    pub self_test_failures: u64,
+    /// Whether the last reading was above `safetyTemp`, so each excursion counts once.
+    #[serde(skip)]
+    overheated: bool,
+}
+
+impl ThermalStats {
+    pub fn new(since: u64) -> Self {
+        Self {
+            since,
+            ..Self::default()
+        }
+    }
+
+    /// Reads the counters saved at `path`; a missing or unreadable file starts over.
+    pub fn load(path: &Path) -> Self {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Self::new(unix_now()),
+            Err(e) => {
+                tracing::warn!("Failed to read {}: {}; starting over", path.display(), e);
+                return Self::new(unix_now());
+            }
+        };
+        serde_json::from_str(&content).unwrap_or_else(|e| {
+            tracing::warn!("Ignoring corrupt {}: {}", path.display(), e);
+            Self::new(unix_now())
+        })
+    }
+
+    /// Writes the counters to `path`, creating its folder. The file is replaced
+    /// atomically so a crash never leaves it half-written.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(folder) = path.parent() {
+            fs::create_dir_all(folder)?;
+        }
+        let temp = path.with_extension("json.tmp");
+        fs::write(
+            &temp,
+            serde_json::to_string_pretty(self).unwrap_or_default(),
+        )?;
+        fs::rename(&temp, path)?;
+        Ok(())
+    }
+
+    /// Accounts `seconds` at temperature `temp`.
+    pub fn record(&mut self, temp: f64, seconds: u64, bands: &[u32], safety_temp: u32) {
+        for band in bands {
+            let above = self.seconds_above.entry(*band).or_default();
+            if temp > *band as f64 {
+                *above += seconds;
+            }
+        }
+
+        let overheated = temp > safety_temp as f64;
+        if overheated && !self.overheated {
+            self.overheat_events += 1;
+        }
+        self.overheated = overheated;
+    }
+
+    pub fn record_failsafe(&mut self) {
+        self.failsafe_activations += 1;
+    }
+
+    pub fn record_self_test(&mut self, result: SelfTestResult) {
+        if !result.passed {
+            self.self_test_failures += 1;
+        }
+        self.last_self_test = Some(result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_excursions_count_once() {
+        let mut stats = ThermalStats::new(0);
+        for temp in [70.0, 90.0, 95.0, 80.0, 91.0] {
+            stats.record(temp, 1, &[75, 85], 85);
+        }
+
+        assert_eq!(stats.overheat_events, 2);
+        assert_eq!(stats.seconds_above[&75], 4);
+        assert_eq!(stats.seconds_above[&85], 3);
+    }
+
+    #[test]
+    fn test_stats_survive_a_restart() {
+        let dir = std::env::temp_dir().join(format!("fw-fanctrl-stats-{}", std::process::id()));
+        let path = dir.join("stats.json");
+        assert!(ThermalStats::load(&path).since > 42);
+
+        let mut stats = ThermalStats::new(42);
+        stats.record(90.0, 60, &[80], 85);
+        stats.record_failsafe();
+        stats.record_self_test(SelfTestResult {
+            at: 100,
+            duty: 30,
+            rpm: 0,
+            passed: false,
+        });
+        stats.save(&path).unwrap();
+
+        let loaded = ThermalStats::load(&path);
+        assert_eq!(loaded.since, 42);
+        assert_eq!(loaded.overheat_events, 1);
+        assert_eq!(loaded.failsafe_activations, 1);
+        assert_eq!(loaded.seconds_above[&80], 60);
+        assert_eq!(loaded.self_test_failures, 1);
+        assert_eq!(loaded.last_self_test.map(|result| result.at), Some(100));
+
+        fs::write(&path, "not json").unwrap();
+        assert_eq!(ThermalStats::load(&path).overheat_events, 0);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,310 @@
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::hardware::ChargeState;
+
+const HWMON_PATH: &str = "/sys/class/hwmon";
+const POWER_SUPPLY_PATH: &str = "/sys/class/power_supply";
+const DRM_PATH: &str = "/sys/class/drm";
+const USB_DEVICES_PATH: &str = "/sys/bus/usb/devices";
+const THERMAL_PATH: &str = "/sys/class/thermal";
+/// Where power-profiles-daemon keeps the selected profile across restarts.
+pub const POWER_PROFILES_STATE_PATH: &str = "/var/lib/power-profiles-daemon/state.ini";
+
+/// A temperature reading taken from the kernel hwmon interface.
+#[derive(Debug, Clone)]
+pub struct HwmonSensor {
+    pub label: String,
+    pub celsius: f64,
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+/// Reads every `temp*_input` exposed under `/sys/class/hwmon`.
+///
+/// Sensors without a `temp*_label` are labelled `<chip>/tempN`.
+pub fn read_hwmon_temperatures() -> Vec<HwmonSensor> {
+    let mut sensors = Vec::new();
+
+    let Ok(chips) = fs::read_dir(HWMON_PATH) else {
+        return sensors;
+    };
+
+    for chip in chips.flatten() {
+        let chip_path = chip.path();
+        let chip_name = read_trimmed(&chip_path.join("name")).unwrap_or_else(|| "hwmon".into());
+
+        let Ok(entries) = fs::read_dir(&chip_path) else {
+            continue;
+        };
+
+        for entry in entries.flatten() {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            let Some(prefix) = file_name
+                .strip_suffix("_input")
+                .filter(|p| p.starts_with("temp"))
+            else {
+                continue;
+            };
+
+            let Some(millidegrees) =
+                read_trimmed(&entry.path()).and_then(|v| v.parse::<i64>().ok())
+            else {
+                continue;
+            };
+
+            let label = read_trimmed(&chip_path.join(format!("{}_label", prefix)))
+                .unwrap_or_else(|| format!("{}/{}", chip_name, prefix));
+
+            sensors.push(HwmonSensor {
+                label,
+                celsius: millidegrees as f64 / 1000.0,
+            });
+        }
+    }
+
+    sensors
+}
+
+/// A passive or critical trip point of a kernel thermal zone, where the kernel
+/// starts throttling or shuts the machine down.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThermalTrip {
+    /// The zone's `type`, e.g. `acpitz` or `x86_pkg_temp`.
+    pub zone: String,
+    /// `passive` or `critical`.
+    pub kind: String,
+    pub trip_celsius: f64,
+    /// The zone's temperature when it was read.
+    pub zone_celsius: f64,
+}
+
+impl ThermalTrip {
+    /// Degrees left before the zone reaches the trip point.
+    pub fn margin(&self) -> f64 {
+        self.trip_celsius - self.zone_celsius
+    }
+}
+
+/// Passive and critical trip points of every thermal zone under `/sys/class/thermal`.
+pub fn read_thermal_trips() -> Vec<ThermalTrip> {
+    let Ok(entries) = fs::read_dir(THERMAL_PATH) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with("thermal_zone")
+        })
+        .flat_map(|entry| read_zone_trips(&entry.path()))
+        .collect()
+}
+
+/// Trip points of one `thermal_zoneN` directory; disabled ones (at or below 0°C)
+/// are skipped.
+fn read_zone_trips(zone: &Path) -> Vec<ThermalTrip> {
+    let millidegrees = |path: &Path| -> Option<f64> {
+        Some(read_trimmed(path)?.parse::<i64>().ok()? as f64 / 1000.0)
+    };
+    let Some(zone_celsius) = millidegrees(&zone.join("temp")) else {
+        return Vec::new();
+    };
+    let name = read_trimmed(&zone.join("type")).unwrap_or_else(|| {
+        zone.file_name()
+            .map_or_else(String::new, |n| n.to_string_lossy().into_owned())
+    });
+    let mut trips: Vec<ThermalTrip> = (0..)
+        .map_while(|n| {
+            let kind = read_trimmed(&zone.join(format!("trip_point_{}_type", n)))?;
+            let celsius = millidegrees(&zone.join(format!("trip_point_{}_temp", n)));
+            Some((kind, celsius))
+        })
+        .filter_map(|(kind, celsius)| {
+            let trip_celsius = celsius.filter(|c| *c > 0.0)?;
+            matches!(kind.as_str(), "passive" | "critical").then(|| ThermalTrip {
+                zone: name.clone(),
+                kind,
+                trip_celsius,
+                zone_celsius,
+            })
+        })
+        .collect();
+    trips.sort_by(|a, b| a.trip_celsius.total_cmp(&b.trip_celsius));
+    trips
+}
+
+/// Highest `gpu_busy_percent` among the DRM cards exposing it (amdgpu), or
+/// `None` when no card does.
+pub fn read_gpu_busy_percent() -> Option<u32> {
+    fs::read_dir(DRM_PATH)
+        .ok()?
+        .flatten()
+        .filter(|card| {
+            let name = card.file_name();
+            let name = name.to_string_lossy();
+            // Skip connectors such as card1-eDP-1, which link to the same device
+            name.starts_with("card") && !name.contains('-')
+        })
+        .filter_map(|card| {
+            read_trimmed(&card.path().join("device/gpu_busy_percent"))?
+                .parse()
+                .ok()
+        })
+        .max()
+}
+
+/// Charge of the first battery in percent, if one reports its capacity.
+pub fn read_battery_capacity() -> Option<u32> {
+    let supplies = fs::read_dir(POWER_SUPPLY_PATH).ok()?;
+
+    supplies.flatten().find_map(|supply| {
+        let path = supply.path();
+        if read_trimmed(&path.join("type")).as_deref() != Some("Battery") {
+            return None;
+        }
+        read_trimmed(&path.join("capacity"))?.parse().ok()
+    })
+}
+
+/// Charge state of the first battery that reports a status.
+pub fn read_charge_state() -> Option<ChargeState> {
+    let supplies = fs::read_dir(POWER_SUPPLY_PATH).ok()?;
+
+    supplies.flatten().find_map(|supply| {
+        let path = supply.path();
+        if read_trimmed(&path.join("type")).as_deref() != Some("Battery") {
+            return None;
+        }
+        parse_charge_state(&read_trimmed(&path.join("status"))?)
+    })
+}
+
+/// Power the first battery is discharging at in watts, 0 unless it reports
+/// `Discharging`; from `power_now`, or `current_now` times `voltage_now`.
+pub fn read_battery_discharge_watts() -> Option<f64> {
+    let supplies = fs::read_dir(POWER_SUPPLY_PATH).ok()?;
+
+    supplies.flatten().find_map(|supply| {
+        let path = supply.path();
+        if read_trimmed(&path.join("type")).as_deref() != Some("Battery") {
+            return None;
+        }
+        if read_trimmed(&path.join("status"))? != "Discharging" {
+            return Some(0.0);
+        }
+        let micro = |name: &str| -> Option<f64> { read_trimmed(&path.join(name))?.parse().ok() };
+        let microwatts = micro("power_now")
+            .or_else(|| Some(micro("current_now")? * micro("voltage_now")? / 1_000_000.0))?;
+        Some(microwatts.abs() / 1_000_000.0)
+    })
+}
+
+/// Maps a power_supply `status` attribute to a [`ChargeState`].
+fn parse_charge_state(status: &str) -> Option<ChargeState> {
+    match status {
+        "Charging" => Some(ChargeState::Charging),
+        "Discharging" => Some(ChargeState::Discharging),
+        "Full" => Some(ChargeState::Full),
+        "Not charging" => Some(ChargeState::ChargeLimited),
+        _ => None,
+    }
+}
+
+/// Returns whether a mains power supply reports being online, or `None` if the
+/// machine exposes no mains supply at all.
+pub fn read_ac_online() -> Option<bool> {
+    let supplies = fs::read_dir(POWER_SUPPLY_PATH).ok()?;
+
+    let mut found = false;
+    for supply in supplies.flatten() {
+        let path = supply.path();
+        if read_trimmed(&path.join("type")).as_deref() != Some("Mains") {
+            continue;
+        }
+        found = true;
+        if read_trimmed(&path.join("online")).as_deref() == Some("1") {
+            return Some(true);
+        }
+    }
+
+    found.then_some(false)
+}
+
+/// `vendor:product` IDs of the connected USB devices, in lowercase hex.
+pub fn read_usb_devices() -> Vec<String> {
+    let Ok(devices) = fs::read_dir(USB_DEVICES_PATH) else {
+        return Vec::new();
+    };
+
+    devices
+        .flatten()
+        .filter_map(|device| {
+            let path = device.path();
+            let vendor = read_trimmed(&path.join("idVendor"))?;
+            let product = read_trimmed(&path.join("idProduct"))?;
+            Some(format!("{}:{}", vendor, product).to_ascii_lowercase())
+        })
+        .collect()
+}
+
+/// The profile power-profiles-daemon last selected, e.g. `power-saver`.
+pub fn read_power_profile() -> Option<String> {
+    parse_power_profile(&fs::read_to_string(POWER_PROFILES_STATE_PATH).ok()?)
+}
+
+fn parse_power_profile(state: &str) -> Option<String> {
+    state
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("Profile="))
+        .map(|profile| profile.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_power_profiles_state() {
+        let state = "[State]\nDriver=amd_pstate\nProfile=power-saver\n";
+        assert_eq!(parse_power_profile(state).as_deref(), Some("power-saver"));
+        assert_eq!(parse_power_profile("[State]\n"), None);
+    }
+
+    #[test]
+    fn test_reads_passive_and_critical_trip_points() {
+        let zone = std::env::temp_dir().join(format!("fw-fanctrl-zone-{}", std::process::id()));
+        fs::create_dir_all(&zone).unwrap();
+        for (name, value) in [
+            ("type", "acpitz"),
+            ("temp", "61500"),
+            ("trip_point_0_type", "critical"),
+            ("trip_point_0_temp", "105000"),
+            ("trip_point_1_type", "active"),
+            ("trip_point_1_temp", "50000"),
+            ("trip_point_2_type", "passive"),
+            ("trip_point_2_temp", "95000"),
+            ("trip_point_3_type", "passive"),
+            ("trip_point_3_temp", "0"),
+        ] {
+            fs::write(zone.join(name), value).unwrap();
+        }
+
+        let trips = read_zone_trips(&zone);
+        let kinds: Vec<(&str, f64)> = trips
+            .iter()
+            .map(|t| (t.kind.as_str(), t.trip_celsius))
+            .collect();
+        assert_eq!(kinds, vec![("passive", 95.0), ("critical", 105.0)]);
+        assert_eq!(trips[0].zone, "acpitz");
+        assert_eq!(trips[0].margin(), 33.5);
+        fs::remove_dir_all(&zone).unwrap();
+    }
+}
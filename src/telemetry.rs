@@ -0,0 +1,295 @@
+use std::io::{Read, Write};
+use std::net::{TcpStream, UdpSocket};
+use std::time::Duration;
+
+use crate::config::{TelemetryConfig, TelemetrySinkConfig};
+use crate::controller::FanController;
+use crate::error::{Error, Result};
+use crate::socket::ControllerHandle;
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// One telemetry data point.
+#[derive(Debug, Clone)]
+pub struct Sample {
+    pub strategy: String,
+    pub temperature: f64,
+    pub effective_temperature: f64,
+    /// Samples behind the moving average, out of `moving_average_window`.
+    pub moving_average_samples: usize,
+    pub moving_average_window: usize,
+    pub speed: u32,
+    pub active: bool,
+}
+
+impl Sample {
+    pub fn capture(controller: &FanController) -> Result<Self> {
+        let temperature = controller.get_actual_temperature()?;
+        let interval = controller.get_current_strategy().moving_average_interval;
+        let (samples, window) = controller.moving_average_fill(interval);
+        Ok(Self {
+            strategy: controller.get_current_strategy_name(),
+            temperature,
+            effective_temperature: controller.get_effective_temperature(temperature, interval),
+            moving_average_samples: samples,
+            moving_average_window: window,
+            speed: controller.get_current_speed(),
+            active: controller.is_active(),
+        })
+    }
+
+    /// Formats the sample as a single InfluxDB line-protocol record.
+    pub fn to_line_protocol(&self) -> String {
+        format!(
+            "fw_fanctrl,strategy={} temperature={},effective_temperature={},\
+             moving_average_samples={}i,moving_average_window={}i,speed={}i,active={}",
+            escape_tag(&self.strategy),
+            self.temperature,
+            self.effective_temperature,
+            self.moving_average_samples,
+            self.moving_average_window,
+            self.speed,
+            self.active
+        )
+    }
+
+    /// Formats the sample as newline-separated statsd gauges.
+    pub fn to_statsd(&self, prefix: &str) -> String {
+        format!(
+            "{p}.temperature:{}|g\n{p}.effective_temperature:{}|g\n\
+             {p}.moving_average_samples:{}|g\n{p}.moving_average_window:{}|g\n\
+             {p}.speed:{}|g\n{p}.active:{}|g",
+            self.temperature,
+            self.effective_temperature,
+            self.moving_average_samples,
+            self.moving_average_window,
+            self.speed,
+            self.active as u8,
+            p = prefix
+        )
+    }
+}
+
+fn escape_tag(value: &str) -> String {
+    value
+        .replace(',', "\\,")
+        .replace('=', "\\=")
+        .replace(' ', "\\ ")
+}
+
+/// A destination for telemetry samples.
+pub trait TelemetrySink: Send {
+    fn name(&self) -> &str;
+    fn send(&mut self, sample: &Sample) -> Result<()>;
+}
+
+struct InfluxDbUdpSink {
+    socket: UdpSocket,
+    address: String,
+}
+
+impl TelemetrySink for InfluxDbUdpSink {
+    fn name(&self) -> &str {
+        "influxdb-udp"
+    }
+
+    fn send(&mut self, sample: &Sample) -> Result<()> {
+        self.socket
+            .send_to(sample.to_line_protocol().as_bytes(), &self.address)
+            .map_err(|e| Error::Socket(format!("Failed to send to {}: {}", self.address, e)))?;
+        Ok(())
+    }
+}
+
+struct InfluxDbHttpSink {
+    host: String,
+    path: String,
+    token: Option<String>,
+}
+
+impl InfluxDbHttpSink {
+    fn new(url: &str, token: Option<String>) -> Result<Self> {
+        let rest = url.strip_prefix("http://").ok_or_else(|| {
+            Error::Config(format!("InfluxDB URL must start with http://: {}", url))
+        })?;
+        let (host, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+        let host = if host.contains(':') {
+            host.to_string()
+        } else {
+            format!("{}:80", host)
+        };
+        Ok(Self {
+            host,
+            path: path.to_string(),
+            token,
+        })
+    }
+}
+
+impl TelemetrySink for InfluxDbHttpSink {
+    fn name(&self) -> &str {
+        "influxdb-http"
+    }
+
+    fn send(&mut self, sample: &Sample) -> Result<()> {
+        let body = sample.to_line_protocol();
+        let auth = self
+            .token
+            .as_ref()
+            .map(|t| format!("Authorization: Token {}\r\n", t))
+            .unwrap_or_default();
+        let request = format!(
+            "POST {} HTTP/1.1\r\nHost: {}\r\n{}Content-Type: text/plain\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.path,
+            self.host,
+            auth,
+            body.len(),
+            body
+        );
+
+        let io_err = |e: std::io::Error| Error::Socket(format!("InfluxDB {}: {}", self.host, e));
+        let mut stream = TcpStream::connect(&self.host).map_err(io_err)?;
+        stream
+            .set_read_timeout(Some(HTTP_TIMEOUT))
+            .map_err(io_err)?;
+        stream
+            .set_write_timeout(Some(HTTP_TIMEOUT))
+            .map_err(io_err)?;
+        stream.write_all(request.as_bytes()).map_err(io_err)?;
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).map_err(io_err)?;
+        let status_line = response.lines().next().unwrap_or_default();
+        if !status_line.contains(" 2") {
+            return Err(Error::Socket(format!(
+                "InfluxDB {} rejected write: {}",
+                self.host, status_line
+            )));
+        }
+        Ok(())
+    }
+}
+
+struct StatsdSink {
+    socket: UdpSocket,
+    address: String,
+    prefix: String,
+}
+
+impl TelemetrySink for StatsdSink {
+    fn name(&self) -> &str {
+        "statsd"
+    }
+
+    fn send(&mut self, sample: &Sample) -> Result<()> {
+        self.socket
+            .send_to(sample.to_statsd(&self.prefix).as_bytes(), &self.address)
+            .map_err(|e| Error::Socket(format!("Failed to send to {}: {}", self.address, e)))?;
+        Ok(())
+    }
+}
+
+fn udp_socket() -> Result<UdpSocket> {
+    UdpSocket::bind("0.0.0.0:0")
+        .map_err(|e| Error::Socket(format!("Failed to open UDP socket: {}", e)))
+}
+
+pub fn build_sinks(config: &TelemetryConfig) -> Result<Vec<Box<dyn TelemetrySink>>> {
+    config
+        .sinks
+        .iter()
+        .map(|sink| -> Result<Box<dyn TelemetrySink>> {
+            Ok(match sink {
+                TelemetrySinkConfig::InfluxDbUdp { address } => Box::new(InfluxDbUdpSink {
+                    socket: udp_socket()?,
+                    address: address.clone(),
+                }),
+                TelemetrySinkConfig::InfluxDbHttp { url, token } => {
+                    Box::new(InfluxDbHttpSink::new(url, token.clone())?)
+                }
+                TelemetrySinkConfig::Statsd { address, prefix } => Box::new(StatsdSink {
+                    socket: udp_socket()?,
+                    address: address.clone(),
+                    prefix: prefix.clone(),
+                }),
+            })
+        })
+        .collect()
+}
+
+/// Captures a sample every `interval_seconds` and pushes it to every sink.
+///
+/// Sinks do blocking I/O, so sending happens on the blocking pool.
+pub async fn run_telemetry(controller: ControllerHandle, config: TelemetryConfig) -> Result<()> {
+    let mut sinks = build_sinks(&config)?;
+    let mut ticker = tokio::time::interval(Duration::from_secs(config.interval_seconds));
+
+    loop {
+        ticker.tick().await;
+        let sample = match Sample::capture(&*controller.lock().await) {
+            Ok(sample) => sample,
+            Err(e) => {
+                tracing::warn!("Failed to capture telemetry sample: {}", e);
+                continue;
+            }
+        };
+
+        sinks = tokio::task::spawn_blocking(move || {
+            for sink in sinks.iter_mut() {
+                if let Err(e) = sink.send(&sample) {
+                    tracing::warn!("Telemetry sink {} failed: {}", sink.name(), e);
+                }
+            }
+            sinks
+        })
+        .await
+        .map_err(|e| Error::Socket(format!("Telemetry task failed: {}", e)))?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Sample {
+        Sample {
+            strategy: "lazy fan".into(),
+            temperature: 52.0,
+            effective_temperature: 50.5,
+            moving_average_samples: 12,
+            moving_average_window: 30,
+            speed: 30,
+            active: true,
+        }
+    }
+
+    #[test]
+    fn test_line_protocol() {
+        assert_eq!(
+            sample().to_line_protocol(),
+            "fw_fanctrl,strategy=lazy\\ fan temperature=52,effective_temperature=50.5,\
+             moving_average_samples=12i,moving_average_window=30i,speed=30i,active=true"
+        );
+    }
+
+    #[test]
+    fn test_statsd() {
+        assert_eq!(
+            sample().to_statsd("fw"),
+            "fw.temperature:52|g\nfw.effective_temperature:50.5|g\nfw.moving_average_samples:12|g\n\
+             fw.moving_average_window:30|g\nfw.speed:30|g\nfw.active:1|g"
+        );
+    }
+
+    #[test]
+    fn test_influxdb_http_url_parsing() {
+        let sink = InfluxDbHttpSink::new("http://localhost:8086/write?db=fw", None).unwrap();
+        assert_eq!(sink.host, "localhost:8086");
+        assert_eq!(sink.path, "/write?db=fw");
+        assert!(InfluxDbHttpSink::new("https://localhost/write", None).is_err());
+    }
+}
@@ -0,0 +1,62 @@
+use crate::config::Config;
+use crate::controller::FanController;
+use crate::error::Result;
+use crate::hardware::HardwareController;
+use crate::mock::MockEc;
+
+/// A [`FanController`] wired to a [`MockEc`] and run one control tick at a
+/// time, for tests that feed in temperatures and check the duties it commands.
+pub struct Simulation {
+    ec: MockEc,
+    controller: FanController,
+}
+
+impl Simulation {
+    /// Runs `strategy` from `config`, whatever the power source.
+    pub fn new(config: Config, strategy: &str) -> Self {
+        let ec = MockEc::new();
+        let hw = HardwareController::with_backend(Box::new(ec.clone()), None, "mock");
+        let controller = FanController::new(hw, config, Some(strategy.to_string()));
+        Self { ec, controller }
+    }
+
+    /// Sets the single EC sensor to `celsius`.
+    pub fn set_temperature(&self, celsius: u8) {
+        self.ec.set_temperatures(&[celsius]);
+    }
+
+    /// Runs one control tick and returns the duty the controller commands.
+    pub fn tick(&mut self) -> Result<u32> {
+        self.controller.step()?;
+        Ok(self.controller.get_current_speed())
+    }
+
+    /// Runs one tick per temperature in `trace` and returns the commanded
+    /// duty after each.
+    pub fn run_trace(&mut self, trace: &[u8]) -> Result<Vec<u32>> {
+        trace
+            .iter()
+            .map(|&celsius| {
+                self.set_temperature(celsius);
+                self.tick()
+            })
+            .collect()
+    }
+
+    /// Every duty written to the EC so far, in order.
+    pub fn duty_writes(&self) -> Vec<u32> {
+        self.ec.duty_writes()
+    }
+
+    pub fn ec(&self) -> &MockEc {
+        &self.ec
+    }
+
+    pub fn controller(&self) -> &FanController {
+        &self.controller
+    }
+
+    pub fn controller_mut(&mut self) -> &mut FanController {
+        &mut self.controller
+    }
+}
@@ -0,0 +1,252 @@
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style};
+use ratatui::symbols::Marker;
+use ratatui::text::Line;
+use ratatui::widgets::{Axis, Block, Chart, Dataset, GraphType, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::config::CurvePoint;
+use crate::error::Result;
+
+const HELP: &str = "←/→ select  ↑/↓ speed  shift+←/→ temp  a add  d delete  \
+                    enter save  esc cancel";
+
+/// How the editor was left.
+#[derive(Debug, Clone)]
+pub enum Outcome {
+    Save(Vec<CurvePoint>),
+    Cancel,
+}
+
+/// Editing state for one strategy's speed curve.
+#[derive(Debug, Clone)]
+pub struct CurveEditor {
+    pub name: String,
+    pub points: Vec<CurvePoint>,
+    pub selected: usize,
+    status: String,
+}
+
+impl CurveEditor {
+    pub fn new(name: impl Into<String>, points: Vec<CurvePoint>) -> Self {
+        Self {
+            name: name.into(),
+            points,
+            selected: 0,
+            status: String::new(),
+        }
+    }
+
+    pub fn select(&mut self, delta: isize) {
+        let last = self.points.len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(delta).min(last);
+    }
+
+    pub fn adjust_speed(&mut self, delta: i32) {
+        if let Some(point) = self.points.get_mut(self.selected) {
+            point.speed = (point.speed as i32 + delta).clamp(0, 100) as u32;
+        }
+    }
+
+    /// Moves the selected point sideways, staying between its neighbours.
+    pub fn adjust_temp(&mut self, delta: i32) {
+        let i = self.selected;
+        let min = if i == 0 {
+            0
+        } else {
+            self.points[i - 1].temp as i32 + 1
+        };
+        let max = self.points.get(i + 1).map_or(100, |p| p.temp as i32 - 1);
+        if let Some(point) = self.points.get_mut(i) {
+            point.temp = (point.temp as i32 + delta).clamp(min, max.max(min)) as u32;
+        }
+    }
+
+    /// Adds a point halfway to the next one (or 5°C past the last) and selects it.
+    pub fn insert_point(&mut self) {
+        let Some(current) = self.points.get(self.selected).cloned() else {
+            return;
+        };
+        let point = match self.points.get(self.selected + 1) {
+            Some(next) if next.temp - current.temp >= 2 => CurvePoint {
+                temp: (current.temp + next.temp) / 2,
+                speed: (current.speed + next.speed) / 2,
+            },
+            None if current.temp < 100 => CurvePoint {
+                temp: (current.temp + 5).min(100),
+                speed: current.speed,
+            },
+            _ => return,
+        };
+        self.points.insert(self.selected + 1, point);
+        self.selected += 1;
+    }
+
+    /// Removes the selected point, always keeping at least one.
+    pub fn remove_point(&mut self) {
+        if self.points.len() > 1 {
+            self.points.remove(self.selected);
+            self.select(0);
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [chart_area, status_area] =
+            Layout::vertical([Constraint::Min(10), Constraint::Length(3)]).areas(frame.area());
+
+        let mut line: Vec<(f64, f64)> = self
+            .points
+            .iter()
+            .map(|p| (p.temp as f64, p.speed as f64))
+            .collect();
+        // The curve holds its last speed up to 100°C
+        if let Some(&(temp, speed)) = line.last() {
+            if temp < 100.0 {
+                line.push((100.0, speed));
+            }
+        }
+        let selected: Vec<(f64, f64)> = self
+            .points
+            .get(self.selected)
+            .map(|p| vec![(p.temp as f64, p.speed as f64)])
+            .unwrap_or_default();
+
+        let datasets = vec![
+            Dataset::default()
+                .marker(Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(Color::Cyan))
+                .data(&line),
+            Dataset::default()
+                .marker(Marker::Block)
+                .graph_type(GraphType::Scatter)
+                .style(Style::default().fg(Color::Yellow))
+                .data(&selected),
+        ];
+        let axis = |title: &'static str| {
+            Axis::default()
+                .title(title)
+                .bounds([0.0, 100.0])
+                .labels(["0", "50", "100"])
+        };
+        let chart = Chart::new(datasets)
+            .block(Block::bordered().title(format!(" Speed curve: {} ", self.name)))
+            .x_axis(axis("°C"))
+            .y_axis(axis("%"));
+        frame.render_widget(chart, chart_area);
+
+        let point = self
+            .points
+            .get(self.selected)
+            .map(|p| format!("Point {}: {}°C → {}%", self.selected + 1, p.temp, p.speed))
+            .unwrap_or_default();
+        let status = Paragraph::new(vec![
+            Line::from(format!("{}  {}", point, self.status)),
+            Line::from(HELP),
+        ])
+        .block(Block::bordered());
+        frame.render_widget(status, status_area);
+    }
+}
+
+/// Runs the full-screen editor; `apply` is called after every change so the
+/// running daemon can follow along.
+pub fn run(
+    mut editor: CurveEditor,
+    mut apply: impl FnMut(&[CurvePoint]) -> Result<()>,
+) -> Result<Outcome> {
+    let mut terminal = ratatui::try_init()?;
+    let result = event_loop(&mut terminal, &mut editor, &mut apply);
+    ratatui::restore();
+    result
+}
+
+fn event_loop(
+    terminal: &mut DefaultTerminal,
+    editor: &mut CurveEditor,
+    apply: &mut impl FnMut(&[CurvePoint]) -> Result<()>,
+) -> Result<Outcome> {
+    loop {
+        terminal.draw(|frame| editor.draw(frame))?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        let before = editor.points.clone();
+        let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+        let step = if key.modifiers.contains(KeyModifiers::CONTROL) {
+            5
+        } else {
+            1
+        };
+        match key.code {
+            KeyCode::Left if shift => editor.adjust_temp(-step),
+            KeyCode::Right if shift => editor.adjust_temp(step),
+            KeyCode::Left => editor.select(-1),
+            KeyCode::Right => editor.select(1),
+            KeyCode::Up => editor.adjust_speed(step),
+            KeyCode::Down => editor.adjust_speed(-step),
+            KeyCode::Char('a') => editor.insert_point(),
+            KeyCode::Char('d') => editor.remove_point(),
+            KeyCode::Enter => return Ok(Outcome::Save(editor.points.clone())),
+            KeyCode::Esc | KeyCode::Char('q') => return Ok(Outcome::Cancel),
+            _ => {}
+        }
+
+        if editor.points != before {
+            editor.status = match apply(&editor.points) {
+                Ok(()) => "(applied live)".to_string(),
+                Err(e) => format!("(not applied: {})", e),
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::parse_points;
+
+    fn editor() -> CurveEditor {
+        CurveEditor::new("lazy", parse_points("0:10,50:20,90:100").unwrap())
+    }
+
+    #[test]
+    fn test_temperature_stays_between_neighbours() {
+        let mut editor = editor();
+        editor.select(1);
+        editor.adjust_temp(100);
+        assert_eq!(editor.points[1].temp, 89);
+        editor.adjust_temp(-100);
+        assert_eq!(editor.points[1].temp, 1);
+        editor.adjust_speed(-50);
+        assert_eq!(editor.points[1].speed, 0);
+    }
+
+    #[test]
+    fn test_insert_and_remove_points() {
+        let mut editor = editor();
+        editor.select(1);
+        editor.insert_point();
+        assert_eq!(editor.selected, 2);
+        assert_eq!(
+            crate::curve::format_points(&editor.points),
+            "0:10,50:20,70:60,90:100"
+        );
+
+        editor.select(10);
+        editor.insert_point();
+        assert_eq!(editor.points.last().unwrap().temp, 95);
+
+        for _ in 0..10 {
+            editor.remove_point();
+        }
+        assert_eq!(editor.points.len(), 1);
+        assert_eq!(editor.selected, 0);
+    }
+}
@@ -0,0 +1,125 @@
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+use tokio::io::unix::AsyncFd;
+
+use crate::error::Result;
+
+/// Multicast group the kernel broadcasts uevents on (udev uses group 2).
+const KERNEL_UEVENT_GROUP: u32 = 1;
+const UEVENT_BUFFER_SIZE: usize = 8192;
+
+/// Kernel uevents the daemon reacts to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceEvent {
+    /// A power supply changed, e.g. the charger was plugged in.
+    PowerSupply,
+    /// A USB device was plugged in or removed, e.g. a dock.
+    UsbDevice,
+}
+
+/// Kernel uevents for power supplies and USB devices.
+pub struct DeviceEvents {
+    socket: AsyncFd<OwnedFd>,
+}
+
+impl DeviceEvents {
+    /// Subscribes to kernel uevents over netlink.
+    pub fn open() -> Result<Self> {
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_DGRAM | libc::SOCK_CLOEXEC | libc::SOCK_NONBLOCK,
+                libc::NETLINK_KOBJECT_UEVENT,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let mut address: libc::sockaddr_nl = unsafe { std::mem::zeroed() };
+        address.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        address.nl_groups = KERNEL_UEVENT_GROUP;
+        let bound = unsafe {
+            libc::bind(
+                socket.as_raw_fd(),
+                &address as *const libc::sockaddr_nl as *const libc::sockaddr,
+                std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        };
+        if bound < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+
+        Ok(Self {
+            socket: AsyncFd::new(socket)?,
+        })
+    }
+
+    /// Waits for the next power supply or USB device uevent, skipping all others.
+    pub async fn next(&mut self) -> Result<DeviceEvent> {
+        let mut buffer = [0u8; UEVENT_BUFFER_SIZE];
+        loop {
+            let mut ready = self.socket.readable().await?;
+            let received = ready.try_io(|socket| {
+                let n = unsafe {
+                    libc::recv(
+                        socket.as_raw_fd(),
+                        buffer.as_mut_ptr() as *mut libc::c_void,
+                        buffer.len(),
+                        0,
+                    )
+                };
+                if n < 0 {
+                    Err(io::Error::last_os_error())
+                } else {
+                    Ok(n as usize)
+                }
+            });
+            match received {
+                Ok(Ok(n)) => match device_event(&buffer[..n]) {
+                    Some(event) => return Ok(event),
+                    None => continue,
+                },
+                Err(_) => continue,
+                Ok(Err(e)) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+/// Kernel uevents are a `action@devpath` header followed by NUL-separated
+/// `KEY=value` pairs. USB events count for whole devices being added or
+/// removed, not their interfaces.
+fn device_event(message: &[u8]) -> Option<DeviceEvent> {
+    let has = |wanted: &[u8]| message.split(|&b| b == 0).any(|field| field == wanted);
+    if has(b"SUBSYSTEM=power_supply") {
+        Some(DeviceEvent::PowerSupply)
+    } else if has(b"SUBSYSTEM=usb")
+        && has(b"DEVTYPE=usb_device")
+        && (has(b"ACTION=add") || has(b"ACTION=remove"))
+    {
+        Some(DeviceEvent::UsbDevice)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_power_supply_and_usb_devices() {
+        let ac = b"change@/devices/LNXSYSTM:00/ACPI0003:00/power_supply/ACAD\0ACTION=change\0\
+                   SUBSYSTEM=power_supply\0POWER_SUPPLY_ONLINE=1\0";
+        let dock = b"add@/devices/pci0000:00/usb1/1-1\0ACTION=add\0SUBSYSTEM=usb\0\
+                     DEVTYPE=usb_device\0";
+        let interface = b"add@/devices/pci0000:00/usb1/1-1/1-1:1.0\0ACTION=add\0\
+                          SUBSYSTEM=usb\0DEVTYPE=usb_interface\0";
+        assert_eq!(device_event(ac), Some(DeviceEvent::PowerSupply));
+        assert_eq!(device_event(dock), Some(DeviceEvent::UsbDevice));
+        assert_eq!(device_event(interface), None);
+    }
+}
@@ -19,6 +19,7 @@ fn test_no_command() {
 }
 
 #[test]
+#[cfg(feature = "daemon")]
 fn test_run_help() {
     let mut cmd = Command::cargo_bin("fw-fanctrl").unwrap();
     cmd.arg("run")
@@ -29,6 +30,7 @@ fn test_run_help() {
 }
 
 #[test]
+#[cfg(feature = "daemon")]
 fn test_run_requires_config() {
     let mut cmd = Command::cargo_bin("fw-fanctrl").unwrap();
     cmd.arg("run")
@@ -39,6 +41,27 @@ fn test_run_requires_config() {
         .stderr(predicate::str::contains("Failed to read config"));
 }
 
+#[test]
+#[cfg(feature = "daemon")]
+fn test_daemon_binary_takes_run_options() {
+    let mut cmd = Command::cargo_bin("fw-fanctrld").unwrap();
+    cmd.args(["--config", "/nonexistent/path/config.json"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed to read config"));
+}
+
+#[test]
+#[cfg(feature = "daemon")]
+fn test_apply_requires_config() {
+    let mut cmd = Command::cargo_bin("fw-fanctrl").unwrap();
+    cmd.args(["apply", "--strategy", "lazy", "--config"])
+        .arg("/nonexistent/path/config.json")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed to read config"));
+}
+
 #[test]
 fn test_use_requires_strategy() {
     let mut cmd = Command::cargo_bin("fw-fanctrl").unwrap();
@@ -46,6 +69,7 @@ fn test_use_requires_strategy() {
 }
 
 #[test]
+#[cfg(feature = "framework")]
 fn test_sanity_check_non_framework() {
     let mut cmd = Command::cargo_bin("fw-fanctrl").unwrap();
     cmd.arg("sanity-check")
@@ -111,3 +135,239 @@ fn test_resume_is_subcommand() {
     let mut cmd = Command::cargo_bin("fw-fanctrl").unwrap();
     cmd.arg("resume").assert().failure();
 }
+
+#[test]
+fn test_tune_requires_field_and_value() {
+    let mut cmd = Command::cargo_bin("fw-fanctrl").unwrap();
+    cmd.args(["tune", "lazy"]).assert().failure();
+
+    let mut cmd = Command::cargo_bin("fw-fanctrl").unwrap();
+    cmd.args(["tune", "lazy", "hysteresis", "3"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed to connect"));
+}
+
+#[test]
+fn test_version_requires_daemon() {
+    let mut cmd = Command::cargo_bin("fw-fanctrl").unwrap();
+    cmd.arg("version")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed to connect"));
+}
+
+#[test]
+#[cfg(feature = "daemon")]
+fn test_run_help_lists_allow_unsupported() {
+    let mut cmd = Command::cargo_bin("fw-fanctrl").unwrap();
+    cmd.arg("run")
+        .arg("--help")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--allow-unsupported"));
+}
+
+#[test]
+fn test_export_rejects_invalid_duration() {
+    let mut cmd = Command::cargo_bin("fw-fanctrl").unwrap();
+    cmd.arg("export")
+        .arg("--since")
+        .arg("1 week")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid duration"));
+
+    let mut cmd = Command::cargo_bin("fw-fanctrl").unwrap();
+    cmd.arg("export")
+        .arg("--since")
+        .arg("999999999999999999d")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid duration"));
+}
+
+#[test]
+fn test_export_requires_daemon() {
+    let mut cmd = Command::cargo_bin("fw-fanctrl").unwrap();
+    cmd.arg("export")
+        .arg("--since")
+        .arg("30m")
+        .arg("--format")
+        .arg("json")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed to connect"));
+}
+
+#[test]
+fn test_watch_requires_daemon() {
+    let mut cmd = Command::cargo_bin("fw-fanctrl").unwrap();
+    cmd.arg("watch")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed to connect"));
+}
+
+#[test]
+fn test_adjust_accepts_negative_bias() {
+    let mut cmd = Command::cargo_bin("fw-fanctrl").unwrap();
+    cmd.arg("adjust")
+        .arg("-5")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed to connect"));
+}
+
+#[test]
+fn test_install_stages_files_under_root() {
+    let root = std::env::temp_dir().join(format!("fw-fanctrl-root-{}", uuid::Uuid::new_v4()));
+    let mut cmd = Command::cargo_bin("fw-fanctrl").unwrap();
+    cmd.args(["install", "--init", "runit", "--root"])
+        .arg(&root)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Wrote default config"));
+
+    let run = std::fs::read_to_string(root.join("etc/sv/fw-fanctrl/run")).unwrap();
+    assert!(run.contains(" run --config /etc/fw-fanctrl/config.json --silent"));
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_setup_permissions_stages_udev_rule() {
+    let root = std::env::temp_dir().join(format!("fw-fanctrl-root-{}", uuid::Uuid::new_v4()));
+    let mut cmd = Command::cargo_bin("fw-fanctrl").unwrap();
+    cmd.args(["setup-permissions", "--root"])
+        .arg(&root)
+        .assert()
+        .success();
+
+    let rule = std::fs::read_to_string(root.join("etc/udev/rules.d/60-fw-fanctrl.rules")).unwrap();
+    assert!(rule.contains("KERNEL==\"cros_ec\", GROUP=\"fw-fanctrl\", MODE=\"0660\""));
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_doctor_reports_missing_daemon() {
+    let mut cmd = Command::cargo_bin("fw-fanctrl").unwrap();
+    cmd.args(["--color", "never", "doctor", "-c", "config.json"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("[OK  ] Config"))
+        .stdout(predicate::str::contains("[FAIL] Socket"));
+}
+
+#[test]
+fn test_check_config_reports_unknown_keys() {
+    let path = std::env::temp_dir().join(format!("fw-fanctrl-{}.json", uuid::Uuid::new_v4()));
+    std::fs::write(
+        &path,
+        r#"{"defaultStrategy": "a", "strategyOnDischarging": "",
+            "strategies": {"a": {"updateIntervalSeconds": 5, "movingAverageInterval": 30,
+            "speedCurve": [{"temp": 0, "speed": 0}], "speedcurve": []}}}"#,
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("fw-fanctrl").unwrap();
+    cmd.args(["--color", "never", "check-config", "-c"])
+        .arg(&path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "unknown key 'strategies.a.speedcurve'",
+        ));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_config_migrate_renames_legacy_keys() {
+    let path = std::env::temp_dir().join(format!("fw-fanctrl-{}.json", uuid::Uuid::new_v4()));
+    std::fs::write(
+        &path,
+        r#"{"defaultStrategy": "a", "strategies": {"a": {"fanSpeedUpdateFrequency": 3,
+            "speedCurve": [{"temp": 0, "speed": 0}]}}}"#,
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("fw-fanctrl").unwrap();
+    cmd.args(["config", "migrate", "-c"])
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Migrated"));
+    let migrated: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(migrated["strategies"]["a"]["updateIntervalSeconds"], 3);
+    assert!(migrated["strategies"]["a"]
+        .get("fanSpeedUpdateFrequency")
+        .is_none());
+
+    let mut cmd = Command::cargo_bin("fw-fanctrl").unwrap();
+    cmd.args(["config", "migrate", "-c"])
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "already uses the current key names",
+        ));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_strategy_export_and_import() {
+    let dir = std::env::temp_dir();
+    let source = dir.join(format!("fw-fanctrl-{}.json", uuid::Uuid::new_v4()));
+    let target = dir.join(format!("fw-fanctrl-{}.json", uuid::Uuid::new_v4()));
+    let exported = dir.join(format!("fw-fanctrl-{}.json", uuid::Uuid::new_v4()));
+    std::fs::write(
+        &source,
+        r#"{"defaultStrategy": "quiet", "strategies": {"quiet": {"hysteresis": 4,
+            "speedCurve": [{"temp": 50, "speed": 0}, {"temp": 90, "speed": 100}]}}}"#,
+    )
+    .unwrap();
+    std::fs::write(
+        &target,
+        r#"{"defaultStrategy": "a", "strategies": {"a": {"speedCurve": [{"temp": 0, "speed": 0}]}}}"#,
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("fw-fanctrl").unwrap();
+    cmd.args(["strategy", "export", "quiet", "-c"])
+        .arg(&source)
+        .arg("-o")
+        .arg(&exported)
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("fw-fanctrl").unwrap();
+    cmd.args(["strategy", "import", "--name", "desk", "-c"])
+        .arg(&target)
+        .arg(&exported)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Imported 'desk'"));
+    let imported: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&target).unwrap()).unwrap();
+    assert_eq!(imported["strategies"]["desk"]["hysteresis"], 4);
+
+    // The same name again needs --force
+    let mut cmd = Command::cargo_bin("fw-fanctrl").unwrap();
+    cmd.args(["strategy", "import", "--name", "desk", "-c"])
+        .arg(&target)
+        .arg(&exported)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("already exists"));
+
+    for path in [source, target, exported] {
+        std::fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn test_config_schema_is_json() {
+    let mut cmd = Command::cargo_bin("fw-fanctrl").unwrap();
+    let output = cmd.args(["config", "schema"]).assert().success();
+    let schema: serde_json::Value = serde_json::from_slice(&output.get_output().stdout).unwrap();
+    assert_eq!(schema["title"], "Config");
+}
@@ -1,9 +1,13 @@
-use std::collections::HashMap;
-use std::fs::File;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
 use std::io::Write;
 use std::path::PathBuf;
 
-use fw_fanctrl::config::{Config, CurvePoint, Strategy};
+use chrono::NaiveTime;
+use fw_fanctrl::config::{
+    AcDetectionFallback, Config, CurvePoint, ProfileEnvironment, SensorAggregation, Smoothing,
+    StartupBehavior, Strategy, StrategyChange, StrategyExport,
+};
 
 fn create_temp_config(content: &str) -> PathBuf {
     let dir = std::env::temp_dir();
@@ -47,7 +51,8 @@ fn create_valid_config() -> (PathBuf, Config) {
     strategies.insert(
         "performance".to_string(),
         Strategy {
-            fan_speed_update_frequency: 2,
+            description: None,
+            update_interval_seconds: 2,
             moving_average_interval: 30,
             speed_curve: vec![
                 CurvePoint { temp: 0, speed: 0 },
@@ -64,12 +69,23 @@ fn create_valid_config() -> (PathBuf, Config) {
                     speed: 100,
                 },
             ],
+            compose: Vec::new(),
+            hysteresis: 0,
+            speed_decay_seconds: 0,
+            max_speed_changes_per_minute: 0,
+            safety_temp: 85,
+            smoothing: Smoothing::MovingAverage,
+            sensor_aggregation: SensorAggregation::Max,
+            sensor_curves: Vec::new(),
+            charge_rate_limit: None,
+            discharge_saver: None,
         },
     );
     strategies.insert(
         "balanced".to_string(),
         Strategy {
-            fan_speed_update_frequency: 5,
+            description: None,
+            update_interval_seconds: 5,
             moving_average_interval: 60,
             speed_curve: vec![
                 CurvePoint { temp: 0, speed: 0 },
@@ -83,12 +99,43 @@ fn create_valid_config() -> (PathBuf, Config) {
                     speed: 100,
                 },
             ],
+            compose: Vec::new(),
+            hysteresis: 0,
+            speed_decay_seconds: 0,
+            max_speed_changes_per_minute: 0,
+            safety_temp: 85,
+            smoothing: Smoothing::MovingAverage,
+            sensor_aggregation: SensorAggregation::Max,
+            sensor_curves: Vec::new(),
+            charge_rate_limit: None,
+            discharge_saver: None,
         },
     );
     let config = Config {
         default_strategy: "performance".to_string(),
         strategy_on_discharging: "balanced".to_string(),
+        strategy_while_charging: String::new(),
         strategies,
+        telemetry: None,
+        quiet_hours: None,
+        strategy_cycle: Vec::new(),
+        ec_layout: None,
+        gpu_boost: None,
+        trip_points: None,
+        scheduled_self_test: None,
+        profiles: BTreeMap::new(),
+        power_switch_delay_seconds: 0,
+        spike_filter_celsius: 0,
+        discharging_strategy_battery_threshold: 0,
+        ac_detection_fallback: AcDetectionFallback::HoldLast,
+        startup_behavior: StartupBehavior::Average,
+        startup_speed: 0,
+        exclude_battery_sensor: None,
+        exclude_sensors: Vec::new(),
+        sensor_sources: Vec::new(),
+        include_sensors: Vec::new(),
+        temperature_bands: vec![70, 80, 90],
+        alerts: Vec::new(),
     };
 
     (path, config)
@@ -184,7 +231,7 @@ fn test_get_strategy() {
     let config = Config::load(&path).unwrap();
     let strategy = config.get_strategy("performance");
     assert!(strategy.is_some());
-    assert_eq!(strategy.unwrap().fan_speed_update_frequency, 2);
+    assert_eq!(strategy.unwrap().update_interval_seconds, 2);
 }
 
 #[test]
@@ -192,7 +239,7 @@ fn test_get_default_strategy() {
     let (path, _) = create_valid_config();
     let config = Config::load(&path).unwrap();
     let strategy = config.get_default_strategy();
-    assert_eq!(strategy.fan_speed_update_frequency, 2);
+    assert_eq!(strategy.update_interval_seconds, 2);
 }
 
 #[test]
@@ -200,7 +247,7 @@ fn test_get_discharging_strategy() {
     let (path, _) = create_valid_config();
     let config = Config::load(&path).unwrap();
     let strategy = config.get_discharging_strategy();
-    assert_eq!(strategy.fan_speed_update_frequency, 5);
+    assert_eq!(strategy.update_interval_seconds, 5);
 }
 
 #[test]
@@ -219,5 +266,537 @@ fn test_discharging_fallback_to_default() {
     let path = create_temp_config(content);
     let config = Config::load(&path).unwrap();
     let strategy = config.get_discharging_strategy();
-    assert_eq!(strategy.fan_speed_update_frequency, 2);
+    assert_eq!(strategy.update_interval_seconds, 2);
+}
+
+#[test]
+fn test_config_with_telemetry_sinks() {
+    let content = r#"{
+        "defaultStrategy": "performance",
+        "strategyOnDischarging": "",
+        "strategies": {
+            "performance": {
+                "fanSpeedUpdateFrequency": 2,
+                "movingAverageInterval": 30,
+                "speedCurve": [{"temp": 0, "speed": 0}]
+            }
+        },
+        "telemetry": {
+            "sinks": [
+                {"type": "influxdb-udp", "address": "127.0.0.1:8089"},
+                {"type": "statsd", "address": "127.0.0.1:8125"}
+            ]
+        }
+    }"#;
+    let path = create_temp_config(content);
+    let config = Config::load(&path).unwrap();
+    let telemetry = config.telemetry.unwrap();
+    assert_eq!(telemetry.interval_seconds, 10);
+    assert_eq!(telemetry.sinks.len(), 2);
+}
+
+#[test]
+fn test_cycle_order_defaults_to_sorted_names() {
+    let (path, _) = create_valid_config();
+    let mut config = Config::load(&path).unwrap();
+    assert_eq!(config.cycle_order(), vec!["balanced", "performance"]);
+
+    config.strategy_cycle = vec!["performance".into(), "balanced".into()];
+    assert_eq!(config.cycle_order(), vec!["performance", "balanced"]);
+
+    config.strategy_cycle.push("missing".into());
+    assert!(config.validate().is_err());
+}
+
+#[test]
+fn test_quiet_hours_window_spans_midnight() {
+    let content = r#"{
+        "defaultStrategy": "performance",
+        "strategyOnDischarging": "",
+        "strategies": {
+            "performance": {
+                "fanSpeedUpdateFrequency": 2,
+                "movingAverageInterval": 30,
+                "speedCurve": [{"temp": 0, "speed": 0}]
+            }
+        },
+        "quietHours": {"start": "22:30", "end": "07:00", "maxSpeed": 40}
+    }"#;
+    let path = create_temp_config(content);
+    let quiet = Config::load(&path).unwrap().quiet_hours.unwrap();
+    let at = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+    assert!(quiet.contains(at(23, 0)));
+    assert!(quiet.contains(at(3, 15)));
+    assert!(!quiet.contains(at(7, 0)));
+    assert!(!quiet.contains(at(12, 0)));
+}
+
+#[test]
+fn test_quiet_hours_rejects_invalid_time() {
+    let content = r#"{
+        "defaultStrategy": "performance",
+        "strategyOnDischarging": "",
+        "strategies": {
+            "performance": {
+                "fanSpeedUpdateFrequency": 2,
+                "movingAverageInterval": 30,
+                "speedCurve": [{"temp": 0, "speed": 0}]
+            }
+        },
+        "quietHours": {"start": "25:00", "end": "07:00", "maxSpeed": 40}
+    }"#;
+    let path = create_temp_config(content);
+    let result = Config::load(&path);
+    assert!(result.unwrap_err().to_string().contains("Invalid time"));
+}
+
+#[test]
+fn test_unknown_keys_warn_by_default_and_fail_when_strict() {
+    let content = r#"{
+        "defaultStrategy": "performance",
+        "strategyOnDischarging": "",
+        "strategies": {
+            "performance": {
+                "fanSpeedUpdateFrequency": 2,
+                "movingAverageInterval": 30,
+                "speedCurve": [{"temp": 0, "speed": 0}],
+                "speedcurve": []
+            }
+        },
+        "quietHour": {}
+    }"#;
+    let path = create_temp_config(content);
+
+    let (_, unknown) = Config::load_with_unknown_keys(&path).unwrap();
+    assert_eq!(
+        unknown,
+        vec!["quietHour", "strategies.performance.speedcurve"]
+    );
+    assert!(Config::load(&path).is_ok());
+
+    let result = Config::load_strict(&path);
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("strategies.performance.speedcurve"));
+}
+
+#[test]
+fn test_snake_case_keys_are_accepted() {
+    let content = r#"{
+        "default_strategy": "performance",
+        "strategyOnDischarging": "",
+        "quiet_hours": {"start": "22:00", "end": "07:00", "max_speed": 30},
+        "strategies": {
+            "performance": {
+                "fan_speed_update_frequency": 2,
+                "movingAverageInterval": 20,
+                "speed_curve": [{"temp": 0, "speed": 0}]
+            }
+        }
+    }"#;
+    let path = create_temp_config(content);
+
+    let (config, unknown) = Config::load_with_unknown_keys(&path).unwrap();
+    assert!(unknown.is_empty());
+    assert_eq!(config.default_strategy, "performance");
+    assert_eq!(config.quiet_hours.unwrap().max_speed, 30);
+    let strategy = &config.strategies["performance"];
+    assert_eq!(strategy.update_interval_seconds, 2);
+    assert_eq!(strategy.moving_average_interval, 20);
+
+    // Configs are always written back with the camelCase names
+    let saved = serde_json::to_string(&config.strategies["performance"]).unwrap();
+    assert!(saved.contains("updateIntervalSeconds"));
+}
+
+#[test]
+fn test_fan_speed_update_frequency_reads_as_update_interval() {
+    let content = r#"{
+        "defaultStrategy": "legacy",
+        "strategies": {
+            "legacy": {"fanSpeedUpdateFrequency": 3, "speedCurve": [{"temp": 0, "speed": 20}]},
+            "snake": {"fan_speed_update_frequency": 4, "speedCurve": [{"temp": 0, "speed": 20}]}
+        }
+    }"#;
+    let path = create_temp_config(content);
+
+    let (config, unknown) = Config::load_with_unknown_keys(&path).unwrap();
+    assert!(unknown.is_empty());
+    assert_eq!(config.strategies["legacy"].update_interval_seconds, 3);
+    assert_eq!(config.strategies["snake"].update_interval_seconds, 4);
+
+    let saved = serde_json::to_string(&config.strategies["legacy"]).unwrap();
+    assert!(saved.contains("\"updateIntervalSeconds\":3"));
+    assert!(!saved.contains("fanSpeedUpdateFrequency"));
+}
+
+#[test]
+fn test_ac_detection_fallback_is_camel_case() {
+    let fallback: AcDetectionFallback = serde_json::from_str(r#""assumeBattery""#).unwrap();
+    assert_eq!(fallback, AcDetectionFallback::AssumeBattery);
+    assert!(serde_json::from_str::<AcDetectionFallback>(r#""assume-battery""#).is_err());
+    assert_eq!(
+        serde_json::to_string(&fallback).unwrap(),
+        r#""assumeBattery""#
+    );
+}
+
+#[test]
+fn test_save_replaces_the_file_atomically() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let (path, mut config) = create_valid_config();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+    config.default_strategy = "balanced".into();
+    config.save(&path).unwrap();
+
+    assert_eq!(Config::load(&path).unwrap().default_strategy, "balanced");
+    assert_eq!(
+        fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+        0o600
+    );
+    assert!(!path.with_extension("json.tmp").exists());
+
+    config.default_strategy = "missing".into();
+    assert!(config.save(&path).is_err());
+    assert_eq!(Config::load(&path).unwrap().default_strategy, "balanced");
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_minimal_strategy_uses_defaults() {
+    let content = r#"{
+        "defaultStrategy": "minimal",
+        "strategies": {
+            "minimal": {"speedCurve": [{"temp": 0, "speed": 20}]}
+        }
+    }"#;
+    let path = create_temp_config(content);
+    let config = Config::load(&path).unwrap();
+    assert_eq!(config.strategy_on_discharging, "");
+
+    let strategy = config.get_strategy("minimal").unwrap();
+    assert_eq!(strategy.update_interval_seconds, 5);
+    assert_eq!(strategy.moving_average_interval, 30);
+    assert_eq!(strategy.hysteresis, 0);
+    assert_eq!(strategy.speed_decay_seconds, 0);
+    assert_eq!(strategy.max_speed_changes_per_minute, 0);
+    assert_eq!(strategy.safety_temp, 85);
+    assert_eq!(strategy.smoothing, Smoothing::MovingAverage);
+}
+
+#[test]
+fn test_curve_shorthands_are_normalized() {
+    let content = r#"{
+        "defaultStrategy": "map",
+        "strategies": {
+            "map": {"speedCurve": {"80": 70, "40": 0, "60": 30}},
+            "pairs": {"speedCurve": [[40, 0], [60, 30], [80, 70]]},
+            "objects": {"speedCurve": [
+                {"temp": 40, "speed": 0}, {"temp": 60, "speed": 30}, {"temp": 80, "speed": 70}
+            ]}
+        }
+    }"#;
+    let path = create_temp_config(content);
+    let config = Config::load(&path).unwrap();
+
+    let expected = config.strategies["objects"].speed_curve.clone();
+    assert_eq!(config.strategies["map"].speed_curve, expected);
+    assert_eq!(config.strategies["pairs"].speed_curve, expected);
+
+    let path = create_temp_config(
+        r#"{"defaultStrategy": "a", "strategies": {"a": {"speedCurve": [[40, 0, 1]]}}}"#,
+    );
+    let err = Config::load(&path).unwrap_err().to_string();
+    assert!(err.contains("curve pairs must be [temp, speed]"), "{}", err);
+}
+
+#[test]
+fn test_sensor_curve_names_are_unique() {
+    let content = r#"{
+        "defaultStrategy": "desk",
+        "strategies": {
+            "desk": {
+                "speedCurve": [{"temp": 0, "speed": 20}],
+                "sensorCurves": [
+                    {"name": "nvme", "sensor": "Composite", "speedCurve": [{"temp": 50, "speed": 40}]},
+                    {"name": "nvme", "sensor": 4, "speedCurve": [{"temp": 60, "speed": 80}]}
+                ]
+            }
+        }
+    }"#;
+    let err = Config::load(&create_temp_config(content)).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("more than one sensor curve named 'nvme'"));
+}
+
+#[test]
+fn test_compose_references_are_validated() {
+    let content = |compose: &str| {
+        format!(
+            r#"{{
+                "defaultStrategy": "both",
+                "strategies": {{
+                    "cpu": {{"speedCurve": [{{"temp": 0, "speed": 20}}]}},
+                    "nested": {{"compose": ["cpu"]}},
+                    "both": {{"compose": {}}}
+                }}
+            }}"#,
+            compose
+        )
+    };
+    let config = Config::load(&create_temp_config(&content(r#"["cpu"]"#))).unwrap();
+    assert!(config.strategies["both"].speed_curve.is_empty());
+
+    let err = Config::load(&create_temp_config(&content(r#"["gpu"]"#))).unwrap_err();
+    assert!(err.to_string().contains("composes unknown strategy 'gpu'"));
+    let err = Config::load(&create_temp_config(&content(r#"["nested"]"#))).unwrap_err();
+    assert!(err.to_string().contains("which composes others in turn"));
+    let err = Config::load(&create_temp_config(&content("[]"))).unwrap_err();
+    assert!(err.to_string().contains("has an empty speed curve"));
+}
+
+#[test]
+fn test_profiles_replace_top_level_values() {
+    let content = |docked: &str| {
+        format!(
+            r#"{{
+                "defaultStrategy": "quiet",
+                "strategies": {{"quiet": {{"speedCurve": [{{"temp": 0, "speed": 10}}]}}}},
+                "profiles": {{
+                    "docked": {},
+                    "mobile": {{"when": {{"powerProfile": "power-saver"}}, "startupSpeed": 20}}
+                }}
+            }}"#,
+            docked
+        )
+    };
+    let docked = r#"{
+        "when": {"usbDevice": "17EF:a396"},
+        "defaultStrategy": "loud",
+        "strategies": {"loud": {"speedCurve": [{"temp": 0, "speed": 60}]}}
+    }"#;
+    let config = Config::load(&create_temp_config(&content(docked))).unwrap();
+
+    let profile = config.with_profile("docked").unwrap();
+    assert_eq!(profile.default_strategy, "loud");
+    assert!(profile.get_strategy("quiet").is_none());
+    assert!(profile.profiles.is_empty());
+    assert_eq!(config.with_profile("mobile").unwrap().startup_speed, 20);
+    assert!(config.with_profile("office").is_err());
+
+    let mut env = ProfileEnvironment {
+        usb_devices: vec!["17ef:a396".into()],
+        power_profile: Some("power-saver".into()),
+    };
+    assert_eq!(config.matching_profile(&env), Some("docked"));
+    env.usb_devices.clear();
+    assert_eq!(config.matching_profile(&env), Some("mobile"));
+    env.power_profile = None;
+    assert_eq!(config.matching_profile(&env), None);
+
+    let err = Config::load(&create_temp_config(&content(
+        r#"{"defaultStrategy": "loud"}"#,
+    )))
+    .unwrap_err();
+    assert!(err.to_string().contains("Profile 'docked'"));
+    let err = Config::load(&create_temp_config(&content(
+        r#"{"when": {"usbDevice": "dock"}}"#,
+    )))
+    .unwrap_err();
+    assert!(err.to_string().contains("must be vendor:product"));
+}
+
+#[test]
+fn test_low_pass_smoothing_is_validated() {
+    let content = |time_constant: &str| {
+        format!(
+            r#"{{
+                "defaultStrategy": "smooth",
+                "strategies": {{
+                    "smooth": {{
+                        "smoothing": {{"type": "lowPass", "timeConstantSeconds": {}}},
+                        "speedCurve": [{{"temp": 0, "speed": 20}}]
+                    }}
+                }}
+            }}"#,
+            time_constant
+        )
+    };
+    let config = Config::load(&create_temp_config(&content("8"))).unwrap();
+    assert_eq!(
+        config.get_strategy("smooth").unwrap().smoothing,
+        Smoothing::LowPass {
+            time_constant_seconds: 8.0
+        }
+    );
+    assert!(Config::load(&create_temp_config(&content("-1"))).is_err());
+}
+
+#[test]
+fn test_sensor_aggregation_parses_and_collapses() {
+    let content = r#"{
+        "defaultStrategy": "quiet",
+        "strategies": {
+            "quiet": {"sensorAggregation": "p90", "speedCurve": [{"temp": 0, "speed": 20}]}
+        }
+    }"#;
+    let config = Config::load(&create_temp_config(content)).unwrap();
+    let aggregation = config.get_strategy("quiet").unwrap().sensor_aggregation;
+    assert_eq!(aggregation, SensorAggregation::P90);
+
+    let temps: Vec<f64> = (1..=10).map(|t| t as f64 * 10.0).collect();
+    assert_eq!(aggregation.apply(&temps), Some(90.0));
+    assert_eq!(SensorAggregation::SecondHighest.apply(&[55.0]), Some(55.0));
+    assert_eq!(SensorAggregation::Mean.apply(&[]), None);
+}
+
+#[test]
+fn test_ec_layout_overrides_only_given_offsets() {
+    let content = r#"{
+        "defaultStrategy": "minimal",
+        "strategies": {"minimal": {"speedCurve": [{"temp": 0, "speed": 20}]}},
+        "ecLayout": {"temperatureSensorCount": 16}
+    }"#;
+    let path = create_temp_config(content);
+    let layout = Config::load(&path).unwrap().ec_layout.unwrap();
+    assert_eq!(layout.temperature_sensor_count, 16);
+    assert_eq!(layout.temperature_offset, 0x00);
+    assert_eq!(layout.fan_offset, 0x10);
+}
+
+#[test]
+fn test_set_parameter_by_config_key() {
+    let (_, mut config) = create_valid_config();
+    let strategy = config.strategies.get_mut("performance").unwrap();
+
+    strategy.set_parameter("safetyTemp", 80).unwrap();
+    strategy.set_parameter("movingAverageInterval", 10).unwrap();
+    assert_eq!(strategy.safety_temp, 80);
+    assert_eq!(strategy.moving_average_interval, 10);
+
+    assert!(strategy
+        .set_parameter("fanSpeedUpdateFrequency", 0)
+        .is_err());
+    assert!(strategy.set_parameter("speedCurve", 1).is_err());
+    for field in Strategy::TUNABLE {
+        assert!(strategy.parameter_mut(field).is_some());
+    }
+}
+
+#[test]
+fn test_json_schema_describes_strategies() {
+    let schema = Config::json_schema();
+    assert!(schema["required"]
+        .as_array()
+        .unwrap()
+        .contains(&serde_json::json!("defaultStrategy")));
+
+    let strategy = &schema["$defs"]["Strategy"];
+    // `speedCurve` may be left out in favor of `compose`
+    assert!(strategy.get("required").is_none());
+    assert!(strategy["properties"]["compose"].is_object());
+    assert!(strategy["properties"]["sensorAggregation"].is_object());
+    assert!(schema["$defs"]["CurvePoint"]["properties"]["temp"].is_object());
+}
+
+#[test]
+fn test_changes_list_dotted_paths() {
+    let (path, old) = create_valid_config();
+    let mut new = old.clone();
+    new.strategies.get_mut("performance").unwrap().hysteresis = 3;
+    let quiet = new.strategies["balanced"].clone();
+    new.strategies.insert("quiet".into(), quiet);
+
+    assert_eq!(
+        old.changes(&new),
+        vec![
+            "strategies.performance.hysteresis: 0 -> 3".to_string(),
+            "strategies.quiet: added".to_string(),
+        ]
+    );
+    assert!(old.changes(&old).is_empty());
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_diff_groups_strategy_changes() {
+    let (path, old) = create_valid_config();
+    let mut new = old.clone();
+    new.strategies.get_mut("balanced").unwrap().safety_temp = 90;
+    let performance = new.strategies.remove("performance").unwrap();
+    new.strategies.insert("turbo".into(), performance);
+    new.default_strategy = "turbo".into();
+
+    let diff = old.diff(&new);
+    assert_eq!(diff.strategies_added, vec!["turbo"]);
+    assert_eq!(diff.strategies_removed, vec!["performance"]);
+    assert_eq!(diff.strategies_modified, vec!["balanced"]);
+    assert_eq!(
+        diff.default_strategy,
+        Some(StrategyChange {
+            from: "performance".into(),
+            to: "turbo".into()
+        })
+    );
+    assert!(old.diff(&old).is_empty());
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_strategy_export_folds_in_composed_curves() {
+    let (config, _) = Config::parse(
+        r#"{"defaultStrategy": "both", "strategies": {
+            "cpu": {"speedCurve": [[40, 0], [80, 100]]},
+            "ssd": {"speedCurve": [[0, 20]]},
+            "both": {"compose": ["cpu", "ssd"]}
+        }}"#,
+    )
+    .unwrap();
+    let export = config.export_strategy("both").unwrap();
+    assert!(export.definition.compose.is_empty());
+    assert_eq!(
+        export.definition.speed_curve,
+        config.resolved_curve("both").unwrap()
+    );
+
+    let content = serde_json::to_string(&export).unwrap();
+    let (mut other, _) =
+        Config::parse(r#"{"defaultStrategy": "a", "strategies": {"a": {"speedCurve": [[0, 0]]}}}"#)
+            .unwrap();
+    other
+        .import_strategy(StrategyExport::parse(&content, None).unwrap(), false)
+        .unwrap();
+    assert!(other.strategies.contains_key("both"));
+    assert!(config.export_strategy("missing").is_err());
+}
+
+#[test]
+fn test_strategy_import_checks_names_and_keys() {
+    let (mut config, _) = Config::parse(
+        r#"{"defaultStrategy": "quiet", "strategies": {"quiet": {"speedCurve": [[0, 0]]}}}"#,
+    )
+    .unwrap();
+    let bare = r#"{"speedCurve": [[50, 0], [90, 100]], "hysteresis": 3}"#;
+
+    assert!(StrategyExport::parse(bare, None).is_err());
+    let export = StrategyExport::parse(bare, Some("quiet")).unwrap();
+    assert!(config.import_strategy(export.clone(), false).is_err());
+    config.import_strategy(export, true).unwrap();
+    assert_eq!(config.strategies["quiet"].hysteresis, 3);
+
+    let renamed = StrategyExport::parse(
+        r#"{"name": "quiet", "definition": {"speedCurve": [[0, 10]]}}"#,
+        Some("desk"),
+    )
+    .unwrap();
+    assert_eq!(renamed.name, "desk");
+
+    let typo = StrategyExport::parse(r#"{"speedcurve": [[0, 0]]}"#, Some("x"));
+    assert!(typo.unwrap_err().to_string().contains("speedcurve"));
+    let empty = StrategyExport::parse("{}", Some("empty")).unwrap();
+    assert!(config.import_strategy(empty, false).is_err());
+    assert!(!config.strategies.contains_key("empty"));
 }
@@ -0,0 +1,176 @@
+//! Runs canonical temperature traces through the shipped strategies and checks
+//! the exact duty the controller commands on every tick.
+#![cfg(feature = "daemon")]
+
+use fw_fanctrl::config::Config;
+use fw_fanctrl::testing::Simulation;
+
+fn shipped_config() -> Config {
+    serde_json::from_str(include_str!("../config.json")).unwrap()
+}
+
+/// Idle, a ramp up to a sustained load, then back to idle.
+fn load_trace() -> Vec<u8> {
+    let mut trace = vec![40; 10];
+    trace.extend((0..20).map(|i| 40 + i * 2));
+    trace.extend([80; 20]);
+    trace.extend([45; 20]);
+    trace
+}
+
+/// A short burst that the smoothing should mostly ride out.
+fn spike_trace() -> Vec<u8> {
+    let mut trace = vec![50; 10];
+    trace.extend([90; 3]);
+    trace.extend([50; 12]);
+    trace
+}
+
+/// The duties as `(duty, ticks)` runs, to keep the expectations readable.
+fn runs(duties: &[u32]) -> Vec<(u32, usize)> {
+    let mut runs: Vec<(u32, usize)> = Vec::new();
+    for &duty in duties {
+        match runs.last_mut() {
+            Some((last, ticks)) if *last == duty => *ticks += 1,
+            _ => runs.push((duty, 1)),
+        }
+    }
+    runs
+}
+
+fn run(strategy: &str, trace: &[u8]) -> Vec<(u32, usize)> {
+    let mut sim = Simulation::new(shipped_config(), strategy);
+    runs(&sim.run_trace(trace).unwrap())
+}
+
+#[test]
+fn test_laziest_golden_traces() {
+    assert_eq!(
+        run("laziest", &load_trace()),
+        vec![
+            (0, 20),
+            (4, 5),
+            (10, 5),
+            (16, 5),
+            (19, 5),
+            (27, 5),
+            (33, 5),
+            (16, 10),
+            (15, 5),
+            (14, 5)
+        ]
+    );
+    assert_eq!(
+        run("laziest", &spike_trace()),
+        vec![(5, 10), (18, 5), (10, 5), (9, 5)]
+    );
+}
+
+#[test]
+fn test_lazy_golden_traces() {
+    assert_eq!(
+        run("lazy", &load_trace()),
+        vec![(15, 35), (27, 5), (35, 5), (47, 5), (15, 20)]
+    );
+    assert_eq!(run("lazy", &spike_trace()), vec![(15, 25)]);
+}
+
+#[test]
+fn test_performance_golden_traces() {
+    assert_eq!(
+        run("performance", &load_trace()),
+        vec![
+            (20, 25),
+            (27, 5),
+            (42, 5),
+            (56, 5),
+            (68, 5),
+            (77, 5),
+            (46, 5),
+            (34, 5),
+            (26, 5),
+            (20, 5)
+        ]
+    );
+    assert_eq!(
+        run("performance", &spike_trace()),
+        vec![(20, 10), (36, 5), (25, 5), (24, 5)]
+    );
+}
+
+#[test]
+fn test_simulation_writes_once_per_update_interval() {
+    let mut sim = Simulation::new(shipped_config(), "lazy");
+    let duties = sim.run_trace(&load_trace()).unwrap();
+    // 70 one-second ticks with `updateIntervalSeconds` 5
+    assert_eq!(sim.duty_writes().len(), 14);
+    assert_eq!(sim.duty_writes().last(), duties.last());
+}
+
+/// A fast and a quiet strategy with hysteresis, updated on every tick.
+fn hysteresis_config(extra: &str) -> Config {
+    serde_json::from_str(&format!(
+        r#"{{
+            "defaultStrategy": "fast",
+            "strategyOnDischarging": "",
+            {}
+            "strategies": {{
+                "fast": {{
+                    "updateIntervalSeconds": 1, "movingAverageInterval": 1, "hysteresis": 3,
+                    "speedCurve": [{{"temp": 0, "speed": 0}}, {{"temp": 100, "speed": 100}}],
+                    "dischargeSaver": {{"minWatts": 20, "maxTemp": 90, "speedReduction": 15}}
+                }},
+                "quiet": {{
+                    "updateIntervalSeconds": 1, "movingAverageInterval": 1, "hysteresis": 3,
+                    "speedCurve": [{{"temp": 0, "speed": 25}}]
+                }}
+            }}
+        }}"#,
+        extra
+    ))
+    .unwrap()
+}
+
+/// A simulation of `fast` settled at a steady 50°C.
+fn settled() -> Simulation {
+    let mut sim = Simulation::new(hysteresis_config(""), "fast");
+    assert_eq!(sim.run_trace(&[50; 3]).unwrap(), vec![50; 3]);
+    sim
+}
+
+#[test]
+fn test_hysteresis_lets_a_lower_strategy_in() {
+    let mut sim = settled();
+    sim.controller_mut().overwrite_strategy("quiet").unwrap();
+    assert_eq!(sim.run_trace(&[50; 3]).unwrap(), vec![25; 3]);
+}
+
+#[test]
+fn test_hysteresis_lets_a_negative_bias_in() {
+    let mut sim = settled();
+    sim.controller_mut().set_speed_bias(-10);
+    assert_eq!(sim.run_trace(&[50; 3]).unwrap(), vec![40; 3]);
+}
+
+#[test]
+fn test_hysteresis_lets_quiet_hours_in() {
+    let mut sim = settled();
+    let now = chrono::Local::now();
+    let quiet_hours = format!(
+        r#""quietHours": {{"start": "{}", "end": "{}", "maxSpeed": 20}},"#,
+        (now - chrono::Duration::hours(1)).format("%H:%M"),
+        (now + chrono::Duration::hours(1)).format("%H:%M")
+    );
+    sim.controller_mut()
+        .reload_config(hysteresis_config(&quiet_hours));
+    assert_eq!(sim.run_trace(&[50; 3]).unwrap(), vec![20; 3]);
+}
+
+#[test]
+fn test_hysteresis_lets_the_discharge_saver_in() {
+    let mut sim = settled();
+    sim.ec().set_ac_present(false);
+    sim.ec().set_battery_discharge_watts(25.0);
+    sim.controller_mut().handle_power_change().unwrap();
+    assert_eq!(sim.run_trace(&[50; 3]).unwrap(), vec![35; 3]);
+}